@@ -0,0 +1,49 @@
+#shader_type vertex
+#version 450
+
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec2 uv;
+
+struct Glyph {
+    // top left corner in pixels
+    vec2 pos;
+    uint layer;
+};
+
+layout (std430, binding = 5) readonly buffer Glyphs {
+    Glyph glyphs[];
+};
+
+uniform mat4 u_view;
+uniform vec2 u_glyph_size;
+uniform vec2 u_offset;
+
+out vec3 v_uv;
+
+void main() {
+    Glyph glyph = glyphs[gl_InstanceID];
+
+    // the quad's uv spans the glyph, but screen space y points downwards
+    vec2 pos = glyph.pos + u_offset + vec2(uv.x, 1 - uv.y) * u_glyph_size;
+    v_uv = vec3(uv, glyph.layer);
+    gl_Position = u_view * vec4(pos, 0, 1);
+}
+
+// ------------------------------------------------------------
+
+#shader_type fragment
+#version 450
+
+in vec3 v_uv;
+
+layout (location = 0) out vec4 color;
+
+uniform sampler2DArray u_font;
+uniform vec3 u_color;
+
+void main() {
+    if (texture(u_font, v_uv).a < 0.5) {
+        discard;
+    }
+    color = vec4(u_color, 1);
+}
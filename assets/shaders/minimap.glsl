@@ -0,0 +1,55 @@
+#shader_type vertex
+#version 450
+
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec2 uv;
+
+uniform mat4 u_view;
+uniform vec2 u_offset;// top left corner of the minimap in pixels
+uniform vec2 u_size;// width & height of the minimap in pixels
+
+out vec2 v_uv;
+
+void main() {
+    // screen space y points downwards
+    vec2 pos = u_offset + vec2(uv.x, 1 - uv.y) * u_size;
+    v_uv = uv;
+    gl_Position = u_view * vec4(pos, 0, 1);
+}
+
+// ------------------------------------------------------------
+
+#shader_type fragment
+#version 450
+
+in vec2 v_uv;
+
+layout (location = 0) out vec4 color;
+
+uniform sampler2D u_map;
+uniform vec2 u_size;
+uniform vec2 u_heading;// normalized look direction of the player, y points north
+
+const float border = 2;
+const float marker_radius = 3;
+const float marker_length = 10;
+
+void main() {
+    // convert uv into pixels relative to the center
+    vec2 p = (v_uv - 0.5) * u_size;
+    vec2 dst_to_edge = u_size * 0.5 - abs(p);
+    if (min(dst_to_edge.x, dst_to_edge.y) < border) {
+        color = vec4(0, 0, 0, 1);
+        return;
+    }
+
+    // mark the player with a dot and a line towards the look direction
+    float along = dot(p, u_heading);
+    float across = abs(dot(p, vec2(-u_heading.y, u_heading.x)));
+    if (length(p) < marker_radius || (along > 0 && along < marker_length && across < 1)) {
+        color = vec4(1, 0, 0, 1);
+        return;
+    }
+
+    color = vec4(texture(u_map, v_uv).rgb, 1);
+}
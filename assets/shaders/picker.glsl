@@ -9,43 +9,90 @@ layout (local_size_x = 1) in;
 struct PickerResult {
     float dst;
     bool inside_voxel;
+    uint value;
     vec3 pos;
     vec3 normal;
 };
 layout (std430, binding = 1) writeonly buffer picker_output {
-    PickerResult results[100];
+    PickerResult results[];
 };
 
 struct PickerTask {
     float max_dst;// stop raytracing if nothing was hit past this distance
+    uint max_hits;// number of results to write starting at the task index (0 = placeholder for a multi-hit task)
     vec3 pos;// ray origin
     vec3 dir;// ray direction
 };
 layout (std430, binding = 3) readonly buffer picker_input {
-    PickerTask tasks[100];
+    PickerTask tasks[];
 };
 
 uniform sampler2DArray u_texture;
 
+// distance to move past a hit, so that the next cast starts within the hit voxel
+#define MULTI_HIT_STEP 0.001
+// upper bound of casts per multi-hit task, including skipped voxels of the same kind
+#define MAX_MULTI_HIT_CASTS 64
+
+void write_hit(uint index, float dst, OctreeResult res) {
+    results[index].dst = dst;
+    results[index].inside_voxel = res.inside_voxel;
+    results[index].value = res.value;
+    results[index].pos = res.pos;
+    results[index].normal = FACE_NORMALS[res.face_id];
+}
+
+void write_miss(uint index) {
+    results[index].dst = -1;
+    results[index].inside_voxel = false;
+    results[index].value = 0;
+    results[index].pos = vec3(0);
+    results[index].normal = vec3(0);
+}
+
 void main() {
     // pick one task per invocation group
     uint index = gl_GlobalInvocationID.x;
     PickerTask task = tasks[index];
 
-    // cast into octree and stop at translucent blocks
-    OctreeResult res;
-    intersect_octree(task.pos, task.dir, task.max_dst, false, u_texture, res);
-
-    // write hit into result buffer
-    if (res.t > 0) {
-        results[index].dst = res.t;
-        results[index].inside_voxel = res.inside_voxel;
-        results[index].pos = res.pos;
-        results[index].normal = FACE_NORMALS[res.face_id];
-    } else {
-        results[index].dst = -1;
-        results[index].inside_voxel = false;
-        results[index].pos = vec3(0);
-        results[index].normal = vec3(0);
+    // placeholders only reserve result slots for a preceding multi-hit task
+    if (task.max_hits == 0) {
+        return;
+    }
+
+    // Cast into the octree and stop at translucent blocks. For multi-hit tasks, continue from within every hit voxel
+    // until max_hits results are recorded. Like `cast_translucent` does, adjacent voxels of the same kind are only
+    // reported once.
+    uint hits = 0;
+    float travelled = 0;
+    vec3 pos = task.pos;
+    for (int i = 0; i < MAX_MULTI_HIT_CASTS && hits < task.max_hits; ++i) {
+        float max_dst = task.max_dst;
+        if (max_dst > 0) {
+            max_dst -= travelled;
+            if (max_dst <= 0) break;
+        }
+
+        OctreeResult res;
+        intersect_octree(pos, task.dir, max_dst, false, u_texture, res);
+        if (!(res.t > 0)) break;
+
+        uint prev_value;
+        bool first_of_kind = i == 0 ||
+            !lookup_octree(res.pos - task.dir * MULTI_HIT_STEP, prev_value) ||
+            prev_value != res.value;
+
+        // write hit into result buffer
+        if (first_of_kind) {
+            write_hit(index + hits, travelled + res.t, res);
+            ++hits;
+        }
+
+        travelled += res.t + MULTI_HIT_STEP;
+        pos = res.pos + task.dir * MULTI_HIT_STEP;
+    }
+
+    for (; hits < task.max_hits; ++hits) {
+        write_miss(index + hits);
     }
 }
@@ -58,6 +58,9 @@ struct Material {
     int tex_top_normal;
     int tex_side_normal;
     int tex_bottom_normal;
+
+    vec3 emissive_color;// tints the texture color of emissive materials
+    float emissive_strength;// 0 = lit by light sources only, 1 = fully emissive & unaffected by light
 };
 
 layout (std430, binding = 2) readonly buffer MaterialRegistry {
@@ -455,3 +458,94 @@ void intersect_octree(vec3 ro, vec3 rd, float max_dst, bool cast_translucent, sa
         }
     }
 }
+
+// Looks up the leaf value at the given world space position by descending into the octree until a leaf or an empty
+// octant is found. Returns true if a leaf exists at that position and writes its value to `value`.
+bool lookup_octree(vec3 pos, out uint value) {
+    value = 0;
+
+    // rescale input to be [0;1]
+    pos *= octree_scale;
+    if (any(lessThan(pos, vec3(0))) || any(greaterThanEqual(pos, vec3(1)))) {
+        return false;
+    }
+
+    uint ptr = 0;
+    uint parent_octant_idx = 0;
+    float half_size = 0.5;
+    vec3 corner = vec3(0);
+
+    for (int scale = 0; scale < MAX_SCALE; ++scale) {
+        // calculate child index (see `intersect_octree` for the index layout)
+        vec3 center = corner + half_size;
+        uint idx = 0;
+        if (pos.x >= center.x) idx |= 1, corner.x += half_size;
+        if (pos.y >= center.y) idx |= 2, corner.y += half_size;
+        if (pos.z >= center.z) idx |= 4, corner.z += half_size;
+
+        uint descriptor = descriptors[ptr + (parent_octant_idx / 2)];
+        if ((parent_octant_idx % 2) != 0) {
+            descriptor >>= 16;
+        }
+        uint bit = 1u << idx;
+        if ((descriptor & (bit << 8)) == 0) {
+            return false;
+        }
+
+        uint next_ptr = get_octant_ptr(ptr, parent_octant_idx);
+        if ((descriptor & bit) != 0) {
+            value = descriptors[next_ptr + 4 + idx];
+            return true;
+        }
+
+        ptr = next_ptr;
+        parent_octant_idx = idx;
+        half_size *= 0.5;
+    }
+
+    return false;
+}
+
+// Returns the normal of the face that was hit by `intersect_octree`. If the material has a normal texture for that face,
+// the geometric face normal is perturbed by the texture's tangent space normal (red = tangent, green = bitangent,
+// blue = face normal).
+vec3 get_hit_normal(OctreeResult res, sampler2DArray textures) {
+    // select the normal texture for the given face of the voxel's material
+    Material mat = materials[res.value];
+    int tex_normal_id = mat.tex_side_normal;
+    if (res.face_id == 3) { tex_normal_id = mat.tex_top_normal; }
+    else if (res.face_id == 2) { tex_normal_id = mat.tex_bottom_normal; }
+
+    vec3 normal = FACE_NORMALS[res.face_id];
+    if (tex_normal_id >= 0) {
+        vec3 tex = textureLod(textures, vec3(res.uv, float(tex_normal_id)), res.lod).xzy;
+
+        // map [0;1] to [-1;1]
+        tex = normalize(tex * 2 - 1);
+
+        // blue = up -> y axis
+        normal = tex.x * FACE_TANGENTS[res.face_id] + tex.y * normal + tex.z * FACE_BITANGENTS[res.face_id];
+    }
+    return normal;
+}
+
+// Calculates the final color of a hit using ambient, diffuse and specular lighting of a directional light. Emissive
+// materials blend from the lit color towards their texture color tinted by the emissive color, so that fully emissive
+// materials are independent of the light direction.
+vec3 shade_hit(OctreeResult res, vec3 normal, vec3 light_dir, vec3 light_color, vec3 view_dir, float ambient, float shadow) {
+    Material mat = materials[res.value];
+
+    // calculate diffuse lighting using the light direction vector
+    float diffuse = max(dot(normal, -light_dir), 0.0);
+
+    // calculate specular lighting using view and light direction
+    float specular = 0.0;
+    if (mat.specular_strength > 0) {
+        vec3 reflect_dir = reflect(-light_dir, normal);
+        specular = pow(max(dot(view_dir, reflect_dir), 0.0), mat.specular_pow) * mat.specular_strength;
+    }
+
+    vec3 light = clamp(ambient + light_color * (diffuse + specular) * shadow, 0.0, 1.0);
+    vec3 emissive = res.color.rgb * mat.emissive_color;
+    return mix(res.color.rgb * light, emissive, clamp(mat.emissive_strength, 0.0, 1.0));
+}
@@ -32,11 +32,14 @@ struct StackFrame {
 
 layout (std430, binding = 12) buffer buffer_out {
     Result out_result;
+    vec3 out_normal;
     int out_stack_ptr;
+    vec3 out_shaded_color;
     StackFrame out_stack[];
 };
 
 uniform sampler2DArray u_texture;
+uniform vec3 u_light_dir;
 
 // override debug function to capture raytracing frames
 #define OCTREE_RAYTRACE_DEBUG_FN(t_min, ptr, idx, parent_octant_idx, scale, is_child, is_leaf) \
@@ -69,4 +72,6 @@ void main() {
     out_result.uv = res.uv;
     out_result.color = res.color;
     out_result.inside_voxel = res.inside_voxel;
+    out_normal = res.t < 0 ? vec3(0) : get_hit_normal(res, u_texture);
+    out_shaded_color = res.t < 0 ? vec3(0) : shade_hit(res, out_normal, u_light_dir, vec3(1), in_dir, 0.0, 1.0);
 }
@@ -1,6 +1,15 @@
 #shader_type compute
 #version 450
 
+// Scale and index of the last leaf octant visited by `intersect_octree`. Only tracked in the octant debug render mode.
+int dbg_leaf_scale;
+int dbg_leaf_idx;
+uniform int u_render_mode;// 0 = shaded, 1 = octant debug colors
+
+// record the last visited leaf octant during traversal
+#define OCTREE_RAYTRACE_DEBUG_FN(t_min, ptr, idx, parent_octant_idx, scale, is_child, is_leaf) \
+    if (u_render_mode == 1 && is_leaf) { dbg_leaf_scale = scale; dbg_leaf_idx = idx; }
+
 #include "svo.glsl"
 
 #define PI 3.141592
@@ -12,28 +21,174 @@ layout (rgba32f, binding = 0) uniform image2D render_target;
 uniform mat4 u_view;// converts world to view space
 uniform float u_fovy;
 uniform float u_aspect;// screen width / height
+uniform vec2 u_ortho_size;// width & height of the view in blocks for orthographic projections, (0, 0) for perspective
+uniform float u_max_ray_dst;// maximum distance primary rays travel, -1 = unlimited
 uniform sampler2DArray u_texture;
 
 // lighting
 uniform float u_ambient;// ambient light intensity - to fake global illumination
 uniform vec3 u_light_dir;// sun light direction
+uniform vec3 u_light_color;// sun light color, black while the sun is below the horizon
 uniform vec3 u_cam_pos;// world space position of the camera
 uniform bool u_render_shadows;// enables secondary ray casting
 uniform float u_shadow_distance;// distance until which shadows are rendered
+uniform bool u_shadow_accumulate;// enables temporal accumulation of jittered shadow rays
+uniform int u_shadow_sample_index;// number of accumulated shadow samples since the last reset
+uniform float u_shadow_softness;// radius of the cone around the light direction that shadow rays are spread over
+uniform int u_shadow_kernel_size;// number of shadow rays per axis of the filter kernel (1 = hard shadows)
+
+// shadow history
+layout (rgba32f, binding = 1) uniform image2D shadow_history;// accumulated shadow value per pixel (red channel)
 
 // block highlighting
 uniform vec3 u_highlight_pos;// world space position of the block the player is highlighting
 
-vec4 trace_ray(vec3 ro, vec3 rd, out bool hit) {
+// fog
+struct Fog {
+    vec3 color;
+    float density;// exponential fog density, 0 disables fog
+    float start;// distance from which on fog is applied
+    float end;// distance at which everything is fully fogged, e.g. the boundary of the loaded chunks
+};
+uniform Fog u_fog;
+
+// edge outlines
+uniform float u_edge_outline_width;// width of outlines between different voxels relative to the voxel size (0 = off)
+uniform vec3 u_edge_outline_color;// color of the outlines
+
+// Returns the blend factor [0;1] for the outline color at the given hit. Outlines are drawn on all face edges where
+// the adjacent voxel on the same plane has a different value than the hit voxel.
+float get_edge_outline_factor(OctreeResult res) {
+    if (u_edge_outline_width <= 0) {
+        return 0.0;
+    }
+
+    vec3 normal = FACE_NORMALS[res.face_id];
+    vec3 voxel = floor(res.pos) + 0.5;// hit position is always clamped to be inside the voxel
+    vec3 local = fract(res.pos);
+
+    float factor = 0.0;
+    for (int axis = 0; axis < 3; ++axis) {
+        if (normal[axis] != 0) {
+            continue;
+        }
+
+        // check the closest edge along the axis
+        float dst = min(local[axis], 1.0 - local[axis]);
+        if (dst >= u_edge_outline_width) {
+            continue;
+        }
+
+        vec3 offset = vec3(0);
+        offset[axis] = local[axis] < 0.5 ? -1.0 : 1.0;
+
+        uint neighbour;
+        bool exists = lookup_octree(voxel + offset, neighbour);
+        if (!exists || neighbour != res.value) {
+            // smooth out the outline towards its inner side to reduce aliasing
+            factor = max(factor, 1.0 - smoothstep(u_edge_outline_width * 0.5, u_edge_outline_width, dst));
+        }
+    }
+    return factor;
+}
+
+// Hashes the given values into three pseudo random values within [0;1].
+// Source: Mark Jarzynski and Marc Olano. 2020 "Hash Functions for GPU Rendering" (pcg3d)
+vec3 hash3(uvec3 v) {
+    v = v * 1664525u + 1013904223u;
+    v.x += v.y * v.z; v.y += v.z * v.x; v.z += v.x * v.y;
+    v ^= v >> 16u;
+    v.x += v.y * v.z; v.y += v.z * v.x; v.z += v.x * v.y;
+    return vec3(v) / float(0xffffffffu);
+}
+
+// Calculates two vectors that are perpendicular to the given direction and to each other.
+void get_light_basis(vec3 dir, out vec3 tangent, out vec3 bitangent) {
+    tangent = normalize(cross(dir, abs(dir.y) < 0.99 ? vec3(0, 1, 0) : vec3(1, 0, 0)));
+    bitangent = cross(dir, tangent);
+}
+
+// Randomly offsets the light direction inside a small cone around it. Averaging many of these samples over time
+// results in soft shadow edges.
+vec3 jitter_light_dir(vec3 dir, ivec2 pixel) {
+    const float SOFTNESS = 0.02;
+
+    vec3 rnd = hash3(uvec3(pixel, u_shadow_sample_index));
+    vec3 tangent, bitangent;
+    get_light_basis(dir, tangent, bitangent);
+    vec2 offset = (rnd.xy * 2 - 1) * SOFTNESS;
+    return normalize(dir + tangent * offset.x + bitangent * offset.y);
+}
+
+// Returns the fraction [0;1] of light that reaches the given position. Shadow rays are cast in a grid of
+// u_shadow_kernel_size^2 directions spread over a cone of radius u_shadow_softness around the light direction and
+// averaged (percentage-closer filtering). A kernel size of 1 casts a single ray, which results in hard shadows.
+float trace_shadow(vec3 pos, vec3 light_dir) {
+    int kernel_size = max(u_shadow_kernel_size, 1);
+
+    vec3 tangent, bitangent;
+    get_light_basis(light_dir, tangent, bitangent);
+
+    float lit = 0.0;
+    for (int y = 0; y < kernel_size; ++y) {
+        for (int x = 0; x < kernel_size; ++x) {
+            vec2 offset = vec2(0);
+            if (kernel_size > 1) {
+                offset = (vec2(x, y) / float(kernel_size - 1) * 2 - 1) * u_shadow_softness;
+            }
+            vec3 dir = normalize(light_dir + tangent * offset.x + bitangent * offset.y);
+
+            OctreeResult shadow_res;
+            intersect_octree(pos, -dir, -1, true, u_texture, shadow_res);
+            lit += shadow_res.t < 0 ? 1.0 : 0.0;
+        }
+    }
+    return lit / float(kernel_size * kernel_size);
+}
+
+// Converts a color from HSV to RGB, all components within [0;1].
+vec3 hsv_to_rgb(vec3 c) {
+    vec3 p = abs(fract(c.xxx + vec3(1.0, 2.0/3.0, 1.0/3.0)) * 6.0 - 3.0);
+    return c.z * mix(vec3(1.0), clamp(p - 1.0, 0.0, 1.0), c.y);
+}
+
+// Returns the debug color of a leaf octant. The hue encodes the octant's scale, i.e. its size in the octree, and cycles
+// every 8 scales. The brightness encodes the octant's index [0;7] within its parent, from dark (0) to bright (7), so
+// that adjacent octants of the same size can be told apart.
+vec3 get_octant_debug_color(int scale, int idx) {
+    float hue = float(scale % 8) / 8.0;
+    float value = 0.4 + 0.6 * float(idx) / 7.0;
+    return hsv_to_rgb(vec3(hue, 0.8, value));
+}
+
+// Returns the fog blend factor [0;1] for a hit at distance t. Fog increases exponentially with the distance after
+// u_fog.start and fades to 1 towards u_fog.end, so that the boundary of the loaded world does not cut off harshly.
+float get_fog_factor(float t) {
+    if (u_fog.density <= 0) {
+        return 0.0;
+    }
+
+    float fog = 1.0 - exp(-u_fog.density * max(t - u_fog.start, 0.0));
+    if (u_fog.end > u_fog.start) {
+        fog = max(fog, smoothstep(mix(u_fog.start, u_fog.end, 0.75), u_fog.end, t));
+    }
+    return fog;
+}
+
+vec4 trace_ray(vec3 ro, vec3 rd, ivec2 pixel, out bool hit, out float t) {
     OctreeResult res;
-    intersect_octree(ro, rd, -1, true, u_texture, res);
+    intersect_octree(ro, rd, u_max_ray_dst, true, u_texture, res);
 
     hit = res.t != -1;
+    t = res.t;
 
     if (res.t < 0) {
         // return early on no hit
         return vec4(0);
     }
+    if (u_render_mode == 1) {
+        return vec4(get_octant_debug_color(dbg_leaf_scale, dbg_leaf_idx), 1);
+    }
     if (floor(res.pos) == floor(u_highlight_pos)) {
         // if block is highlighted, draw a white outline around it
         const float thickness = 1./16.;
@@ -44,48 +199,30 @@ vec4 trace_ray(vec3 ro, vec3 rd, out bool hit) {
         }
     }
 
-    // select the normal texture for the given face of the voxel's material
-    Material mat = materials[res.value];
-    int tex_normal_id = mat.tex_side_normal;
-    if (res.face_id == 3) { tex_normal_id = mat.tex_top_normal; }
-    else if (res.face_id == 2) { tex_normal_id = mat.tex_bottom_normal; }
-
-    // select precalculated vectors for the face
-    vec3 normal = FACE_NORMALS[res.face_id];
-    vec3 tangent = FACE_TANGENTS[res.face_id];
-    vec3 bitangent = FACE_BITANGENTS[res.face_id];
-
-    // if a normal texture is set, use its value as the normal vector
-    if (tex_normal_id != -1) {
-        vec3 tex = textureLod(u_texture, vec3(res.uv, float(tex_normal_id)), res.lod).xzy;
-
-        // map [0;1] to [-1;1]
-        tex = normalize(tex * 2 - 1);
-
-        // blue = up -> y axis
-        normal = tex.x * tangent + tex.y * normal + tex.z * bitangent;
-    }
-
-    // calculate diffuse lighting using the sun light direction vector
-    float diffuse = max(dot(normal, -u_light_dir), 0.0);
-
-    // calculate specular lighting using camera position and sun light direction
-    vec3 view_dir = normalize(res.pos - u_cam_pos);
-    vec3 reflect_dir = reflect(-u_light_dir, normal);
-    float specular = pow(max(dot(view_dir, reflect_dir), 0.0), mat.specular_pow) * mat.specular_strength;
+    // use the face normal, perturbed by the material's normal texture if it has one
+    vec3 normal = get_hit_normal(res, u_texture);
 
     // Calculate shadow by casting another ray from the previous hit location towards the sun. Skip if the hit is too
     // far away.
     float shadow = 1;
-    if (u_render_shadows && res.t < u_shadow_distance) {
-        OctreeResult shadow_res;
-        intersect_octree(res.pos + normal*0.001, -u_light_dir, -1, true, u_texture, shadow_res);
-        shadow = shadow_res.t < 0 ? 1.0 : 0.0;
+    if (u_render_shadows && res.t < u_shadow_distance && u_light_color != vec3(0)) {
+        vec3 light_dir = u_shadow_accumulate ? jitter_light_dir(u_light_dir, pixel) : u_light_dir;
+
+        shadow = trace_shadow(res.pos + normal*0.001, light_dir);
+
+        // Blend the new sample into the history so that the value converges towards the average of all samples. The
+        // first sample after a reset overrides the history.
+        if (u_shadow_accumulate) {
+            float history = imageLoad(shadow_history, pixel).r;
+            shadow = mix(history, shadow, 1.0 / float(u_shadow_sample_index + 1));
+            imageStore(shadow_history, pixel, vec4(shadow));
+        }
     }
 
-    // combine light calculations and color
-    float light = clamp(u_ambient + (diffuse + specular) * shadow, 0.0, 1.0);
-    res.color.rgb *= light;
+    // combine light calculations, emission and color
+    vec3 view_dir = normalize(res.pos - u_cam_pos);
+    res.color.rgb = shade_hit(res, normal, u_light_dir, u_light_color, view_dir, u_ambient, shadow);
+    res.color.rgb = mix(res.color.rgb, u_edge_outline_color, get_edge_outline_factor(res));
     return res.color;
 }
 
@@ -108,14 +245,22 @@ vec3 get_sky_color(vec3 rd) {
 }
 
 void main() {
-    // convert uv from [0;1] to [-1;1], apply screen aspect ratio & vertical FoV
+    // convert uv from [0;1] to [-1;1]
     vec2 uv = vec2(gl_GlobalInvocationID.xy) / imageSize(render_target);
     uv = uv * 2.0 - 1.0;
-    uv.x *= u_aspect;
-    uv *= tan(u_fovy * 0.5);
 
     vec3 ro = vec3(0.0, 0.0, 0.0);
-    vec3 look_at = vec3(uv, -1.0);
+    vec3 look_at;
+    if (u_ortho_size.y > 0) {
+        // orthographic rays start on the view plane and are all parallel
+        ro.xy = uv * u_ortho_size * 0.5;
+        look_at = ro + vec3(0.0, 0.0, -1.0);
+    } else {
+        // apply screen aspect ratio & vertical FoV
+        uv.x *= u_aspect;
+        uv *= tan(u_fovy * 0.5);
+        look_at = vec3(uv, -1.0);
+    }
 
     // convert ray origin to view space
     vec4 ro_view = u_view * vec4(ro, 1.0);
@@ -128,13 +273,16 @@ void main() {
     // cast ray from origin to look_at
     vec3 rd = normalize(look_at - ro);
     bool hit = false;
+    float t;
 
-    vec4 color = trace_ray(ro, rd, hit);
+    vec4 color = trace_ray(ro, rd, ivec2(gl_GlobalInvocationID.xy), hit, t);
 
-    // calculate sky color if nothing was hit
+    // calculate sky color if nothing was hit, otherwise blend in fog
     if (!hit) {
         vec3 sky = get_sky_color(rd);
         color = vec4(sky, 1.0);
+    } else {
+        color.rgb = mix(color.rgb, u_fog.color, get_fog_factor(t));
     }
 
     imageStore(render_target, ivec2(gl_GlobalInvocationID.xy), color);
@@ -0,0 +1,279 @@
+use std::fs;
+use std::path::Path;
+
+use glfw::{Key, MouseButton};
+use rustc_hash::FxHashMap;
+
+use crate::core::Input;
+
+/// `Action` is a named game action that is triggered by a [`Binding`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    Forward,
+    Back,
+    Left,
+    Right,
+    /// `Up` jumps while walking and ascends while flying.
+    Up,
+    /// `Down` sprints while walking and descends while flying.
+    Down,
+    ToggleInput,
+    /// `TogglePhysics` switches between flying and walking.
+    TogglePhysics,
+    Screenshot,
+    Place,
+    Remove,
+    /// `Undo` reverts the last block edit while control is held.
+    Undo,
+    /// `Redo` applies the last undone block edit again while control is held.
+    Redo,
+}
+
+impl Action {
+    pub const ALL: [Self; 13] = [
+        Self::Forward, Self::Back, Self::Left, Self::Right, Self::Up, Self::Down,
+        Self::ToggleInput, Self::TogglePhysics, Self::Screenshot, Self::Place, Self::Remove, Self::Undo, Self::Redo,
+    ];
+
+    /// Returns the name of the action in binding config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Forward => "forward",
+            Self::Back => "back",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::ToggleInput => "toggle-input",
+            Self::TogglePhysics => "toggle-physics",
+            Self::Screenshot => "screenshot",
+            Self::Place => "place",
+            Self::Remove => "remove",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+        }
+    }
+}
+
+/// `Binding` is a keyboard key or mouse button.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    const KEY_NAMES: [(&'static str, Key); 56] = [
+        ("a", Key::A), ("b", Key::B), ("c", Key::C), ("d", Key::D), ("e", Key::E), ("f", Key::F), ("g", Key::G),
+        ("h", Key::H), ("i", Key::I), ("j", Key::J), ("k", Key::K), ("l", Key::L), ("m", Key::M), ("n", Key::N),
+        ("o", Key::O), ("p", Key::P), ("q", Key::Q), ("r", Key::R), ("s", Key::S), ("t", Key::T), ("u", Key::U),
+        ("v", Key::V), ("w", Key::W), ("x", Key::X), ("y", Key::Y), ("z", Key::Z),
+        ("0", Key::Num0), ("1", Key::Num1), ("2", Key::Num2), ("3", Key::Num3), ("4", Key::Num4),
+        ("5", Key::Num5), ("6", Key::Num6), ("7", Key::Num7), ("8", Key::Num8), ("9", Key::Num9),
+        ("f1", Key::F1), ("f2", Key::F2), ("f3", Key::F3), ("f4", Key::F4), ("f5", Key::F5), ("f6", Key::F6),
+        ("f7", Key::F7), ("f8", Key::F8), ("f9", Key::F9), ("f10", Key::F10), ("f11", Key::F11), ("f12", Key::F12),
+        ("space", Key::Space), ("tab", Key::Tab), ("enter", Key::Enter),
+        ("left-shift", Key::LeftShift), ("right-shift", Key::RightShift),
+        ("left-control", Key::LeftControl), ("right-control", Key::RightControl), ("left-alt", Key::LeftAlt),
+    ];
+    const BUTTON_NAMES: [(&'static str, MouseButton); 3] = [
+        ("mouse-left", MouseButton::Button1),
+        ("mouse-right", MouseButton::Button2),
+        ("mouse-middle", MouseButton::Button3),
+    ];
+
+    /// Parses a binding by its name, e.g. `w`, `left-shift`, `f2` or `mouse-left`. Names are case-insensitive.
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        Self::KEY_NAMES.iter().find(|(n, _)| *n == name).map(|(_, key)| Self::Key(*key))
+            .or_else(|| Self::BUTTON_NAMES.iter().find(|(n, _)| *n == name).map(|(_, button)| Self::Mouse(*button)))
+    }
+
+    fn is_pressed(self, input: &Input) -> bool {
+        match self {
+            Self::Key(key) => input.is_key_pressed(key),
+            Self::Mouse(button) => input.is_button_pressed(button),
+        }
+    }
+
+    fn was_triggered(self, input: &Input) -> bool {
+        match self {
+            Self::Key(key) => input.was_key_pressed(key),
+            Self::Mouse(button) => input.is_button_pressed_once(button),
+        }
+    }
+}
+
+/// `KeyBindings` maps every [`Action`] to the [`Binding`] that triggers it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBindings {
+    bindings: FxHashMap<Action, Binding>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let bindings = Action::ALL.into_iter()
+            .map(|action| {
+                let binding = match action {
+                    Action::Forward => Binding::Key(Key::W),
+                    Action::Back => Binding::Key(Key::S),
+                    Action::Left => Binding::Key(Key::A),
+                    Action::Right => Binding::Key(Key::D),
+                    Action::Up => Binding::Key(Key::Space),
+                    Action::Down => Binding::Key(Key::LeftShift),
+                    Action::ToggleInput => Binding::Key(Key::T),
+                    Action::TogglePhysics => Binding::Key(Key::F),
+                    Action::Screenshot => Binding::Key(Key::F2),
+                    Action::Place => Binding::Mouse(MouseButton::Button2),
+                    Action::Remove => Binding::Mouse(MouseButton::Button1),
+                    Action::Undo => Binding::Key(Key::Z),
+                    Action::Redo => Binding::Key(Key::Y),
+                };
+                (action, binding)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl KeyBindings {
+    /// Loads the bindings from the config file at `path`. See [`KeyBindings::parse`]. If the file cannot be read, the
+    /// error is reported and the default bindings are used.
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        match fs::read_to_string(&path) {
+            Ok(config) => Self::parse(&config),
+            Err(err) => {
+                println!("error reading key bindings {}, using defaults: {err}", path.as_ref().display());
+                Self::default()
+            }
+        }
+    }
+
+    /// Parses a config with one `action = binding` pair per line, e.g. `forward = z`. Empty lines and lines starting
+    /// with `#` are ignored. Actions that are not configured keep their default binding. Invalid lines, actions that
+    /// are configured more than once and bindings that are used for multiple actions are reported.
+    pub fn parse(config: &str) -> Self {
+        let mut result = Self::default();
+        let mut configured = Vec::new();
+
+        for (i, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((name, binding_name)) = line.split_once('=') else {
+                println!("key bindings line {}: expected `action = binding`: {line}", i + 1);
+                continue;
+            };
+            let (name, binding_name) = (name.trim(), binding_name.trim());
+
+            let Some(action) = Action::ALL.into_iter().find(|action| action.name() == name) else {
+                println!("key bindings line {}: unknown action: {name}", i + 1);
+                continue;
+            };
+            let Some(binding) = Binding::parse(binding_name) else {
+                println!("key bindings line {}: unknown key: {binding_name}", i + 1);
+                continue;
+            };
+            if configured.contains(&action) {
+                println!("key bindings line {}: {name} is already bound, ignoring {binding_name}", i + 1);
+                continue;
+            }
+
+            configured.push(action);
+            result.set(action, binding);
+        }
+
+        for (action, other) in result.find_duplicates() {
+            println!("key bindings: {} and {} are bound to the same key", action.name(), other.name());
+        }
+        result
+    }
+
+    /// Returns all pairs of actions that are bound to the same binding.
+    pub fn find_duplicates(&self) -> Vec<(Action, Action)> {
+        let mut duplicates = Vec::new();
+        for (i, action) in Action::ALL.iter().enumerate() {
+            for other in &Action::ALL[i + 1..] {
+                if self.get(*action) == self.get(*other) {
+                    duplicates.push((*action, *other));
+                }
+            }
+        }
+        duplicates
+    }
+
+    pub fn get(&self, action: Action) -> Binding {
+        self.bindings[&action]
+    }
+
+    pub fn set(&mut self, action: Action, binding: Binding) {
+        self.bindings.insert(action, binding);
+    }
+
+    /// Returns true, while the action's binding is held down or the action is deflected at least halfway on the
+    /// gamepad.
+    pub fn is_pressed(&self, input: &Input, action: Action) -> bool {
+        self.get(action).is_pressed(input) || self.get_axis(input, action) >= 0.5
+    }
+
+    /// Returns how far the action is deflected in `[0, 1]`. If the action is deflected on the gamepad, its analog
+    /// value is preferred. Otherwise, it is one while the action's binding is held down.
+    /// See [`Gamepad::get_action_value`](crate::core::Gamepad::get_action_value).
+    pub fn get_axis(&self, input: &Input, action: Action) -> f32 {
+        let analog = input.get_gamepad().map_or(0.0, |gamepad| gamepad.get_action_value(action));
+        if analog > 0.0 {
+            return analog;
+        }
+        if self.get(action).is_pressed(input) { 1.0 } else { 0.0 }
+    }
+
+    /// Returns true, if the action was triggered in the last update. Keys trigger when they are released, and mouse
+    /// buttons when they are pressed.
+    pub fn was_triggered(&self, input: &Input, action: Action) -> bool {
+        self.get(action).was_triggered(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glfw::{Key, Modifiers, MouseButton, WindowEvent};
+
+    use crate::core::{Action, Binding, Input, KeyBindings};
+
+    /// Tests that remapped actions trigger on their new key, and that invalid and duplicate bindings are skipped.
+    #[test]
+    fn remap() {
+        let bindings = KeyBindings::parse("
+            # azerty layout
+            forward = Z
+            undo = w
+            left = q
+            remove = mouse-right
+            place = mouse-right
+            jump = space
+            back = umlaut
+            left = a
+        ");
+        assert_eq!(bindings.get(Action::Forward), Binding::Key(Key::Z));
+        assert_eq!(bindings.get(Action::Left), Binding::Key(Key::Q));
+        assert_eq!(bindings.get(Action::Back), Binding::Key(Key::S));
+        assert_eq!(bindings.get(Action::Undo), Binding::Key(Key::W));
+        assert_eq!(bindings.find_duplicates(), vec![(Action::Place, Action::Remove)]);
+        assert!(KeyBindings::default().find_duplicates().is_empty());
+
+        let mut input = Input::new();
+        input.handle_event(&WindowEvent::Key(Key::Z, 0, glfw::Action::Press, Modifiers::empty()));
+        input.handle_event(&WindowEvent::MouseButton(MouseButton::Button2, glfw::Action::Press, Modifiers::empty()));
+        assert!(bindings.is_pressed(&input, Action::Forward));
+        assert!(!KeyBindings::default().is_pressed(&input, Action::Forward));
+        assert!(bindings.was_triggered(&input, Action::Remove));
+
+        input.update();
+        input.handle_event(&WindowEvent::Key(Key::Z, 0, glfw::Action::Release, Modifiers::empty()));
+        assert!(!bindings.is_pressed(&input, Action::Forward));
+        assert!(bindings.was_triggered(&input, Action::Forward));
+        assert!(!bindings.was_triggered(&input, Action::Remove));
+    }
+}
@@ -0,0 +1,107 @@
+use cgmath::{InnerSpace, Vector2, Zero};
+use glfw::GamepadAxis;
+
+use crate::core::Action;
+
+/// `Gamepad` is the analog state of a connected gamepad with the dead zone already applied. Stick values are in
+/// `[-1, 1]` with positive y pointing down, trigger values are in `[0, 1]`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Gamepad {
+    pub left_stick: Vector2<f32>,
+    pub right_stick: Vector2<f32>,
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+}
+
+impl Gamepad {
+    /// `DEFAULT_DEAD_ZONE` is large enough to hide the drift of most worn sticks.
+    pub const DEFAULT_DEAD_ZONE: f32 = 0.15;
+
+    pub(super) fn from_state(state: &glfw::GamepadState, dead_zone: f32) -> Self {
+        let stick = |x, y| apply_dead_zone(Vector2::new(state.get_axis(x), state.get_axis(y)), dead_zone);
+        // triggers rest at -1
+        let trigger = |axis| apply_dead_zone(Vector2::new((state.get_axis(axis) + 1.0) * 0.5, 0.0), dead_zone).x;
+        Self {
+            left_stick: stick(GamepadAxis::AxisLeftX, GamepadAxis::AxisLeftY),
+            right_stick: stick(GamepadAxis::AxisRightX, GamepadAxis::AxisRightY),
+            left_trigger: trigger(GamepadAxis::AxisLeftTrigger),
+            right_trigger: trigger(GamepadAxis::AxisRightTrigger),
+        }
+    }
+
+    /// Returns how far the action is deflected in `[0, 1]`. The left stick moves, the right trigger ascends and the
+    /// left trigger descends. All other actions are not bound to the gamepad and return zero.
+    pub fn get_action_value(&self, action: Action) -> f32 {
+        let value = match action {
+            Action::Forward => -self.left_stick.y,
+            Action::Back => self.left_stick.y,
+            Action::Left => -self.left_stick.x,
+            Action::Right => self.left_stick.x,
+            Action::Up => self.right_trigger,
+            Action::Down => self.left_trigger,
+            _ => 0.0,
+        };
+        value.max(0.0)
+    }
+}
+
+/// Treats all deflections shorter than `dead_zone` as zero, and rescales the remaining range so that the output starts
+/// at zero on the edge of the dead zone and still reaches a length of one.
+fn apply_dead_zone(value: Vector2<f32>, dead_zone: f32) -> Vector2<f32> {
+    let length = value.magnitude();
+    if length <= dead_zone {
+        return Vector2::zero();
+    }
+    let scaled = ((length - dead_zone) / (1.0 - dead_zone)).min(1.0);
+    value * (scaled / length)
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{InnerSpace, Vector2, Zero};
+    use glfw::{Key, Modifiers, WindowEvent};
+
+    use crate::core::{Action, Input, KeyBindings};
+    use crate::core::gamepad::{apply_dead_zone, Gamepad};
+
+    /// Tests that small deflections are ignored and that the remaining range is rescaled.
+    #[test]
+    fn dead_zone() {
+        let assert_near = |value: Vector2<f32>, expected: Vector2<f32>| {
+            assert!((value - expected).magnitude() < 1e-5, "{value:?} != {expected:?}");
+        };
+        assert_near(apply_dead_zone(Vector2::new(0.1, -0.1), 0.2), Vector2::zero());
+        assert_near(apply_dead_zone(Vector2::new(0.6, 0.0), 0.2), Vector2::new(0.5, 0.0));
+        assert_near(apply_dead_zone(Vector2::new(0.0, -1.0), 0.2), Vector2::new(0.0, -1.0));
+        assert_near(apply_dead_zone(Vector2::new(0.0, 1.5), 0.2), Vector2::new(0.0, 1.0));
+        assert_near(apply_dead_zone(Vector2::new(0.3, 0.0), 0.0), Vector2::new(0.3, 0.0));
+    }
+
+    /// Tests that gamepad values are mapped to actions, and that bound keys are used when the gamepad is idle.
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn actions() {
+        let gamepad = Gamepad {
+            left_stick: Vector2::new(0.5, -1.0),
+            right_stick: Vector2::zero(),
+            left_trigger: 0.0,
+            right_trigger: 0.25,
+        };
+        assert_eq!(gamepad.get_action_value(Action::Forward), 1.0);
+        assert_eq!(gamepad.get_action_value(Action::Back), 0.0);
+        assert_eq!(gamepad.get_action_value(Action::Right), 0.5);
+        assert_eq!(gamepad.get_action_value(Action::Up), 0.25);
+        assert_eq!(gamepad.get_action_value(Action::Place), 0.0);
+
+        let bindings = KeyBindings::default();
+        let mut input = Input::new();
+        input.set_gamepad(Some(gamepad));
+        input.handle_event(&WindowEvent::Key(Key::Space, 0, glfw::Action::Press, Modifiers::empty()));
+        input.handle_event(&WindowEvent::Key(Key::A, 0, glfw::Action::Press, Modifiers::empty()));
+        assert_eq!(bindings.get_axis(&input, Action::Up), 0.25);
+        assert_eq!(bindings.get_axis(&input, Action::Left), 1.0);
+        assert!(bindings.is_pressed(&input, Action::Forward));
+        assert!(bindings.is_pressed(&input, Action::Right));
+        assert!(!bindings.is_pressed(&input, Action::Back));
+    }
+}
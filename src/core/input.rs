@@ -1,6 +1,8 @@
 use cgmath::MetricSpace;
 use rustc_hash::FxHashSet;
 
+use crate::core::Gamepad;
+
 pub struct Input {
     pressed_keys: FxHashSet<glfw::Key>,
     released_keys: FxHashSet<glfw::Key>,
@@ -13,6 +15,8 @@ pub struct Input {
     pressed_buttons: FxHashSet<glfw::MouseButton>,
     released_buttons: FxHashSet<glfw::MouseButton>,
     last_button_state: FxHashSet<glfw::MouseButton>,
+
+    gamepad: Option<Gamepad>,
 }
 
 #[allow(dead_code)]
@@ -30,6 +34,8 @@ impl Input {
             pressed_buttons: FxHashSet::default(),
             released_buttons: FxHashSet::default(),
             last_button_state: FxHashSet::default(),
+
+            gamepad: None,
         }
     }
 
@@ -43,6 +49,10 @@ impl Input {
         self.last_button_state.clone_from(&self.pressed_buttons);
     }
 
+    pub(super) fn set_gamepad(&mut self, gamepad: Option<Gamepad>) {
+        self.gamepad = gamepad;
+    }
+
     pub(super) fn handle_event(&mut self, event: &glfw::WindowEvent) {
         match *event {
             glfw::WindowEvent::Key(key, _, action, modifiers) => match action {
@@ -110,11 +120,26 @@ impl Input {
         self.released_buttons.contains(&button)
     }
 
+    /// `get_typed_chars` returns all characters that were typed since the last update.
+    pub fn get_typed_chars(&self) -> &[char] {
+        &self.char_input_buffer
+    }
+
     /// `get_mouse_delta` returns the distance the mouse has moved since the last update.
     pub fn get_mouse_delta(&self) -> cgmath::Vector2<f32> {
         self.mouse_delta
     }
 
+    /// `get_mouse_wheel_delta` returns the vertical distance the mouse wheel was scrolled since the last update.
+    pub fn get_mouse_wheel_delta(&self) -> f32 {
+        self.mouse_wheel_delta
+    }
+
+    /// `get_gamepad` returns the state of the active gamepad, if gamepad input is enabled and one is connected.
+    pub fn get_gamepad(&self) -> Option<&Gamepad> {
+        self.gamepad.as_ref()
+    }
+
     pub(super) fn apply_imgui_io(&self, io: &mut imgui::Io, forward_input_events: bool) {
         if forward_input_events {
             io.mouse_pos = [self.last_mouse_pos.x, self.last_mouse_pos.y];
@@ -1,8 +1,12 @@
 mod imgui;
 mod input;
+mod bindings;
+mod gamepad;
 mod window;
 pub mod assets;
 mod imgui_opengl;
 
 pub use window::*;
 pub use input::*;
+pub use bindings::*;
+pub use gamepad::*;
@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::ffi::{c_void, CStr};
 use std::sync::{mpsc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
@@ -7,7 +8,7 @@ use glfw::{Context, SwapInterval};
 use once_cell::sync::Lazy;
 
 use crate::core::imgui as imgui_wrapper;
-use crate::core::Input;
+use crate::core::{Gamepad, Input};
 
 pub struct Config {
     pub width: u32,
@@ -18,6 +19,8 @@ pub struct Config {
     pub resizable: bool,
     pub buffering: Buffering,
     pub target_fps: Option<u32>,
+    /// `gamepad_dead_zone` enables gamepad input, if set, and applies the dead zone to its sticks and triggers.
+    pub gamepad_dead_zone: Option<f32>,
 }
 
 #[derive(Default)]
@@ -34,18 +37,145 @@ pub enum Buffering {
 pub struct GlContext {
     window: glfw::Window,
     events: mpsc::Receiver<(f64, glfw::WindowEvent)>,
+    capabilities: GlCapabilities,
+    vsync: bool,
+    debug_severity: Box<DebugSeverity>,
+}
+
+/// `GlCapabilities` describes the OpenGL context that was actually created. Drivers that do not
+/// support the preferred version get a lower one, in which case compute shaders might not be
+/// available.
+#[derive(Clone, Debug)]
+pub struct GlCapabilities {
+    /// `version` is the (major, minor) OpenGL version of the context.
+    pub version: (i32, i32),
+    /// `compute_supported` is true, if compute shaders and shader storage buffers are available.
+    pub compute_supported: bool,
+    /// `software_rendering` is true, if the driver reports a known software rasterizer.
+    pub software_rendering: bool,
+    /// `max_ssbo_size` is the maximum size of a shader storage block in bytes, or 0 if
+    /// unsupported.
+    pub max_ssbo_size: i64,
+    pub vendor: String,
+    pub renderer: String,
+}
+
+impl GlCapabilities {
+    /// Queries the capabilities of the currently bound context.
+    fn query(context: &glfw::Glfw) -> Self {
+        let mut version = (0, 0);
+        let mut max_ssbo_size = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAJOR_VERSION, &mut version.0);
+            gl::GetIntegerv(gl::MINOR_VERSION, &mut version.1);
+        }
+
+        let compute_supported = version >= (4, 3) || context.extension_supported("GL_ARB_compute_shader");
+        if compute_supported {
+            unsafe { gl::GetInteger64v(gl::MAX_SHADER_STORAGE_BLOCK_SIZE, &mut max_ssbo_size); }
+        }
+
+        let renderer = get_gl_string(gl::RENDERER);
+        let software_rendering = ["llvmpipe", "softpipe", "SwiftShader", "Software Rasterizer"]
+            .iter()
+            .any(|name| renderer.contains(name));
+
+        Self {
+            version,
+            compute_supported,
+            software_rendering,
+            max_ssbo_size,
+            vendor: get_gl_string(gl::VENDOR),
+            renderer,
+        }
+    }
+
+    /// Returns true, if the game is expected to run poorly or not at all with this context.
+    pub fn is_limited(&self) -> bool {
+        !self.compute_supported || self.software_rendering
+    }
+}
+
+/// `DebugSeverity` is the severity of OpenGL debug messages, ordered from least to most severe.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugSeverity {
+    Notification,
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+impl DebugSeverity {
+    fn from_gl(severity: gl::types::GLenum) -> Self {
+        match severity {
+            gl::DEBUG_SEVERITY_HIGH => Self::High,
+            gl::DEBUG_SEVERITY_MEDIUM => Self::Medium,
+            gl::DEBUG_SEVERITY_LOW => Self::Low,
+            _ => Self::Notification,
+        }
+    }
+}
+
+/// Prints all debug messages that are at least as severe as the [`DebugSeverity`] passed as
+/// `user_param`.
+extern "system" fn gl_debug_callback(
+    _source: gl::types::GLenum,
+    _type: gl::types::GLenum,
+    id: gl::types::GLuint,
+    severity: gl::types::GLenum,
+    _length: gl::types::GLsizei,
+    message: *const gl::types::GLchar,
+    user_param: *mut c_void,
+) {
+    let severity = DebugSeverity::from_gl(severity);
+    let min_severity = unsafe { *user_param.cast::<DebugSeverity>() };
+    if severity < min_severity {
+        return;
+    }
+
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+    println!("gl debug [{severity:?}] {id}: {message}");
+}
+
+fn get_gl_string(name: gl::types::GLenum) -> String {
+    unsafe {
+        let ptr = gl::GetString(name);
+        if ptr.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(ptr.cast()).to_string_lossy().into_owned()
+    }
 }
 
 /// True if `GL_ARB_texture_filter_anisotropic` extension is loaded.
 pub static mut SUPPORTS_GL_ARB_TEXTURE_FILTER_ANISOTROPIC: bool = false;
 
+/// `SPIN_THRESHOLD` is the remaining time until a deadline, below which [`wait_until`] busy-waits instead of sleeping.
+const SPIN_THRESHOLD: Duration = Duration::from_millis(2);
+
+/// Blocks the current thread until `deadline`. The thread sleeps until shortly before the deadline and then busy-waits,
+/// as sleeping alone overshoots by up to a millisecond or more on most platforms.
+fn wait_until(deadline: Instant) {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining > SPIN_THRESHOLD {
+        thread::sleep(remaining - SPIN_THRESHOLD);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+/// OpenGL versions to try in order when creating a context. 4.3 is the minimum version that
+/// supports compute shaders, 3.3 allows to at least report the limitation on older drivers.
+const GL_CONTEXT_VERSIONS: [(u32, u32); 3] = [(4, 5), (4, 3), (3, 3)];
+
 // GLFW_CONTEXT is represented as a singleton because it can only be created once per process.
 // To allow multiple tests to initialise windows, for graphical testing in parallel, this is
 // the only viable option of sharing this state without adding a custom test execution framework
 // on top of rust's inbuilt one.
 static GLFW_CONTEXT: Lazy<Mutex<glfw::Glfw>> = Lazy::new(|| {
     let mut context = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
-    context.window_hint(glfw::WindowHint::ContextVersion(4, 5));
     context.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
     Mutex::new(context)
 });
@@ -61,14 +191,22 @@ impl GlContext {
         context.window_hint(glfw::WindowHint::Visible(!cfg.headless));
         context.window_hint(glfw::WindowHint::Resizable(cfg.resizable));
 
-        let (mut window, events) = context
-            .create_window(cfg.width, cfg.height, cfg.title, glfw::WindowMode::Windowed)
-            .expect("failed to create window");
+        // failing to create a context is expected for unsupported versions, so errors are only logged while trying
+        context.set_error_callback(glfw::LOG_ERRORS);
+        let window = GL_CONTEXT_VERSIONS.iter()
+            .find_map(|&(major, minor)| {
+                context.window_hint(glfw::WindowHint::ContextVersion(major, minor));
+                context.create_window(cfg.width, cfg.height, cfg.title, glfw::WindowMode::Windowed)
+            });
+        context.set_error_callback(glfw::FAIL_ON_ERRORS);
+        let (mut window, events) = window.expect("failed to create window");
 
         window.make_current();
 
         gl::load_with(|symbol| window.get_proc_address(symbol).cast());
 
+        let capabilities = GlCapabilities::query(&context);
+
         context.set_swap_interval(match cfg.buffering {
             Buffering::Single => SwapInterval::None,
             Buffering::Double => SwapInterval::Sync(1),
@@ -90,7 +228,49 @@ impl GlContext {
             }
         }
 
-        Self { window, events }
+        let vsync = !matches!(cfg.buffering, Buffering::Single);
+        Self { window, events, capabilities, vsync, debug_severity: Box::<DebugSeverity>::default() }
+    }
+
+    pub fn get_capabilities(&self) -> &GlCapabilities {
+        &self.capabilities
+    }
+
+    pub fn get_vsync(&self) -> bool {
+        self.vsync
+    }
+
+    /// Installs a debug message callback that prints all driver messages with at least
+    /// `min_severity`. Notifications are very verbose and should only be enabled when needed.
+    /// Does nothing if `GL_KHR_debug` is not supported.
+    pub fn enable_debug_output(&mut self, min_severity: DebugSeverity) {
+        let supported = self.capabilities.version >= (4, 3) || GLFW_CONTEXT.lock().unwrap().extension_supported("GL_KHR_debug");
+        if !supported {
+            println!("warning: GL_KHR_debug is not supported, debug output stays disabled");
+            return;
+        }
+
+        // the callback reads the severity through a pointer, so it has to stay at a stable address
+        *self.debug_severity = min_severity;
+
+        self.window.make_current();
+        unsafe {
+            gl::Enable(gl::DEBUG_OUTPUT);
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+            gl::DebugMessageCallback(Some(gl_debug_callback), std::ptr::addr_of!(*self.debug_severity).cast());
+
+            // notifications are filtered by the driver, as they are by far the most frequent messages
+            let notifications = gl::types::GLboolean::from(min_severity == DebugSeverity::Notification);
+            gl::DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl::DEBUG_SEVERITY_NOTIFICATION, 0, std::ptr::null(), notifications);
+        }
+    }
+
+    /// Enables or disables waiting for the vertical blank before swapping buffers. Disabling it
+    /// uncaps the frame rate, e.g. for benchmarking.
+    pub fn set_vsync(&mut self, enabled: bool) {
+        self.window.make_current();
+        GLFW_CONTEXT.lock().unwrap().set_swap_interval(if enabled { SwapInterval::Sync(1) } else { SwapInterval::None });
+        self.vsync = enabled;
     }
 
     pub fn new_headless(width: u32, height: u32) -> Self {
@@ -103,6 +283,7 @@ impl GlContext {
             resizable: false,
             buffering: Buffering::Single,
             target_fps: None,
+            gamepad_dead_zone: None,
         })
     }
 }
@@ -113,6 +294,8 @@ pub struct Window {
     context: RefCell<GlContext>,
     imgui: imgui_wrapper::Wrapper,
     target_fps: Option<u32>,
+    gamepad_dead_zone: Option<f32>,
+    gamepad_id: Option<glfw::JoystickId>,
 
     current_stats: FrameStats,
     is_cursor_grabbed: bool,
@@ -147,6 +330,8 @@ impl Window {
             context: RefCell::new(context),
             imgui,
             target_fps,
+            gamepad_dead_zone: cfg.gamepad_dead_zone,
+            gamepad_id: None,
             current_stats: FrameStats {
                 last_frame: Instant::now(),
                 last_measurement: Instant::now(),
@@ -214,12 +399,8 @@ impl Window {
 
         // if enabled, limit fps to target
         if let Some(target) = self.target_fps {
-            let target_delta = 1.0 / f64::from(target);
-            let actual_delta = self.current_stats.last_frame.elapsed().as_secs_f64();
-            let diff = target_delta - actual_delta;
-            if diff > 0.0 {
-                thread::sleep(Duration::from_secs_f64(diff));
-            }
+            let target_delta = Duration::from_secs_f64(1.0 / f64::from(target.max(1)));
+            wait_until(self.current_stats.last_frame + target_delta);
         }
     }
 
@@ -256,6 +437,7 @@ impl Window {
                 _ => self.input.handle_event(&event),
             }
         }
+        self.poll_gamepad();
 
         let size = self.get_size();
         let io = self.imgui.context.io_mut();
@@ -267,6 +449,37 @@ impl Window {
         (size, was_resized)
     }
 
+    /// Passes the state of the active gamepad to the input. The previous gamepad stays active as long as it is
+    /// connected. Otherwise, the first connected gamepad is used, so that gamepads can be plugged in at any time.
+    fn poll_gamepad(&mut self) {
+        let Some(dead_zone) = self.gamepad_dead_zone else {
+            return;
+        };
+
+        let context = GLFW_CONTEXT.lock().unwrap();
+        let joystick = self.gamepad_id
+            .map(|id| context.get_joystick(id))
+            .filter(glfw::Joystick::is_gamepad)
+            .or_else(|| (0..16)
+                .filter_map(glfw::JoystickId::from_i32)
+                .map(|id| context.get_joystick(id))
+                .find(glfw::Joystick::is_gamepad));
+
+        let id = joystick.as_ref().map(|joystick| joystick.id);
+        if id != self.gamepad_id {
+            match &joystick {
+                Some(joystick) => println!("using gamepad: {}", joystick.get_gamepad_name().unwrap_or_default()),
+                None => println!("gamepad disconnected"),
+            }
+            self.gamepad_id = id;
+        }
+
+        let gamepad = joystick
+            .and_then(|joystick| joystick.get_gamepad_state())
+            .map(|state| Gamepad::from_state(&state, dead_zone));
+        self.input.set_gamepad(gamepad);
+    }
+
     /// `should_close` returns true, if a close was requested on this window.
     pub fn should_close(&self) -> bool {
         self.context.borrow_mut().window.should_close()
@@ -297,6 +510,25 @@ impl Window {
         let (w, h) = self.get_size();
         w as f32 / h as f32
     }
+
+    pub fn get_vsync(&self) -> bool {
+        self.context.borrow().get_vsync()
+    }
+
+    /// Calls [`GlContext::enable_debug_output`].
+    pub fn enable_debug_output(&mut self, min_severity: DebugSeverity) {
+        self.context.borrow_mut().enable_debug_output(min_severity);
+    }
+
+    /// Calls [`GlContext::set_vsync`].
+    pub fn set_vsync(&mut self, enabled: bool) {
+        self.context.borrow_mut().set_vsync(enabled);
+    }
+
+    /// `get_capabilities` returns the capabilities of the underlying OpenGL context.
+    pub fn get_capabilities(&self) -> GlCapabilities {
+        self.context.borrow().capabilities.clone()
+    }
 }
 
 pub struct Frame<'window> {
@@ -329,3 +561,63 @@ impl<'window> Frame<'window> {
         w as f32 / h as f32
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::core::{DebugSeverity, GlContext};
+    use crate::core::window::wait_until;
+
+    /// Tests that the capabilities of the created context are queried.
+    #[test]
+    fn capabilities() {
+        let context = GlContext::new_headless(1, 1);
+        let capabilities = context.get_capabilities();
+
+        assert!(capabilities.version >= (3, 3));
+        assert!(!capabilities.vendor.is_empty());
+        assert!(!capabilities.renderer.is_empty());
+        assert!(capabilities.compute_supported);
+        assert!(capabilities.max_ssbo_size > 0);
+    }
+
+    /// Tests that the vsync state is derived from the buffering config and can be toggled.
+    #[test]
+    fn vsync() {
+        let mut context = GlContext::new_headless(1, 1);
+        assert!(!context.get_vsync());
+
+        context.set_vsync(true);
+        assert!(context.get_vsync());
+
+        context.set_vsync(false);
+        assert!(!context.get_vsync());
+    }
+
+    /// Tests that enabling the debug output installs the callback without raising errors.
+    #[test]
+    fn enable_debug_output() {
+        let mut context = GlContext::new_headless(1, 1);
+        context.enable_debug_output(DebugSeverity::High);
+
+        unsafe {
+            assert_eq!(gl::IsEnabled(gl::DEBUG_OUTPUT), gl::TRUE);
+            assert_eq!(gl::GetError(), gl::NO_ERROR);
+        }
+    }
+
+    /// Tests that waiting does not return before the deadline and returns immediately if it has already passed.
+    #[test]
+    fn wait_until_deadline() {
+        let start = Instant::now();
+        wait_until(start + Duration::from_millis(5));
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(5));
+        assert!(elapsed < Duration::from_millis(50), "overshot by {elapsed:?}");
+
+        let start = Instant::now();
+        wait_until(start.checked_sub(Duration::from_millis(5)).unwrap());
+        assert!(start.elapsed() < Duration::from_millis(5));
+    }
+}
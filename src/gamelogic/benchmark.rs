@@ -0,0 +1,742 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+#[cfg(feature = "profiling")]
+use std::time::Instant;
+use std::time::Duration;
+
+use cgmath::{Point3, Vector3};
+
+use crate::world::memory;
+
+/// `Benchmark` collects timing samples per named section, e.g. for every frame, and summarizes them once the game is
+/// closed. Summaries can be printed or exported as JSON or CSV to track performance across runs.
+pub struct Benchmark {
+    sections: Vec<Section>,
+    frames: FrameTimes,
+}
+
+/// `Section` keeps running statistics over all samples of a section, while percentiles are calculated from the most
+/// recent samples only, so that memory usage does not grow with the duration of a run.
+struct Section {
+    name: &'static str,
+    count: usize,
+    min: Duration,
+    max: Duration,
+    total: Duration,
+    samples: FrameTimes,
+}
+
+impl Section {
+    fn new(name: &'static str, capacity: usize) -> Self {
+        Self {
+            name,
+            count: 0,
+            min: Duration::MAX,
+            max: Duration::ZERO,
+            total: Duration::ZERO,
+            samples: FrameTimes::new(capacity),
+        }
+    }
+
+    fn add(&mut self, sample: Duration) {
+        self.count += 1;
+        self.min = self.min.min(sample);
+        self.max = self.max.max(sample);
+        self.total += sample;
+        self.samples.push(sample);
+    }
+}
+
+/// `SectionSummary` contains the statistics of all samples of a section. Percentiles only consider the most recent
+/// samples, see [`Benchmark::SECTION_CAPACITY`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SectionSummary {
+    pub name: &'static str,
+    pub count: usize,
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+/// `Percentiles` describes the distribution of frame times. Unlike averages, high percentiles reveal stutter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Percentiles {
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+/// `FrameTimes` keeps the durations of the most recent frames in a fixed-capacity ring buffer. All memory is allocated
+/// upfront, so that recording frames does not affect the allocation statistics.
+pub struct FrameTimes {
+    samples: Box<[Duration]>,
+    len: usize,
+    next: usize,
+    // sorted is scratch space for calculating percentiles without allocating
+    sorted: RefCell<Box<[Duration]>>,
+}
+
+impl FrameTimes {
+    /// Creates a new ring buffer that retains the last `capacity` frames. A capacity of 0 is treated as 1.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            samples: vec![Duration::ZERO; capacity].into_boxed_slice(),
+            len: 0,
+            next: 0,
+            sorted: RefCell::new(vec![Duration::ZERO; capacity].into_boxed_slice()),
+        }
+    }
+
+    /// Adds a frame, replacing the oldest one if the buffer is full.
+    pub fn push(&mut self, frame_time: Duration) {
+        self.samples[self.next] = frame_time;
+        self.next = (self.next + 1) % self.samples.len();
+        self.len = (self.len + 1).min(self.samples.len());
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the percentiles of all retained frames, or zero durations if no frame was recorded yet.
+    pub fn percentiles(&self) -> Percentiles {
+        if self.is_empty() {
+            return Percentiles::default();
+        }
+
+        let mut sorted = self.sorted.borrow_mut();
+        let sorted = &mut sorted[..self.len];
+        sorted.copy_from_slice(&self.samples[..self.len]);
+        sorted.sort_unstable();
+
+        Percentiles {
+            p50: percentile(sorted, 50),
+            p95: percentile(sorted, 95),
+            p99: percentile(sorted, 99),
+        }
+    }
+
+    /// Renders a text histogram of all retained frames with `bucket_count` equally sized buckets between the fastest
+    /// and slowest frame. Bars are scaled so that the largest bucket is `width` characters wide.
+    pub fn histogram(&self, bucket_count: usize, width: usize) -> String {
+        let samples = &self.samples[..self.len];
+        let (Some(min), Some(max)) = (samples.iter().min(), samples.iter().max()) else {
+            return String::new();
+        };
+        let bucket_count = bucket_count.max(1);
+        let bucket_size = (*max - *min) / bucket_count as u32;
+
+        let mut counts = vec![0usize; bucket_count];
+        for sample in samples {
+            let index = (*sample - *min).as_nanos().checked_div(bucket_size.as_nanos()).unwrap_or(0) as usize;
+            counts[index.min(bucket_count - 1)] += 1;
+        }
+
+        let max_count = counts.iter().copied().max().unwrap_or(0).max(1);
+        let mut result = String::new();
+        for (i, count) in counts.iter().enumerate() {
+            let lower = *min + bucket_size * i as u32;
+            let upper = if i == bucket_count - 1 { *max } else { lower + bucket_size };
+            let bar = "#".repeat(count * width / max_count);
+            let _ = writeln!(result, "{:>8.3} - {:>8.3}ms | {bar} {count}", ms(lower), ms(upper));
+        }
+        result
+    }
+}
+
+impl Benchmark {
+    /// `FRAME_CAPACITY` is the number of most recent frames used for frame time percentiles.
+    const FRAME_CAPACITY: usize = 4096;
+    /// `SECTION_CAPACITY` is the number of most recent samples per section used for section percentiles.
+    pub const SECTION_CAPACITY: usize = 4096;
+
+    pub fn new() -> Self {
+        Self { sections: Vec::new(), frames: FrameTimes::new(Self::FRAME_CAPACITY) }
+    }
+
+    /// Records the duration of a whole frame as `frame` section and for frame time percentiles.
+    pub fn record_frame(&mut self, frame_time: Duration) {
+        self.record("frame", frame_time);
+        self.frames.push(frame_time);
+    }
+
+    /// Returns the frame time percentiles of the most recent frames.
+    pub fn frame_percentiles(&self) -> Percentiles {
+        self.frames.percentiles()
+    }
+
+    /// Returns the number of frames per second over all recorded frames, or `None` if no frame was recorded yet. If the
+    /// recorded frame times include waiting for vsync or a frame rate limit, this is the frame rate that was achieved.
+    pub fn effective_fps(&self) -> Option<f32> {
+        let frames = self.sections.iter().find(|s| s.name == "frame")?;
+        (!frames.total.is_zero()).then(|| frames.count as f32 / frames.total.as_secs_f32())
+    }
+
+    /// Adds a timing sample to `section`. Sections are reported in the order they were first recorded.
+    pub fn record(&mut self, section: &'static str, sample: Duration) {
+        if let Some(section) = self.sections.iter_mut().find(|s| s.name == section) {
+            section.add(sample);
+            return;
+        }
+        let mut new_section = Section::new(section, Self::SECTION_CAPACITY);
+        new_section.add(sample);
+        self.sections.push(new_section);
+    }
+
+    pub fn summarize(&self) -> Vec<SectionSummary> {
+        self.sections.iter().map(|section| {
+            let percentiles = section.samples.percentiles();
+            SectionSummary {
+                name: section.name,
+                count: section.count,
+                min: section.min,
+                max: section.max,
+                mean: section.total / section.count as u32,
+                p50: percentiles.p50,
+                p95: percentiles.p95,
+                p99: percentiles.p99,
+            }
+        }).collect()
+    }
+
+    /// Prints a summary of all sections to stdout, followed by the percentiles and a histogram of the most recent
+    /// frame times.
+    pub fn print(&self) {
+        println!("benchmark results (ms):");
+        println!("{:<16} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}", "section", "count", "min", "max", "mean", "p50", "p95", "p99");
+        for s in self.summarize() {
+            println!(
+                "{:<16} {:>8} {:>8.3} {:>8.3} {:>8.3} {:>8.3} {:>8.3} {:>8.3}",
+                s.name, s.count, ms(s.min), ms(s.max), ms(s.mean), ms(s.p50), ms(s.p95), ms(s.p99),
+            );
+        }
+
+        if !self.frames.is_empty() {
+            let p = self.frame_percentiles();
+            println!();
+            println!(
+                "frame times of the last {} frames: p50 {:.3}ms, p95 {:.3}ms, p99 {:.3}ms",
+                self.frames.len(), ms(p.p50), ms(p.p95), ms(p.p99),
+            );
+            print!("{}", self.frames.histogram(10, 40));
+        }
+        if let Some(fps) = self.effective_fps() {
+            println!("effective fps: {fps:.1}");
+        }
+
+        let subsystems = memory::labeled_allocated_bytes();
+        if !subsystems.is_empty() {
+            println!();
+            println!("memory by subsystem (MB):");
+            for (label, bytes) in subsystems {
+                println!("{label:<16} {:>10.3}", bytes as f32 / 1024.0 / 1024.0);
+            }
+        }
+
+        let spans = span_stats();
+        if !spans.is_empty() {
+            println!();
+            println!("spans (ms):");
+            println!("{:<32} {:>8} {:>10} {:>8}", "span", "count", "total", "mean");
+            for s in spans {
+                let name = s.path.rsplit('/').next().unwrap_or_default();
+                println!(
+                    "{:<32} {:>8} {:>10.3} {:>8.3}",
+                    format!("{}{name}", "  ".repeat(s.depth)), s.count, ms(s.total), ms(s.total) / s.count as f32,
+                );
+            }
+        }
+    }
+
+    /// Writes the summary of all sections as JSON to `path`. All times are in milliseconds. `allocated_bytes` should be
+    /// the amount of memory that is allocated at the time of the export.
+    pub fn export_json<P: AsRef<Path>>(&self, path: P, allocated_bytes: usize) -> io::Result<()> {
+        let mut json = format!("{{\n  \"allocated_bytes\": {allocated_bytes},\n  \"sections\": [");
+        for (i, s) in self.summarize().iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            let _ = write!(
+                json,
+                "{separator}\n    {{\"name\": \"{}\", \"count\": {}, \"min_ms\": {:.4}, \"max_ms\": {:.4}, \"mean_ms\": {:.4}, \"p50_ms\": {:.4}, \"p95_ms\": {:.4}, \"p99_ms\": {:.4}}}",
+                s.name.escape_default(), s.count, ms(s.min), ms(s.max), ms(s.mean), ms(s.p50), ms(s.p95), ms(s.p99),
+            );
+        }
+        json.push_str("\n  ]\n}\n");
+        fs::write(path, json)
+    }
+
+    /// Writes the summary of all sections as CSV to `path`, one row per section. All times are in milliseconds.
+    /// `allocated_bytes` is repeated in every row, so that each row is self-contained.
+    pub fn export_csv<P: AsRef<Path>>(&self, path: P, allocated_bytes: usize) -> io::Result<()> {
+        let mut csv = String::from("section,count,min_ms,max_ms,mean_ms,p50_ms,p95_ms,p99_ms,allocated_bytes\n");
+        for s in self.summarize() {
+            let _ = writeln!(
+                csv,
+                "{},{},{:.4},{:.4},{:.4},{:.4},{:.4},{:.4},{allocated_bytes}",
+                s.name, s.count, ms(s.min), ms(s.max), ms(s.mean), ms(s.p50), ms(s.p95), ms(s.p99),
+            );
+        }
+        fs::write(path, csv)
+    }
+
+    /// Calls [`Benchmark::export_csv`] if `path` has a `.csv` extension, and [`Benchmark::export_json`] otherwise.
+    pub fn export<P: AsRef<Path>>(&self, path: P, allocated_bytes: usize) -> io::Result<()> {
+        let is_csv = path.as_ref().extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        if is_csv {
+            self.export_csv(path, allocated_bytes)
+        } else {
+            self.export_json(path, allocated_bytes)
+        }
+    }
+}
+
+/// `SpanStats` contains the accumulated time of all spans with the same name and parent span.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpanStats {
+    /// `path` contains the names of all parent spans and the span itself, separated by `/`.
+    pub path: String,
+    /// `depth` is the number of parent spans.
+    pub depth: usize,
+    pub count: usize,
+    pub total: Duration,
+}
+
+/// `SpanGuard` ends its span when dropped. Without the `profiling` feature, it is zero-sized and does nothing.
+#[must_use = "the span ends as soon as the guard is dropped"]
+pub struct SpanGuard {
+    #[cfg(feature = "profiling")]
+    start: Instant,
+}
+
+/// Starts a named profiling span that lasts until the returned guard is dropped. Spans that are started while
+/// another span is active are nested into it. All spans with the same name and parent are accumulated and included
+/// in [`Benchmark::print`].
+///
+/// Spans are only recorded if the `profiling` feature is enabled, and only per thread. The aggregation of the calling
+/// thread is returned by [`span_stats`].
+#[inline]
+pub fn span(name: &str) -> SpanGuard {
+    #[cfg(feature = "profiling")]
+    {
+        SPANS.with_borrow_mut(|recorder| recorder.enter(name));
+        SpanGuard { start: Instant::now() }
+    }
+    #[cfg(not(feature = "profiling"))]
+    {
+        let _ = name;
+        SpanGuard {}
+    }
+}
+
+/// Returns the accumulated spans of the calling thread in depth-first order.
+pub fn span_stats() -> Vec<SpanStats> {
+    #[cfg(feature = "profiling")]
+    {
+        SPANS.with_borrow(SpanRecorder::stats)
+    }
+    #[cfg(not(feature = "profiling"))]
+    {
+        Vec::new()
+    }
+}
+
+#[cfg(feature = "profiling")]
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        SPANS.with_borrow_mut(|recorder| recorder.exit(elapsed));
+    }
+}
+
+#[cfg(feature = "profiling")]
+thread_local! {
+    static SPANS: RefCell<SpanRecorder> = const { RefCell::new(SpanRecorder::new()) };
+}
+
+/// `SpanRecorder` stores all spans as a tree. `stack` contains the indices of all currently active spans.
+#[cfg(feature = "profiling")]
+struct SpanRecorder {
+    spans: Vec<RecordedSpan>,
+    stack: Vec<usize>,
+}
+
+#[cfg(feature = "profiling")]
+struct RecordedSpan {
+    name: String,
+    parent: Option<usize>,
+    count: usize,
+    total: Duration,
+}
+
+#[cfg(feature = "profiling")]
+impl SpanRecorder {
+    const fn new() -> Self {
+        Self { spans: Vec::new(), stack: Vec::new() }
+    }
+
+    fn enter(&mut self, name: &str) {
+        let parent = self.stack.last().copied();
+        let index = self.spans.iter()
+            .position(|span| span.parent == parent && span.name == name)
+            .unwrap_or_else(|| {
+                self.spans.push(RecordedSpan { name: name.to_string(), parent, count: 0, total: Duration::ZERO });
+                self.spans.len() - 1
+            });
+        self.stack.push(index);
+    }
+
+    fn exit(&mut self, elapsed: Duration) {
+        if let Some(index) = self.stack.pop() {
+            let span = &mut self.spans[index];
+            span.count += 1;
+            span.total += elapsed;
+        }
+    }
+
+    fn stats(&self) -> Vec<SpanStats> {
+        let mut result = Vec::with_capacity(self.spans.len());
+        self.collect_stats(None, "", 0, &mut result);
+        result
+    }
+
+    fn collect_stats(&self, parent: Option<usize>, prefix: &str, depth: usize, result: &mut Vec<SpanStats>) {
+        for (index, span) in self.spans.iter().enumerate().filter(|(_, span)| span.parent == parent) {
+            let path = if prefix.is_empty() { span.name.clone() } else { format!("{prefix}/{}", span.name) };
+            result.push(SpanStats { path: path.clone(), depth, count: span.count, total: span.total });
+            self.collect_stats(Some(index), &path, depth + 1, result);
+        }
+    }
+}
+
+/// `CameraKeyframe` is a single point on a [`CameraPath`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraKeyframe {
+    pub position: Point3<f32>,
+    /// `euler_rotation` uses the same convention as [`crate::systems::physics::Entity::euler_rotation`] in radians.
+    pub euler_rotation: Vector3<f32>,
+}
+
+impl fmt::Display for CameraKeyframe {
+    /// Formats the keyframe as a line of the format described in [`CameraPath::load`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (p, r) = (self.position, self.euler_rotation);
+        write!(f, "{} {} {} {} {}", p.x, p.y, p.z, r.x.to_degrees(), r.y.to_degrees())
+    }
+}
+
+#[derive(Debug)]
+pub enum CameraPathError {
+    Io(io::Error),
+    /// `InvalidKeyframe` contains the 1-based line number of a keyframe that could not be parsed.
+    InvalidKeyframe(usize),
+    Empty,
+}
+
+impl From<io::Error> for CameraPathError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// `CameraPath` describes a camera movement through evenly spaced keyframes, between which the camera is linearly
+/// interpolated. It is used to render the same frames in every benchmark run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    /// Creates a path from the given keyframes. At least one keyframe is required.
+    pub fn new(keyframes: Vec<CameraKeyframe>) -> Result<Self, CameraPathError> {
+        if keyframes.is_empty() {
+            return Err(CameraPathError::Empty);
+        }
+        Ok(Self { keyframes })
+    }
+
+    /// Loads a path from a text file with one keyframe per line in the form of `x y z pitch yaw`. Angles are in
+    /// degrees. Empty lines and lines starting with `#` are ignored.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CameraPathError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses a path in the format described in [`CameraPath::load`].
+    pub fn parse(src: &str) -> Result<Self, CameraPathError> {
+        let mut keyframes = Vec::new();
+        for (i, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let values: Vec<f32> = line.split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| CameraPathError::InvalidKeyframe(i + 1))?;
+            let [x, y, z, pitch, yaw] = values[..] else {
+                return Err(CameraPathError::InvalidKeyframe(i + 1));
+            };
+            keyframes.push(CameraKeyframe {
+                position: Point3::new(x, y, z),
+                euler_rotation: Vector3::new(pitch.to_radians(), yaw.to_radians(), 0.0),
+            });
+        }
+        Self::new(keyframes)
+    }
+
+    /// Returns the interpolated keyframe at `t` within \[0;1\], where 0 is the first and 1 the last keyframe.
+    pub fn sample(&self, t: f32) -> CameraKeyframe {
+        let segments = self.keyframes.len() - 1;
+        if segments == 0 {
+            return self.keyframes[0];
+        }
+
+        let f = t.clamp(0.0, 1.0) * segments as f32;
+        let index = (f as usize).min(segments - 1);
+        let (a, b) = (self.keyframes[index], self.keyframes[index + 1]);
+        let local = f - index as f32;
+        CameraKeyframe {
+            position: a.position + (b.position - a.position) * local,
+            euler_rotation: a.euler_rotation + (b.euler_rotation - a.euler_rotation) * local,
+        }
+    }
+}
+
+/// Returns the nearest-rank percentile `p` within \[0;100\] of the non-empty, sorted `samples`.
+fn percentile(samples: &[Duration], p: usize) -> Duration {
+    let rank = (p * samples.len()).div_ceil(100);
+    samples[rank.clamp(1, samples.len()) - 1]
+}
+
+fn ms(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use cgmath::{Point3, Vector3};
+    use indoc::indoc;
+
+    use crate::gamelogic::benchmark::{Benchmark, CameraKeyframe, CameraPath, CameraPathError, FrameTimes, Percentiles, SectionSummary};
+    use crate::graphics::macros::assert_vec3_eq;
+
+    fn create_benchmark() -> Benchmark {
+        let mut benchmark = Benchmark::new();
+        for i in (1..=100).rev() {
+            benchmark.record("frame", Duration::from_millis(i));
+        }
+        benchmark.record("render", Duration::from_millis(2));
+        benchmark
+    }
+
+    /// Tests that samples are summarized per section in the order they were first recorded.
+    #[test]
+    fn summarize() {
+        assert_eq!(create_benchmark().summarize(), vec![
+            SectionSummary {
+                name: "frame",
+                count: 100,
+                min: Duration::from_millis(1),
+                max: Duration::from_millis(100),
+                mean: Duration::from_micros(50500),
+                p50: Duration::from_millis(50),
+                p95: Duration::from_millis(95),
+                p99: Duration::from_millis(99),
+            },
+            SectionSummary {
+                name: "render",
+                count: 1,
+                min: Duration::from_millis(2),
+                max: Duration::from_millis(2),
+                mean: Duration::from_millis(2),
+                p50: Duration::from_millis(2),
+                p95: Duration::from_millis(2),
+                p99: Duration::from_millis(2),
+            },
+        ]);
+    }
+
+    /// Tests that the export format is chosen by the file extension.
+    #[test]
+    fn export() {
+        let dir = tempfile::tempdir().unwrap();
+        let benchmark = create_benchmark();
+
+        let json_path = dir.path().join("out.json");
+        benchmark.export(&json_path, 1024).unwrap();
+        assert_eq!(fs::read_to_string(&json_path).unwrap(), indoc! {r#"
+            {
+              "allocated_bytes": 1024,
+              "sections": [
+                {"name": "frame", "count": 100, "min_ms": 1.0000, "max_ms": 100.0000, "mean_ms": 50.5000, "p50_ms": 50.0000, "p95_ms": 95.0000, "p99_ms": 99.0000},
+                {"name": "render", "count": 1, "min_ms": 2.0000, "max_ms": 2.0000, "mean_ms": 2.0000, "p50_ms": 2.0000, "p95_ms": 2.0000, "p99_ms": 2.0000}
+              ]
+            }
+        "#});
+
+        let csv_path = dir.path().join("out.CSV");
+        benchmark.export(&csv_path, 1024).unwrap();
+        assert_eq!(fs::read_to_string(&csv_path).unwrap(), indoc! {"
+            section,count,min_ms,max_ms,mean_ms,p50_ms,p95_ms,p99_ms,allocated_bytes
+            frame,100,1.0000,100.0000,50.5000,50.0000,95.0000,99.0000,1024
+            render,1,2.0000,2.0000,2.0000,2.0000,2.0000,2.0000,1024
+        "});
+    }
+
+    /// Tests that the ring buffer only retains the most recent frames and calculates their percentiles.
+    #[test]
+    fn frame_times_percentiles() {
+        let mut frames = FrameTimes::new(100);
+        assert!(frames.is_empty());
+        assert_eq!(frames.percentiles(), Percentiles::default());
+
+        // the first 50 frames are overwritten
+        for i in 1..=150 {
+            frames.push(Duration::from_millis(if i <= 50 { 1000 } else { i - 50 }));
+        }
+        assert_eq!(frames.len(), 100);
+        assert_eq!(frames.percentiles(), Percentiles {
+            p50: Duration::from_millis(50),
+            p95: Duration::from_millis(95),
+            p99: Duration::from_millis(99),
+        });
+
+        let mut benchmark = Benchmark::new();
+        benchmark.record_frame(Duration::from_millis(16));
+        assert_eq!(benchmark.frame_percentiles().p99, Duration::from_millis(16));
+        assert_eq!(benchmark.summarize()[0].name, "frame");
+    }
+
+    /// Tests that the effective frame rate is derived from the total time of all recorded frames.
+    #[test]
+    fn effective_fps() {
+        let mut benchmark = Benchmark::new();
+        assert_eq!(benchmark.effective_fps(), None);
+
+        benchmark.record("render", Duration::from_millis(5));
+        assert_eq!(benchmark.effective_fps(), None);
+
+        benchmark.record_frame(Duration::from_millis(10));
+        benchmark.record_frame(Duration::from_millis(30));
+        assert!((benchmark.effective_fps().unwrap() - 50.0).abs() < 1e-3);
+    }
+
+    /// Tests that sections keep statistics over all samples, but only retain the most recent samples for percentiles.
+    #[test]
+    fn section_capacity() {
+        let mut benchmark = Benchmark::new();
+        benchmark.record("update", Duration::from_millis(1000));
+        for _ in 0..Benchmark::SECTION_CAPACITY {
+            benchmark.record("update", Duration::from_millis(2));
+        }
+
+        let summary = benchmark.summarize()[0];
+        assert_eq!(summary.count, Benchmark::SECTION_CAPACITY + 1);
+        assert_eq!(summary.min, Duration::from_millis(2));
+        assert_eq!(summary.max, Duration::from_millis(1000));
+        assert_eq!(summary.p99, Duration::from_millis(2));
+    }
+
+    /// Tests that frames are sorted into equally sized buckets and bars are scaled to the largest bucket.
+    #[test]
+    fn frame_times_histogram() {
+        assert_eq!(FrameTimes::new(10).histogram(4, 10), "");
+
+        let mut frames = FrameTimes::new(10);
+        for ms in [10, 10, 11, 12, 12, 12, 12, 13, 18] {
+            frames.push(Duration::from_millis(ms));
+        }
+        assert_eq!(frames.histogram(4, 10), [
+            "  10.000 -   12.000ms | ###### 3\n",
+            "  12.000 -   14.000ms | ########## 5\n",
+            "  14.000 -   16.000ms |  0\n",
+            "  16.000 -   18.000ms | ## 1\n",
+        ].concat());
+    }
+
+    /// Tests that nested spans accumulate under their parent span's path.
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn nested_spans() {
+        use crate::gamelogic::benchmark::{span, span_stats};
+
+        for _ in 0..2 {
+            let _outer = span("outer");
+            {
+                let _inner = span("inner");
+            }
+            {
+                let _inner = span("inner");
+            }
+            let _other = span("other");
+        }
+        {
+            let _inner = span("inner");
+        }
+
+        let stats: Vec<(String, usize, usize)> = span_stats().into_iter().map(|s| (s.path, s.depth, s.count)).collect();
+        assert_eq!(stats, vec![
+            ("outer".to_string(), 0, 2),
+            ("outer/inner".to_string(), 1, 4),
+            ("outer/other".to_string(), 1, 2),
+            ("inner".to_string(), 0, 1),
+        ]);
+    }
+
+    /// Tests that camera paths are parsed and linearly interpolated between evenly spaced keyframes.
+    #[test]
+    fn camera_path() {
+        let path = CameraPath::parse(indoc! {"
+            # x y z pitch yaw
+            0 10 0 0 0
+
+            10 10 0 0 90
+            10 30 10 -90 180
+        "}).unwrap();
+
+        let cases = [
+            (-1.0, Point3::new(0.0, 10.0, 0.0), Vector3::new(0.0, 0.0, 0.0)),
+            (0.25, Point3::new(5.0, 10.0, 0.0), Vector3::new(0.0, 45.0, 0.0)),
+            (0.75, Point3::new(10.0, 20.0, 5.0), Vector3::new(-45.0, 135.0, 0.0)),
+            (1.0, Point3::new(10.0, 30.0, 10.0), Vector3::new(-90.0, 180.0, 0.0)),
+        ];
+        for (t, position, rotation_deg) in cases {
+            let keyframe = path.sample(t);
+            assert_vec3_eq!(keyframe.position, position);
+            assert_vec3_eq!(keyframe.euler_rotation, rotation_deg.map(f32::to_radians));
+        }
+
+        let single = CameraPath::parse("1 2 3 0 0").unwrap();
+        assert_eq!(single.sample(0.5).position, Point3::new(1.0, 2.0, 3.0));
+
+        assert!(matches!(CameraPath::parse("# nothing"), Err(CameraPathError::Empty)));
+        assert!(matches!(CameraPath::parse("0 0 0 0 0\n1 2 3"), Err(CameraPathError::InvalidKeyframe(2))));
+        assert!(matches!(CameraPath::parse("0 0 a 0 0"), Err(CameraPathError::InvalidKeyframe(1))));
+    }
+
+    /// Tests that formatted keyframes are parsed as the same keyframe again.
+    #[test]
+    fn camera_keyframe_format() {
+        let keyframe = CameraKeyframe {
+            position: Point3::new(-24.5, 80.0, 174.25),
+            euler_rotation: Vector3::new(-0.5, 1.25, 0.0),
+        };
+        let parsed = CameraPath::parse(&keyframe.to_string()).unwrap().sample(0.0);
+        assert_vec3_eq!(parsed.position, keyframe.position);
+        assert_vec3_eq!(parsed.euler_rotation, keyframe.euler_rotation);
+    }
+}
@@ -0,0 +1,162 @@
+use std::time::{Duration, Instant};
+
+use cgmath::Point3;
+
+use crate::core::Input;
+
+/// `Command` is a parsed console command.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Command {
+    /// `Goto` moves the player to the given position.
+    Goto(Point3<f32>),
+}
+
+impl Command {
+    /// Parses a command like `goto 10 80 -5`. Command names are case-insensitive and may be prefixed with `/`.
+    /// Arguments are separated by whitespace or commas. Returns a readable error for invalid commands.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut words = input.split(|c: char| c.is_whitespace() || c == ',').filter(|word| !word.is_empty());
+        let Some(name) = words.next() else {
+            return Err(String::from("empty command"));
+        };
+        let args: Vec<&str> = words.collect();
+
+        match name.trim_start_matches('/').to_lowercase().as_str() {
+            "goto" | "tp" => {
+                let [x, y, z] = args[..] else {
+                    return Err(format!("expected 3 coordinates, got {}", args.len()));
+                };
+                let parse = |value: &str| value.parse::<f32>()
+                    .ok()
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(|| format!("invalid coordinate: {value}"));
+                Ok(Self::Goto(Point3::new(parse(x)?, parse(y)?, parse(z)?)))
+            }
+            _ => Err(format!("unknown command: {name}, expected goto or tp")),
+        }
+    }
+}
+
+/// `Console` is a single line text input for [`Command`]s. While it is open, all typed characters are appended to the
+/// input line. Feedback for the last command is shown for a few seconds after the console was closed.
+#[derive(Default)]
+pub struct Console {
+    input: Option<String>,
+    message: Option<(String, Instant)>,
+}
+
+impl Console {
+    /// `MESSAGE_DURATION` is the duration for which the last message is shown.
+    const MESSAGE_DURATION: Duration = Duration::from_secs(5);
+
+    pub fn is_open(&self) -> bool {
+        self.input.is_some()
+    }
+
+    pub fn open(&mut self) {
+        self.input = Some(String::new());
+    }
+
+    /// Shows `message` as feedback to the last command.
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some((message, Instant::now()));
+    }
+
+    /// Handles the input of the current frame, if the console is open: typed characters are appended, backspace
+    /// removes the last one, escape closes the console and enter submits the input. Returns the submitted command,
+    /// if it could be parsed. Otherwise, the error is shown as message.
+    pub fn handle_input(&mut self, input: &Input) -> Option<Command> {
+        self.edit(
+            input.get_typed_chars(),
+            input.was_key_pressed(glfw::Key::Backspace),
+            input.was_key_pressed(glfw::Key::Enter),
+            input.was_key_pressed(glfw::Key::Escape),
+        )
+    }
+
+    fn edit(&mut self, typed: &[char], backspace: bool, submit: bool, cancel: bool) -> Option<Command> {
+        let line = self.input.as_mut()?;
+        if cancel {
+            self.input = None;
+            return None;
+        }
+
+        line.extend(typed.iter().filter(|c| !c.is_control()));
+        if backspace {
+            line.pop();
+        }
+        if !submit {
+            return None;
+        }
+
+        let line = self.input.take().unwrap_or_default();
+        if line.trim().is_empty() {
+            return None;
+        }
+        match Command::parse(&line) {
+            Ok(command) => Some(command),
+            Err(err) => {
+                self.set_message(format!("error: {err}"));
+                None
+            }
+        }
+    }
+
+    /// Returns the text to show on screen: the input line while the console is open, followed by the last message,
+    /// unless it has expired.
+    pub fn text(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(input) = &self.input {
+            lines.push(format!("> {input}_"));
+        }
+        if let Some((message, time)) = &self.message {
+            if time.elapsed() < Self::MESSAGE_DURATION {
+                lines.push(message.clone());
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Point3;
+
+    use crate::gamelogic::console::{Command, Console};
+
+    /// Tests that coordinates are parsed leniently and that invalid commands are described.
+    #[test]
+    fn parse() {
+        assert_eq!(Command::parse("goto 10 80 -5"), Ok(Command::Goto(Point3::new(10.0, 80.0, -5.0))));
+        assert_eq!(Command::parse("  /TP 1.5, 2,3 "), Ok(Command::Goto(Point3::new(1.5, 2.0, 3.0))));
+        assert_eq!(Command::parse(""), Err(String::from("empty command")));
+        assert_eq!(Command::parse("tp 1 2"), Err(String::from("expected 3 coordinates, got 2")));
+        assert_eq!(Command::parse("tp 1 2 x"), Err(String::from("invalid coordinate: x")));
+        assert_eq!(Command::parse("tp 1 2 NaN"), Err(String::from("invalid coordinate: NaN")));
+        assert_eq!(Command::parse("fly 1 2 3"), Err(String::from("unknown command: fly, expected goto or tp")));
+    }
+
+    /// Tests that typed characters are collected until the input is submitted or cancelled.
+    #[test]
+    fn edit() {
+        let mut console = Console::default();
+        assert_eq!(console.edit(&['t'], false, true, false), None);
+        assert_eq!(console.text(), "");
+
+        console.open();
+        assert_eq!(console.edit(&['t', 'p', 'x'], true, false, false), None);
+        assert_eq!(console.edit(&[' ', '1', '\u{8}', ' ', '2', ' ', '3'], false, false, false), None);
+        assert_eq!(console.text(), "> tp 1 2 3_");
+        assert_eq!(console.edit(&[], false, true, false), Some(Command::Goto(Point3::new(1.0, 2.0, 3.0))));
+        assert!(!console.is_open());
+
+        console.open();
+        console.edit(&['t', 'p'], false, true, false);
+        assert!(!console.is_open());
+        assert_eq!(console.text(), "error: expected 3 coordinates, got 0");
+
+        console.open();
+        console.edit(&['t'], false, false, true);
+        assert!(!console.is_open());
+    }
+}
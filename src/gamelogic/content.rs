@@ -10,6 +10,19 @@ pub mod blocks {
     pub const STONE_BRICKS: BlockId = 4;
     pub const GLASS: BlockId = 5;
 
+    /// Returns the block with the given name, e.g. `stone_bricks`.
+    pub fn from_name(name: &str) -> Option<BlockId> {
+        match name {
+            "air" => Some(AIR),
+            "grass" => Some(GRASS),
+            "dirt" => Some(DIRT),
+            "stone" => Some(STONE),
+            "stone_bricks" => Some(STONE_BRICKS),
+            "glass" => Some(GLASS),
+            _ => None,
+        }
+    }
+
     pub fn new_registry() -> VoxelRegistry {
         let mut registry = VoxelRegistry::new();
         registry
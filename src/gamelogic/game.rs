@@ -1,87 +1,614 @@
+use std::f32::consts::PI;
+use std::{fs, io};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::time::{Duration, Instant};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use cgmath::{Point3, Vector3};
 use imgui::Condition;
 
-use crate::core::{Buffering, Config, Frame, Window};
-use crate::gamelogic::gameplay::Gameplay;
+use crate::core::{Action, Buffering, Config, DebugSeverity, Frame, Gamepad, KeyBindings, Window};
+use crate::gamelogic::benchmark::{Benchmark, CameraKeyframe, CameraPath, CameraPathError};
+use crate::gamelogic::console::{Command, Console};
+use crate::gamelogic::gameplay::{Gameplay, MovementSettings};
+use crate::gamelogic::hud::Hud;
+use crate::gamelogic::minimap::Minimap;
+use crate::gamelogic::sun::Sun;
 use crate::gamelogic::world::World;
-use crate::global_allocated_bytes;
+use crate::gamelogic::worldgen::{GenPalette, GeneratorKind};
+use crate::{global_allocated_bytes, memory_pressure, set_memory_limit};
+use crate::graphics::svo::{EdgeOutline, RenderMode};
+use crate::systems::chunkloader::LodBands;
 use crate::systems::jobs::JobSystem;
-use crate::systems::physics::{AABBDef, Entity};
+use crate::systems::physics::{AABBDef, Entity, PhysicsMode};
+use crate::systems::storage::Storage;
 use crate::world::chunk::ChunkPos;
 
+/// `GameArgs` contains all options that can be passed on the command line.
+#[derive(Debug, Default, PartialEq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct GameArgs {
+    /// `vsync` enables waiting for the vertical blank. It is disabled by default, so that the
+    /// frame rate is not capped when benchmarking.
+    pub vsync: bool,
+    /// `max_fps` caps the frame rate by waiting at the end of every frame. `--max-fps=0` leaves
+    /// the frame rate uncapped, which is the default.
+    pub max_fps: Option<u32>,
+    /// `gl_debug` prints OpenGL driver messages. Notifications are included, if set to verbose.
+    pub gl_debug: Option<DebugSeverity>,
+    /// `shadow_softness` is the radius of the cone over which shadow rays are filtered. 0 renders
+    /// hard shadows.
+    pub shadow_softness: f32,
+    /// `shadow_accumulate` blends the soft shadows of consecutive frames while the camera and the sun do not move, so
+    /// that fewer samples are needed per frame.
+    pub shadow_accumulate: bool,
+    /// `edge_outline` enables darkened edges between differing voxels. Passed as the width relative to a voxel face,
+    /// optionally followed by an RGB color, e.g. `--edge-outline=0.05,0.1,0.1,0.1`.
+    pub edge_outline: Option<EdgeOutline>,
+    /// `debug_render` starts with the octant debug render mode enabled.
+    pub debug_render: bool,
+    /// `fog_density` is the density of the distance fog. 0 disables fog.
+    pub fog_density: f32,
+    /// `fog_color` overrides the default fog color. Passed as comma separated RGB values within
+    /// \[0;1\], e.g. `--fog-color=0.7,0.8,0.9`.
+    pub fog_color: Option<Vector3<f32>>,
+    /// `fov_y_deg` overrides the camera's initial vertical field of view in degrees. It can be
+    /// changed at runtime with the mouse wheel.
+    pub fov_y_deg: Option<f32>,
+    /// `benchmark_out` enables collecting frame timings, which are written to the given path when
+    /// the game is closed. The results are exported as CSV for `.csv` files, and as JSON otherwise.
+    pub benchmark_out: Option<PathBuf>,
+    /// `benchmark_frames` runs a deterministic benchmark in a hidden window: the camera follows
+    /// `benchmark_path` for the given number of frames, after which the results are printed and
+    /// the game is closed.
+    pub benchmark_frames: Option<u32>,
+    /// `benchmark_path` is a file with camera keyframes, see [`CameraPath::load`]. By default,
+    /// the camera turns once around the spawn point.
+    pub benchmark_path: Option<PathBuf>,
+    /// `render_distance` overrides the radius in chunks around the player, in which chunks are loaded and rendered. It
+    /// can be changed at runtime with `+` and `-`.
+    pub render_distance: Option<u32>,
+    /// `memory_limit_mb` is a soft memory limit in MiB. Chunk loading is paused while more memory
+    /// is allocated.
+    pub memory_limit_mb: Option<usize>,
+    /// `svo_memory_limit_mb` limits the memory in MiB used by chunks in the world SVO. The least recently
+    /// serialized chunks are evicted while more memory is used.
+    pub svo_memory_limit_mb: Option<usize>,
+    /// `compress_chunks` keeps serialized chunks run-length encoded until they are added to the world SVO.
+    pub compress_chunks: bool,
+    /// `generator` selects how new chunks are generated: `noise` (default) or `flat`.
+    pub generator: GeneratorKind,
+    /// `palette` is a config file that selects the blocks of the generated terrain, see [`GenPalette::parse`].
+    pub palette: Option<PathBuf>,
+    /// `chunk_cache` is a directory in which generated chunks are stored, so that they are loaded instead of generated
+    /// on the next launch. Every generator uses its own subdirectory.
+    pub chunk_cache: Option<PathBuf>,
+    /// `lod_bands` overrides the distances in chunks up to which chunks are rendered with full
+    /// detail, and then with every lower LOD. Passed as ascending comma separated radii, e.g.
+    /// `--lod-radii=6,12,19`.
+    pub lod_bands: Option<LodBands>,
+    /// `worker_threads` is the number of threads that generate and serialize chunks. 0 selects one
+    /// thread per available core, except for one core that is left for the main thread.
+    pub worker_threads: usize,
+    /// `time_scale` enables the day/night cycle, in which the given number of in-game seconds pass
+    /// per real second.
+    pub time_scale: Option<f32>,
+    /// `start_time` enables the day/night cycle, starting at the given hour of the day.
+    pub start_time: Option<f32>,
+    /// `move_speed` overrides the player's walking speed in blocks per second.
+    pub move_speed: Option<f32>,
+    /// `sprint_multiplier` overrides the factor by which sprinting increases the walking speed.
+    pub sprint_multiplier: Option<f32>,
+    /// `mouse_sensitivity` overrides the camera rotation in radians per pixel of mouse movement.
+    pub mouse_sensitivity: Option<f32>,
+    /// `gamepad` enables gamepad input. The first connected gamepad is used and it can be plugged in at any time. Its
+    /// analog values are preferred over the keyboard while a stick or trigger is deflected.
+    pub gamepad: bool,
+    /// `gamepad_dead_zone` overrides how far the gamepad's sticks and triggers have to be deflected within \[0;1) to
+    /// have an effect.
+    pub gamepad_dead_zone: Option<f32>,
+    /// `minimap_size` enables the minimap with the given width and height in pixels.
+    pub minimap_size: Option<u32>,
+    /// `minimap_zoom` overrides the number of pixels per block on the minimap.
+    pub minimap_zoom: Option<f32>,
+    /// `key_bindings` is a config file that remaps the controls, see [`KeyBindings::parse`].
+    pub key_bindings: Option<PathBuf>,
+    /// `position` overrides the player's start position, e.g. `--pos=0,80,0`.
+    pub position: Option<Point3<f32>>,
+    /// `rotation_deg` overrides the player's start orientation as pitch and yaw in degrees, e.g.
+    /// `--rot=-30,90`.
+    pub rotation_deg: Option<(f32, f32)>,
+    /// `physics` selects whether the player starts flying (`fly`, default) or walking (`walk`).
+    pub physics: PhysicsMode,
+    /// `no_restore` disables restoring the player's position and orientation of the last session.
+    /// By default, they are restored unless `position` or `rotation_deg` are set.
+    pub no_restore: bool,
+}
+
+impl GameArgs {
+    /// Parses the given command line arguments, excluding the program name. Unknown arguments are
+    /// reported and ignored.
+    #[allow(clippy::cognitive_complexity)]
+    pub fn parse<I: IntoIterator<Item=String>>(args: I) -> Self {
+        let mut result = Self::default();
+        for arg in args {
+            match arg.as_str() {
+                "--vsync" => result.vsync = true,
+                "--debug-render" => result.debug_render = true,
+                "--shadow-accumulate" => result.shadow_accumulate = true,
+                "--compress-chunks" => result.compress_chunks = true,
+                "--no-restore" => result.no_restore = true,
+                "--gamepad" => result.gamepad = true,
+                "--minimap" => result.minimap_size = Some(Minimap::DEFAULT_SIZE),
+                "--gl-debug" => result.gl_debug = Some(DebugSeverity::default()),
+                "--gl-debug=verbose" => result.gl_debug = Some(DebugSeverity::Notification),
+                _ if arg.starts_with("--shadow-softness=") => {
+                    if let Some(softness) = Self::parse_value::<f32>(&arg) {
+                        result.shadow_softness = softness.max(0.0);
+                    }
+                }
+                _ if arg.starts_with("--edge-outline=") => {
+                    if let Some(outline) = Self::parse_value::<EdgeOutlineArg>(&arg) {
+                        result.edge_outline = Some(outline.0);
+                    }
+                }
+                _ if arg.starts_with("--fog-density=") => {
+                    if let Some(density) = Self::parse_value::<f32>(&arg) {
+                        result.fog_density = density.max(0.0);
+                    }
+                }
+                _ if arg.starts_with("--fov=") => {
+                    if let Some(fov) = Self::parse_value::<f32>(&arg) {
+                        result.fov_y_deg = Some(fov);
+                    }
+                }
+                _ if arg.starts_with("--render-distance=") => {
+                    if let Some(chunks) = Self::parse_value::<u32>(&arg) {
+                        result.render_distance = Some(chunks);
+                    }
+                }
+                _ if arg.starts_with("--max-fps=") => {
+                    if let Some(fps) = Self::parse_value::<u32>(&arg) {
+                        result.max_fps = (fps > 0).then_some(fps);
+                    }
+                }
+                _ if arg.starts_with("--benchmark-out=") => {
+                    if let Some(path) = Self::parse_value::<PathBuf>(&arg) {
+                        result.benchmark_out = Some(path);
+                    }
+                }
+                _ if arg.starts_with("--benchmark-frames=") => {
+                    if let Some(frames) = Self::parse_value::<u32>(&arg) {
+                        result.benchmark_frames = Some(frames.max(1));
+                    }
+                }
+                _ if arg.starts_with("--benchmark-path=") => {
+                    if let Some(path) = Self::parse_value::<PathBuf>(&arg) {
+                        result.benchmark_path = Some(path);
+                    }
+                }
+                _ if arg.starts_with("--memory-limit=") => {
+                    if let Some(limit) = Self::parse_value::<usize>(&arg) {
+                        result.memory_limit_mb = Some(limit);
+                    }
+                }
+                _ if arg.starts_with("--svo-memory-limit=") => {
+                    if let Some(limit) = Self::parse_value::<usize>(&arg) {
+                        result.svo_memory_limit_mb = Some(limit);
+                    }
+                }
+                _ if arg.starts_with("--physics=") => {
+                    if let Some(mode) = Self::parse_value::<PhysicsMode>(&arg) {
+                        result.physics = mode;
+                    }
+                }
+                _ if arg.starts_with("--generator=") => {
+                    if let Some(generator) = Self::parse_value::<GeneratorKind>(&arg) {
+                        result.generator = generator;
+                    }
+                }
+                _ if arg.starts_with("--palette=") => {
+                    if let Some(path) = Self::parse_value::<PathBuf>(&arg) {
+                        result.palette = Some(path);
+                    }
+                }
+                _ if arg.starts_with("--chunk-cache=") => {
+                    if let Some(dir) = Self::parse_value::<PathBuf>(&arg) {
+                        result.chunk_cache = Some(dir);
+                    }
+                }
+                _ if arg.starts_with("--lod-radii=") => {
+                    if let Some(bands) = Self::parse_value::<LodBands>(&arg) {
+                        result.lod_bands = Some(bands);
+                    }
+                }
+                _ if arg.starts_with("--worker-threads=") => {
+                    if let Some(count) = Self::parse_value::<usize>(&arg) {
+                        result.worker_threads = count;
+                    }
+                }
+                _ if arg.starts_with("--time-scale=") => {
+                    if let Some(scale) = Self::parse_value::<f32>(&arg) {
+                        result.time_scale = Some(scale);
+                    }
+                }
+                _ if arg.starts_with("--start-time=") => {
+                    if let Some(time) = Self::parse_value::<f32>(&arg) {
+                        result.start_time = Some(time);
+                    }
+                }
+                _ if arg.starts_with("--move-speed=") => {
+                    if let Some(speed) = Self::parse_value::<f32>(&arg) {
+                        result.move_speed = Some(speed.max(0.0));
+                    }
+                }
+                _ if arg.starts_with("--sprint-multiplier=") => {
+                    if let Some(multiplier) = Self::parse_value::<f32>(&arg) {
+                        result.sprint_multiplier = Some(multiplier.max(0.0));
+                    }
+                }
+                _ if arg.starts_with("--mouse-sensitivity=") => {
+                    if let Some(sensitivity) = Self::parse_value::<f32>(&arg) {
+                        result.mouse_sensitivity = Some(sensitivity);
+                    }
+                }
+                _ if arg.starts_with("--gamepad-dead-zone=") => {
+                    if let Some(dead_zone) = Self::parse_value::<f32>(&arg) {
+                        result.gamepad_dead_zone = Some(dead_zone.clamp(0.0, 0.95));
+                    }
+                }
+                _ if arg.starts_with("--minimap=") => {
+                    if let Some(size) = Self::parse_value::<u32>(&arg) {
+                        result.minimap_size = (size > 0).then_some(size);
+                    }
+                }
+                _ if arg.starts_with("--minimap-zoom=") => {
+                    if let Some(zoom) = Self::parse_value::<f32>(&arg) {
+                        result.minimap_zoom = Some(zoom);
+                    }
+                }
+                _ if arg.starts_with("--key-bindings=") => {
+                    if let Some(path) = Self::parse_value::<PathBuf>(&arg) {
+                        result.key_bindings = Some(path);
+                    }
+                }
+                _ if arg.starts_with("--pos=") => {
+                    if let Some(FloatsArg([x, y, z])) = Self::parse_value::<FloatsArg<3>>(&arg) {
+                        result.position = Some(Point3::new(x, y, z));
+                    }
+                }
+                _ if arg.starts_with("--rot=") => {
+                    if let Some(FloatsArg(rotation)) = Self::parse_value::<FloatsArg<2>>(&arg) {
+                        result.rotation_deg = Some(rotation.into());
+                    }
+                }
+                _ if arg.starts_with("--fog-color=") => {
+                    if let Some(color) = Self::parse_value::<RgbArg>(&arg) {
+                        result.fog_color = Some(color.0);
+                    }
+                }
+                _ => println!("ignoring unknown argument: {arg}"),
+            }
+        }
+        result
+    }
+
+    /// Parses the value of an argument in the form of `--name=value`. Invalid values are reported.
+    fn parse_value<T: FromStr>(arg: &str) -> Option<T> {
+        let value = arg.split_once('=').and_then(|(_, value)| value.parse().ok());
+        if value.is_none() {
+            println!("ignoring argument with invalid value: {arg}");
+        }
+        value
+    }
+}
+
+/// `RgbArg` is a color command line argument in the form of `r,g,b` with values within \[0;1\].
+struct RgbArg(Vector3<f32>);
+
+impl FromStr for RgbArg {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<f32> = s.split(',')
+            .map(|v| v.trim().parse::<f32>().map_err(|_| ()))
+            .collect::<Result<_, _>>()?;
+        match values[..] {
+            [r, g, b] if values.iter().all(|v| (0.0..=1.0).contains(v)) => Ok(Self(Vector3::new(r, g, b))),
+            _ => Err(()),
+        }
+    }
+}
+
+/// `EdgeOutlineArg` is an edge outline command line argument in the form of `width` or `width,r,g,b`. The width has
+/// to be within \[0;0.5\] and the color values within \[0;1\]. The color defaults to black.
+struct EdgeOutlineArg(EdgeOutline);
+
+impl FromStr for EdgeOutlineArg {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, color) = match s.split_once(',') {
+            Some((width, color)) => (width, color.parse::<RgbArg>()?.0),
+            None => (s, EdgeOutline::default().color),
+        };
+        let width = width.trim().parse::<f32>().map_err(|_| ())?;
+        if !(0.0..=0.5).contains(&width) {
+            return Err(());
+        }
+        Ok(Self(EdgeOutline { width, color }))
+    }
+}
+
+/// `FloatsArg` is a command line argument with `N` comma separated values, e.g. `1,2.5,3`.
+struct FloatsArg<const N: usize>([f32; N]);
+
+impl<const N: usize> FromStr for FloatsArg<N> {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<f32> = s.split(',')
+            .map(|v| v.trim().parse::<f32>().map_err(|_| ()))
+            .collect::<Result<_, _>>()?;
+        values.try_into().map(Self).map_err(|_| ())
+    }
+}
+
 /// Game runs the actual game loop and handles communication and calling to the different game
 /// systems.
 pub struct Game {
     window: Window,
     job_system: Rc<JobSystem>,
     state: State,
+    benchmark_out: Option<PathBuf>,
+    /// `player_state_path` is the file to which the player's pose is saved when the game is closed.
+    player_state_path: Option<PathBuf>,
 }
 
 struct State {
     job_system: Rc<JobSystem>,
     world: World,
     gameplay: Gameplay,
+    hud: Hud,
+    minimap: Option<Minimap>,
+    console: Console,
     player: Entity,
 
     physics_target_fps: u32,
     physics_fps: u32,
 
     render_debug_ui: bool,
+    render_hud: bool,
+    pause: Pause,
+    shader_watch_refresh: Instant,
     plot_refresh: Instant,
     plot_fps: Plot,
     plot_frame_time: Plot,
     plot_jobs: Plot,
     plot_memory: Plot,
+    benchmark: Option<Benchmark>,
+    benchmark_run: Option<BenchmarkRun>,
+    /// `benchmark_gpu_frame` is the frame of the GPU times that were last recorded, to skip frames without new results.
+    benchmark_gpu_frame: Option<u64>,
+}
+
+/// `Pause` freezes all updates of the world and the player for debugging, while the current state is still rendered.
+/// While paused, the game can be advanced by single frames.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Pause {
+    paused: bool,
+    stepping: bool,
+}
+
+impl Pause {
+    /// Applies the input of the current frame: `toggle` pauses or resumes the game and `step` advances a paused game by
+    /// one frame.
+    fn update(&mut self, toggle: bool, step: bool) {
+        if toggle {
+            self.paused = !self.paused;
+        }
+        self.stepping = self.paused && step;
+    }
+
+    fn is_paused(self) -> bool {
+        self.paused
+    }
+
+    /// Returns true, if the game must not be updated in the current frame.
+    fn is_frozen(self) -> bool {
+        self.paused && !self.stepping
+    }
+}
+
+/// `BenchmarkRun` moves the player along a camera path for a fixed number of frames, after which
+/// the game is closed.
+struct BenchmarkRun {
+    path: CameraPath,
+    frames: u32,
+    frame: u32,
+}
+
+impl BenchmarkRun {
+    /// `MAX_LOAD_WAIT` limits how long a frame waits for the chunks around the player to load, so that a run does not
+    /// hang if chunks can never be loaded, e.g. while loading is paused due to the memory budget.
+    const MAX_LOAD_WAIT: Duration = Duration::from_secs(30);
 }
 
 impl Game {
-    pub fn new() -> Self {
+    /// `PLAYER_STATE_PATH` is the file in which the player's pose is kept between sessions.
+    const PLAYER_STATE_PATH: &'static str = "player_state.txt";
+
+    pub fn new(args: &GameArgs) -> Self {
+        let is_benchmark_run = args.benchmark_frames.is_some();
+        set_memory_limit(args.memory_limit_mb.map(|mb| mb * 1024 * 1024));
+
         let mut window = Window::new(&Config {
             width: 1920,
             height: 1080,
             title: "voxel engine",
             msaa_samples: 0,
-            headless: false,
-            resizable: true,
+            headless: is_benchmark_run,
+            resizable: !is_benchmark_run,
             buffering: Buffering::Single,
-            target_fps: None,
+            // benchmark runs measure how fast frames can be rendered
+            target_fps: args.max_fps.filter(|_| !is_benchmark_run),
+            gamepad_dead_zone: (args.gamepad && !is_benchmark_run)
+                .then(|| args.gamepad_dead_zone.unwrap_or(Gamepad::DEFAULT_DEAD_ZONE)),
         });
-        window.request_grab_cursor(true);
+        window.request_grab_cursor(!is_benchmark_run);
+        window.set_vsync(args.vsync);
+        if let Some(severity) = args.gl_debug {
+            window.enable_debug_output(severity);
+        }
+
+        let capabilities = window.get_capabilities();
+        println!("OpenGL {}.{}: {} ({})", capabilities.version.0, capabilities.version.1, capabilities.renderer, capabilities.vendor);
+        if capabilities.is_limited() {
+            println!("warning: the graphics driver does not support hardware accelerated compute shaders, expect poor performance");
+        }
 
         let mut player = Entity::new(
             Point3::new(-24.0, 80.0, 174.0),
             AABBDef::new(Vector3::new(-0.4, -1.7, -0.4), Vector3::new(0.8, 1.8, 0.8)),
         );
         player.euler_rotation = Vector3::new(0.0, -90f32.to_radians(), 0.0);
-        player.caps.flying = true;
+        player.set_physics_mode(args.physics);
 
-        let job_system = Rc::new(JobSystem::new(num_cpus::get() - 1));
-        let world = World::new(Rc::clone(&job_system), 20);
-        let gameplay = Gameplay::new();
+        // restore the last session's pose, unless a start pose is given or the pose is controlled by the benchmark
+        let restore = !args.no_restore && !is_benchmark_run;
+        if args.position.is_some() || args.rotation_deg.is_some() {
+            if let Some(position) = args.position {
+                player.position = position;
+            }
+            if let Some((pitch, yaw)) = args.rotation_deg {
+                player.euler_rotation = Vector3::new(pitch.to_radians(), yaw.to_radians(), 0.0);
+            }
+        } else if restore {
+            if let Some(keyframe) = Self::load_player_state(Path::new(Self::PLAYER_STATE_PATH)) {
+                player.position = keyframe.position;
+                player.euler_rotation = keyframe.euler_rotation;
+            }
+        }
 
-        Self {
+        let job_system = Rc::new(JobSystem::new(JobSystem::resolve_worker_count(args.worker_threads)));
+        let palette = args.palette.as_ref().map_or_else(GenPalette::default, GenPalette::load);
+        let mut world = World::new(Rc::clone(&job_system), 20, args.generator, palette);
+        world.shadow_softness = args.shadow_softness;
+        world.shadow_accumulate = args.shadow_accumulate;
+        world.edge_outline = args.edge_outline;
+        if args.debug_render {
+            world.render_mode = RenderMode::Octants;
+        }
+        world.fog.density = args.fog_density;
+        if let Some(color) = args.fog_color {
+            world.fog.color = color;
+        }
+        if let Some(fov) = args.fov_y_deg {
+            world.set_fov_y_deg(fov);
+        }
+        if let Some(bands) = &args.lod_bands {
+            world.set_lod_bands(bands.clone());
+        }
+        world.set_svo_memory_limit(args.svo_memory_limit_mb.map(|mb| mb * 1024 * 1024));
+        world.set_chunk_compression(args.compress_chunks);
+        if args.time_scale.is_some() || args.start_time.is_some() {
+            world.sun = Some(Sun::new(
+                args.start_time.unwrap_or(Sun::NOON),
+                args.time_scale.unwrap_or(Sun::DEFAULT_TIME_SCALE),
+            ));
+        }
+        if let Some(dir) = &args.chunk_cache {
+            world.set_storage(Storage::with_dir(dir.join(format!("{:?}", args.generator).to_lowercase())));
+        }
+        let mut gameplay = Gameplay::new();
+        let default_movement = MovementSettings::default();
+        gameplay.set_movement_settings(MovementSettings {
+            move_speed: args.move_speed.unwrap_or(default_movement.move_speed),
+            sprint_multiplier: args.sprint_multiplier.unwrap_or(default_movement.sprint_multiplier),
+            mouse_sensitivity: args.mouse_sensitivity.unwrap_or(default_movement.mouse_sensitivity),
+            stick_sensitivity: default_movement.stick_sensitivity,
+        });
+        if let Some(path) = &args.key_bindings {
+            gameplay.set_key_bindings(KeyBindings::load(path));
+        }
+
+        let benchmark_run = args.benchmark_frames.map(|frames| {
+            let path = args.benchmark_path.as_ref().map_or_else(
+                || Self::default_benchmark_path(&player),
+                |path| CameraPath::load(path).unwrap_or_else(|e| panic!("error loading benchmark path {}: {e:?}", path.display())),
+            );
+            BenchmarkRun { path, frames, frame: 0 }
+        });
+
+        let mut game = Self {
             window,
             job_system: Rc::clone(&job_system),
             state: State {
                 job_system,
                 world,
                 gameplay,
+                hud: Hud::new(),
+                minimap: args.minimap_size
+                    .map(|size| Minimap::new(size, args.minimap_zoom.unwrap_or(Minimap::DEFAULT_ZOOM))),
+                console: Console::default(),
                 player,
                 physics_target_fps: 250,
                 physics_fps: 0,
-                render_debug_ui: true,
+                render_debug_ui: !is_benchmark_run,
+                render_hud: false,
+                pause: Pause::default(),
+                shader_watch_refresh: Instant::now(),
                 plot_refresh: Instant::now(),
                 plot_fps: Plot::new(),
                 plot_frame_time: Plot::new(),
                 plot_jobs: Plot::new(),
                 plot_memory: Plot::new(),
+                benchmark: (args.benchmark_out.is_some() || is_benchmark_run).then(Benchmark::new),
+                benchmark_run,
+                benchmark_gpu_frame: None,
             },
+            benchmark_out: args.benchmark_out.clone(),
+            player_state_path: restore.then(|| PathBuf::from(Self::PLAYER_STATE_PATH)),
+        };
+        if let Some(chunks) = args.render_distance {
+            game.set_render_distance(chunks);
+        }
+        game
+    }
+
+    /// Changes the radius in chunks around the player, in which chunks are loaded and rendered. See
+    /// [`World::set_render_distance`].
+    pub fn set_render_distance(&mut self, chunks: u32) {
+        self.state.world.set_render_distance(chunks);
+    }
+
+    /// Loads the player's pose from a file in the format of [`CameraPath::load`]. Returns `None`,
+    /// if the file does not exist or is invalid.
+    fn load_player_state(path: &Path) -> Option<CameraKeyframe> {
+        match CameraPath::load(path) {
+            Ok(path) => Some(path.sample(0.0)),
+            Err(CameraPathError::Io(err)) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                println!("error loading player state from {}, using the default position: {err:?}", path.display());
+                None
+            }
         }
     }
 
+    fn save_player_state(path: &Path, player: &Entity) -> io::Result<()> {
+        let keyframe = CameraKeyframe { position: player.position, euler_rotation: player.euler_rotation };
+        fs::write(path, format!("# x y z pitch yaw\n{keyframe}\n"))
+    }
+
+    /// Returns a path that turns the camera once around the player's position.
+    fn default_benchmark_path(player: &Entity) -> CameraPath {
+        let start = CameraKeyframe { position: player.position, euler_rotation: player.euler_rotation };
+        let end = CameraKeyframe { euler_rotation: start.euler_rotation + Vector3::new(0.0, 2.0 * PI, 0.0), ..start };
+        CameraPath::new(vec![start, end]).unwrap()
+    }
+
     pub fn run(self) {
         let mut window = self.window;
         let mut state = self.state;
@@ -100,16 +627,28 @@ impl Game {
                 if frame.was_resized {
                     state.handle_window_resize(frame.size.0, frame.size.1, frame.get_aspect());
                 }
-                state.update(frame);
+                state.advance_benchmark_run(frame);
 
-                // accumulate frame time for fixed update
-                frame_time_accumulator += frame.stats.delta_time;
+                let update_start = Instant::now();
+                state.update(frame);
+                let update_time = update_start.elapsed();
 
-                // consume accumulated time for fixed physics updates
-                while frame_time_accumulator >= fixed_frame_time {
+                if state.pause.is_frozen() {
+                    // time does not pass while paused
+                } else if state.pause.is_paused() {
+                    // a stepped frame advances by exactly one fixed update, regardless of how long it was paused
                     state.update_fixed(frame, fixed_frame_time);
-                    frame_time_accumulator -= fixed_frame_time;
                     fixed_frames += 1;
+                } else {
+                    // accumulate frame time for fixed update
+                    frame_time_accumulator += frame.stats.delta_time;
+
+                    // consume accumulated time for fixed physics updates
+                    while frame_time_accumulator >= fixed_frame_time {
+                        state.update_fixed(frame, fixed_frame_time);
+                        frame_time_accumulator -= fixed_frame_time;
+                        fixed_frames += 1;
+                    }
                 }
                 if last_fixed_frame_measurement.elapsed().as_secs() >= 1 {
                     state.physics_fps = fixed_frames;
@@ -122,10 +661,37 @@ impl Game {
                     gl::ClearColor(0.0, 0.0, 0.0, 1.0);
                     gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
                 }
+                let render_start = Instant::now();
                 state.render(frame);
+                if state.benchmark_run.is_some() {
+                    // include the GPU work in the measured render time
+                    unsafe { gl::Finish(); }
+                }
+                let render_time = render_start.elapsed();
+
+                // paused frames, including stepped ones, do not reflect the performance of the game
+                if !state.pause.is_paused() {
+                    state.record_benchmark(frame, update_time, render_time);
+                }
             });
         }
 
+        if let Some(benchmark) = &state.benchmark {
+            benchmark.print();
+            if let Some(path) = &self.benchmark_out {
+                match benchmark.export(path, global_allocated_bytes()) {
+                    Ok(()) => println!("saved benchmark results to {}", path.display()),
+                    Err(e) => println!("error saving benchmark results: {e:?}"),
+                }
+            }
+        }
+
+        if let Some(path) = &self.player_state_path {
+            if let Err(err) = Self::save_player_state(path, &state.player) {
+                println!("error saving player state to {}: {err:?}", path.display());
+            }
+        }
+
         self.job_system.clear();
         self.job_system.wait_until_processed();
 
@@ -140,20 +706,85 @@ impl Game {
 
 impl State {
     fn update_fixed(&mut self, frame: &mut Frame, delta_time: f32) {
+        // the player is moved by the benchmark path instead
+        if self.benchmark_run.is_some() {
+            return;
+        }
         self.world.update_fixed(&mut self.player, delta_time);
     }
 
+    /// Moves the player to the next keyframe of the benchmark run and waits until all chunks around
+    /// it are loaded, so that every run renders the same world. Closing is requested on the last
+    /// frame.
+    fn advance_benchmark_run(&mut self, frame: &mut Frame) {
+        let Some(run) = &mut self.benchmark_run else {
+            return;
+        };
+
+        let t = if run.frames > 1 { run.frame as f32 / (run.frames - 1) as f32 } else { 0.0 };
+        let keyframe = run.path.sample(t);
+        self.player.position = keyframe.position;
+        self.player.euler_rotation = keyframe.euler_rotation;
+        self.player.velocity = Vector3::new(0.0, 0.0, 0.0);
+
+        run.frame += 1;
+        if run.frame >= run.frames {
+            frame.request_close();
+        }
+        let run_frame = run.frame;
+
+        let wait_start = Instant::now();
+        loop {
+            self.world.update(&self.player);
+            if !self.world.is_loading() {
+                break;
+            }
+            if wait_start.elapsed() >= BenchmarkRun::MAX_LOAD_WAIT {
+                println!("benchmark frame {run_frame}: chunks are still loading after {:?}, continuing", BenchmarkRun::MAX_LOAD_WAIT);
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        self.job_system.wait_until_empty_and_processed();
+    }
+
     fn update(&mut self, frame: &mut Frame) {
         self.handle_debug_keys(frame);
 
+        if self.shader_watch_refresh.elapsed().as_secs() >= 1 {
+            self.shader_watch_refresh = Instant::now();
+            self.world.reload_changed_shaders();
+            self.gameplay.reload_changed_shaders();
+            self.hud.reload_changed_shaders();
+            if let Some(minimap) = &mut self.minimap {
+                minimap.reload_changed_shaders();
+            }
+        }
+        if self.pause.is_frozen() {
+            return;
+        }
+
         self.world.update(&self.player);
-        self.gameplay.update(frame, &mut self.player, &mut self.world);
-        self.world.selected_voxel = self.gameplay.looking_at_block.map(|result| result.pos);
+        if self.benchmark_run.is_none() && !self.console.is_open() {
+            self.gameplay.update(frame, &mut self.player, &mut self.world);
+            self.world.selected_voxel = self.gameplay.looking_at_block.map(|result| result.pos);
+        }
+        if let Some(minimap) = &mut self.minimap {
+            minimap.update(self.player.position, self.player.get_forward());
+        }
     }
 
     fn render(&mut self, frame: &mut Frame) {
         self.world.render(frame.get_aspect());
+        if let Some(minimap) = &self.minimap {
+            minimap.render(&self.world);
+        }
         self.gameplay.render_ui(frame.size);
+        if self.render_hud {
+            self.draw_hud_overlay(frame);
+        }
+        self.draw_console(frame);
+        self.hud.render();
 
         if self.render_debug_ui {
             self.render_debug_window(frame);
@@ -161,23 +792,94 @@ impl State {
         }
     }
 
+    fn record_benchmark(&mut self, frame: &Frame, update_time: Duration, render_time: Duration) {
+        let Some(benchmark) = &mut self.benchmark else {
+            return;
+        };
+        let frame_time = if self.benchmark_run.is_some() {
+            // the time between frames includes waiting for chunks to load
+            update_time + render_time
+        } else {
+            Duration::from_secs_f32(frame.stats.delta_time)
+        };
+        benchmark.record_frame(frame_time);
+        benchmark.record("update", update_time);
+        benchmark.record("render", render_time);
+
+        // gpu times are reported with a delay, so the same results are returned until a newer frame completed
+        let gpu_frame = self.world.world_svo.get_gpu_times_frame();
+        if gpu_frame.is_some() && gpu_frame != self.benchmark_gpu_frame {
+            self.benchmark_gpu_frame = gpu_frame;
+            for (pass, time) in self.world.world_svo.get_gpu_times().iter() {
+                benchmark.record(pass, *time);
+            }
+        }
+    }
+
     fn handle_window_resize(&mut self, width: i32, height: i32, aspect_ratio: f32) {
         self.world.handle_window_resize(width, height, aspect_ratio);
         self.gameplay.handle_window_resize(width, height);
+        self.hud.handle_window_resize(width, height);
+        if let Some(minimap) = &mut self.minimap {
+            minimap.handle_window_resize(width, height);
+        }
     }
 
     fn handle_resource_reload(&mut self) {
         self.world.reload_resources();
         self.gameplay.reload_resources();
+        self.hud.reload_resources();
+        if let Some(minimap) = &mut self.minimap {
+            minimap.reload_resources();
+        }
         println!("tried reloading all resources");
     }
 
+    /// Draws the most important debug information in the bottom left corner, so that it is included in screenshots.
+    fn draw_hud_overlay(&mut self, frame: &Frame) {
+        let position = self.player.position;
+        let text = format!(
+            "fps: {} ({:.2}ms)\npos: {:.1} {:.1} {:.1}\nchunks: {}\nmemory: {:.1}mb",
+            frame.stats.frames_per_second,
+            frame.stats.avg_frame_time_per_second * 1000.0,
+            position.x, position.y, position.z,
+            self.world.chunk_storage_allocator.used_count(),
+            global_allocated_bytes() as f32 / 1024.0 / 1024.0,
+        );
+
+        let margin = 8.0;
+        let height = Hud::measure_text(&text).y;
+        self.hud.draw_text(margin, frame.size.1 as f32 - height - margin, &text);
+    }
+
+    /// Draws the console's input line and last message centered at the bottom of the screen.
+    fn draw_console(&mut self, frame: &Frame) {
+        let text = self.console.text();
+        if text.is_empty() {
+            return;
+        }
+        let margin = 8.0;
+        let size = Hud::measure_text(&text);
+        self.hud.draw_text((frame.size.0 as f32 - size.x) / 2.0, frame.size.1 as f32 - size.y - margin, &text);
+    }
+
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Goto(position) => {
+                // chunks around the new position are streamed in with the next world update
+                self.player.position = position;
+                self.player.velocity = Vector3::new(0.0, 0.0, 0.0);
+                self.console.set_message(format!("teleported to {:.1} {:.1} {:.1}", position.x, position.y, position.z));
+            }
+        }
+    }
+
     fn render_debug_window(&mut self, frame: &mut Frame) {
         let camera = &self.world.camera;
 
         frame.ui.window("Debug")
             .position([8.0, 8.0], Condition::Once)
-            .size([400.0, 290.0], Condition::Once)
+            .size([400.0, 310.0], Condition::Once)
             .collapsed(false, Condition::Once)
             .build(|| {
                 frame.ui.text(format!(
@@ -191,6 +893,9 @@ impl State {
                     self.physics_fps,
                     (1.0 / self.physics_target_fps as f32) * 1000.0,
                 ));
+                if self.pause.is_paused() {
+                    frame.ui.text("paused, F6 to resume, F7 to step a frame");
+                }
 
                 frame.ui.separator();
 
@@ -249,8 +954,9 @@ impl State {
 
                 let bytes = global_allocated_bytes();
                 frame.ui.text(format!(
-                    "engine memory: {:.3}mb",
+                    "engine memory: {:.3}mb, pressure: {:.0}%",
                     bytes as f32 / 1024f32 / 1024f32,
+                    memory_pressure() * 100.0,
                 ));
 
                 frame.ui.separator();
@@ -263,6 +969,16 @@ impl State {
                     svo_stats.depth,
                 ));
 
+                let gpu_times = self.world.world_svo.get_gpu_times();
+                if gpu_times.is_empty() {
+                    frame.ui.text("gpu passes: n/a");
+                } else {
+                    let passes: Vec<String> = gpu_times.iter()
+                        .map(|(pass, time)| format!("{pass}: {:.2}ms", time.as_secs_f32() * 1000.0))
+                        .collect();
+                    frame.ui.text(format!("gpu passes: {}", passes.join(", ")));
+                }
+
                 let alloc_stats = self.world.world_svo.get_alloc_stats();
                 frame.ui.text(format!(
                     "cpu svo size: {:.3}mb",
@@ -274,6 +990,7 @@ impl State {
                     alloc_stats.chunk_buffers_allocated,
                     alloc_stats.chunk_buffers_bytes_total as f32 / 1024f32 / 1024f32,
                 ));
+                frame.ui.text(format!("chunk load queue: {}", self.world.load_queue_len()));
             });
 
         let now = Instant::now();
@@ -301,6 +1018,28 @@ impl State {
     }
 
     fn handle_debug_keys(&mut self, frame: &mut Frame) {
+        // all keys are typed into the console while it is open
+        if self.console.is_open() {
+            self.pause.update(false, false);
+            if let Some(command) = self.console.handle_input(frame.input) {
+                self.run_command(command);
+            }
+            return;
+        }
+        if frame.input.was_key_pressed(glfw::Key::Enter) {
+            self.console.open();
+            // movement input is ignored while the console is open, so the player would keep its last velocity
+            self.player.velocity.x = 0.0;
+            self.player.velocity.z = 0.0;
+            if self.player.physics_mode() == PhysicsMode::FreeFly {
+                self.player.velocity.y = 0.0;
+            }
+        }
+
+        if frame.input.was_key_pressed(glfw::Key::Escape) {
+            frame.request_close();
+        }
+        self.pause.update(frame.input.was_key_pressed(glfw::Key::F6), frame.input.was_key_pressed(glfw::Key::F7));
         if frame.input.was_key_pressed(glfw::Key::P) {
             self.render_debug_ui = !self.render_debug_ui;
         }
@@ -310,10 +1049,45 @@ impl State {
         if frame.input.was_key_pressed(glfw::Key::R) {
             self.handle_resource_reload();
         }
-        if frame.input.was_key_pressed(glfw::Key::T) {
+        if self.gameplay.key_bindings().was_triggered(frame.input, Action::ToggleInput) {
             let is_grabbed = frame.is_cursor_grabbed();
             frame.request_grab_cursor(!is_grabbed);
         }
+        if self.gameplay.key_bindings().was_triggered(frame.input, Action::Screenshot) {
+            self.handle_screenshot();
+        }
+        if frame.input.was_key_pressed(glfw::Key::F3) {
+            self.render_hud = !self.render_hud;
+        }
+        if frame.input.was_key_pressed(glfw::Key::F5) {
+            self.world.render_mode = match self.world.render_mode {
+                RenderMode::Shaded => RenderMode::Octants,
+                RenderMode::Octants => RenderMode::Shaded,
+            };
+        }
+        if frame.input.was_key_pressed(glfw::Key::F4) {
+            self.world.render_block_outline = !self.world.render_block_outline;
+        }
+        if frame.input.was_key_pressed(glfw::Key::Equal) || frame.input.was_key_pressed(glfw::Key::KpAdd) {
+            self.change_render_distance(1);
+        }
+        if frame.input.was_key_pressed(glfw::Key::Minus) || frame.input.was_key_pressed(glfw::Key::KpSubtract) {
+            self.change_render_distance(-1);
+        }
+    }
+
+    fn change_render_distance(&mut self, delta: i32) {
+        self.world.set_render_distance(self.world.render_distance().saturating_add_signed(delta));
+        self.console.set_message(format!("render distance: {} chunks", self.world.render_distance()));
+    }
+
+    fn handle_screenshot(&self) {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let path = format!("screenshot-{timestamp}.png");
+        match self.world.save_screenshot(&path) {
+            Ok(()) => println!("saved screenshot to {path}"),
+            Err(e) => println!("error saving screenshot: {e:?}"),
+        }
     }
 }
 
@@ -341,3 +1115,120 @@ impl<const N: usize> Plot<N> {
             .build();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use cgmath::{Point3, Vector3};
+
+    use crate::core::DebugSeverity;
+    use crate::gamelogic::game::{GameArgs, Pause};
+    use crate::gamelogic::worldgen::GeneratorKind;
+    use crate::graphics::svo::EdgeOutline;
+    use crate::systems::chunkloader::LodBands;
+    use crate::systems::physics::PhysicsMode;
+
+    /// Tests that command line arguments are parsed and vsync stays disabled by default.
+    #[test]
+    #[allow(clippy::cognitive_complexity)]
+    fn parse_args() {
+        assert_eq!(GameArgs::parse(Vec::new()), GameArgs { vsync: false, gl_debug: None, ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--vsync".to_string()]), GameArgs { vsync: true, gl_debug: None, ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--unknown".to_string()]), GameArgs { vsync: false, gl_debug: None, ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--gl-debug".to_string()]), GameArgs { vsync: false, gl_debug: Some(DebugSeverity::Low), ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--gl-debug=verbose".to_string()]), GameArgs { vsync: false, gl_debug: Some(DebugSeverity::Notification), ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--shadow-softness=0.05".to_string()]), GameArgs { shadow_softness: 0.05, ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--shadow-softness=abc".to_string()]), GameArgs::default());
+        assert_eq!(GameArgs::parse(vec!["--shadow-accumulate".to_string()]), GameArgs { shadow_accumulate: true, ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--render-distance=8".to_string()]), GameArgs { render_distance: Some(8), ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--max-fps=60".to_string()]), GameArgs { max_fps: Some(60), ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--max-fps=0".to_string()]), GameArgs::default());
+        assert_eq!(GameArgs::parse(vec!["--max-fps=-1".to_string()]), GameArgs::default());
+        assert_eq!(GameArgs::parse(vec!["--debug-render".to_string()]), GameArgs { debug_render: true, ..GameArgs::default() });
+        assert_eq!(
+            GameArgs::parse(vec!["--fog-density=0.01".to_string(), "--fog-color=0.5,0.25,1".to_string()]),
+            GameArgs { fog_density: 0.01, fog_color: Some(Vector3::new(0.5, 0.25, 1.0)), ..GameArgs::default() },
+        );
+        assert_eq!(GameArgs::parse(vec!["--fog-color=0.5,0.25".to_string()]), GameArgs::default());
+        assert_eq!(GameArgs::parse(vec!["--fog-color=0.5,0.25,2".to_string()]), GameArgs::default());
+        assert_eq!(
+            GameArgs::parse(vec!["--edge-outline=0.05".to_string()]),
+            GameArgs { edge_outline: Some(EdgeOutline { width: 0.05, color: Vector3::new(0.0, 0.0, 0.0) }), ..GameArgs::default() },
+        );
+        assert_eq!(
+            GameArgs::parse(vec!["--edge-outline=0.1,0.2,0.3,0.4".to_string()]),
+            GameArgs { edge_outline: Some(EdgeOutline { width: 0.1, color: Vector3::new(0.2, 0.3, 0.4) }), ..GameArgs::default() },
+        );
+        assert_eq!(GameArgs::parse(vec!["--edge-outline=0.6".to_string()]), GameArgs::default());
+        assert_eq!(GameArgs::parse(vec!["--edge-outline=0.1,0.2".to_string()]), GameArgs::default());
+        assert_eq!(GameArgs::parse(vec!["--fov=90".to_string()]), GameArgs { fov_y_deg: Some(90.0), ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--fov=wide".to_string()]), GameArgs::default());
+        assert_eq!(
+            GameArgs::parse(vec!["--benchmark-out=results.json".to_string()]),
+            GameArgs { benchmark_out: Some(PathBuf::from("results.json")), ..GameArgs::default() },
+        );
+        assert_eq!(
+            GameArgs::parse(vec!["--benchmark-frames=500".to_string(), "--benchmark-path=path.txt".to_string()]),
+            GameArgs { benchmark_frames: Some(500), benchmark_path: Some(PathBuf::from("path.txt")), ..GameArgs::default() },
+        );
+        assert_eq!(GameArgs::parse(vec!["--benchmark-frames=0".to_string()]), GameArgs { benchmark_frames: Some(1), ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--memory-limit=2048".to_string()]), GameArgs { memory_limit_mb: Some(2048), ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--svo-memory-limit=512".to_string()]), GameArgs { svo_memory_limit_mb: Some(512), ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--compress-chunks".to_string()]), GameArgs { compress_chunks: true, ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--generator=flat".to_string()]), GameArgs { generator: GeneratorKind::Flat, ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--generator=mc".to_string()]), GameArgs::default());
+        assert_eq!(GameArgs::parse(vec!["--physics=walk".to_string()]), GameArgs { physics: PhysicsMode::Walk, ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--physics=swim".to_string()]), GameArgs::default());
+        assert_eq!(GameArgs::parse(vec!["--palette=desert.cfg".to_string()]), GameArgs { palette: Some(PathBuf::from("desert.cfg")), ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--chunk-cache=cache".to_string()]), GameArgs { chunk_cache: Some(PathBuf::from("cache")), ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--lod-radii=4,8".to_string()]), GameArgs { lod_bands: LodBands::new(vec![4, 8]), ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--lod-radii=8,4".to_string()]), GameArgs::default());
+        assert_eq!(GameArgs::parse(vec!["--worker-threads=4".to_string()]), GameArgs { worker_threads: 4, ..GameArgs::default() });
+        assert_eq!(
+            GameArgs::parse(vec!["--time-scale=0".to_string(), "--start-time=18.5".to_string()]),
+            GameArgs { time_scale: Some(0.0), start_time: Some(18.5), ..GameArgs::default() },
+        );
+        assert_eq!(
+            GameArgs::parse(vec!["--move-speed=12".to_string(), "--sprint-multiplier=2".to_string(), "--mouse-sensitivity=0.004".to_string()]),
+            GameArgs { move_speed: Some(12.0), sprint_multiplier: Some(2.0), mouse_sensitivity: Some(0.004), ..GameArgs::default() },
+        );
+        assert_eq!(GameArgs::parse(vec!["--move-speed=-1".to_string()]), GameArgs { move_speed: Some(0.0), ..GameArgs::default() });
+        assert_eq!(
+            GameArgs::parse(vec!["--gamepad".to_string(), "--gamepad-dead-zone=0.25".to_string()]),
+            GameArgs { gamepad: true, gamepad_dead_zone: Some(0.25), ..GameArgs::default() },
+        );
+        assert_eq!(GameArgs::parse(vec!["--gamepad-dead-zone=2".to_string()]), GameArgs { gamepad_dead_zone: Some(0.95), ..GameArgs::default() });
+        assert_eq!(
+            GameArgs::parse(vec!["--minimap".to_string(), "--minimap-zoom=0.5".to_string()]),
+            GameArgs { minimap_size: Some(256), minimap_zoom: Some(0.5), ..GameArgs::default() },
+        );
+        assert_eq!(GameArgs::parse(vec!["--minimap=128".to_string()]), GameArgs { minimap_size: Some(128), ..GameArgs::default() });
+        assert_eq!(GameArgs::parse(vec!["--minimap=0".to_string()]), GameArgs::default());
+        assert_eq!(GameArgs::parse(vec!["--key-bindings=keys.cfg".to_string()]), GameArgs { key_bindings: Some(PathBuf::from("keys.cfg")), ..GameArgs::default() });
+        assert_eq!(
+            GameArgs::parse(vec!["--pos=1,80.5,-3".to_string(), "--rot=-30,90".to_string(), "--no-restore".to_string()]),
+            GameArgs { position: Some(Point3::new(1.0, 80.5, -3.0)), rotation_deg: Some((-30.0, 90.0)), no_restore: true, ..GameArgs::default() },
+        );
+        assert_eq!(GameArgs::parse(vec!["--pos=1,2".to_string(), "--rot=0,0,0".to_string()]), GameArgs::default());
+    }
+
+    /// Tests that pausing freezes the game until it is resumed, except for single stepped frames.
+    #[test]
+    fn pause() {
+        let mut pause = Pause::default();
+        pause.update(false, true);
+        assert!(!pause.is_paused() && !pause.is_frozen());
+
+        pause.update(true, false);
+        assert!(pause.is_paused() && pause.is_frozen());
+
+        pause.update(false, true);
+        assert!(pause.is_paused() && !pause.is_frozen());
+        pause.update(false, false);
+        assert!(pause.is_frozen());
+
+        pause.update(true, false);
+        assert!(!pause.is_paused() && !pause.is_frozen());
+    }
+}
@@ -2,17 +2,42 @@ use std::f32::consts::PI;
 use std::ffi::c_int;
 use std::ops::Add;
 
-use cgmath::{ElementWise, InnerSpace, Matrix4, SquareMatrix, Vector2, Vector3, Zero};
+use cgmath::{ElementWise, InnerSpace, Matrix4, Point3, SquareMatrix, Vector2, Vector3};
 
-use crate::core::Frame;
+use crate::core::{Action, Frame, KeyBindings};
 use crate::gamelogic;
 use crate::gamelogic::content::blocks;
 use crate::graphics::resource::Resource;
 use crate::graphics::screen_quad::ScreenQuad;
 use crate::graphics::shader::{ShaderError, ShaderProgram, ShaderProgramBuilder};
-use crate::graphics::svo_picker::{PickerBatch, PickerBatchResult, RayResult};
-use crate::systems::physics::{Entity, Raycaster};
-use crate::world::chunk::{BlockId, BlockPos, Chunk};
+use crate::graphics::svo_picker::{PickerBatch, RayResult};
+use crate::systems::physics::{Entity, PhysicsMode};
+use crate::world::chunk::BlockId;
+use crate::world::history::{BlockEdit, EditHistory};
+
+/// `MovementSettings` configures how fast the player moves and turns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovementSettings {
+    /// `move_speed` is the walking speed in blocks per second. Flying uses a fixed speed.
+    pub move_speed: f32,
+    /// `sprint_multiplier` is applied to `move_speed` while sprinting.
+    pub sprint_multiplier: f32,
+    /// `mouse_sensitivity` is the rotation in radians per pixel of mouse movement.
+    pub mouse_sensitivity: f32,
+    /// `stick_sensitivity` is the rotation in radians per second while the gamepad's right stick is fully deflected.
+    pub stick_sensitivity: f32,
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        Self {
+            move_speed: 9.0,
+            sprint_multiplier: 1.5,
+            mouse_sensitivity: 0.002,
+            stick_sensitivity: 3.0,
+        }
+    }
+}
 
 /// Gameplay handles all user input and uses it to implement the gameplay logic. The in-game UI is
 /// also rendered here.
@@ -25,46 +50,65 @@ pub struct Gameplay {
     was_grounded: bool,
     pub looking_at_block: Option<RayResult>,
     selected_block: BlockId,
+    movement: MovementSettings,
+    key_bindings: KeyBindings,
+    history: EditHistory,
 
-    look_ray_batch: PickerBatch,
-    look_ray_result: PickerBatchResult,
+    /// `look_ray_batch` is reused for the asynchronous look ray, once its results were polled.
+    look_ray_batch: Option<PickerBatch>,
 }
 
 impl Gameplay {
     const FLY_SPEED: f32 = 60.0;
-    const NORMAL_SPEED: f32 = 9.0;
-    const SPRINT_FACTOR: f32 = 1.5;
     const JUMP_SPEED: f32 = 13.0;
-    const ROTATION_SPEED: f32 = 0.002;
+    const FOV_STEP_DEG: f32 = 5.0;
+    /// `HISTORY_CAPACITY` is the number of block edits that can be undone.
+    const HISTORY_CAPACITY: usize = 256;
 
     pub fn new() -> Self {
         Self {
             ui_view: Matrix4::identity(),
             crosshair_shader: Resource::new(
                 || ShaderProgramBuilder::new().load_shader_bundle("assets/shaders/crosshair.glsl")?.build()
-            ).unwrap(),
+            ),
             screen_quad: ScreenQuad::new(),
             is_jumping: false,
             was_grounded: false,
             looking_at_block: None,
             selected_block: blocks::GRASS,
-            look_ray_batch: PickerBatch::with_capacity(1),
-            look_ray_result: PickerBatchResult::with_capacity(1),
+            movement: MovementSettings::default(),
+            key_bindings: KeyBindings::default(),
+            history: EditHistory::new(Self::HISTORY_CAPACITY),
+            look_ray_batch: None,
         }
     }
 
-    pub fn update(&mut self, frame: &mut Frame, player: &mut Entity, world: &mut gamelogic::world::World) {
-        if frame.input.was_key_pressed(glfw::Key::Escape) {
-            frame.request_close();
-        }
+    pub fn update(&mut self, frame: &Frame, player: &mut Entity, world: &mut gamelogic::world::World) {
         if frame.is_cursor_grabbed() {
-            Self::handle_mouse_movement(frame, player);
+            self.handle_mouse_movement(frame, player);
+            Self::handle_zoom(frame, world);
             self.handle_voxel_placement(frame, player, world);
         }
 
         self.handle_movement(frame, player);
     }
 
+    pub fn movement_settings(&self) -> MovementSettings {
+        self.movement
+    }
+
+    pub fn set_movement_settings(&mut self, movement: MovementSettings) {
+        self.movement = movement;
+    }
+
+    pub fn key_bindings(&self) -> &KeyBindings {
+        &self.key_bindings
+    }
+
+    pub fn set_key_bindings(&mut self, key_bindings: KeyBindings) {
+        self.key_bindings = key_bindings;
+    }
+
     pub fn handle_window_resize(&mut self, width: i32, height: i32) {
         self.ui_view = cgmath::ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
     }
@@ -75,6 +119,12 @@ impl Gameplay {
         }
     }
 
+    pub fn reload_changed_shaders(&mut self) {
+        if let Err(e) = self.crosshair_shader.reload_if_changed() {
+            println!("error reloading crosshair shader: {e:?}");
+        }
+    }
+
     fn handle_movement(&mut self, frame: &Frame, player: &mut Entity) {
         let forward = player.get_forward()
             .mul_element_wise(Vector3::new(1.0, 0.0, 1.0))
@@ -83,56 +133,39 @@ impl Gameplay {
 
         let speed = if player.caps.flying {
             Self::FLY_SPEED
-        } else if frame.input.is_key_pressed(glfw::Key::LeftShift) {
-            Self::NORMAL_SPEED * Self::SPRINT_FACTOR
+        } else if self.key_bindings.is_pressed(frame.input, Action::Down) {
+            self.movement.move_speed * self.movement.sprint_multiplier
         } else {
-            Self::NORMAL_SPEED
+            self.movement.move_speed
         };
 
-        let mut impulse = Vector3::new(0.0, 0.0, 0.0);
+        let axis = |action| self.key_bindings.get_axis(frame.input, action);
+        let mut impulse = forward * (axis(Action::Forward) - axis(Action::Back))
+            + right * (axis(Action::Right) - axis(Action::Left));
 
-        if frame.input.is_key_pressed(glfw::Key::W) {
-            let speed = forward * speed;
-            impulse += speed;
-        }
-        if frame.input.is_key_pressed(glfw::Key::S) {
-            let speed = -forward * speed;
-            impulse += speed;
-        }
-        if frame.input.is_key_pressed(glfw::Key::A) {
-            let speed = -right * speed;
-            impulse += speed;
-        }
-        if frame.input.is_key_pressed(glfw::Key::D) {
-            let speed = right * speed;
-            impulse += speed;
-        }
-
-        if !impulse.is_zero() {
-            impulse = impulse.normalize_to(speed);
+        // diagonal movement is not faster, but partially deflected sticks move slower
+        if impulse.magnitude2() > 1.0 {
+            impulse = impulse.normalize();
         }
+        impulse *= speed;
         player.velocity.x = impulse.x;
         player.velocity.z = impulse.z;
 
-        if frame.input.was_key_pressed(glfw::Key::F) {
-            player.caps.flying = !player.caps.flying;
+        if self.key_bindings.was_triggered(frame.input, Action::TogglePhysics) {
+            player.set_physics_mode(match player.physics_mode() {
+                PhysicsMode::FreeFly => PhysicsMode::Walk,
+                PhysicsMode::Walk => PhysicsMode::FreeFly,
+            });
         }
         if player.caps.flying {
             self.is_jumping = false;
             self.was_grounded = false;
 
-            player.velocity.y = 0.0;
-
-            if frame.input.is_key_pressed(glfw::Key::Space) {
-                player.velocity.y = speed;
-            }
-            if frame.input.is_key_pressed(glfw::Key::LeftShift) {
-                player.velocity.y = -speed;
-            }
+            player.velocity.y = (axis(Action::Up) - axis(Action::Down)) * speed;
         } else {
             let is_grounded = player.get_state().is_grounded;
 
-            if frame.input.is_key_pressed(glfw::Key::Space) && self.was_grounded {
+            if self.key_bindings.is_pressed(frame.input, Action::Up) && self.was_grounded {
                 if !self.is_jumping {
                     self.is_jumping = true;
                     player.velocity.y = Self::JUMP_SPEED;
@@ -145,33 +178,45 @@ impl Gameplay {
         }
     }
 
-    fn handle_mouse_movement(frame: &Frame, player: &mut Entity) {
+    fn handle_mouse_movement(&self, frame: &Frame, player: &mut Entity) {
         let delta = frame.input.get_mouse_delta();
         if delta.x.abs() > 0.01 {
-            player.euler_rotation.y += delta.x * Self::ROTATION_SPEED;
+            player.euler_rotation.y += delta.x * self.movement.mouse_sensitivity;
         }
         if delta.y.abs() > 0.01 {
-            player.euler_rotation.x -= delta.y * Self::ROTATION_SPEED;
+            player.euler_rotation.x -= delta.y * self.movement.mouse_sensitivity;
+        }
+        if let Some(gamepad) = frame.input.get_gamepad() {
+            let delta = gamepad.right_stick * self.movement.stick_sensitivity * frame.stats.delta_time;
+            player.euler_rotation.y += delta.x;
+            player.euler_rotation.x -= delta.y;
+        }
+
+        let limit = PI / 2.0 - 0.01;
+        player.euler_rotation.x = player.euler_rotation.x.clamp(-limit, limit);
+    }
 
-            let limit = PI / 2.0 - 0.01;
-            player.euler_rotation.x = player.euler_rotation.x.clamp(-limit, limit);
+    fn handle_zoom(frame: &Frame, world: &mut gamelogic::world::World) {
+        let delta = frame.input.get_mouse_wheel_delta();
+        if delta.abs() > 0.01 {
+            // scrolling up zooms in
+            let fov = delta.mul_add(-Self::FOV_STEP_DEG, world.camera.get_fov_y_deg());
+            world.set_fov_y_deg(fov);
         }
     }
 
     fn handle_voxel_placement(&mut self, frame: &Frame, player: &Entity, world: &mut gamelogic::world::World) {
-        self.look_ray_batch.reset();
-        self.look_ray_batch.add_ray(player.position, player.get_forward(), 30.0);
-
-        self.look_ray_result.reset();
-        world.world_svo.raycast(&mut self.look_ray_batch, &mut self.look_ray_result);
-
-        let block_result = self.look_ray_result.rays[0];
-
-        if block_result.did_hit() {
-            self.looking_at_block = Some(block_result);
-        } else {
-            self.looking_at_block = None;
+        // the look ray is raycast asynchronously to not stall on the GPU, so the looked at block is up to the picker
+        // latency frames old
+        if let Some(completed) = world.world_svo.poll_raycasts() {
+            let block_result = completed.result.rays[0];
+            self.looking_at_block = block_result.did_hit().then_some(block_result);
+            self.look_ray_batch = Some(completed.batch);
         }
+        let mut batch = self.look_ray_batch.take().unwrap_or_else(|| PickerBatch::with_capacity(1));
+        batch.reset();
+        batch.add_ray(player.position, player.get_forward(), 30.0);
+        world.world_svo.raycast_async(batch);
 
         let hot_bar = [blocks::GRASS, blocks::DIRT, blocks::STONE, blocks::STONE_BRICKS, blocks::GLASS];
         for i in 1..=hot_bar.len() {
@@ -185,52 +230,97 @@ impl Gameplay {
         }
 
         // removing blocks
-        if frame.input.is_button_pressed_once(glfw::MouseButton::Button1) && block_result.did_hit() {
-            let x = block_result.pos.x.floor() as i32;
-            let y = block_result.pos.y.floor() as i32;
-            let z = block_result.pos.z.floor() as i32;
-            world.world.set_block(x, y, z, blocks::AIR);
+        if self.key_bindings.was_triggered(frame.input, Action::Remove) {
+            self.remove_block(world);
         }
 
         // block picking
-        if frame.input.is_button_pressed_once(glfw::MouseButton::Button3) && block_result.did_hit() {
-            let x = block_result.pos.x.floor() as i32;
-            let y = block_result.pos.y.floor() as i32;
-            let z = block_result.pos.z.floor() as i32;
-            self.selected_block = world.world.get_block(x, y, z);
+        if frame.input.is_button_pressed_once(glfw::MouseButton::Button3) {
+            if let Some(block_result) = self.looking_at_block {
+                let x = block_result.pos.x.floor() as i32;
+                let y = block_result.pos.y.floor() as i32;
+                let z = block_result.pos.z.floor() as i32;
+                self.selected_block = world.world.get_block(x, y, z);
+            }
         }
 
         // adding blocks
-        if frame.input.is_button_pressed_once(glfw::MouseButton::Button2) && block_result.did_hit() {
-            let block_normal = block_result.normal;
-            let block_pos = block_result.pos.add(block_normal);
-            let x = block_pos.x.floor() as i32 as f32;
-            let y = block_pos.y.floor() as i32 as f32;
-            let z = block_pos.z.floor() as i32 as f32;
-
-            let aabb = &player.aabb_def;
-            let player_min_x = player.position.x + aabb.offset.x;
-            let player_min_y = player.position.y + aabb.offset.y - 0.1; // add offset to prevent physics glitches
-            let player_min_z = player.position.z + aabb.offset.z;
-            let player_max_x = player.position.x + aabb.offset.x + aabb.extents.x;
-            let player_max_y = player.position.y + aabb.offset.y + aabb.extents.y;
-            let player_max_z = player.position.z + aabb.offset.z + aabb.extents.z;
-
-            if (player_max_x < x || player_min_x > x + 1.0) ||
-                (player_max_y < y || player_min_y > y + 1.0) ||
-                (player_max_z < z || player_min_z > z + 1.0) ||
-                player.caps.flying {
-                let did_set = world.world.set_block(x as i32, y as i32, z as i32, self.selected_block);
-                if !did_set {
-                    // if the block could not be placed because of no chunk being present, manually add the chunk
-                    let pos = BlockPos::new(x as i32, y as i32, z as i32);
-                    let storage = world.chunk_storage_allocator.allocate();
-                    let mut chunk = Chunk::new(pos.chunk, 5, storage);
-                    chunk.set_block(pos.rel_x as u32, pos.rel_y as u32, pos.rel_z as u32, self.selected_block);
-                    world.add_chunk(chunk);
-                }
-            }
+        if self.key_bindings.was_triggered(frame.input, Action::Place) {
+            self.place_block(player, world, self.selected_block);
+        }
+
+        // undo & redo
+        let is_control_pressed = frame.input.is_key_pressed(glfw::Key::LeftControl) || frame.input.is_key_pressed(glfw::Key::RightControl);
+        if is_control_pressed && self.key_bindings.was_triggered(frame.input, Action::Undo) {
+            self.undo(world);
+        }
+        if is_control_pressed && self.key_bindings.was_triggered(frame.input, Action::Redo) {
+            self.redo(world);
+        }
+    }
+
+    /// Sets the block at `pos` and records the change, so that it can be undone. Edits that would not change the block
+    /// are skipped, e.g. when removing a block again, before the look ray caught up with the previous removal.
+    fn edit_block(&mut self, world: &mut gamelogic::world::World, pos: Point3<i32>, block: BlockId) -> bool {
+        let old = world.world.get_block(pos.x, pos.y, pos.z);
+        if old == block {
+            return false;
+        }
+        if !world.set_block(pos.x, pos.y, pos.z, block) {
+            return false;
         }
+        self.history.record(BlockEdit { pos, old, new: block });
+        true
+    }
+
+    /// Reverts the most recent block edit. Returns true, if an edit was undone.
+    pub fn undo(&mut self, world: &mut gamelogic::world::World) -> bool {
+        self.history.undo(|pos, block| world.set_block(pos.x, pos.y, pos.z, block)).is_some()
+    }
+
+    /// Applies the most recently undone block edit again. Returns true, if an edit was redone.
+    pub fn redo(&mut self, world: &mut gamelogic::world::World) -> bool {
+        self.history.redo(|pos, block| world.set_block(pos.x, pos.y, pos.z, block)).is_some()
+    }
+
+    /// Removes the block the player is looking at. The changed chunk is serialized into the SVO again with the next
+    /// world update. The edit can be reverted with [`Gameplay::undo`]. Returns true, if a block was removed.
+    pub fn remove_block(&mut self, world: &mut gamelogic::world::World) -> bool {
+        let Some(block_result) = self.looking_at_block else {
+            return false;
+        };
+        let pos = block_result.pos.map(|v| v.floor() as i32);
+        self.edit_block(world, pos, blocks::AIR)
+    }
+
+    /// Places `material` next to the face of the block the player is looking at, unless it would intersect with the
+    /// player while walking. Missing chunks are created. The changed chunk is serialized into the SVO again with the
+    /// next world update. The edit can be reverted with [`Gameplay::undo`]. Returns true, if a block was placed.
+    pub fn place_block(&mut self, player: &Entity, world: &mut gamelogic::world::World, material: BlockId) -> bool {
+        let Some(block_result) = self.looking_at_block else {
+            return false;
+        };
+        let block_pos = block_result.pos.add(block_result.normal);
+        let x = block_pos.x.floor() as i32 as f32;
+        let y = block_pos.y.floor() as i32 as f32;
+        let z = block_pos.z.floor() as i32 as f32;
+
+        let aabb = &player.aabb_def;
+        let player_min_x = player.position.x + aabb.offset.x;
+        let player_min_y = player.position.y + aabb.offset.y - 0.1; // add offset to prevent physics glitches
+        let player_min_z = player.position.z + aabb.offset.z;
+        let player_max_x = player.position.x + aabb.offset.x + aabb.extents.x;
+        let player_max_y = player.position.y + aabb.offset.y + aabb.extents.y;
+        let player_max_z = player.position.z + aabb.offset.z + aabb.extents.z;
+
+        let is_clear_of_player = (player_max_x < x || player_min_x > x + 1.0) ||
+            (player_max_y < y || player_min_y > y + 1.0) ||
+            (player_max_z < z || player_min_z > z + 1.0);
+        if !is_clear_of_player && !player.caps.flying {
+            return false;
+        }
+
+        self.edit_block(world, Point3::new(x as i32, y as i32, z as i32), material)
     }
 
     pub fn render_ui(&self, screen_size: (i32, i32)) {
@@ -254,3 +344,55 @@ impl Gameplay {
         self.crosshair_shader.unbind();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use cgmath::{Point3, Vector3};
+
+    use crate::core::GlContext;
+    use crate::gamelogic::content::blocks;
+    use crate::gamelogic::gameplay::Gameplay;
+    use crate::gamelogic::world::World;
+    use crate::gamelogic::worldgen::{GenPalette, GeneratorKind};
+    use crate::graphics::svo_picker::RayResult;
+    use crate::systems::jobs::JobSystem;
+    use crate::systems::physics::{AABBDef, Entity};
+
+    /// Tests that removing a block that was already removed, e.g. because the look ray is a frame behind, is not
+    /// recorded, so that a single undo restores the block.
+    #[test]
+    fn repeated_remove_is_not_recorded() {
+        let _context = GlContext::new_headless(64, 64); // do not drop context
+
+        let player = Entity::new(
+            Point3::new(16.0, 80.0, 16.0),
+            AABBDef::new(Vector3::new(-0.4, -1.7, -0.4), Vector3::new(0.8, 1.8, 0.8)),
+        );
+
+        let job_system = Rc::new(JobSystem::new(2));
+        let mut world = World::new(Rc::clone(&job_system), 1, GeneratorKind::Flat, GenPalette::default());
+        world.update(&player);
+        assert!(world.set_block(20, 70, 20, blocks::STONE));
+
+        let mut gameplay = Gameplay::new();
+        gameplay.looking_at_block = Some(RayResult {
+            dst: 9.5,
+            inside_voxel: false,
+            value: blocks::STONE,
+            pos: Point3::new(20.5, 70.99, 20.5),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+        });
+
+        assert!(gameplay.remove_block(&mut world));
+        assert!(!gameplay.remove_block(&mut world));
+        assert_eq!(world.world.get_block(20, 70, 20), blocks::AIR);
+
+        assert!(gameplay.undo(&mut world));
+        assert_eq!(world.world.get_block(20, 70, 20), blocks::STONE);
+        assert!(!gameplay.undo(&mut world));
+
+        job_system.wait_until_empty_and_processed();
+    }
+}
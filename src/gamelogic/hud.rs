@@ -0,0 +1,258 @@
+use cgmath::{Matrix4, SquareMatrix, Vector2, Vector3};
+
+use crate::graphics::buffer::MappedBuffer;
+use crate::graphics::fence::Fence;
+use crate::graphics::resource::Resource;
+use crate::graphics::screen_quad::ScreenQuad;
+use crate::graphics::shader::{ShaderError, ShaderProgram, ShaderProgramBuilder};
+use crate::graphics::texture_array::{TextureArray, TextureArrayBuilder};
+
+/// `GLYPHS` is a 3x5 pixel bitmap font. Every glyph consists of five rows from top to bottom, in which the three lowest
+/// bits are the pixels from left to right. Lowercase letters are rendered as uppercase letters.
+const GLYPHS: [(char, [u8; 5]); 51] = [
+    (' ', [0b000, 0b000, 0b000, 0b000, 0b000]),
+    ('?', [0b111, 0b001, 0b010, 0b000, 0b010]),
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    (',', [0b000, 0b000, 0b000, 0b010, 0b100]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+    ('=', [0b000, 0b111, 0b000, 0b111, 0b000]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+    ('%', [0b101, 0b001, 0b010, 0b100, 0b101]),
+    ('(', [0b001, 0b010, 0b010, 0b010, 0b001]),
+    (')', [0b100, 0b010, 0b010, 0b010, 0b100]),
+    ('<', [0b001, 0b010, 0b100, 0b010, 0b001]),
+    ('>', [0b100, 0b010, 0b001, 0b010, 0b100]),
+];
+
+/// `Glyph` is a single character on screen, as read by the HUD shader.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Glyph {
+    /// `pos` is the top left corner in pixels.
+    pos: Vector2<f32>,
+    /// `layer` is the index of the character in [`GLYPHS`] and the font texture array.
+    layer: u32,
+    // pads the struct to the 8 byte alignment of the vec2 in std430
+    _padding: u32,
+}
+
+/// `Hud` renders text on top of the screen with a built-in bitmap font. Text is queued with [`Hud::draw_text`] during a
+/// frame and drawn with the next call to [`Hud::render`].
+pub struct Hud {
+    ui_view: Matrix4<f32>,
+    shader: Resource<ShaderProgram, ShaderError>,
+    font: TextureArray,
+    screen_quad: ScreenQuad,
+
+    glyphs: Vec<Glyph>,
+    glyph_buffer: MappedBuffer<Glyph>,
+    // render_fence synchronizes writing the glyph buffer with the last draw call that reads from it
+    render_fence: Fence,
+}
+
+impl Hud {
+    /// `SCALE` is the size of a font pixel in screen pixels.
+    pub const SCALE: f32 = 3.0;
+    /// `MAX_GLYPHS` is the number of characters that can be drawn per frame. Additional characters are dropped.
+    const MAX_GLYPHS: usize = 4096;
+    const GLYPH_SIZE: Vector2<f32> = Vector2 { x: 3.0, y: 5.0 };
+    /// `ADVANCE` is the horizontal distance between two characters in font pixels.
+    const ADVANCE: f32 = 4.0;
+    /// `LINE_HEIGHT` is the vertical distance between two lines in font pixels.
+    const LINE_HEIGHT: f32 = 7.0;
+    const SHADOW_COLOR: Vector3<f32> = Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+    const TEXT_COLOR: Vector3<f32> = Vector3 { x: 1.0, y: 1.0, z: 1.0 };
+
+    pub fn new() -> Self {
+        let mut font = TextureArrayBuilder::new(1, 0.0);
+        for (c, rows) in GLYPHS {
+            let pixels = rows.iter()
+                .flat_map(|row| (0..3).rev().map(move |bit| (row >> bit) & 1 == 1))
+                .flat_map(|set| [255, 255, 255, if set { 255 } else { 0 }])
+                .collect();
+            font.add_rgba8(&c.to_string(), 3, 5, pixels).unwrap();
+        }
+
+        Self {
+            ui_view: Matrix4::identity(),
+            shader: Resource::new(
+                || ShaderProgramBuilder::new().load_shader_bundle("assets/shaders/hud.glsl")?.build()
+            ),
+            font: font.build().unwrap(),
+            screen_quad: ScreenQuad::new(),
+            glyphs: Vec::new(),
+            glyph_buffer: MappedBuffer::new(Self::MAX_GLYPHS),
+            render_fence: Fence::new(),
+        }
+    }
+
+    pub fn handle_window_resize(&mut self, width: i32, height: i32) {
+        self.ui_view = cgmath::ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
+    }
+
+    pub fn reload_resources(&mut self) {
+        if let Err(e) = self.shader.reload() {
+            println!("error reloading hud shader: {e:?}");
+        }
+    }
+
+    pub fn reload_changed_shaders(&mut self) {
+        if let Err(e) = self.shader.reload_if_changed() {
+            println!("error reloading hud shader: {e:?}");
+        }
+    }
+
+    /// Queues `text` to be drawn with its top left corner at (`x`, `y`) in pixels from the top left corner of the
+    /// screen. Lines are separated by `\n`. Characters that are not part of the font are drawn as `?`.
+    pub fn draw_text(&mut self, x: f32, y: f32, text: &str) {
+        layout_text(Vector2::new(x, y), text, Self::SCALE, &mut self.glyphs);
+    }
+
+    /// Returns the width and height of `text` in pixels, if drawn with [`Hud::draw_text`].
+    pub fn measure_text(text: &str) -> Vector2<f32> {
+        let columns = text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+        let lines = text.lines().count();
+        if columns == 0 {
+            return Vector2::new(0.0, 0.0);
+        }
+        Vector2::new(
+            ((columns - 1) as f32).mul_add(Self::ADVANCE, Self::GLYPH_SIZE.x) * Self::SCALE,
+            ((lines - 1) as f32).mul_add(Self::LINE_HEIGHT, Self::GLYPH_SIZE.y) * Self::SCALE,
+        )
+    }
+
+    /// Draws all queued text with a drop shadow and clears the queue.
+    pub fn render(&mut self) {
+        if self.glyphs.is_empty() {
+            return;
+        }
+
+        // wait for the last draw call to finish before overwriting the glyphs it reads
+        self.render_fence.wait();
+        let count = self.glyphs.len().min(Self::MAX_GLYPHS);
+        self.glyph_buffer.as_slice_mut()[..count].copy_from_slice(&self.glyphs[..count]);
+        self.glyphs.clear();
+
+        self.shader.bind();
+        self.shader.set_f32mat4("u_view", &self.ui_view);
+        self.shader.set_f32vec2("u_glyph_size", &(Self::GLYPH_SIZE * Self::SCALE));
+        self.shader.set_texture("u_font", 0, &self.font);
+        self.glyph_buffer.bind_as_storage_buffer(5);
+
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+
+        for (offset, color) in [(Self::SCALE, Self::SHADOW_COLOR), (0.0, Self::TEXT_COLOR)] {
+            self.shader.set_f32vec2("u_offset", &Vector2::new(offset, offset));
+            self.shader.set_f32vec3("u_color", &color);
+            self.screen_quad.render_instanced(count);
+        }
+
+        unsafe { gl::Disable(gl::BLEND); }
+        self.shader.unbind();
+
+        self.render_fence.place();
+    }
+}
+
+/// Appends a glyph for every visible character of `text` to `glyphs`, starting at `pos` with every font pixel being
+/// `scale` pixels in size.
+fn layout_text(pos: Vector2<f32>, text: &str, scale: f32, glyphs: &mut Vec<Glyph>) {
+    let fallback = glyph_layer('?').unwrap();
+    for (line_index, line) in text.lines().enumerate() {
+        let y = (line_index as f32 * Hud::LINE_HEIGHT).mul_add(scale, pos.y);
+        for (column, c) in line.chars().enumerate() {
+            if c.is_whitespace() {
+                continue;
+            }
+            glyphs.push(Glyph {
+                pos: Vector2::new((column as f32 * Hud::ADVANCE).mul_add(scale, pos.x), y),
+                layer: glyph_layer(c).unwrap_or(fallback),
+                _padding: 0,
+            });
+        }
+    }
+}
+
+/// Returns the index of `c` in [`GLYPHS`], which is also its layer in the font texture array.
+fn glyph_layer(c: char) -> Option<u32> {
+    let c = c.to_ascii_uppercase();
+    GLYPHS.iter().position(|(glyph, _)| *glyph == c).map(|index| index as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector2;
+
+    use crate::gamelogic::hud::{Glyph, GLYPHS, glyph_layer, Hud, layout_text};
+
+    /// Tests that every glyph is defined once and only uses the three lowest bits.
+    #[test]
+    fn glyphs() {
+        for (i, (c, rows)) in GLYPHS.iter().enumerate() {
+            assert_eq!(glyph_layer(*c), Some(i as u32), "{c} is defined more than once");
+            assert!(rows.iter().all(|row| *row < 0b1000), "{c} is wider than 3 pixels");
+        }
+        assert_eq!(glyph_layer('f'), glyph_layer('F'));
+        assert_eq!(glyph_layer('~'), None);
+    }
+
+    /// Tests that characters are placed on a grid, skipping whitespace, and that unknown characters fall back to `?`.
+    #[test]
+    fn layout() {
+        let glyph = |x: f32, y: f32, c: char| Glyph { pos: Vector2::new(x, y), layer: glyph_layer(c).unwrap(), _padding: 0 };
+
+        let mut glyphs = Vec::new();
+        layout_text(Vector2::new(10.0, 20.0), "a b\n~1", 2.0, &mut glyphs);
+        assert_eq!(glyphs, vec![
+            glyph(10.0, 20.0, 'A'),
+            glyph(26.0, 20.0, 'B'),
+            glyph(10.0, 34.0, '?'),
+            glyph(18.0, 34.0, '1'),
+        ]);
+
+        assert_eq!(Hud::measure_text(""), Vector2::new(0.0, 0.0));
+        assert_eq!(Hud::measure_text("ab\nc"), Vector2::new(7.0 * Hud::SCALE, 12.0 * Hud::SCALE));
+    }
+}
@@ -0,0 +1,123 @@
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Vector2, Vector3};
+
+use crate::gamelogic::world::World;
+use crate::graphics::camera::Camera;
+use crate::graphics::framebuffer::Framebuffer;
+use crate::graphics::resource::Resource;
+use crate::graphics::screen_quad::ScreenQuad;
+use crate::graphics::shader::{ShaderError, ShaderProgram, ShaderProgramBuilder};
+
+/// `Minimap` renders the world from above with an orthographic camera into an offscreen framebuffer, and draws it into
+/// the top right corner of the screen. North (-z) is always up, and the player is marked in the center together with
+/// the direction they are looking in.
+pub struct Minimap {
+    ui_view: Matrix4<f32>,
+    shader: Resource<ShaderProgram, ShaderError>,
+    screen_quad: ScreenQuad,
+    fbo: Framebuffer,
+    camera: Camera,
+    screen_width: f32,
+
+    size: u32,
+    zoom: f32,
+    heading: Vector2<f32>,
+}
+
+impl Minimap {
+    /// `DEFAULT_SIZE` is the default width and height of the minimap in pixels.
+    pub const DEFAULT_SIZE: u32 = 256;
+    /// `DEFAULT_ZOOM` is the default number of pixels per block.
+    pub const DEFAULT_ZOOM: f32 = 2.0;
+    /// `CAMERA_HEIGHT` is the distance in blocks above the player, from which the world is rendered.
+    const CAMERA_HEIGHT: f32 = 96.0;
+    /// `MAX_RAY_DISTANCE` limits how far rays travel downwards from the camera, so that the map does not show the
+    /// ground far below the player.
+    const MAX_RAY_DISTANCE: f32 = 256.0;
+    const MARGIN: f32 = 16.0;
+
+    /// Creates a minimap that is `size` pixels wide and high, and shows `zoom` pixels per block.
+    pub fn new(size: u32, zoom: f32) -> Self {
+        let mut camera = Camera::new(72.0, 1.0, 0.01, Self::MAX_RAY_DISTANCE);
+        camera.forward = -Vector3::unit_y();
+        camera.up = -Vector3::unit_z();
+
+        let mut minimap = Self {
+            ui_view: Matrix4::identity(),
+            shader: Resource::new(
+                || ShaderProgramBuilder::new().load_shader_bundle("assets/shaders/minimap.glsl")?.build()
+            ),
+            screen_quad: ScreenQuad::new(),
+            fbo: Framebuffer::new(size as i32, size as i32, false, false),
+            camera,
+            screen_width: 0.0,
+            size,
+            zoom: 1.0,
+            heading: Vector2::new(0.0, 1.0),
+        };
+        minimap.set_zoom(zoom);
+        minimap
+    }
+
+    /// Changes the number of pixels per block. The value is clamped to a minimum of 0.1.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(0.1);
+        let extent = self.size as f32 / self.zoom;
+        self.camera.set_orthographic(extent, extent, 0.01, Self::MAX_RAY_DISTANCE);
+    }
+
+    pub fn handle_window_resize(&mut self, width: i32, height: i32) {
+        self.ui_view = cgmath::ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
+        self.screen_width = width as f32;
+    }
+
+    pub fn reload_resources(&mut self) {
+        if let Err(e) = self.shader.reload() {
+            println!("error reloading minimap shader: {e:?}");
+        }
+    }
+
+    pub fn reload_changed_shaders(&mut self) {
+        if let Err(e) = self.shader.reload_if_changed() {
+            println!("error reloading minimap shader: {e:?}");
+        }
+    }
+
+    /// Centers the minimap on the player's position. The marker points in the direction of `forward`.
+    pub fn update(&mut self, position: Point3<f32>, forward: Vector3<f32>) {
+        self.camera.position = position + Vector3::unit_y() * Self::CAMERA_HEIGHT;
+
+        // screen space y points towards north (-z)
+        let heading = Vector2::new(forward.x, -forward.z);
+        if heading.magnitude2() > 0.0 {
+            self.heading = heading.normalize();
+        }
+    }
+
+    /// Renders the world into the minimap's framebuffer and draws it on top of the current framebuffer.
+    pub fn render(&self, world: &World) {
+        world.render_minimap(&self.camera, &self.fbo);
+
+        let size = self.size as f32;
+        let offset = Vector2::new(self.screen_width - size - Self::MARGIN, Self::MARGIN);
+
+        self.shader.bind();
+        self.shader.set_f32mat4("u_view", &self.ui_view);
+        self.shader.set_f32vec2("u_offset", &offset);
+        self.shader.set_f32vec2("u_size", &Vector2::new(size, size));
+        self.shader.set_f32vec2("u_heading", &self.heading);
+        self.shader.set_i32("u_map", 0);
+
+        unsafe {
+            // the map is written by a compute shader
+            gl::MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, self.fbo.color_attachment());
+
+            self.screen_quad.render();
+
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        self.shader.unbind();
+    }
+}
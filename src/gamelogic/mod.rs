@@ -1,5 +1,10 @@
 pub mod game;
+pub mod benchmark;
+mod console;
 mod content;
 mod world;
 mod gameplay;
+mod hud;
+mod minimap;
+mod sun;
 mod worldgen;
@@ -0,0 +1,97 @@
+use std::f32::consts::PI;
+
+use cgmath::{InnerSpace, Vector3, VectorSpace};
+
+/// `Sun` advances the time of day and derives the direction and color of the sun light from it. The sun rises in
+/// negative x, culminates at noon and sets in positive x. Its path is tilted towards positive z, so that the light does
+/// not shine straight down at noon. Below the horizon, the sun light fades to black and only ambient light remains.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sun {
+    /// `time` is the time of day in hours within \[0;24).
+    time: f32,
+    /// `time_scale` is the number of in-game seconds that pass per real second.
+    time_scale: f32,
+}
+
+impl Sun {
+    /// `DEFAULT_TIME_SCALE` lets a day pass in 20 minutes.
+    pub const DEFAULT_TIME_SCALE: f32 = 72.0;
+    /// `NOON` is the default start time in hours.
+    pub const NOON: f32 = 12.0;
+    /// `TILT` is the angle between the sun's path and the zenith.
+    const TILT: f32 = PI / 6.0;
+    /// `TWILIGHT_ELEVATION` is the elevation of the sun's direction vector, above which the sun light is at full
+    /// intensity. Below, it fades out until the sun reaches the horizon.
+    const TWILIGHT_ELEVATION: f32 = 0.2;
+    /// `WARM_ELEVATION` is the elevation, below which the sun light gets warmer towards [`Self::SUNSET_COLOR`].
+    const WARM_ELEVATION: f32 = 0.5;
+    const DAY_COLOR: Vector3<f32> = Vector3 { x: 1.0, y: 1.0, z: 1.0 };
+    const SUNSET_COLOR: Vector3<f32> = Vector3 { x: 1.0, y: 0.55, z: 0.3 };
+
+    /// Creates a sun at `time` hours, which is wrapped into \[0;24).
+    pub fn new(time: f32, time_scale: f32) -> Self {
+        Self { time: time.rem_euclid(24.0), time_scale }
+    }
+
+    pub fn time(self) -> f32 {
+        self.time
+    }
+
+    /// Advances the time of day by `delta_time` real seconds.
+    pub fn update(&mut self, delta_time: f32) {
+        self.time = (delta_time * self.time_scale / 3600.0 + self.time).rem_euclid(24.0);
+    }
+
+    /// Returns the normalized direction from the world towards the sun.
+    fn position(self) -> Vector3<f32> {
+        let hour_angle = (self.time - 12.0) / 24.0 * 2.0 * PI;
+        let (sin, cos) = hour_angle.sin_cos();
+        Vector3::new(sin, cos * Self::TILT.cos(), cos * Self::TILT.sin())
+    }
+
+    /// Returns the normalized direction in which the sun light shines.
+    pub fn direction(self) -> Vector3<f32> {
+        -self.position().normalize()
+    }
+
+    /// Returns the color of the sun light, which is black while the sun is below the horizon.
+    pub fn light_color(self) -> Vector3<f32> {
+        let elevation = self.position().y;
+        let intensity = (elevation / Self::TWILIGHT_ELEVATION).clamp(0.0, 1.0);
+        let warmth = (elevation / Self::WARM_ELEVATION).clamp(0.0, 1.0);
+        Self::SUNSET_COLOR.lerp(Self::DAY_COLOR, warmth) * intensity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{InnerSpace, Vector3};
+
+    use crate::gamelogic::sun::Sun;
+
+    fn assert_vec_eq(actual: Vector3<f32>, expected: Vector3<f32>) {
+        assert!((actual - expected).magnitude() < 1e-5, "expected {expected:?}, got {actual:?}");
+    }
+
+    /// Tests that the sun's direction and color match the time of day and that time advances with the time scale.
+    #[test]
+    fn direction_and_color() {
+        let mut sun = Sun::new(Sun::NOON, 3600.0);
+        assert_vec_eq(sun.direction(), Vector3::new(0.0, -(30f32.to_radians().cos()), -0.5));
+        assert_vec_eq(sun.light_color(), Vector3::new(1.0, 1.0, 1.0));
+
+        // sunset after six in-game hours
+        sun.update(6.0);
+        assert!((sun.time() - 18.0).abs() < 1e-4);
+        assert_vec_eq(sun.direction(), Vector3::new(-1.0, 0.0, 0.0));
+        assert_vec_eq(sun.light_color(), Vector3::new(0.0, 0.0, 0.0));
+
+        // time wraps around at midnight
+        sun.update(8.0);
+        assert!((sun.time() - 2.0).abs() < 1e-4);
+        assert!(sun.direction().y > 0.0);
+        assert_vec_eq(sun.light_color(), Vector3::new(0.0, 0.0, 0.0));
+
+        assert_vec_eq(Sun::new(-18.0, 1.0).direction(), Vector3::new(1.0, 0.0, 0.0));
+    }
+}
@@ -1,25 +1,30 @@
-use std::ops::{Add, Sub};
+use std::path::Path;
 use std::rc::Rc;
 use std::sync::Arc;
 
-use cgmath::{EuclideanSpace, InnerSpace, Point3, Vector3};
+use cgmath::{InnerSpace, Point3, Vector2, Vector3};
 use imgui::{Condition, TreeNodeFlags};
+use rustc_hash::FxHashSet;
 
 use crate::{graphics, systems};
 use crate::core::Frame;
+use crate::gamelogic::benchmark;
 use crate::gamelogic::content::blocks;
+use crate::gamelogic::sun::Sun;
 use crate::gamelogic::worldgen;
-use crate::gamelogic::worldgen::{Generator, Noise, SplinePoint};
-use crate::graphics::camera::Camera;
+use crate::gamelogic::worldgen::{FlatGenerator, GenPalette, Generator, GeneratorKind, Noise, SplinePoint};
+use crate::graphics::camera::{Camera, ProjectionMode};
 use crate::graphics::framebuffer::Framebuffer;
-use crate::graphics::svo::RenderParams;
-use crate::systems::{storage, worldsvo};
-use crate::systems::chunkloader::{ChunkEvent, ChunkLoader};
+use crate::graphics::svo::{EdgeOutline, FogParams, RenderMode, RenderParams};
+use crate::systems::worldsvo;
+use crate::systems::worldsvo::UnsupportedChunkSize;
+use crate::systems::chunkloader::{ChunkEvent, ChunkLoader, LoadQueue, LodBands};
 use crate::systems::jobs::JobSystem;
 use crate::systems::physics::{Entity, Physics};
 use crate::systems::storage::Storage;
-use crate::world::chunk::{Chunk, ChunkPos, ChunkStorageAllocator};
+use crate::world::chunk::{BlockId, Chunk, ChunkPos, ChunkStorageAllocator};
 use crate::world::world;
+use crate::world::world::BorrowedChunk;
 
 /// World is the game system responsible for keeping all chunks in the voxel world loaded and
 /// renders them. It delegates loading from memory or generating chunks, as well as serialization
@@ -28,12 +33,19 @@ pub struct World {
     job_system: Rc<JobSystem>,
 
     chunk_loader: ChunkLoader,
+    load_queue: LoadQueue,
+    /// `evicted_chunks` were evicted from the world SVO due to its memory budget. They stay registered with the chunk
+    /// loader and are kept in the world, so that they are serialized again with all block changes once the budget
+    /// frees up.
+    evicted_chunks: FxHashSet<ChunkPos>,
     pub chunk_storage_allocator: Arc<ChunkStorageAllocator>,
-    pub storage: Storage,
 
     pub world: world::World,
     world_generator: systems::worldgen::Generator,
+    world_generator_kind: GeneratorKind,
     world_generator_cfg: worldgen::Config,
+    /// `storage` is kept to be handed to the world generator again, when the world is regenerated.
+    storage: Arc<Storage>,
     pub world_svo: worldsvo::Svo,
     world_fbo: Framebuffer,
 
@@ -41,14 +53,40 @@ pub struct World {
 
     pub camera: Camera,
     pub selected_voxel: Option<Point3<f32>>,
+    /// `render_block_outline` enables the outline around the selected voxel.
+    pub render_block_outline: bool,
     pub ambient_intensity: f32,
     pub sun_direction: Vector3<f32>,
+    pub sun_color: Vector3<f32>,
+    /// `sun` advances the time of day and overrides `sun_direction` and `sun_color`, if set.
+    pub sun: Option<Sun>,
     pub render_shadows: bool,
     pub shadow_distance: f32,
+    pub shadow_accumulate: bool,
+    pub shadow_softness: f32,
+    pub edge_outline: Option<EdgeOutline>,
+    pub render_mode: RenderMode,
+    /// `fog` is applied when rendering. If `fog.end` is not set, fog ends at the loading radius.
+    pub fog: FogParams,
 }
 
 impl World {
-    pub fn new(job_system: Rc<JobSystem>, loading_radius: u32) -> Self {
+    pub const MIN_FOV_Y_DEG: f32 = 30.0;
+    pub const MAX_FOV_Y_DEG: f32 = 110.0;
+    pub const MIN_RENDER_DISTANCE: u32 = 1;
+    pub const MAX_RENDER_DISTANCE: u32 = 50;
+    /// `IDLE_CHUNK_BUFFERS` is the number of chunk buffers that are kept for block changes once all chunks are loaded.
+    const IDLE_CHUNK_BUFFERS: usize = 8;
+    /// `FLAT_WORLD_HEIGHT` is the y level of the surface, if the world is generated by [`GeneratorKind::Flat`].
+    const FLAT_WORLD_HEIGHT: i32 = 64;
+    /// `UNLOAD_MARGIN` is the number of chunks outside the loading radius, that are kept loaded before being unloaded.
+    /// The world SVO covers the margin as well, so that chunks inside the margin stay visible.
+    const UNLOAD_MARGIN: u32 = 2;
+    /// `MAX_QUEUED_JOBS` limits the number of jobs in the job system, up to which chunks are taken from the load queue.
+    /// Keeping the job system's queue short allows to react to camera rotations quickly.
+    const MAX_QUEUED_JOBS: usize = 64;
+
+    pub fn new(job_system: Rc<JobSystem>, loading_radius: u32, generator_kind: GeneratorKind, palette: GenPalette) -> Self {
         let world_cfg = worldgen::Config {
             sea_level: 70,
             continentalness: Noise {
@@ -71,33 +109,73 @@ impl World {
                     SplinePoint { x: 1.0, y: 4.0 },
                 ],
             },
+            palette,
         };
         let chunk_allocator = Arc::new(ChunkStorageAllocator::new());
-        let chunk_generator = Generator::new(1, world_cfg.clone());
-        let graphics_svo = graphics::Svo::new(&blocks::new_registry());
+        let storage = Arc::new(Storage::new());
+        let world_generator = Self::new_world_generator(&job_system, &chunk_allocator, generator_kind, &world_cfg, &storage);
+        let world_svo = Self::new_world_svo(&job_system, loading_radius + Self::UNLOAD_MARGIN, None, false);
+        let mut chunk_loader = ChunkLoader::new(loading_radius, 0, 8);
+        chunk_loader.set_unload_margin(Self::UNLOAD_MARGIN);
 
         Self {
-            job_system: Rc::clone(&job_system),
-            chunk_loader: ChunkLoader::new(loading_radius, 0, 8),
-            chunk_storage_allocator: chunk_allocator.clone(),
-            storage: Storage::new(),
+            job_system,
+            chunk_loader,
+            load_queue: LoadQueue::new(),
+            evicted_chunks: FxHashSet::default(),
+            chunk_storage_allocator: chunk_allocator,
             world: world::World::new(),
-            world_generator: systems::worldgen::Generator::new(Rc::clone(&job_system), chunk_allocator, chunk_generator),
+            world_generator,
+            world_generator_kind: generator_kind,
             world_generator_cfg: world_cfg,
-            world_svo: worldsvo::Svo::new(job_system, graphics_svo, loading_radius),
+            storage,
+            world_svo,
             world_fbo: Framebuffer::new(1920, 1080, false, false),
             physics: Physics::new(),
             camera: Camera::new(72.0, 1.0, 0.01, 1024.0),
             selected_voxel: None,
+            render_block_outline: true,
             ambient_intensity: 0.3,
             sun_direction: Vector3::new(-1.0, -1.0, -1.0).normalize(),
+            sun_color: Vector3::new(1.0, 1.0, 1.0),
+            sun: None,
             render_shadows: true,
             shadow_distance: 500.0,
+            shadow_accumulate: false,
+            shadow_softness: 0.0,
+            edge_outline: None,
+            render_mode: RenderMode::Shaded,
+            fog: FogParams::default(),
         }
     }
 
+    fn new_world_generator(job_system: &Rc<JobSystem>, allocator: &Arc<ChunkStorageAllocator>, kind: GeneratorKind, cfg: &worldgen::Config, storage: &Arc<Storage>) -> systems::worldgen::Generator {
+        let job_system = Rc::clone(job_system);
+        let allocator = Arc::clone(allocator);
+        let mut generator = match kind {
+            GeneratorKind::Noise => systems::worldgen::Generator::new(job_system, allocator, Generator::new(1, cfg.clone())),
+            GeneratorKind::Flat => systems::worldgen::Generator::new(job_system, allocator, FlatGenerator::new(Self::FLAT_WORLD_HEIGHT, cfg.palette)),
+        };
+        generator.set_storage(Arc::clone(storage));
+        generator
+    }
+
+    fn new_world_svo(job_system: &Rc<JobSystem>, render_distance: u32, memory_limit: Option<usize>, compress_chunks: bool) -> worldsvo::Svo {
+        let graphics_svo = graphics::Svo::new(&blocks::new_registry());
+        let mut world_svo = worldsvo::Svo::new(Rc::clone(job_system), graphics_svo, render_distance);
+        world_svo.set_max_bytes(memory_limit);
+        world_svo.set_chunk_compression(compress_chunks);
+        world_svo
+    }
+
     pub fn update_fixed(&mut self, entity: &mut Entity, delta_time: f32) {
         self.physics.step(delta_time, &self.world_svo, entity);
+
+        if let Some(sun) = &mut self.sun {
+            sun.update(delta_time);
+            self.sun_direction = sun.direction();
+            self.sun_color = sun.light_color();
+        }
     }
 
     pub fn update(&mut self, entity: &Entity) {
@@ -107,54 +185,95 @@ impl World {
         self.handle_chunk_loading();
     }
 
+    /// Returns true, while chunks are still queued, generated or serialized into the SVO. Queued chunks are not
+    /// considered while loading is paused due to the memory budget, as they are not loaded until memory is freed.
+    pub fn is_loading(&self) -> bool {
+        let is_queue_loading = !self.load_queue.is_empty() && !crate::is_over_memory_budget();
+        is_queue_loading || self.world_generator.has_pending_jobs() || self.world_svo.has_pending_jobs()
+    }
+
+    /// Returns the number of chunks that are waiting to be loaded or generated.
+    pub fn load_queue_len(&self) -> usize {
+        self.load_queue.len()
+    }
+
     pub fn handle_window_resize(&mut self, width: i32, height: i32, aspect_ratio: f32) {
-        self.camera.update_projection(72.0, aspect_ratio, 0.01, 1024.0);
+        self.camera.update_projection(self.camera.get_fov_y_deg(), aspect_ratio, 0.01, 1024.0);
         self.world_fbo = Framebuffer::new(width, height, false, false);
     }
 
+    /// Changes the camera's vertical field of view. The value is clamped to
+    /// \[[`Self::MIN_FOV_Y_DEG`]; [`Self::MAX_FOV_Y_DEG`]\].
+    pub fn set_fov_y_deg(&mut self, fov: f32) {
+        self.camera.set_fov_y_deg(fov.clamp(Self::MIN_FOV_Y_DEG, Self::MAX_FOV_Y_DEG));
+    }
+
+    /// Returns the radius in chunks around the camera, in which chunks are loaded and rendered.
+    pub fn render_distance(&self) -> u32 {
+        self.chunk_loader.get_radius()
+    }
+
+    /// Changes the radius in chunks around the camera, in which chunks are loaded and rendered. The value is clamped to
+    /// \[[`Self::MIN_RENDER_DISTANCE`]; [`Self::MAX_RENDER_DISTANCE`]\]. When shrinking, chunks outside the new radius
+    /// and its unload margin are unloaded immediately, so that their buffers are returned right away. When growing, the
+    /// new chunks are queued by priority with the next update.
+    pub fn set_render_distance(&mut self, chunks: u32) {
+        let chunks = chunks.clamp(Self::MIN_RENDER_DISTANCE, Self::MAX_RENDER_DISTANCE);
+        let old_chunks = self.chunk_loader.get_radius();
+        if chunks == old_chunks {
+            return;
+        }
+
+        self.chunk_loader.set_radius(chunks);
+        // removes all chunks outside the new bounds from the SVO
+        self.world_svo.set_radius(chunks + Self::UNLOAD_MARGIN);
+
+        if chunks < old_chunks {
+            let events = self.chunk_loader.update(self.camera.position);
+            self.handle_chunk_events(&events);
+        }
+    }
+
+    /// Limits the memory used by serialized chunks in the world SVO. If exceeded, the least recently serialized chunks
+    /// are evicted and serialized again once enough memory is available. See [`worldsvo::Svo::set_max_bytes`].
+    pub fn set_svo_memory_limit(&mut self, bytes: Option<usize>) {
+        self.world_svo.set_max_bytes(bytes);
+    }
+
+    /// Reduces the memory of chunks that wait to be added to the world SVO by run-length encoding them. See
+    /// [`worldsvo::Svo::set_chunk_compression`].
+    pub fn set_chunk_compression(&mut self, compress: bool) {
+        self.world_svo.set_chunk_compression(compress);
+    }
+
+    /// Sets the distance bands that select the LOD of chunks. Loaded chunks that cross into another band are serialized
+    /// again with their new LOD.
+    pub fn set_lod_bands(&mut self, lod_bands: LodBands) {
+        self.chunk_loader.set_lod_bands(lod_bands);
+    }
+
     pub fn reload_resources(&mut self) {
         self.world_svo.reload_resources();
     }
 
+    pub fn reload_changed_shaders(&mut self) {
+        self.world_svo.reload_changed_shaders();
+    }
+
     fn handle_chunk_loading(&mut self) {
+        let _span = benchmark::span("chunk_loading");
+
+        // unloads and LOD changes are always handled, so that memory is freed while over budget
         let chunk_events = self.chunk_loader.update(self.camera.position);
         if !chunk_events.is_empty() {
-            let mut generate_count = 0;
-
-            let chunk_events = Self::sort_chunks_by_view_frustum(chunk_events, &self.camera);
-            for event in &chunk_events {
-                match event {
-                    ChunkEvent::Load { pos, lod } => {
-                        let result = self.storage.load(pos);
-                        if result.is_ok() {
-                            let mut chunk = result.ok().unwrap();
-                            chunk.lod = *lod;
-                            self.world.set_chunk(chunk);
-                            continue;
-                        }
-
-                        let err = result.err().unwrap();
-                        match err {
-                            storage::LoadError::NotFound => {
-                                self.world_generator.enqueue_chunk(*pos, *lod);
-                                generate_count += 1;
-                            }
-                        }
-                    }
-                    ChunkEvent::Unload { pos } => {
-                        self.world_generator.dequeue_chunk(pos);
-                        self.world.remove_chunk(pos);
-                    }
-                    ChunkEvent::LodChange { pos, lod } => {
-                        if let Some(chunk) = self.world.get_chunk_mut(pos) {
-                            chunk.lod = *lod;
-                        }
-                    }
-                }
-            }
-            if !chunk_events.is_empty() {
-                println!("generate {generate_count} new chunks");
-            }
+            self.handle_chunk_events(&chunk_events);
+        } else if !self.is_loading() {
+            // free the chunk buffers that were needed for loading, once the player stopped moving
+            self.world_svo.shrink_chunk_buffer_pool(Self::IDLE_CHUNK_BUFFERS);
+        }
+        // pause loading new chunks while over the memory budget, until enough memory was freed
+        if !crate::is_over_memory_budget() {
+            self.load_queued_chunks();
         }
         for chunk in self.world_generator.get_generated_chunks(400) {
             if self.chunk_loader.is_loaded(&chunk.pos) {
@@ -164,14 +283,14 @@ impl World {
                 self.world.set_chunk_unchanged(chunk);
 
                 let chunk = self.world.borrow_chunk(&pos).unwrap();
-                self.world_svo.set_chunk(chunk);
+                self.set_svo_chunk(chunk);
             }
         }
         for pos in self.world.get_changed_chunks(400) {
             if let Some(chunk) = self.world.get_chunk(&pos) {
                 if chunk.storage.is_some() {
                     let chunk = self.world.borrow_chunk(&pos).unwrap();
-                    self.world_svo.set_chunk(chunk);
+                    self.set_svo_chunk(chunk);
                 }
             } else {
                 self.world_svo.remove_chunk(&pos);
@@ -183,37 +302,68 @@ impl World {
         for chunk in chunks {
             self.world.return_chunk(chunk);
         }
-    }
 
-    /// `sort_chunks_by_view_frustum` sorts the given chunk event to contain all chunks that are in
-    /// the camera's view first. All other chunks are sorted radially from forward to backward
-    /// camera vector.
-    fn sort_chunks_by_view_frustum(events: Vec<ChunkEvent>, camera: &Camera) -> Vec<ChunkEvent> {
-        let mut visible_chunks = Vec::new();
-        let mut other_chunks = Vec::new();
-        for evt in events {
-            let pos = evt.get_pos().as_block_pos().add(Vector3::new(16, 16, 16));
-            if camera.is_in_frustum(pos.cast().unwrap(), 32.0) {
-                visible_chunks.push(evt);
-            } else {
-                other_chunks.push(evt);
+        self.evicted_chunks.extend(self.world_svo.take_evicted_chunks());
+        if !self.evicted_chunks.is_empty() && self.world_svo.has_budget_for_evicted_chunks() {
+            for pos in std::mem::take(&mut self.evicted_chunks) {
+                // chunks that are borrowed right now are already being serialized again
+                if self.world.get_chunk(&pos).is_some_and(|chunk| chunk.storage.is_some()) {
+                    let chunk = self.world.borrow_chunk(&pos).unwrap();
+                    self.set_svo_chunk(chunk);
+                }
             }
         }
+    }
+
+    /// Adds the chunk to the world SVO. Chunks that cannot be rendered are returned to the world right away.
+    fn set_svo_chunk(&mut self, chunk: BorrowedChunk) {
+        if let Err(UnsupportedChunkSize(chunk)) = self.world_svo.set_chunk(chunk) {
+            println!("chunk {:?} cannot be rendered, as its depth {} differs from {}", chunk.pos, chunk.depth(), Chunk::DEPTH);
+            self.world.return_chunk(chunk);
+        }
+    }
 
-        other_chunks.sort_by(|lhs, rhs| {
-            let lhs: Vector3<f32> = lhs.get_pos().as_block_pos().to_vec().cast().unwrap();
-            let rhs: Vector3<f32> = rhs.get_pos().as_block_pos().to_vec().cast().unwrap();
+    fn handle_chunk_events(&mut self, events: &[ChunkEvent]) {
+        for event in events {
+            match event {
+                ChunkEvent::Load { pos, lod } => {
+                    self.load_queue.push(*pos, *lod);
+                }
+                ChunkEvent::Unload { pos } => {
+                    self.load_queue.remove(pos);
+                    self.evicted_chunks.remove(pos);
+                    self.world_generator.dequeue_chunk(pos);
+                    self.world.remove_chunk(pos);
+                }
+                ChunkEvent::LodChange { pos, lod } => {
+                    self.load_queue.set_lod(pos, *lod);
+                    if let Some(chunk) = self.world.get_chunk_mut(pos) {
+                        chunk.lod = *lod;
+                    }
+                }
+            }
+        }
+    }
 
-            let tl = lhs.sub(camera.position.to_vec()).normalize();
-            let tr = rhs.sub(camera.position.to_vec()).normalize();
-            let dl = -tl.dot(camera.forward);
-            let dr = -tr.dot(camera.forward);
+    /// Takes the highest priority chunks from the load queue, until the job system holds [`Self::MAX_QUEUED_JOBS`]
+    /// jobs. Chunks are loaded from storage on the worker threads, if possible, otherwise they are generated.
+    fn load_queued_chunks(&mut self) {
+        let n = Self::MAX_QUEUED_JOBS.saturating_sub(self.job_system.queue_len());
+        if n == 0 || self.load_queue.is_empty() {
+            return;
+        }
 
-            dl.total_cmp(&dr)
-        });
+        let chunks = self.load_queue.pop(&self.camera, n);
+        println!("load {} new chunks", chunks.len());
+        for (pos, lod) in chunks {
+            self.world_generator.enqueue_chunk(pos, lod);
+        }
+    }
 
-        visible_chunks.extend(other_chunks.iter());
-        visible_chunks
+    /// Sets the storage from which chunks are loaded and in which generated chunks are stored.
+    pub fn set_storage(&mut self, storage: Storage) {
+        self.storage = Arc::new(storage);
+        self.world_generator.set_storage(Arc::clone(&self.storage));
     }
 
     pub fn add_chunk(&mut self, chunk: Chunk) {
@@ -221,22 +371,90 @@ impl World {
         self.world.set_chunk(chunk);
     }
 
+    /// Sets the block at the given position in world space. If no chunk exists at that position, an empty chunk is
+    /// added, if it is within the loading radius. See [`world::World::set_block_or_create_chunk`]. Returns true, if the
+    /// block was set.
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: BlockId) -> bool {
+        // chunks outside the loading radius would never be unloaded again
+        let pos = ChunkPos::from_block_pos(x, y, z);
+        let is_loaded = self.chunk_loader.is_loaded(&pos);
+        if !is_loaded && !self.chunk_loader.is_in_radius(&pos) {
+            return false;
+        }
+
+        if !self.world.set_block_or_create_chunk(x, y, z, block, &self.chunk_storage_allocator) {
+            return false;
+        }
+        if !is_loaded {
+            self.chunk_loader.add_loaded_chunk(pos, Chunk::DEPTH);
+        }
+        true
+    }
+
     pub fn render(&self, aspect_ratio: f32) {
+        let mut fog = self.fog;
+        if fog.end <= 0.0 {
+            fog.end = (self.chunk_loader.get_radius() * Chunk::SIZE) as f32;
+        }
+
         self.world_svo.render(RenderParams {
             ambient_intensity: self.ambient_intensity,
             light_dir: self.sun_direction,
+            light_color: self.sun_color,
             cam_pos: self.camera.position,
             cam_fwd: self.camera.forward,
             cam_up: self.camera.up,
             fov_y_rad: self.camera.get_fov_y_deg().to_radians(),
             aspect_ratio,
-            selected_voxel: self.selected_voxel,
+            ortho_size: None,
+            max_ray_distance: None,
+            selected_voxel: self.selected_voxel.filter(|_| self.render_block_outline),
             render_shadows: self.render_shadows,
             shadow_distance: self.shadow_distance,
+            shadow_accumulate: self.shadow_accumulate,
+            shadow_softness: self.shadow_softness,
+            edge_outline: self.edge_outline,
+            render_mode: self.render_mode,
+            fog,
         }, &self.world_fbo);
         self.world_fbo.blit_to_default();
     }
 
+    /// Renders the world from the orthographic `camera` into `target` with the current lighting, but without shadows,
+    /// fog and outlines. Rays travel at most up to the camera's far plane.
+    pub fn render_minimap(&self, camera: &Camera, target: &Framebuffer) {
+        let ProjectionMode::Orthographic { width, height } = camera.get_projection_mode() else {
+            panic!("minimap camera must be orthographic");
+        };
+
+        self.world_svo.render_view(RenderParams {
+            ambient_intensity: self.ambient_intensity,
+            light_dir: self.sun_direction,
+            light_color: self.sun_color,
+            cam_pos: camera.position,
+            cam_fwd: camera.forward,
+            cam_up: camera.up,
+            fov_y_rad: camera.get_fov_y_deg().to_radians(),
+            aspect_ratio: width / height,
+            ortho_size: Some(Vector2::new(width, height)),
+            max_ray_distance: Some(camera.get_far()),
+            selected_voxel: None,
+            render_shadows: false,
+            shadow_distance: 0.0,
+            shadow_accumulate: false,
+            shadow_softness: 0.0,
+            edge_outline: None,
+            render_mode: self.render_mode,
+            fog: FogParams { density: 0.0, ..self.fog },
+        }, target, "minimap");
+    }
+
+    /// Saves the last rendered frame of the world as PNG to `path`. The UI is not included.
+    pub fn save_screenshot<P: AsRef<Path>>(&self, path: P) -> image::ImageResult<()> {
+        let pixels = self.world_fbo.read_pixels();
+        graphics::save_png(path, self.world_fbo.width() as u32, self.world_fbo.height() as u32, &pixels)
+    }
+
     pub fn render_debug_window(&mut self, frame: &mut Frame) {
         frame.ui.window("World Gen")
             .position([8.0, 2.0f32.mul_add(8.0, 290.0)], Condition::Once)
@@ -252,14 +470,15 @@ impl World {
                     self.job_system.clear();
                     self.job_system.wait_until_processed();
 
-                    let chunk_generator = Generator::new(1, self.world_generator_cfg.clone());
-                    let graphics_svo = graphics::Svo::new(&blocks::new_registry());
-
+                    let lod_bands = self.chunk_loader.get_lod_bands().clone();
                     self.chunk_loader = ChunkLoader::new(self.chunk_loader.get_radius(), 0, 8);
-                    self.storage = Storage::new();
+                    self.chunk_loader.set_lod_bands(lod_bands);
+                    self.chunk_loader.set_unload_margin(Self::UNLOAD_MARGIN);
+                    self.load_queue.clear();
+                    self.evicted_chunks.clear();
                     self.world = world::World::new();
-                    self.world_generator = systems::worldgen::Generator::new(Rc::clone(&self.job_system), self.chunk_storage_allocator.clone(), chunk_generator);
-                    self.world_svo = worldsvo::Svo::new(Rc::clone(&self.job_system), graphics_svo, self.world_svo.get_render_distance());
+                    self.world_generator = Self::new_world_generator(&self.job_system, &self.chunk_storage_allocator, self.world_generator_kind, &self.world_generator_cfg, &self.storage);
+                    self.world_svo = Self::new_world_svo(&self.job_system, self.world_svo.get_render_distance(), self.world_svo.get_max_bytes(), self.world_svo.get_chunk_compression());
                 }
 
                 frame.ui.new_line();
@@ -350,25 +569,43 @@ impl World {
                     ui.columns(1, "", false);
                 }
 
-                let old_rd = self.chunk_loader.get_radius() as i32;
+                let old_rd = self.render_distance() as i32;
                 let mut new_rd = old_rd;
                 frame.ui.input_int("render distance", &mut new_rd).build();
-                new_rd = new_rd.clamp(1, 50);
                 if new_rd != old_rd {
-                    self.chunk_loader.set_radius(new_rd as u32);
-                    self.world_svo.set_radius(new_rd as u32);
+                    self.set_render_distance(new_rd.max(0) as u32);
                 }
 
                 let old_fov = self.camera.get_fov_y_deg();
                 let mut new_fov = old_fov;
                 frame.ui.input_float("FOV", &mut new_fov).step(2.0).build();
-                new_fov = new_fov.clamp(1.0, 130.0);
                 if (new_fov - old_fov).abs() > f32::EPSILON {
-                    self.camera.set_fov_y_deg(new_fov);
+                    self.set_fov_y_deg(new_fov);
                 }
 
                 frame.ui.checkbox("render shadows", &mut self.render_shadows);
                 frame.ui.input_float("shadow distance", &mut self.shadow_distance).step(1.0).build();
+                frame.ui.checkbox("accumulate soft shadows", &mut self.shadow_accumulate);
+                frame.ui.slider("shadow softness", 0.0, 0.1, &mut self.shadow_softness);
+
+                frame.ui.slider("fog density", 0.0, 0.05, &mut self.fog.density);
+                let mut fog_color: [f32; 3] = self.fog.color.into();
+                frame.ui.color_edit3("fog color", &mut fog_color);
+                self.fog.color = fog_color.into();
+
+                frame.ui.checkbox("render block outline", &mut self.render_block_outline);
+
+                let mut render_edge_outline = self.edge_outline.is_some();
+                frame.ui.checkbox("render edge outlines", &mut render_edge_outline);
+                if render_edge_outline {
+                    let outline = self.edge_outline.get_or_insert_with(EdgeOutline::default);
+                    frame.ui.slider("outline width", 0.0, 0.5, &mut outline.width);
+                    let mut color: [f32; 3] = outline.color.into();
+                    frame.ui.color_edit3("outline color", &mut color);
+                    outline.color = color.into();
+                } else {
+                    self.edge_outline = None;
+                }
 
                 frame.ui.new_line();
                 frame.ui.separator();
@@ -393,6 +630,7 @@ impl World {
                     "F: toggle fly mode",
                     "LShift: sprint / descend",
                     "Space: jump / ascend",
+                    "Mouse Wheel: zoom (FOV)",
                 ]);
             });
     }
@@ -406,11 +644,15 @@ mod tests {
     use cgmath::{Point3, Vector3};
 
     use crate::core::GlContext;
+    use crate::gamelogic::content::blocks;
     use crate::gamelogic::world::World;
+    use crate::gamelogic::worldgen::{GenPalette, GeneratorKind};
     use crate::gl_assert_no_error;
     use crate::graphics::framebuffer::diff_images;
+    use crate::graphics::svo_picker::{PickerBatch, PickerBatchResult};
     use crate::systems::jobs::JobSystem;
-    use crate::systems::physics::{AABBDef, Entity};
+    use crate::systems::physics::{AABBDef, Entity, Raycaster};
+    use crate::world::chunk::ChunkPos;
 
     /// Tests if a standalone world object generates chunks, adds them to the SVO and renders them
     /// correctly after given enough time to properly load everything.
@@ -428,7 +670,7 @@ mod tests {
         player.caps.flying = true;
 
         let job_system = Rc::new(JobSystem::new(num_cpus::get() - 1));
-        let mut world = World::new(Rc::clone(&job_system), 15);
+        let mut world = World::new(Rc::clone(&job_system), 15, GeneratorKind::default(), GenPalette::default());
         world.handle_window_resize(width as i32, height as i32, aspect_ratio);
 
         loop {
@@ -452,4 +694,81 @@ mod tests {
         let threshold = env::var("TEST_WORLD_E2E_THRESHOLD").map_or(0.001, |x| x.parse::<f64>().unwrap());
         assert!(diff_percent < threshold, "difference: {:.5} < {:.5}", diff_percent, threshold);
     }
+
+    /// Tests that shrinking the render distance unloads chunks outside the new radius and its unload margin
+    /// immediately, and that growing it loads them again with the next update.
+    #[test]
+    fn set_render_distance() {
+        let _context = GlContext::new_headless(64, 64); // do not drop context
+
+        let player = Entity::new(
+            Point3::new(16.0, 80.0, 16.0),
+            AABBDef::new(Vector3::new(-0.4, -1.7, -0.4), Vector3::new(0.8, 1.8, 0.8)),
+        );
+
+        let job_system = Rc::new(JobSystem::new(2));
+        let mut world = World::new(Rc::clone(&job_system), 4, GeneratorKind::Flat, GenPalette::default());
+        world.update(&player);
+
+        let far = ChunkPos::new(4, 2, 0);
+        let inside_margin = ChunkPos::new(3, 2, 0);
+        assert!(world.chunk_loader.is_loaded(&far));
+
+        world.set_render_distance(0);
+        assert_eq!(world.render_distance(), World::MIN_RENDER_DISTANCE);
+        assert!(!world.chunk_loader.is_loaded(&far));
+        assert!(world.chunk_loader.is_loaded(&inside_margin));
+
+        world.set_render_distance(4);
+        assert!(!world.chunk_loader.is_loaded(&far));
+        world.update(&player);
+        assert!(world.chunk_loader.is_loaded(&far));
+
+        job_system.wait_until_empty_and_processed();
+    }
+
+    /// Tests that block changes survive evicting their chunk from the world SVO due to the memory budget, and that the
+    /// chunk is serialized again with the changes once the budget frees up.
+    #[test]
+    fn evicted_chunks_keep_block_changes() {
+        let _context = GlContext::new_headless(64, 64); // do not drop context
+
+        let player = Entity::new(
+            Point3::new(16.0, 80.0, 16.0),
+            AABBDef::new(Vector3::new(-0.4, -1.7, -0.4), Vector3::new(0.8, 1.8, 0.8)),
+        );
+
+        let job_system = Rc::new(JobSystem::new(2));
+        let mut world = World::new(Rc::clone(&job_system), 1, GeneratorKind::Flat, GenPalette::default());
+        let load_all = |world: &mut World| loop {
+            world.update(&player);
+            job_system.wait_until_empty_and_processed();
+            if !world.is_loading() {
+                break;
+            }
+        };
+        load_all(&mut world);
+
+        let pos = ChunkPos::from_block_pos(20, 70, 20);
+        assert!(world.set_block(20, 70, 20, blocks::STONE_BRICKS));
+        load_all(&mut world);
+
+        // a budget of one byte evicts all chunks and does not allow to serialize them again
+        world.set_svo_memory_limit(Some(1));
+        world.update(&player);
+        assert!(world.evicted_chunks.contains(&pos));
+        assert_eq!(world.world.get_block(20, 70, 20), blocks::STONE_BRICKS);
+
+        world.set_svo_memory_limit(None);
+        load_all(&mut world);
+        assert!(world.evicted_chunks.is_empty());
+        assert_eq!(world.world.get_block(20, 70, 20), blocks::STONE_BRICKS);
+
+        let mut batch = PickerBatch::with_capacity(1);
+        batch.add_ray(Point3::new(20.5, 90.0, 20.5), Vector3::new(0.0, -1.0, 0.0), 30.0);
+        let mut result = PickerBatchResult::with_capacity(1);
+        world.world_svo.raycast(&mut batch, &mut result);
+        assert!(result.rays[0].did_hit());
+        assert_eq!(result.rays[0].value, blocks::STONE_BRICKS);
+    }
 }
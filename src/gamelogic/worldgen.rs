@@ -1,4 +1,7 @@
 use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::{Arc, RwLock};
 use std::thread;
 use std::time::Duration;
@@ -8,7 +11,7 @@ use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::gamelogic::content::blocks;
 use crate::systems::worldgen::ChunkGenerator;
-use crate::world::chunk::{Chunk, ChunkPos};
+use crate::world::chunk::{BlockId, Chunk, ChunkPos, NO_BLOCK};
 
 #[derive(Clone)]
 pub struct Noise {
@@ -151,6 +154,89 @@ pub struct Config {
     /// mountainous it is.
     /// -1 = netherlands, 1 = tibet
     pub erosion: Noise,
+    /// Defines which blocks are used for the generated terrain.
+    pub palette: GenPalette,
+}
+
+/// `GenPalette` maps the semantic roles of blocks in the generated terrain to actual block ids. This allows for
+/// theming generated worlds without changing the generator itself.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GenPalette {
+    /// Top most block of every terrain column.
+    pub surface: BlockId,
+    /// The three blocks below the surface block.
+    pub subsurface: BlockId,
+    /// All blocks below the subsurface blocks.
+    pub base: BlockId,
+    /// Fills all empty space up until the sea level. No liquid is placed if `None`.
+    pub liquid: Option<BlockId>,
+    /// Fills all remaining empty space.
+    pub air: BlockId,
+}
+
+impl Default for GenPalette {
+    fn default() -> Self {
+        Self {
+            surface: blocks::GRASS,
+            subsurface: blocks::DIRT,
+            base: blocks::STONE,
+            liquid: None,
+            air: blocks::AIR,
+        }
+    }
+}
+
+impl GenPalette {
+    /// Loads the palette from the config file at `path`. See [`GenPalette::parse`]. If the file cannot be read, the
+    /// error is reported and the default palette is used.
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        match fs::read_to_string(&path) {
+            Ok(config) => Self::parse(&config),
+            Err(err) => {
+                println!("error reading palette {}, using defaults: {err}", path.as_ref().display());
+                Self::default()
+            }
+        }
+    }
+
+    /// Parses a config with one `role = block` pair per line, e.g. `surface = stone`. Roles are `surface`,
+    /// `subsurface`, `base`, `liquid` and `air`. Blocks are either given by name or by id, and `liquid` can be set to
+    /// `none`. Empty lines and lines starting with `#` are ignored. Roles that are not configured keep their default
+    /// block. Invalid lines are reported.
+    pub fn parse(config: &str) -> Self {
+        let mut result = Self::default();
+
+        for (i, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((role, block_name)) = line.split_once('=') else {
+                println!("palette line {}: expected `role = block`: {line}", i + 1);
+                continue;
+            };
+            let (role, block_name) = (role.trim(), block_name.trim());
+
+            if role == "liquid" && block_name == "none" {
+                result.liquid = None;
+                continue;
+            }
+            let Some(block) = blocks::from_name(block_name).or_else(|| block_name.parse().ok()) else {
+                println!("palette line {}: unknown block: {block_name}", i + 1);
+                continue;
+            };
+            match role {
+                "surface" => result.surface = block,
+                "subsurface" => result.subsurface = block,
+                "base" => result.base = block,
+                "liquid" => result.liquid = Some(block),
+                "air" => result.air = block,
+                _ => println!("palette line {}: unknown role: {role}", i + 1),
+            }
+        }
+        result
+    }
 }
 
 struct GeneratorCache {
@@ -288,30 +374,105 @@ impl Generator {
 impl ChunkGenerator for Generator {
     fn is_interested_in(&self, pos: &ChunkPos) -> bool {
         let col = self.get_or_generate_chunk_column(pos.x, pos.z);
-        col.contains_chunk(pos.y)
+        if col.contains_chunk(pos.y) {
+            return true;
+        }
+
+        // chunks between the terrain and the sea level are filled with liquid, and all chunks above the terrain with air
+        let above_terrain = (pos.y + 1) * 32 > col.min_y;
+        let below_sea_level = pos.y * 32 <= self.cfg.sea_level && above_terrain;
+        (self.cfg.palette.liquid.is_some() && below_sea_level) || (self.cfg.palette.air != NO_BLOCK && above_terrain)
     }
 
     fn generate_chunk(&self, chunk: &mut Chunk) {
         let col = self.get_or_generate_chunk_column(chunk.pos.x, chunk.pos.z);
 
         let chunk_y = chunk.pos.y * 32;
+        let palette = self.cfg.palette;
+        let sea_level = self.cfg.sea_level - chunk_y;
         chunk.fill_with(|x, y, z| {
             let height = col.height_map[(z * 32 + x) as usize] as i32;
             let height = (height - chunk_y).min(31);
 
             let y = y as i32;
-            if y <= height {
-                let block = if y >= height {
-                    blocks::GRASS
+            let block = if y <= height {
+                if y >= height {
+                    palette.surface
                 } else if y >= height - 3 {
-                    blocks::DIRT
+                    palette.subsurface
                 } else {
-                    blocks::STONE
-                };
-                return Some(block);
-            }
+                    palette.base
+                }
+            } else if y <= sea_level {
+                palette.liquid.unwrap_or(palette.air)
+            } else {
+                palette.air
+            };
 
-            None
+            (block != NO_BLOCK).then_some(block)
+        });
+    }
+}
+
+/// `GeneratorKind` selects the chunk generator that is used for new worlds.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum GeneratorKind {
+    /// Perlin noise heightmap terrain, see [`Generator`].
+    #[default]
+    Noise,
+    /// Flat terrain at a fixed height, see [`FlatGenerator`].
+    Flat,
+}
+
+impl FromStr for GeneratorKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "noise" => Ok(Self::Noise),
+            "flat" => Ok(Self::Flat),
+            _ => Err(()),
+        }
+    }
+}
+
+/// `FlatGenerator` implements a world generator that fills all chunks up to a fixed height using the palette's
+/// surface, subsurface and base blocks. Chunks that only contain base blocks are skipped, analogous to the chunks below
+/// the terrain in [`Generator`]. Chunks above the terrain are only generated, if the palette's air is not empty.
+pub struct FlatGenerator {
+    height: i32,
+    palette: GenPalette,
+}
+
+impl FlatGenerator {
+    pub fn new(height: i32, palette: GenPalette) -> Self {
+        Self { height, palette }
+    }
+}
+
+impl ChunkGenerator for FlatGenerator {
+    fn is_interested_in(&self, pos: &ChunkPos) -> bool {
+        // the surface block and the three subsurface blocks below it
+        let below_surface = pos.y * 32 <= self.height || self.palette.air != NO_BLOCK;
+        below_surface && (pos.y + 1) * 32 > self.height - 3
+    }
+
+    fn generate_chunk(&self, chunk: &mut Chunk) {
+        let height = self.height - chunk.pos.y * 32;
+        let palette = self.palette;
+        chunk.fill_with(|_, y, _| {
+            let y = y as i32;
+            let block = if y > height {
+                palette.air
+            } else if y == height {
+                palette.surface
+            } else if y >= height - 3 {
+                palette.subsurface
+            } else {
+                palette.base
+            };
+
+            (block != NO_BLOCK).then_some(block)
         });
     }
 }
@@ -321,7 +482,7 @@ mod benches {
     use test::Bencher;
 
     use crate::gamelogic::worldgen;
-    use crate::gamelogic::worldgen::{Generator, Noise, SplinePoint};
+    use crate::gamelogic::worldgen::{GenPalette, Generator, Noise, SplinePoint};
     use crate::systems::worldgen::ChunkGenerator;
     use crate::world::chunk::{Chunk, ChunkPos, ChunkStorageAllocator};
 
@@ -356,6 +517,7 @@ mod benches {
                     SplinePoint { x: 1.0, y: 4.0 },
                 ],
             },
+            palette: GenPalette::default(),
         };
         let gen = Generator::new(1, cfg);
         let alloc = ChunkStorageAllocator::new();
@@ -379,3 +541,117 @@ mod benches {
         println!("{stats:?}");
     }
 }
+
+#[cfg(test)]
+mod generator_tests {
+    use crate::gamelogic::content::blocks;
+    use crate::gamelogic::worldgen::{Config, FlatGenerator, GenPalette, Generator, GeneratorKind, Noise, SplinePoint};
+    use crate::systems::worldgen::ChunkGenerator;
+    use crate::world::chunk::{Chunk, ChunkPos, ChunkStorageAllocator, NO_BLOCK};
+
+    /// Tests that the generator places blocks according to the configured palette.
+    #[test]
+    fn generate_chunk_with_palette() {
+        // flat terrain at y=40 with sea level at y=50
+        let flat = |y: f32| Noise {
+            frequency: 1.0,
+            octaves: 1,
+            spline_points: vec![SplinePoint { x: 0.0, y }],
+        };
+        let gen = Generator::new(1, Config {
+            sea_level: 50,
+            continentalness: flat(40.0),
+            erosion: flat(0.0),
+            palette: GenPalette { surface: 10, subsurface: 11, base: 12, liquid: Some(13), air: NO_BLOCK },
+        });
+        let alloc = ChunkStorageAllocator::new();
+
+        let pos = ChunkPos::new(0, 1, 0);
+        assert!(gen.is_interested_in(&pos));
+
+        let mut chunk = Chunk::new(pos, 5, alloc.allocate());
+        gen.generate_chunk(&mut chunk);
+        assert_eq!(chunk.get_block(3, 4, 5), 12);
+        assert_eq!(chunk.get_block(3, 5, 5), 11);
+        assert_eq!(chunk.get_block(3, 7, 5), 11);
+        assert_eq!(chunk.get_block(3, 8, 5), 10);
+        assert_eq!(chunk.get_block(3, 9, 5), 13);
+        assert_eq!(chunk.get_block(3, 18, 5), 13);
+        assert_eq!(chunk.get_block(3, 19, 5), NO_BLOCK);
+
+        // chunks above the sea level are skipped, unless they are filled with non-empty air
+        assert!(!gen.is_interested_in(&ChunkPos::new(0, 2, 0)));
+        let gen = Generator::new(1, Config {
+            sea_level: 50,
+            continentalness: flat(40.0),
+            erosion: flat(0.0),
+            palette: GenPalette { liquid: None, air: 14, ..GenPalette::default() },
+        });
+        assert!(gen.is_interested_in(&ChunkPos::new(0, 2, 0)));
+        assert!(!gen.is_interested_in(&ChunkPos::new(0, -1, 0)));
+    }
+
+    /// Tests that palettes are parsed from configs and that invalid lines keep the default blocks.
+    #[test]
+    fn parse_palette() {
+        let palette = GenPalette::parse("
+            # desert
+            surface = stone_bricks
+            subsurface = 7
+            liquid = glass
+            liquid = none
+            base = sand
+            sky = glass
+            air
+        ");
+        assert_eq!(palette, GenPalette {
+            surface: blocks::STONE_BRICKS,
+            subsurface: 7,
+            liquid: None,
+            ..GenPalette::default()
+        });
+        assert_eq!(GenPalette::parse("liquid = glass").liquid, Some(blocks::GLASS));
+        assert_eq!(GenPalette::parse(""), GenPalette::default());
+    }
+
+    /// Tests that the flat generator fills all chunks up to its height and skips chunks above or far below it.
+    #[test]
+    fn flat_generator() {
+        let gen = FlatGenerator::new(33, GenPalette { surface: 10, subsurface: 11, base: 12, liquid: None, air: NO_BLOCK });
+        let alloc = ChunkStorageAllocator::new();
+
+        assert!(!gen.is_interested_in(&ChunkPos::new(0, -1, 0)));
+        assert!(gen.is_interested_in(&ChunkPos::new(0, 0, 0)));
+        assert!(gen.is_interested_in(&ChunkPos::new(7, 1, -3)));
+        assert!(!gen.is_interested_in(&ChunkPos::new(0, 2, 0)));
+
+        // chunks above the terrain are filled with non-empty air
+        let gen_with_air = FlatGenerator::new(33, GenPalette { air: 14, ..GenPalette::default() });
+        assert!(!gen_with_air.is_interested_in(&ChunkPos::new(0, -1, 0)));
+        assert!(gen_with_air.is_interested_in(&ChunkPos::new(0, 2, 0)));
+        let mut chunk = Chunk::new(ChunkPos::new(0, 2, 0), 5, alloc.allocate());
+        gen_with_air.generate_chunk(&mut chunk);
+        assert_eq!(chunk.get_block(3, 0, 5), 14);
+
+        // the subsurface blocks reach into the chunk below the surface
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0), 5, alloc.allocate());
+        gen.generate_chunk(&mut chunk);
+        assert_eq!(chunk.get_block(3, 29, 5), 12);
+        assert_eq!(chunk.get_block(3, 30, 5), 11);
+        assert_eq!(chunk.get_block(3, 31, 5), 11);
+
+        let mut chunk = Chunk::new(ChunkPos::new(7, 1, -3), 5, alloc.allocate());
+        gen.generate_chunk(&mut chunk);
+        assert_eq!(chunk.get_block(3, 0, 5), 11);
+        assert_eq!(chunk.get_block(3, 1, 5), 10);
+        assert_eq!(chunk.get_block(3, 2, 5), NO_BLOCK);
+    }
+
+    /// Tests that generator kinds are parsed from their command line names.
+    #[test]
+    fn parse_generator_kind() {
+        assert_eq!("noise".parse(), Ok(GeneratorKind::Noise));
+        assert_eq!("flat".parse(), Ok(GeneratorKind::Flat));
+        assert_eq!("mc".parse::<GeneratorKind>(), Err(()));
+    }
+}
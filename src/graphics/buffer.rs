@@ -5,6 +5,8 @@ use std::ops::{Deref, DerefMut};
 
 use gl::types::{GLsizeiptr, GLuint};
 
+use crate::graphics::fence::Fence;
+
 // doc: https://registry.khronos.org/OpenGL-Refpages/gl4/html/glBufferData.xhtml
 type BufferUsage = u32;
 
@@ -160,4 +162,174 @@ impl<T> MappedBuffer<T> {
     pub fn len(&self) -> usize {
         self.len
     }
+}
+
+/// `RingMappedBuffer` splits a persistently mapped buffer into a ring of equally sized regions. The CPU writes into
+/// the current region, while the GPU can still read from the previous ones. Every region is guarded by a [`Fence`],
+/// which is placed when moving on to the next region. Blocking only happens when wrapping around to a region that the
+/// GPU has not finished reading yet.
+///
+/// Every region holds its own copy of the data, i.e. the buffer takes up `regions` times the memory of a
+/// [`MappedBuffer`] of the same length.
+pub struct RingMappedBuffer<T> {
+    buffer: MappedBuffer<T>,
+    fences: Vec<Fence>,
+    len: usize,
+    /// `stride` is the number of elements from the start of one region to the start of the next.
+    stride: usize,
+    current: usize,
+}
+
+impl<T> RingMappedBuffer<T> {
+    /// Creates a ring of `regions` regions with `len` elements each. Regions start at offsets that can be bound as
+    /// shader storage buffers.
+    pub fn new(len: usize, regions: usize) -> Self {
+        assert!(regions > 0, "ring buffer needs at least one region");
+
+        let mut alignment = 0;
+        unsafe { gl::GetIntegerv(gl::SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT, &mut alignment); }
+        let alignment = alignment.max(1) as usize;
+        let element_size = mem::size_of::<T>().max(1);
+
+        let mut stride = len.max(1);
+        while (stride * element_size) % alignment != 0 {
+            stride += 1;
+        }
+
+        Self {
+            buffer: MappedBuffer::new(stride * regions),
+            fences: (0..regions).map(|_| Fence::new()).collect(),
+            len,
+            stride,
+            current: 0,
+        }
+    }
+
+    /// Places a fence after all GPU commands that were issued for the current region and moves on to the next one.
+    /// If the GPU has not passed the next region's fence yet, this blocks until it has. Returns true, if it blocked.
+    pub fn advance(&mut self) -> bool {
+        self.fences[self.current].place();
+        self.current = (self.current + 1) % self.fences.len();
+
+        let fence = &self.fences[self.current];
+        if fence.is_signaled() {
+            return false;
+        }
+        fence.wait();
+        true
+    }
+
+    /// Binds the current region.
+    pub fn bind_as_storage_buffer(&self, index: u32) {
+        unsafe {
+            gl::BindBufferRange(
+                gl::SHADER_STORAGE_BUFFER,
+                index,
+                self.buffer.handle,
+                self.offset_in_bytes(self.current) as isize,
+                self.size_in_bytes() as GLsizeiptr,
+            );
+        }
+    }
+
+    /// Returns a pointer to the start of the current region.
+    pub fn as_mut_ptr(&self) -> *mut T {
+        unsafe { self.buffer.add(self.current * self.stride) }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn as_slice_mut(&self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.as_mut_ptr(), self.len) }
+    }
+
+    pub const fn current_region(&self) -> usize {
+        self.current
+    }
+
+    pub fn regions(&self) -> usize {
+        self.fences.len()
+    }
+
+    /// Returns true, if a fence was placed for the given region, and the GPU has not passed it yet.
+    pub fn is_in_flight(&self, region: usize) -> bool {
+        !self.fences[region].is_signaled()
+    }
+
+    /// Returns true, if the given region was left at least once, so that a fence was placed for it.
+    pub fn is_fenced(&self, region: usize) -> bool {
+        self.fences[region].is_placed()
+    }
+
+    /// Returns the byte offset of the given region inside the underlying buffer.
+    pub fn offset_in_bytes(&self, region: usize) -> usize {
+        mem::size_of::<T>() * self.stride * region
+    }
+
+    /// Returns the size of a single region in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        mem::size_of::<T>() * self.len
+    }
+
+    /// Returns the size of all regions, including their alignment, in bytes.
+    pub fn total_size_in_bytes(&self) -> usize {
+        self.buffer.size_in_bytes()
+    }
+
+    /// Returns the number of elements in a single region.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::GlContext;
+    use crate::gl_assert_no_error;
+    use crate::graphics::buffer::RingMappedBuffer;
+
+    /// Tests that regions are fenced when they are left, that wrapping around only continues once the GPU passed the
+    /// fence of the reused region, and that every region keeps its own data.
+    #[test]
+    fn ring_mapped_buffer() {
+        let _context = GlContext::new_headless(16, 16); // do not drop context
+
+        let mut ring = RingMappedBuffer::<u32>::new(3, 3);
+        assert_eq!(ring.regions(), 3);
+
+        let mut alignment = 0;
+        unsafe { gl::GetIntegerv(gl::SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT, &mut alignment); }
+        for region in 0..3 {
+            assert_eq!(ring.offset_in_bytes(region) % alignment.max(1) as usize, 0);
+            assert!(!ring.is_fenced(region));
+            assert!(!ring.is_in_flight(region));
+        }
+
+        for frame in 0..7 {
+            let region = frame % 3;
+            assert_eq!(ring.current_region(), region);
+            ring.as_slice_mut().fill(frame as u32);
+            ring.bind_as_storage_buffer(0);
+
+            let waited = ring.advance();
+            assert!(ring.is_fenced(region));
+            // regions are only waited on after they were used once
+            if frame < 2 {
+                assert!(!waited);
+            }
+            assert!(!ring.is_in_flight(ring.current_region()));
+        }
+        gl_assert_no_error!();
+
+        // the last frame wrote into region 0 and the ring moved on to region 1
+        assert_eq!(ring.current_region(), 1);
+        assert_eq!(ring.as_slice(), &[4, 4, 4]);
+        ring.advance();
+        assert_eq!(ring.as_slice(), &[5, 5, 5]);
+        ring.advance();
+        assert_eq!(ring.as_slice(), &[6, 6, 6]);
+    }
 }
\ No newline at end of file
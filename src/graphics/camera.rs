@@ -2,7 +2,59 @@
 
 use std::ops::Sub;
 
-use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Vector3};
+use cgmath::{EuclideanSpace, InnerSpace, Matrix, Matrix4, Point3, SquareMatrix, Vector3, Vector4};
+
+/// `ProjectionMode` decides how a [`Camera`] projects view space onto the screen.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ProjectionMode {
+    /// `Perspective` uses the camera's vertical field of view and aspect ratio.
+    Perspective,
+    /// `Orthographic` projects a box of `width` x `height` around the view axis without perspective
+    /// distortion, e.g. for maps or building tools.
+    Orthographic { width: f32, height: f32 },
+}
+
+/// `Frustum` is the volume visible by a camera, described by six planes whose normals point to
+/// its inside. Each plane is stored as `(normal, distance)`, so that `dot(normal, p) + distance`
+/// is the signed distance of point `p` to the plane.
+#[derive(Debug, Copy, Clone)]
+pub struct Frustum {
+    /// `planes` in order left, right, bottom, top, near, far.
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the frustum planes from a world to clip space matrix (Gribb & Hartmann).
+    pub fn from_matrix(m: &Matrix4<f32>) -> Self {
+        let (r0, r1, r2, r3) = (m.row(0), m.row(1), m.row(2), m.row(3));
+        let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r3 + r2, r3 - r2]
+            .map(|plane| plane / plane.truncate().magnitude());
+        Self { planes }
+    }
+
+    fn distance(plane: &Vector4<f32>, point: Point3<f32>) -> f32 {
+        plane.truncate().dot(point.to_vec()) + plane.w
+    }
+
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        self.planes.iter().all(|plane| Self::distance(plane, point) >= 0.0)
+    }
+
+    /// Returns true, if the box between `min` and `max` is at least partially inside the
+    /// frustum. The test is conservative: boxes close to the frustum's corners may be reported as
+    /// intersecting, although they are outside.
+    pub fn intersects_aabb(&self, min: Point3<f32>, max: Point3<f32>) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner that is furthest along the plane's normal
+            let corner = Point3::new(
+                if plane.x >= 0.0 { max.x } else { min.x },
+                if plane.y >= 0.0 { max.y } else { min.y },
+                if plane.z >= 0.0 { max.z } else { min.z },
+            );
+            Self::distance(plane, corner) >= 0.0
+        })
+    }
+}
 
 pub struct Camera {
     pub position: Point3<f32>,
@@ -13,6 +65,7 @@ pub struct Camera {
     aspect_ratio: f32,
     near: f32,
     far: f32,
+    mode: ProjectionMode,
     projection: Matrix4<f32>,
 }
 
@@ -26,18 +79,53 @@ impl Camera {
             aspect_ratio,
             near,
             far,
+            mode: ProjectionMode::Perspective,
             projection: Matrix4::identity(),
         };
         cam.update_projection(fov_y_deg, aspect_ratio, near, far);
         cam
     }
 
+    /// Switches to a perspective projection with the given parameters.
     pub fn update_projection(&mut self, fov_y_deg: f32, aspect_ratio: f32, near: f32, far: f32) {
         self.fov_y_deg = fov_y_deg;
         self.aspect_ratio = aspect_ratio;
         self.near = near;
         self.far = far;
-        self.projection = cgmath::perspective(cgmath::Deg(fov_y_deg), aspect_ratio, near, far);
+        self.mode = ProjectionMode::Perspective;
+        self.rebuild_projection();
+    }
+
+    /// Switches to an orthographic projection of a `width` x `height` box centered around the
+    /// view axis. The field of view is kept for switching back to a perspective projection.
+    pub fn set_orthographic(&mut self, width: f32, height: f32, near: f32, far: f32) {
+        self.near = near;
+        self.far = far;
+        self.mode = ProjectionMode::Orthographic { width, height };
+        self.rebuild_projection();
+    }
+
+    pub fn get_projection_mode(&self) -> ProjectionMode {
+        self.mode
+    }
+
+    fn rebuild_projection(&mut self) {
+        self.projection = match self.mode {
+            ProjectionMode::Perspective => cgmath::perspective(cgmath::Deg(self.fov_y_deg), self.aspect_ratio, self.near, self.far),
+            ProjectionMode::Orthographic { width, height } => {
+                cgmath::ortho(-width / 2.0, width / 2.0, -height / 2.0, height / 2.0, self.near, self.far)
+            }
+        };
+    }
+
+    /// Points the camera at `target`. `up` is stored as is, so that it can be reused when the
+    /// forward vector changes later on, and must not be parallel to the view direction.
+    pub fn look_at(&mut self, target: Point3<f32>, up: Vector3<f32>) {
+        let dir = target - self.position;
+        if dir.magnitude2() > 0.0 {
+            self.forward = dir.normalize();
+        }
+        self.up = up.normalize();
     }
 
     pub fn right(&self) -> Vector3<f32> {
@@ -48,7 +136,27 @@ impl Camera {
         self.fov_y_deg
     }
     pub fn set_fov_y_deg(&mut self, fov: f32) {
-        self.update_projection(fov, self.aspect_ratio, self.near, self.far);
+        self.fov_y_deg = fov;
+        self.rebuild_projection();
+    }
+
+    pub fn get_near(&self) -> f32 {
+        self.near
+    }
+
+    pub fn get_far(&self) -> f32 {
+        self.far
+    }
+
+    /// Converts a depth buffer value in [0;1], as written with this camera's projection, into the
+    /// linear distance from the camera along its forward axis.
+    pub fn linearize_depth(&self, depth: f32) -> f32 {
+        if let ProjectionMode::Orthographic { .. } = self.mode {
+            return depth.mul_add(self.far - self.near, self.near);
+        }
+
+        let ndc = depth.mul_add(2.0, -1.0);
+        2.0 * self.near * self.far / ndc.mul_add(-(self.far - self.near), self.far + self.near)
     }
 
     pub fn get_projection_matrix(&self) -> &Matrix4<f32> {
@@ -67,11 +175,17 @@ impl Camera {
         self.projection * self.get_world_to_camera_matrix()
     }
 
+    /// Returns the frustum of the camera's current view and projection.
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(&self.get_world_to_clip_space_matrix())
+    }
+
     /// `is_in_frustum` performs "radar frustum culling" to check if the given sphere is inside the
     /// camera's frustum.
     /// It transforms the point into camera view space and uses the distance to the near plane
     /// and the FOV to figure out the frustum width and height at the point's depth. Using that
-    /// it culls the sphere against all frustum planes.
+    /// it culls the sphere against all frustum planes. Orthographic frusta have the same width and
+    /// height at every depth.
     pub fn is_in_frustum(&self, point: Point3<f32>, r: f32) -> bool {
         let cp = point.sub(self.position);
 
@@ -84,13 +198,19 @@ impl Camera {
         let right = self.right();
         let up = self.forward.cross(right);
         let cy = cp.dot(up);
-        let hh = cz * f32::tan(self.get_fov_y_deg().to_radians() / 2.0);
+        let hh = match self.mode {
+            ProjectionMode::Perspective => cz * f32::tan(self.get_fov_y_deg().to_radians() / 2.0),
+            ProjectionMode::Orthographic { height, .. } => height / 2.0,
+        };
         if cy + r < -hh || cy - r > hh {
             return false;
         }
 
         let cx = cp.dot(right);
-        let wh = hh * self.aspect_ratio;
+        let wh = match self.mode {
+            ProjectionMode::Perspective => hh * self.aspect_ratio,
+            ProjectionMode::Orthographic { width, .. } => width / 2.0,
+        };
         if cx + r < -wh || cx - r > wh {
             return false;
         }
@@ -101,9 +221,9 @@ impl Camera {
 
 #[cfg(test)]
 mod tests {
-    use cgmath::{Point3, Vector3};
+    use cgmath::{InnerSpace, Matrix4, Point3, Vector3, Vector4};
 
-    use crate::graphics::camera::Camera;
+    use crate::graphics::camera::{Camera, ProjectionMode};
 
     /// Tests if culling works along all axes of a camera's frustum.
     #[test]
@@ -135,4 +255,90 @@ mod tests {
         assert!(camera.is_in_frustum(Point3::new(3.0, 0.0, 3.0), 1.0));
         assert!(camera.is_in_frustum(Point3::new(-3.0, 0.0, 3.0), 1.0));
     }
+
+    /// Tests that depth buffer values are converted back into view distances.
+    #[test]
+    fn linearize_depth() {
+        let camera = Camera::new(72.0, 1.0, 0.5, 100.0);
+
+        assert!((camera.linearize_depth(0.0) - 0.5).abs() < 0.0001);
+        assert!((camera.linearize_depth(1.0) - 100.0).abs() < 0.01);
+
+        // project a point at distance 10 and convert it back
+        let clip = camera.get_projection_matrix() * Vector4::new(0.0, 0.0, -10.0, 1.0);
+        let depth = (clip.z / clip.w).mul_add(0.5, 0.5);
+        assert!((camera.linearize_depth(depth) - 10.0).abs() < 0.001);
+    }
+
+    /// Tests that orthographic projections match a hand-computed matrix and can be switched back
+    /// to perspective projections.
+    #[test]
+    fn orthographic_projection() {
+        let mut camera = Camera::new(72.0, 1.0, 0.01, 30.0);
+        camera.set_orthographic(20.0, 10.0, 1.0, 11.0);
+        assert_eq!(camera.get_projection_mode(), ProjectionMode::Orthographic { width: 20.0, height: 10.0 });
+
+        // 2/w, 2/h, -2/(f-n) on the diagonal and -(f+n)/(f-n) as z translation (column major)
+        let expected = Matrix4::new(
+            0.1, 0.0, 0.0, 0.0,
+            0.0, 0.2, 0.0, 0.0,
+            0.0, 0.0, -0.2, 0.0,
+            0.0, 0.0, -1.2, 1.0,
+        );
+        assert_eq!(camera.get_projection_matrix(), &expected);
+
+        let clip = camera.get_projection_matrix() * Vector4::new(5.0, 2.5, -6.0, 1.0);
+        assert!((clip - Vector4::new(0.5, 0.5, 0.0, 1.0)).magnitude() < 1e-6, "{clip:?}");
+        assert!((camera.linearize_depth(0.5) - 6.0).abs() < 1e-6);
+
+        // the frustum does not widen with the distance
+        camera.forward = Vector3::new(0.0, 0.0, -1.0);
+        assert!(camera.is_in_frustum(Point3::new(9.0, 4.0, -2.0), 0.0));
+        assert!(!camera.is_in_frustum(Point3::new(11.0, 0.0, -10.0), 0.0));
+
+        camera.set_fov_y_deg(90.0);
+        assert!(matches!(camera.get_projection_mode(), ProjectionMode::Orthographic { .. }));
+        camera.update_projection(90.0, 1.0, 0.01, 30.0);
+        assert_eq!(camera.get_projection_mode(), ProjectionMode::Perspective);
+    }
+
+    /// Tests that points and boxes are culled against the extracted frustum planes.
+    #[test]
+    fn frustum() {
+        let mut camera = Camera::new(90.0, 1.0, 0.1, 100.0);
+        camera.position = Point3::new(0.0, 0.0, 0.0);
+        camera.forward = Vector3::new(0.0, 0.0, -1.0);
+        let frustum = camera.frustum();
+
+        // points
+        assert!(frustum.contains_point(Point3::new(0.0, 0.0, -10.0)));
+        assert!(frustum.contains_point(Point3::new(9.0, -9.0, -10.0)));
+        assert!(!frustum.contains_point(Point3::new(11.0, 0.0, -10.0)));
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, 10.0)));
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, -101.0)));
+
+        // boxes
+        assert!(frustum.intersects_aabb(Point3::new(-1.0, -1.0, -11.0), Point3::new(1.0, 1.0, -9.0)));
+        assert!(!frustum.intersects_aabb(Point3::new(12.0, -1.0, -11.0), Point3::new(14.0, 1.0, -9.0)));
+        assert!(!frustum.intersects_aabb(Point3::new(-1.0, -1.0, 1.0), Point3::new(1.0, 1.0, 3.0)));
+
+        // boxes straddling the right and far planes
+        assert!(frustum.intersects_aabb(Point3::new(9.0, -1.0, -11.0), Point3::new(12.0, 1.0, -9.0)));
+        assert!(frustum.intersects_aabb(Point3::new(-1.0, -1.0, -102.0), Point3::new(1.0, 1.0, -98.0)));
+    }
+
+    /// Tests that the camera's forward vector points at the target after calling `look_at` and
+    /// that the view matrix moves the target onto the view axis.
+    #[test]
+    fn look_at() {
+        let mut camera = Camera::new(72.0, 1.0, 0.01, 30.0);
+        camera.position = Point3::new(1.0, 2.0, 3.0);
+        camera.look_at(Point3::new(4.0, 6.0, 3.0), Vector3::new(0.0, 2.0, 0.0));
+
+        assert!((camera.forward - Vector3::new(0.6, 0.8, 0.0)).magnitude() < 1e-6, "{:?}", camera.forward);
+        assert!((camera.up - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1e-6);
+
+        let view = camera.get_world_to_camera_matrix() * Vector4::new(4.0, 6.0, 3.0, 1.0);
+        assert!((view - Vector4::new(0.0, 0.0, -5.0, 1.0)).magnitude() < 1e-5, "{view:?}");
+    }
 }
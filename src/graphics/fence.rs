@@ -22,6 +22,21 @@ impl Fence {
         self.handle = Some(handle);
     }
 
+    /// Returns true if a fence was placed.
+    pub const fn is_placed(&self) -> bool {
+        self.handle.is_some()
+    }
+
+    /// Returns true if the GPU has passed the placed fence without blocking. If no fence was placed, it is
+    /// considered signaled.
+    pub fn is_signaled(&self) -> bool {
+        let Some(lock) = self.handle else {
+            return true;
+        };
+        let result = unsafe { gl::ClientWaitSync(lock, gl::SYNC_FLUSH_COMMANDS_BIT, 0) };
+        result == gl::ALREADY_SIGNALED || result == gl::CONDITION_SATISFIED
+    }
+
     pub fn wait(&self) {
         if self.handle.is_none() {
             return;
@@ -1,56 +1,135 @@
 #![allow(dead_code)]
 
+use std::path::Path;
 use std::ptr;
 
-use gl::types::{GLint, GLuint};
+use gl::types::{GLenum, GLint, GLuint};
 use image::{DynamicImage, GenericImageView};
 
 use crate::gl_assert_no_error;
+use crate::graphics::camera::Camera;
 
 pub struct Framebuffer {
     handle: GLuint,
     color_attachment: GLuint,
+    renderbuffers: Vec<GLuint>,
     width: i32,
     height: i32,
+    samples: u32,
+    depth_stencil: bool,
 }
 
-/// Framebuffer is a wrapper around a OpenGL framebuffer object. It attaches color, depth & stencil
-/// buffer for the given resolution. No multi-sampling is applied.
-impl Framebuffer {
-    pub fn new(width: i32, height: i32, depth: bool, stencil: bool) -> Self {
+/// `FramebufferBuilder` configures and creates a [`Framebuffer`].
+pub struct FramebufferBuilder {
+    width: i32,
+    height: i32,
+    depth_stencil: bool,
+    samples: u32,
+}
+
+impl FramebufferBuilder {
+    pub fn new(width: i32, height: i32) -> Self {
+        Self { width, height, depth_stencil: false, samples: 0 }
+    }
+
+    /// Attaches a combined depth & stencil buffer.
+    pub fn with_depth_stencil(&mut self) -> &mut Self {
+        self.depth_stencil = true;
+        self
+    }
+
+    /// Uses multisampled renderbuffers with the given number of samples per pixel. 0 disables
+    /// multisampling. Values above `GL_MAX_SAMPLES` are clamped when building the framebuffer.
+    pub fn with_samples(&mut self, samples: u32) -> &mut Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn build(&self) -> Framebuffer {
+        let samples = Self::clamp_samples(self.samples);
+
         let mut handle = 0;
         let mut color_attachment = 0;
+        let mut renderbuffers = Vec::new();
         unsafe {
             gl::GenFramebuffers(1, &mut handle);
             gl::BindFramebuffer(gl::FRAMEBUFFER, handle);
 
-            gl::GenTextures(1, &mut color_attachment);
-            gl::BindTexture(gl::TEXTURE_2D, color_attachment);
-            gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA32F as GLint, width, height, 0, gl::RGBA, gl::FLOAT, ptr::null());
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, color_attachment, 0);
+            if samples > 0 {
+                let color = Self::create_renderbuffer(gl::RGBA32F, self.width, self.height, samples);
+                gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::RENDERBUFFER, color);
+                renderbuffers.push(color);
+            } else {
+                gl::GenTextures(1, &mut color_attachment);
+                gl::BindTexture(gl::TEXTURE_2D, color_attachment);
+                gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA32F as GLint, self.width, self.height, 0, gl::RGBA, gl::FLOAT, ptr::null());
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+                gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, color_attachment, 0);
+            }
             gl_assert_no_error!();
 
-            if depth && stencil {
-                let mut depth_stencil_attachment = 0;
-                gl::GenRenderbuffers(1, &mut depth_stencil_attachment);
-                gl::BindRenderbuffer(gl::RENDERBUFFER, depth_stencil_attachment);
-                gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8, width, height);
-                gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
-                gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, depth_stencil_attachment);
+            if self.depth_stencil {
+                let depth_stencil = Self::create_renderbuffer(gl::DEPTH24_STENCIL8, self.width, self.height, samples);
+                gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, depth_stencil);
+                renderbuffers.push(depth_stencil);
                 gl_assert_no_error!();
-            } else if depth != stencil {
-                // NOTE: implementations needs to change in order to support independent configuration
-                panic!("depth & stencil must both either be true or false");
             }
 
             assert_eq!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER), gl::FRAMEBUFFER_COMPLETE);
 
             gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
         }
-        Self { handle, color_attachment, width, height }
+        Framebuffer {
+            handle,
+            color_attachment,
+            renderbuffers,
+            width: self.width,
+            height: self.height,
+            samples,
+            depth_stencil: self.depth_stencil,
+        }
+    }
+
+    fn clamp_samples(samples: u32) -> u32 {
+        let mut max_samples = 0;
+        unsafe { gl::GetIntegerv(gl::MAX_SAMPLES, &mut max_samples); }
+
+        let max_samples = max_samples.max(0) as u32;
+        if samples > max_samples {
+            println!("warning: {samples} framebuffer samples requested, but only {max_samples} are supported");
+            return max_samples;
+        }
+        samples
+    }
+
+    unsafe fn create_renderbuffer(format: GLenum, width: i32, height: i32, samples: u32) -> GLuint {
+        let mut renderbuffer = 0;
+        gl::GenRenderbuffers(1, &mut renderbuffer);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+        if samples > 0 {
+            gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, samples as i32, format, width, height);
+        } else {
+            gl::RenderbufferStorage(gl::RENDERBUFFER, format, width, height);
+        }
+        gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        renderbuffer
+    }
+}
+
+/// Framebuffer is a wrapper around a OpenGL framebuffer object. It attaches color, depth & stencil
+/// buffer for the given resolution. Use [`FramebufferBuilder`] for multi-sampling.
+impl Framebuffer {
+    pub fn new(width: i32, height: i32, depth: bool, stencil: bool) -> Self {
+        // NOTE: implementations needs to change in order to support independent configuration
+        assert_eq!(depth, stencil, "depth & stencil must both either be true or false");
+
+        let mut builder = FramebufferBuilder::new(width, height);
+        if depth {
+            builder.with_depth_stencil();
+        }
+        builder.build()
     }
 
     pub fn width(&self) -> i32 {
@@ -61,10 +140,15 @@ impl Framebuffer {
         self.height
     }
 
+    /// Returns the color texture, or 0 if the framebuffer is multisampled.
     pub fn color_attachment(&self) -> GLuint {
         self.color_attachment
     }
 
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
     pub fn bind(&self) {
         unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, self.handle); }
     }
@@ -94,6 +178,23 @@ impl Framebuffer {
         }
     }
 
+    /// Resolves the color buffer into `target` by blitting. This is required to read or present
+    /// the contents of a multisampled framebuffer. Both framebuffers must have the same size.
+    pub fn resolve_to(&self, target: &Self) {
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.handle);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, target.handle);
+            gl::BlitFramebuffer(
+                0, 0, self.width, self.height,
+                0, 0, target.width, target.height,
+                gl::COLOR_BUFFER_BIT, gl::NEAREST,
+            );
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    /// Reads the color attachment as RGBA8. Rows are ordered bottom to top, as is convention in
+    /// OpenGL. Use [`save_png`] or [`Framebuffer::as_image`] to get an image in the usual order.
     pub fn read_pixels(&self) -> Vec<u8> {
         let mut bytes = vec![0; (self.width * self.height * 4) as usize];
         unsafe {
@@ -104,6 +205,28 @@ impl Framebuffer {
         bytes
     }
 
+    /// Reads the depth at the given pixel and converts it into the linear distance from the
+    /// `camera`, whose projection was used for rendering. The origin (0, 0) is the top-left pixel,
+    /// matching window and cursor coordinates, in contrast to OpenGL's bottom-left origin.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the framebuffer has no depth buffer, is multisampled, or the pixel is out of
+    /// bounds.
+    pub fn read_depth(&self, x: u32, y: u32, camera: &Camera) -> f32 {
+        assert!(self.depth_stencil, "framebuffer has no depth buffer");
+        assert_eq!(self.samples, 0, "depth of multisampled framebuffers cannot be read");
+        assert!((x as i32) < self.width && (y as i32) < self.height, "pixel out of bounds");
+
+        let mut depth = 0.0f32;
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.handle);
+            gl::ReadPixels(x as i32, self.height - 1 - y as i32, 1, 1, gl::DEPTH_COMPONENT, gl::FLOAT, ptr::addr_of_mut!(depth).cast());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        camera.linearize_depth(depth)
+    }
+
     pub fn as_image(&self) -> DynamicImage {
         let pixels = self.read_pixels();
         let image = image::RgbaImage::from_raw(self.width as u32, self.height as u32, pixels).unwrap();
@@ -113,10 +236,25 @@ impl Framebuffer {
 
 impl Drop for Framebuffer {
     fn drop(&mut self) {
-        unsafe { gl::DeleteFramebuffers(1, &self.handle) }
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.handle);
+            gl::DeleteTextures(1, &self.color_attachment);
+            gl::DeleteRenderbuffers(self.renderbuffers.len() as i32, self.renderbuffers.as_ptr());
+        }
     }
 }
 
+/// Saves the given RGBA8 pixels, as returned by [`Framebuffer::read_pixels`], as PNG. Rows are
+/// expected in OpenGL order, i.e. bottom to top, and are flipped to have the top row first.
+pub fn save_png<P: AsRef<Path>>(path: P, width: u32, height: u32, rgba: &[u8]) -> image::ImageResult<()> {
+    let row_len = width as usize * 4;
+    let mut flipped = Vec::with_capacity(rgba.len());
+    for row in rgba.chunks_exact(row_len).rev() {
+        flipped.extend_from_slice(row);
+    }
+    image::save_buffer_with_format(path, &flipped, width, height, image::ColorType::Rgba8, image::ImageFormat::Png)
+}
+
 pub fn diff_images(lhs: &DynamicImage, rhs: &DynamicImage) -> f64 {
     // source: https://rosettacode.org/wiki/Percentage_difference_between_images#Rust
     fn diff_rgba3(rgba1: image::Rgba<u8>, rgba2: image::Rgba<u8>) -> i32 {
@@ -132,3 +270,82 @@ pub fn diff_images(lhs: &DynamicImage, rhs: &DynamicImage) -> f64 {
     }
     accum as f64 / (255.0 * 3.0 * (lhs.width() * lhs.height()) as f64)
 }
+
+#[cfg(test)]
+mod tests {
+    use image::GenericImageView;
+
+    use crate::core::GlContext;
+    use crate::gl_assert_no_error;
+    use crate::graphics::camera::Camera;
+    use crate::graphics::framebuffer::{FramebufferBuilder, save_png};
+
+    /// Tests that saved images have their rows flipped from OpenGL order.
+    #[test]
+    fn save_png_flips_rows() {
+        // the extension is required for image::open to detect the format
+        let file = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+        let rgba = [
+            255, 0, 0, 255, 255, 0, 0, 255, // bottom row
+            0, 0, 255, 255, 0, 0, 255, 255, // top row
+        ];
+        save_png(file.path(), 2, 2, &rgba).unwrap();
+
+        let image = image::open(file.path()).unwrap();
+        assert_eq!(image.dimensions(), (2, 2));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(1, 1).0, [255, 0, 0, 255]);
+    }
+
+    /// Tests that multisampled framebuffers clamp their sample count and can be resolved into a
+    /// single-sampled framebuffer.
+    #[test]
+    fn multisampled_resolve() {
+        let _context = GlContext::new_headless(4, 4); // do not drop context
+
+        let mut max_samples = 0;
+        unsafe { gl::GetIntegerv(gl::MAX_SAMPLES, &mut max_samples); }
+
+        let msaa = FramebufferBuilder::new(4, 4).with_depth_stencil().with_samples(u32::MAX).build();
+        assert_eq!(msaa.samples(), max_samples as u32);
+        assert_eq!(msaa.color_attachment(), 0);
+
+        let target = FramebufferBuilder::new(4, 4).build();
+        msaa.bind();
+        msaa.clear(1.0, 0.0, 0.0, 1.0);
+        msaa.unbind();
+        msaa.resolve_to(&target);
+        gl_assert_no_error!();
+
+        let pixels = target.read_pixels();
+        assert!(pixels.chunks_exact(4).all(|pixel| pixel == [255, 0, 0, 255]));
+    }
+
+    /// Tests that depth is read from the top-left based pixel and linearized.
+    #[test]
+    fn read_depth() {
+        let _context = GlContext::new_headless(4, 4); // do not drop context
+        let camera = Camera::new(72.0, 1.0, 0.5, 100.0);
+
+        let fb = FramebufferBuilder::new(4, 2).with_depth_stencil().build();
+        fb.bind();
+        unsafe {
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::ClearDepth(1.0);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+
+            // clear the bottom row (in OpenGL coordinates) to the near plane
+            gl::Scissor(0, 0, 4, 1);
+            gl::ClearDepth(0.0);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::ClearDepth(1.0);
+        }
+        fb.unbind();
+        gl_assert_no_error!();
+
+        assert!((fb.read_depth(2, 0, &camera) - 100.0).abs() < 0.01);
+        assert!((fb.read_depth(2, 1, &camera) - 0.5).abs() < 0.0001);
+    }
+}
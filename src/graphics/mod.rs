@@ -10,6 +10,9 @@ pub mod svo;
 mod svo_shader_tests;
 pub mod screen_quad;
 pub mod svo_picker;
+pub mod svo_picker_system;
 pub mod svo_registry;
+pub mod timer;
 
+pub use framebuffer::save_png;
 pub use svo::Svo;
@@ -1,8 +1,15 @@
+use std::fmt::Debug;
+use std::fs;
 use std::ops::Deref;
+use std::time::SystemTime;
 
 pub struct Resource<T, E> {
     constructor: Box<dyn Fn() -> Result<T, E>>,
     content: T,
+    /// `last_error` is the error of the last reload, if it failed.
+    last_error: Option<E>,
+    /// `failed_modified` is the modification time of the sources that last failed to reload.
+    failed_modified: Option<SystemTime>,
 }
 
 pub trait Constructor<T, E>: Fn() -> Result<T, E> + 'static {}
@@ -12,23 +19,67 @@ impl<X, T, E> Constructor<T, E> for X
 
 /// Resource holds an actual resource and its constructor, allowing it to be reloaded in place.
 impl<T, E> Resource<T, E> {
-    pub fn new<F: Constructor<T, E>>(constructor: F) -> Result<Self, E> {
-        match constructor() {
-            Ok(content) => Ok(Self {
-                constructor: Box::new(constructor),
-                content,
-            }),
-            Err(err) => Err(err),
+    /// Builds the resource with `constructor`. See [`Resource::try_new`] to recover from errors.
+    ///
+    /// # Panics
+    ///
+    /// If the resource cannot be built.
+    pub fn new<F: Constructor<T, E>>(constructor: F) -> Self where E: Debug {
+        Self::try_new(constructor).unwrap_or_else(|err| panic!("error building resource: {err:?}"))
+    }
+
+    /// Builds the resource with `constructor` and keeps the constructor for reloading. Returns the error, if the
+    /// resource cannot be built.
+    pub fn try_new<F: Constructor<T, E>>(constructor: F) -> Result<Self, E> {
+        let content = constructor()?;
+        Ok(Self {
+            constructor: Box::new(constructor),
+            content,
+            last_error: None,
+            failed_modified: None,
+        })
+    }
+
+    /// Builds the resource again. If that fails, the previous content is kept and the error is returned, which is also
+    /// available through [`Resource::last_error`] until the next successful reload.
+    pub fn reload(&mut self) -> Result<(), &E> {
+        match self.constructor.as_ref()() {
+            Ok(content) => {
+                self.content = content;
+                self.last_error = None;
+                Ok(())
+            }
+            Err(err) => Err(&*self.last_error.insert(err)),
         }
     }
 
-    pub fn reload(&mut self) -> Result<(), E> {
-        let result = self.constructor.as_ref()();
-        if let Ok(content) = result {
-            self.content = content;
-            return Ok(());
+    /// Returns the error of the last reload, if it failed.
+    #[allow(dead_code)]
+    pub fn last_error(&self) -> Option<&E> {
+        self.last_error.as_ref()
+    }
+}
+
+impl<T: Watch, E> Resource<T, E> {
+    /// Reloads the resource, if any of its source files was modified since it was loaded. If
+    /// reloading fails, the current content is kept and the same modification is not retried.
+    /// Returns true, if the resource was reloaded.
+    pub fn reload_if_changed(&mut self) -> Result<bool, &E> {
+        let Some(modified) = latest_modified(self.content.source_files()) else {
+            return Ok(false);
+        };
+
+        let loaded = self.content.modified().max(self.failed_modified);
+        if loaded.is_some_and(|loaded| modified <= loaded) {
+            return Ok(false);
+        }
+
+        if self.reload().is_ok() {
+            self.failed_modified = None;
+            return Ok(true);
         }
-        Err(result.err().unwrap())
+        self.failed_modified = Some(modified);
+        Err(self.last_error.as_ref().unwrap())
     }
 }
 
@@ -54,3 +105,122 @@ pub trait Bind {
     fn bind(&self);
     fn unbind(&self);
 }
+
+/// `Watch` is implemented by resources that are loaded from files, so that they can be reloaded
+/// when the files change.
+pub trait Watch {
+    /// Returns the paths of all files the resource was loaded from.
+    fn source_files(&self) -> &[String];
+    /// Returns the latest modification time of the source files at the time of loading.
+    fn modified(&self) -> Option<SystemTime>;
+}
+
+/// Returns the latest modification time of all given files. Files without a modification time,
+/// e.g. because they are bundled into the binary, are ignored.
+pub fn latest_modified<S: AsRef<str>>(paths: &[S]) -> Option<SystemTime> {
+    paths.iter()
+        .filter_map(|path| fs::metadata(path.as_ref()).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::io::Write;
+    use std::rc::Rc;
+    use std::time::{Duration, SystemTime};
+
+    use tempfile::NamedTempFile;
+
+    use crate::graphics::resource::{latest_modified, Resource, Watch};
+
+    struct File {
+        paths: Vec<String>,
+        modified: Option<SystemTime>,
+    }
+
+    impl Watch for File {
+        fn source_files(&self) -> &[String] {
+            &self.paths
+        }
+
+        fn modified(&self) -> Option<SystemTime> {
+            self.modified
+        }
+    }
+
+    fn touch(file: &NamedTempFile, offset: Duration) {
+        file.as_file().set_modified(SystemTime::now() + offset).unwrap();
+    }
+
+    /// Tests that resources are only reloaded if their source files were modified, and that failed
+    /// reloads keep the old content without being retried.
+    #[test]
+    fn reload_if_changed() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"content").unwrap();
+        let path = file.path().to_str().unwrap().to_string();
+
+        let loads = Rc::new(Cell::new(0));
+        let fail = Rc::new(Cell::new(false));
+        let mut resource = {
+            let loads = Rc::clone(&loads);
+            let fail = Rc::clone(&fail);
+            Resource::new(move || {
+                if fail.get() {
+                    return Err("compile error");
+                }
+                loads.set(loads.get() + 1);
+                Ok(File { paths: vec![path.clone()], modified: latest_modified(&[&path]) })
+            })
+        };
+        assert_eq!(loads.get(), 1);
+
+        // unchanged
+        assert_eq!(resource.reload_if_changed(), Ok(false));
+        assert_eq!(loads.get(), 1);
+
+        // changed
+        touch(&file, Duration::from_secs(10));
+        assert_eq!(resource.reload_if_changed(), Ok(true));
+        assert_eq!(loads.get(), 2);
+        assert_eq!(resource.reload_if_changed(), Ok(false));
+
+        // failing reloads are not retried until the file changes again
+        fail.set(true);
+        touch(&file, Duration::from_secs(20));
+        assert_eq!(resource.reload_if_changed(), Err(&"compile error"));
+        assert_eq!(resource.reload_if_changed(), Ok(false));
+        assert_eq!(loads.get(), 2);
+
+        fail.set(false);
+        touch(&file, Duration::from_secs(30));
+        assert_eq!(resource.reload_if_changed(), Ok(true));
+        assert_eq!(loads.get(), 3);
+    }
+
+    /// Tests that a failing constructor is reported, and that a failed reload keeps the previous value and stores the
+    /// error until the next successful reload.
+    #[test]
+    fn reload_error() {
+        assert_eq!(Resource::<u32, &str>::try_new(|| Err("missing file")).err(), Some("missing file"));
+
+        let value = Rc::new(Cell::new(Ok(1)));
+        let mut resource = {
+            let value = Rc::clone(&value);
+            Resource::try_new(move || value.get()).unwrap()
+        };
+        assert_eq!(*resource, 1);
+        assert_eq!(resource.last_error(), None);
+
+        value.set(Err("compile error"));
+        assert_eq!(resource.reload(), Err(&"compile error"));
+        assert_eq!(*resource, 1);
+        assert_eq!(resource.last_error(), Some(&"compile error"));
+
+        value.set(Ok(2));
+        assert_eq!(resource.reload(), Ok(()));
+        assert_eq!(*resource, 2);
+        assert_eq!(resource.last_error(), None);
+    }
+}
@@ -79,6 +79,16 @@ impl ScreenQuad {
             gl::BindVertexArray(0);
         }
     }
+
+    /// Renders the quad `count` times. The instances are expected to be positioned by the shader based on
+    /// `gl_InstanceID`.
+    pub fn render_instanced(&self, count: usize) {
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::DrawElementsInstanced(gl::TRIANGLES, 6, gl::UNSIGNED_INT, ptr::null(), count as GLint);
+            gl::BindVertexArray(0);
+        }
+    }
 }
 
 impl Drop for ScreenQuad {
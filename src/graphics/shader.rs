@@ -5,6 +5,7 @@ use std::cell::RefCell;
 use std::ffi::CString;
 use std::fmt::Write;
 use std::path::Path;
+use std::time::SystemTime;
 
 use cgmath::{Array, Matrix};
 use gl::types::{GLchar, GLenum, GLint, GLsizei, GLuint};
@@ -13,7 +14,7 @@ use regex::Regex;
 use rustc_hash::FxHashMap;
 
 use crate::core::assets;
-use crate::graphics::resource::Bind;
+use crate::graphics::resource::{Bind, latest_modified, Watch};
 
 #[derive(Debug)]
 pub enum ShaderError {
@@ -55,11 +56,61 @@ impl GlError {
     }
 }
 
+/// `SourceLocation` is a line in a GLSL source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SourceLocation {
+    file: String,
+    line: usize,
+}
+
+/// `ShaderSource` is preprocessed GLSL source code. `lines` maps every line of `code` to the file
+/// and line it originates from, so that compile errors can refer to the original location, even
+/// if files were included.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct ShaderSource {
+    code: String,
+    lines: Vec<SourceLocation>,
+}
+
+impl ShaderSource {
+    fn push_line(&mut self, line: &str, location: SourceLocation) {
+        self.code.write_str(line).unwrap();
+        self.code.write_char('\n').unwrap();
+        self.lines.push(location);
+    }
+
+    fn append(&mut self, other: &Self) {
+        self.code.write_str(&other.code).unwrap();
+        self.lines.extend_from_slice(&other.lines);
+    }
+
+    /// Annotates all lines of a driver's compile log that refer to a line number in `code` with the
+    /// original file and line. Common log formats are `0(12) : error` and `0:12(5): error`.
+    fn remap_log(&self, log: &str) -> String {
+        let re_line = Regex::new("\\b\\d+[:(](\\d+)").unwrap();
+
+        let mut result = String::with_capacity(log.len());
+        for line in log.lines() {
+            let location = re_line.captures(line)
+                .and_then(|caps| caps[1].parse::<usize>().ok())
+                .and_then(|line_no| self.lines.get(line_no.wrapping_sub(1)));
+            if let Some(location) = location {
+                write!(result, "{}:{}: ", location.file, location.line).unwrap();
+            }
+            result.write_str(line).unwrap();
+            result.write_char('\n').unwrap();
+        }
+        result
+    }
+}
+
 /// `ShaderProgramBuilder` allows for loading multiple GLSL source files and compiling them into one
 /// OpenGL shader program.
 pub struct ShaderProgramBuilder {
     shaders: FxHashMap<ShaderType, Shader>,
-    include_cache: FxHashMap<String, String>,
+    include_cache: FxHashMap<String, ShaderSource>,
+    source_files: Vec<String>,
+    defines: Vec<(String, String)>,
 }
 
 impl ShaderProgramBuilder {
@@ -67,7 +118,36 @@ impl ShaderProgramBuilder {
         Self {
             shaders: FxHashMap::default(),
             include_cache: FxHashMap::default(),
+            source_files: Vec::new(),
+            defines: Vec::new(),
+        }
+    }
+
+    /// Adds a `#define name value` directive right after the `#version` directive of all shaders.
+    /// Defining the same name again replaces the value. Must be called before loading any shaders.
+    pub fn with_define(&mut self, name: &str, value: &str) -> &mut Self {
+        assert!(self.shaders.is_empty(), "defines must be added before loading any shaders");
+
+        if let Some(define) = self.defines.iter_mut().find(|(n, _)| n == name) {
+            define.1 = value.to_string();
+        } else {
+            self.defines.push((name.to_string(), value.to_string()));
         }
+        self
+    }
+
+    /// Adds a boolean define as `1` or `0`, so that it can be used in `#if` directives.
+    pub fn with_define_bool(&mut self, name: &str, value: bool) -> &mut Self {
+        self.with_define(name, if value { "1" } else { "0" })
+    }
+
+    pub fn with_define_i32(&mut self, name: &str, value: i32) -> &mut Self {
+        self.with_define(name, &value.to_string())
+    }
+
+    /// Returns all defines in the order they are emitted.
+    pub fn defines(&self) -> &[(String, String)] {
+        &self.defines
     }
 
     /// Reads the given file at `path` and assigns it to a shader of type `type`.
@@ -80,7 +160,7 @@ impl ShaderProgramBuilder {
         if src.len() != 1 || !src.keys().next().unwrap().is_empty() {
             return Err(ShaderError::Other(String::from("file must not contain any #shader_type directives")));
         }
-        self.add_shader(type_, src.remove("").unwrap())
+        self.add_shader_source(type_, src.remove("").unwrap())
     }
 
     /// Reads a shader bundle file that can define multiple shader types in one file.
@@ -89,6 +169,8 @@ impl ShaderProgramBuilder {
     /// - `#inlucde "file.glsl"` can be used to include other file's contents
     /// - `#shader_type <vertex|fragment|compute>` will use all lines until the next type directive for compiling the given shader type
     /// - Adds `SHADER_COMPILE_TYPE` definition
+    ///
+    /// Compile errors are annotated with the file and line that the erroneous line originates from.
     pub fn load_shader_bundle(&mut self, path: &str) -> Result<&mut Self, ShaderError> {
         let src = self.load_file(path)?;
         for (type_, src) in src {
@@ -100,21 +182,26 @@ impl ShaderProgramBuilder {
                     return Err(ShaderError::Other(format!("unsupported shader type: {type_}")));
                 }
             };
-            self.add_shader(type_, src)?;
+            self.add_shader_source(type_, src)?;
         }
         Ok(self)
     }
 
-    fn load_file(&mut self, path: &str) -> Result<FxHashMap<String, String>, ShaderError> {
+    fn load_file(&mut self, path: &str) -> Result<FxHashMap<String, ShaderSource>, ShaderError> {
         let re_include = Regex::new("^#include\\s\"(.*)\"$").unwrap();
 
         let mut current_type = String::new();
         let mut shader_types = FxHashMap::default();
 
+        if !self.source_files.iter().any(|file| file == path) {
+            self.source_files.push(path.to_string());
+        }
+
         let source = assets::read(path)?;
         let source = String::from_utf8_lossy(&source);
-        for line in source.split('\n') {
+        for (line_no, line) in source.split('\n').enumerate() {
             let line = line.trim_end();
+            let location = SourceLocation { file: path.to_string(), line: line_no + 1 };
 
             // handle shader type directives
             if line.starts_with("#shader_type") {
@@ -136,7 +223,7 @@ impl ShaderProgramBuilder {
             }
 
             if !shader_types.contains_key(&current_type) {
-                shader_types.insert(current_type.clone(), String::new());
+                shader_types.insert(current_type.clone(), ShaderSource::default());
             }
             let buffer = shader_types.get_mut(&current_type).unwrap();
 
@@ -156,36 +243,35 @@ impl ShaderProgramBuilder {
                 };
 
                 self.write_included_file_to(&include_path, buffer)?;
+                buffer.push_line("", location);
 
                 continue;
             }
 
-            buffer.write_str(line).unwrap();
-            buffer.write_char('\n').unwrap();
+            buffer.push_line(line, location);
         }
 
         Ok(shader_types)
     }
 
-    fn write_included_file_to(&mut self, path: &str, dst: &mut String) -> Result<(), ShaderError> {
+    fn write_included_file_to(&mut self, path: &str, dst: &mut ShaderSource) -> Result<(), ShaderError> {
         let include_src = self.include_cache.get(path);
 
         // use cache and prevent cyclic includes
         if let Some(ok) = include_src {
-            if ok.is_empty() {
+            if ok.code.is_empty() {
                 return Err(ShaderError::Other(
                     format!("cyclic include of {path}"),
                 ));
             }
 
-            dst.write_str(ok).unwrap();
-            dst.write_char('\n').unwrap();
+            dst.append(ok);
 
             return Ok(());
         }
 
-        // add empty string to detect cyclic includes
-        self.include_cache.insert(String::from(path), String::new());
+        // add empty source to detect cyclic includes
+        self.include_cache.insert(String::from(path), ShaderSource::default());
 
         // try loading the included file
         let src = self.load_file(path);
@@ -206,8 +292,7 @@ impl ShaderProgramBuilder {
 
         // write to dst buffer
         let src = src.remove("").unwrap();
-        dst.write_str(&src).unwrap();
-        dst.write_char('\n').unwrap();
+        dst.append(&src);
 
         // cache for next inclusion
         self.include_cache.insert(String::from(path), src);
@@ -215,20 +300,39 @@ impl ShaderProgramBuilder {
         Ok(())
     }
 
+    /// Compiles the given source code as a shader of type `type_`. Compile errors refer to the
+    /// line numbers of `src`.
     pub fn add_shader(&mut self, type_: ShaderType, src: String) -> Result<&mut Self, ShaderError> {
+        self.add_shader_source(type_, ShaderSource { code: src, lines: Vec::new() })
+    }
+
+    fn add_shader_source(&mut self, type_: ShaderType, src: ShaderSource) -> Result<&mut Self, ShaderError> {
         if self.shaders.contains_key(&type_) {
             return Err(ShaderError::Other(format!("type {type_:?} is already registered")));
         }
 
         let mut src = src;
-        Self::inject_preprocessor_defines(&mut src, type_);
+        let (line, count) = Self::inject_preprocessor_defines(&mut src.code, type_, &self.defines);
+        if !src.lines.is_empty() {
+            let injected = (0..count).map(|i| SourceLocation { file: String::from("<injected>"), line: i + 1 });
+            src.lines.splice(line..line, injected);
+        }
 
-        let shader = Shader::new(type_, &src)?;
-        self.shaders.insert(type_, shader);
-        Ok(self)
+        match Shader::new(type_, &src.code) {
+            Ok(shader) => {
+                self.shaders.insert(type_, shader);
+                Ok(self)
+            }
+            Err(ShaderError::Compile(err)) => Err(ShaderError::Compile(GlError {
+                details: src.remap_log(&err.details),
+            })),
+            Err(err) => Err(err),
+        }
     }
 
-    fn inject_preprocessor_defines(src: &mut String, type_: ShaderType) {
+    /// Injects the preprocessor defines right after the `#version` directive. Returns the index of
+    /// the first injected line and the number of injected lines.
+    fn inject_preprocessor_defines(src: &mut String, type_: ShaderType, defines: &[(String, String)]) -> (usize, usize) {
         let mut offset = 0;
         if let Some(version_start) = src.find("#version") {
             if let Some(line_end) = src[version_start..].find('\n') {
@@ -236,19 +340,26 @@ impl ShaderProgramBuilder {
             }
         }
 
-        let inject = formatdoc! {r#"
+        let mut inject = formatdoc! {r#"
             #define SHADER_TYPE_VERTEX      0
             #define SHADER_TYPE_FRAGMENT    1
             #define SHADER_TYPE_COMPUTE     2
             #define SHADER_COMPILE_TYPE     SHADER_TYPE_{}
         "#, type_.string()};
+        for (name, value) in defines {
+            writeln!(inject, "#define {name} {value}").unwrap();
+        }
         src.insert_str(offset, &inject);
+
+        (src[..offset].matches('\n').count(), inject.matches('\n').count())
     }
 
     pub fn build(&self) -> Result<ShaderProgram, ShaderError> {
         let program = ShaderProgram {
             gl_id: unsafe { gl::CreateProgram() },
             uniform_location_cache: RefCell::new(FxHashMap::default()),
+            source_files: self.source_files.clone(),
+            modified: latest_modified(&self.source_files),
         };
         for shader in self.shaders.values() {
             unsafe { gl::AttachShader(program.gl_id, shader.gl_id) }
@@ -271,7 +382,10 @@ mod shader_program_builder_tests {
     use rustc_hash::FxHashMap;
     use tempfile::NamedTempFile;
 
-    use crate::graphics::shader::{ShaderProgramBuilder, ShaderType};
+    use crate::core::GlContext;
+    use crate::graphics::buffer;
+    use crate::graphics::buffer::Buffer;
+    use crate::graphics::shader::{ShaderProgramBuilder, ShaderSource, ShaderType, SourceLocation};
 
     /// Tests if files with includes and other directives are loaded correctly without actually
     /// compiling the shader.
@@ -297,18 +411,135 @@ mod shader_program_builder_tests {
         let shader_path = shader_file.path().as_os_str().to_str().unwrap();
         let result = builder.load_file(shader_path).unwrap();
 
+        let result: FxHashMap<_, _> = result.into_iter().map(|(type_, src)| (type_, src.code)).collect();
         assert_eq!(result, FxHashMap::from_iter([
             ("vertex".to_string(), "// test include\n\n\nvoid main() { gl_Position = vec4(position, 1.0); }\n\n".to_string()),
             ("fragment".to_string(), "void main() { }\n\n".to_string()),
         ]));
-        assert_eq!(builder.include_cache, FxHashMap::from_iter([
+        let include_cache: FxHashMap<_, _> = builder.include_cache.iter().map(|(path, src)| (path.clone(), src.code.clone())).collect();
+        assert_eq!(include_cache, FxHashMap::from_iter([
             (include_path.to_string(), "// test include\n\n".to_string()),
         ]));
+        assert_eq!(builder.source_files, vec![shader_path.to_string(), include_path.to_string()]);
 
         shader_file.close().unwrap();
         include_file.close().unwrap();
     }
 
+    /// Tests that included files are inlined and that every line is mapped to the file and line it
+    /// originates from.
+    #[test]
+    fn load_file_include_line_mapping() {
+        let mut include_file = NamedTempFile::new().unwrap();
+        include_file.write_all(b"float a;\nfloat b;").unwrap();
+        let include_path = include_file.path().to_str().unwrap();
+
+        let mut shader_file = NamedTempFile::new().unwrap();
+        shader_file.write_all(formatdoc! {r#"
+            #version 450
+            #include "{}"
+            void main() {{ }}"#, include_path}.as_bytes()).unwrap();
+        let shader_path = shader_file.path().to_str().unwrap();
+
+        let mut builder = ShaderProgramBuilder::new();
+        let result = builder.load_file(shader_path).unwrap();
+        let src = &result[""];
+
+        assert_eq!(src.code, "#version 450\nfloat a;\nfloat b;\n\nvoid main() { }\n");
+        let location = |file: &str, line| SourceLocation { file: file.to_string(), line };
+        assert_eq!(src.lines, vec![
+            location(shader_path, 1),
+            location(include_path, 1),
+            location(include_path, 2),
+            location(shader_path, 2),
+            location(shader_path, 3),
+        ]);
+    }
+
+    /// Tests that cyclic includes are reported as an error.
+    #[test]
+    fn load_file_cyclic_include() {
+        let mut file_a = NamedTempFile::new().unwrap();
+        let mut file_b = NamedTempFile::new().unwrap();
+        let path_a = file_a.path().to_str().unwrap().to_string();
+        let path_b = file_b.path().to_str().unwrap().to_string();
+        file_a.write_all(format!("#include \"{path_b}\"\n").as_bytes()).unwrap();
+        file_b.write_all(format!("#include \"{path_a}\"\n").as_bytes()).unwrap();
+
+        let mut builder = ShaderProgramBuilder::new();
+        let err = builder.load_file(&path_a).unwrap_err();
+        assert!(format!("{err:?}").contains("cyclic include of"), "{err:?}");
+    }
+
+    /// Tests that line numbers in compile logs of different drivers are mapped back to their
+    /// original file and line.
+    #[test]
+    fn remap_log() {
+        let src = ShaderSource {
+            code: "#version 450\nfloat a\nvoid main() { }\n".to_string(),
+            lines: vec![
+                SourceLocation { file: "main.glsl".to_string(), line: 1 },
+                SourceLocation { file: "common.glsl".to_string(), line: 7 },
+                SourceLocation { file: "main.glsl".to_string(), line: 3 },
+            ],
+        };
+
+        let log = src.remap_log("0(2) : error C0000: syntax error\n0:3(5): error: foo\nlinker error\n0:9(1): error: bar");
+        assert_eq!(log, indoc! {"
+            common.glsl:7: 0(2) : error C0000: syntax error
+            main.glsl:3: 0:3(5): error: foo
+            linker error
+            0:9(1): error: bar
+        "});
+    }
+
+    /// Tests that typed defines are visible to the compiled shader and can be queried.
+    #[test]
+    fn with_define() {
+        let _context = GlContext::new_headless(1, 1); // do not drop context
+
+        let run = |mode: i32, negate: bool| {
+            let mut builder = ShaderProgramBuilder::new();
+            builder.with_define_i32("MODE", mode).with_define_bool("NEGATE", negate);
+            assert_eq!(builder.defines(), &[
+                ("MODE".to_string(), mode.to_string()),
+                ("NEGATE".to_string(), if negate { "1" } else { "0" }.to_string()),
+            ]);
+
+            let shader = builder.add_shader(ShaderType::Compute, indoc! {"
+                #version 450
+                layout (local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+                layout (std430, binding = 0) buffer Out { int result; };
+
+                void main() {
+                #if MODE == 2
+                    result = 20;
+                #else
+                    result = 10;
+                #endif
+                #if NEGATE
+                    result = -result;
+                #endif
+                }
+            "}.to_string()).unwrap().build().unwrap();
+
+            let mut out = Buffer::new(vec![0i32], buffer::STATIC_DRAW | buffer::STATIC_READ);
+            out.bind_as_storage_buffer(0);
+            unsafe {
+                shader.bind();
+                gl::DispatchCompute(1, 1, 1);
+                gl::MemoryBarrier(gl::ALL_BARRIER_BITS);
+                shader.unbind();
+            }
+            out.pull_data();
+            out.take()[0]
+        };
+
+        assert_eq!(run(1, false), 10);
+        assert_eq!(run(2, false), 20);
+        assert_eq!(run(2, true), -20);
+    }
+
     /// Tests if preprocessor defines are injected correctly into shader source.
     #[test]
     fn inject_preprocessor_defines() {
@@ -320,7 +551,8 @@ mod shader_program_builder_tests {
             }
         "#});
 
-        ShaderProgramBuilder::inject_preprocessor_defines(&mut code, ShaderType::Vertex);
+        let defines = [("CUSTOM".to_string(), "1".to_string())];
+        ShaderProgramBuilder::inject_preprocessor_defines(&mut code, ShaderType::Vertex, &defines);
 
         assert_eq!(code, String::from(indoc! {r#"
             #version 450
@@ -328,6 +560,7 @@ mod shader_program_builder_tests {
             #define SHADER_TYPE_FRAGMENT    1
             #define SHADER_TYPE_COMPUTE     2
             #define SHADER_COMPILE_TYPE     SHADER_TYPE_VERTEX
+            #define CUSTOM 1
 
             void main() {
                 gl_Position = vec4(position, 1.0);
@@ -339,6 +572,18 @@ mod shader_program_builder_tests {
 pub struct ShaderProgram {
     gl_id: GLuint,
     uniform_location_cache: RefCell<FxHashMap<&'static str, GLint>>,
+    source_files: Vec<String>,
+    modified: Option<SystemTime>,
+}
+
+impl Watch for ShaderProgram {
+    fn source_files(&self) -> &[String] {
+        &self.source_files
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
 }
 
 impl Drop for ShaderProgram {
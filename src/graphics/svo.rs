@@ -1,19 +1,23 @@
 use std::alloc::Allocator;
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell};
+use std::mem;
+use std::time::Duration;
 
-use cgmath::{EuclideanSpace, Matrix4, Point3, SquareMatrix, Vector3};
+use cgmath::{EuclideanSpace, Matrix4, Point3, SquareMatrix, Vector2, Vector3};
 
-use crate::graphics::buffer::{Buffer, MappedBuffer};
+use crate::graphics::buffer::{Buffer, MappedBuffer, RingMappedBuffer};
 use crate::graphics::fence::Fence;
 use crate::graphics::framebuffer::Framebuffer;
 use crate::graphics::resource::Resource;
 use crate::graphics::screen_quad::ScreenQuad;
 use crate::graphics::shader::{ShaderError, ShaderProgram, ShaderProgramBuilder};
-use crate::graphics::svo_picker::{PickerBatch, PickerBatchResult, PickerResult, PickerTask};
+use crate::graphics::svo_picker::{AabbResult, CapsuleResult, MAX_SVO_PICKER_JOBS, NearestSurfaceResult, PickerBatch, PickerBatchResult, PickerResult, PickerTask, RayResult};
+use crate::graphics::svo_picker_system::{CompletedBatch, PickerSystem};
 use crate::graphics::svo_registry::{MaterialInstance, VoxelRegistry};
 use crate::graphics::texture_array::{TextureArray, TextureArrayError};
+use crate::graphics::timer::GpuTimer;
 use crate::world;
-use crate::world::svo::SerializedChunk;
+use crate::world::svo::{Range, SerializedChunk};
 
 /// Buffer indices are constants for all buffer ids used in the SVO shaders.
 #[allow(dead_code)]
@@ -26,6 +30,11 @@ pub mod buffer_indices {
     pub const DEBUG_OUT: u32 = 12;
 }
 
+/// `WORLD_BUFFER_REGIONS` is the number of copies of the world buffer. While one of them is updated, the renderer can
+/// still read from the other, so that updates do not have to wait for the previous frame. Every region costs the full
+/// size of the world buffer in memory.
+const WORLD_BUFFER_REGIONS: usize = 2;
+
 /// Svo can be used to render an SVO of [`SerializedChunk`]. It is initialised
 /// with a `VoxelRegistry` with textures and materials to render the actual chunks.
 ///
@@ -35,18 +44,26 @@ pub struct Svo {
     // _material_buffer needs to be stored to drop it together with all other resources
     _material_buffer: Buffer<MaterialInstance>,
     world_shader: Resource<ShaderProgram, ShaderError>,
-    world_buffer: MappedBuffer<u32>,
+    world_buffer: RingMappedBuffer<u32>,
+    // stale_ranges contains the changes, that were written to other regions of the world buffer since a region was
+    // updated the last time
+    stale_ranges: Vec<Vec<Range>>,
     // screen_quad is used to render a full-screen quad on which the per-pixel raytracer for the SVO
     // is executed
     screen_quad: ScreenQuad,
-    // render_fence synchronizes changes to the mapped world buffer with the renderer
-    render_fence: RefCell<Fence>,
+    // shadow_accumulation keeps track of the per-pixel shadow history used for soft shadows
+    shadow_accumulation: RefCell<ShadowAccumulation>,
 
     picker_shader: Resource<ShaderProgram, ShaderError>,
-    picker_in_buffer: MappedBuffer<PickerTask>,
-    picker_out_buffer: MappedBuffer<PickerResult>,
+    // picker buffers grow to fit the largest batch that was raycast so far
+    picker_in_buffer: RefCell<MappedBuffer<PickerTask>>,
+    picker_out_buffer: RefCell<MappedBuffer<PickerResult>>,
     picker_fence: RefCell<Fence>,
+    // picker_system runs raycasts asynchronously without stalling the CPU
+    picker_system: PickerSystem,
 
+    // gpu_timer measures the GPU time of the raytracing and picker passes
+    gpu_timer: RefCell<GpuTimer>,
     stats: Stats,
 }
 
@@ -54,17 +71,22 @@ pub struct Svo {
 pub struct Stats {
     /// `used_bytes` is the amount of bytes that is used by of the mapped buffer.
     pub used_bytes: usize,
-    /// `capacity_bytes` is the full size of the mapped buffer on both CPU & GPU.
+    /// `capacity_bytes` is the full size of the mapped buffer, including all of its regions, on both CPU & GPU.
     pub capacity_bytes: usize,
     /// depth is the number of octant divisions the SVO has, until the leaf node is encoded.
     pub depth: u8,
 }
 
+/// `SHADOW_KERNEL_SIZE` is the number of shadow rays per axis that are cast for soft shadows.
+const SHADOW_KERNEL_SIZE: i32 = 3;
+
 pub struct RenderParams {
     /// `ambient_intensity` is the amount of ambient light present in the scene.
     pub ambient_intensity: f32,
     /// `light_dir` indicates in which direction sun light shines in the scene.
     pub light_dir: Vector3<f32>,
+    /// `light_color` is the color of the sun light. Black disables sun light and shadows.
+    pub light_color: Vector3<f32>,
     /// `cam_pos` is the eye position from which the scene is rendered.
     pub cam_pos: Point3<f32>,
     /// `cam_fwd` is the look at direction of the camera.
@@ -75,12 +97,126 @@ pub struct RenderParams {
     pub fov_y_rad: f32,
     /// `aspect_ratio` is `width / height` of the screen's resolution.
     pub aspect_ratio: f32,
+    /// `ortho_size` switches to an orthographic projection with the given width and height of the view in blocks. All
+    /// rays are cast parallel to `cam_fwd` then, and `fov_y_rad` and `aspect_ratio` are ignored.
+    pub ortho_size: Option<Vector2<f32>>,
+    /// `max_ray_distance` limits the distance primary rays travel. Rays that do not hit anything within it show the
+    /// sky.
+    pub max_ray_distance: Option<f32>,
     /// `selected_voxel` is the position of the voxel to be highlighted.
     pub selected_voxel: Option<Point3<f32>>,
     /// `render_shadows` enables secondary ray casting to check for sun light occlusion.
     pub render_shadows: bool,
     /// `shadow_distance` defines the maximum distance to the primary hit, until which secondary rays are cast.
     pub shadow_distance: f32,
+    /// `shadow_accumulate` enables soft shadows by accumulating jittered shadow rays over multiple frames. The
+    /// accumulation is reset if the camera or light changes.
+    pub shadow_accumulate: bool,
+    /// `shadow_softness` is the radius of the cone around the light direction, over which a kernel of shadow rays is
+    /// spread and averaged per pixel. `0` casts a single shadow ray, resulting in hard shadows.
+    pub shadow_softness: f32,
+    /// `edge_outline` enables drawing outlines on voxel face edges that border a different or no voxel.
+    pub edge_outline: Option<EdgeOutline>,
+    /// `render_mode` selects between regular shading and debug visualizations.
+    pub render_mode: RenderMode,
+    /// `fog` fades distant voxels into a fog color.
+    pub fog: FogParams,
+}
+
+/// `ShadowAccumulation` stores the shadow history and the parameters it was rendered with. If any of these change, the
+/// history is invalidated.
+struct ShadowAccumulation {
+    history: Option<Framebuffer>,
+    sample_index: u32,
+    last_view: Option<ShadowView>,
+}
+
+#[derive(PartialEq)]
+struct ShadowView {
+    view_mat: Matrix4<f32>,
+    light_dir: Vector3<f32>,
+    fov_y_rad: f32,
+    aspect_ratio: f32,
+}
+
+impl ShadowAccumulation {
+    /// Maximum number of samples after which new samples still contribute with a constant weight. This allows the
+    /// history to slowly adapt to changes that are not detected, like changes to the world.
+    const MAX_SAMPLES: u32 = 64;
+
+    /// Returns the sample index for the next frame. The history is recreated if the target's size changed.
+    fn next_sample(&mut self, view: ShadowView, target: &Framebuffer) -> u32 {
+        let size_changed = self.history.as_ref()
+            .map_or(true, |fb| fb.width() != target.width() || fb.height() != target.height());
+        if size_changed {
+            self.history = Some(Framebuffer::new(target.width(), target.height(), false, false));
+        }
+
+        if size_changed || self.last_view.as_ref() != Some(&view) {
+            self.sample_index = 0;
+            self.last_view = Some(view);
+        }
+
+        let index = self.sample_index;
+        self.sample_index = (self.sample_index + 1).min(Self::MAX_SAMPLES);
+        index
+    }
+
+    fn reset(&mut self) {
+        self.sample_index = 0;
+    }
+}
+
+/// `RenderMode` selects what the world shader outputs for every hit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// `Shaded` renders textured and lit voxels.
+    #[default]
+    Shaded,
+    /// `Octants` colors every voxel by the leaf octant it was found in: the hue encodes the octant's scale (cycling
+    /// every 8 scales) and the brightness its index within the parent octant. This makes octant subdivisions, LOD
+    /// levels and chunk seams visible.
+    Octants,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FogParams {
+    /// `color` is the RGB color that distant voxels fade into.
+    pub color: Vector3<f32>,
+    /// `density` controls how fast fog increases with the distance, as in `1 - exp(-density * (t - start))`. A density
+    /// of 0 disables fog.
+    pub density: f32,
+    /// `start` is the distance from which on fog is applied.
+    pub start: f32,
+    /// `end` is the distance at which voxels are fully fogged. Usually the boundary of the loaded world, so that it
+    /// fades out softly. Ignored if it is not greater than `start`.
+    pub end: f32,
+}
+
+impl Default for FogParams {
+    fn default() -> Self {
+        Self {
+            // matches the sky's horizon color
+            color: Vector3::new(0.737, 0.890, 0.955),
+            density: 0.0,
+            start: 0.0,
+            end: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EdgeOutline {
+    /// `width` is the thickness of the outline relative to the size of a voxel face. Should be within \[0;0.5\].
+    pub width: f32,
+    /// `color` is the RGB color that is blended over the voxel's color on its edges.
+    pub color: Vector3<f32>,
+}
+
+impl Default for EdgeOutline {
+    fn default() -> Self {
+        Self { width: 0.04, color: Vector3::new(0.0, 0.0, 0.0) }
+    }
 }
 
 impl Svo {
@@ -91,19 +227,19 @@ impl Svo {
 
         let world_shader = Resource::new(
             || ShaderProgramBuilder::new().load_shader_bundle("assets/shaders/world.glsl")?.build()
-        ).unwrap();
+        );
 
-        let world_buffer = MappedBuffer::<u32>::new(100 * 1000 * 1000 / 4); // 100 MB
+        let world_buffer = RingMappedBuffer::<u32>::new(100 * 1000 * 1000 / 4, WORLD_BUFFER_REGIONS); // 100 MB per region
         world_buffer.bind_as_storage_buffer(buffer_indices::WORLD);
 
         let picker_shader = Resource::new(
             || ShaderProgramBuilder::new().load_shader_bundle("assets/shaders/picker.glsl")?.build()
-        ).unwrap();
+        );
 
-        let picker_in_buffer = MappedBuffer::<PickerTask>::new(100);
+        let picker_in_buffer = MappedBuffer::<PickerTask>::new(MAX_SVO_PICKER_JOBS);
         picker_in_buffer.bind_as_storage_buffer(buffer_indices::PICKER_IN);
 
-        let picker_out_buffer = MappedBuffer::<PickerResult>::new(100);
+        let picker_out_buffer = MappedBuffer::<PickerResult>::new(MAX_SVO_PICKER_JOBS);
         picker_out_buffer.bind_as_storage_buffer(buffer_indices::PICKER_OUT);
 
         Self {
@@ -111,14 +247,17 @@ impl Svo {
             _material_buffer: material_buffer,
             world_shader,
             world_buffer,
+            stale_ranges: vec![Vec::new(); WORLD_BUFFER_REGIONS],
             screen_quad: ScreenQuad::new(),
-            render_fence: RefCell::new(Fence::new()),
+            shadow_accumulation: RefCell::new(ShadowAccumulation { history: None, sample_index: 0, last_view: None }),
 
             picker_shader,
-            picker_in_buffer,
-            picker_out_buffer,
+            picker_in_buffer: RefCell::new(picker_in_buffer),
+            picker_out_buffer: RefCell::new(picker_out_buffer),
             picker_fence: RefCell::new(Fence::new()),
+            picker_system: PickerSystem::new(2),
 
+            gpu_timer: RefCell::new(GpuTimer::new(2)),
             stats: Stats { used_bytes: 0, capacity_bytes: 0, depth: 0 },
         }
     }
@@ -135,21 +274,66 @@ impl Svo {
         }
     }
 
-    /// Writes all changes from the given `svo` to the GPU buffer.
+    /// Reloads all shaders whose source files changed since they were loaded.
+    pub fn reload_changed_shaders(&mut self) {
+        if let Err(e) = self.world_shader.reload_if_changed() {
+            println!("error reloading world shader: {e:?}");
+        }
+        if let Err(e) = self.picker_shader.reload_if_changed() {
+            println!("error reloading picker shader: {e:?}");
+        }
+    }
+
+    /// Writes all changes from the given `svo` to the next region of the GPU buffer. Draws that are still in progress
+    /// keep reading the previous region, so this only blocks, if the next region is still in use.
     pub fn update<A: Allocator>(&mut self, svo: &mut world::svo::Svo<SerializedChunk, A>) {
+        if svo.depth() > world::svo::MAX_DEPTH {
+            println!("error: svo depth {} exceeds the maximum of {} supported by the traversal", svo.depth(), world::svo::MAX_DEPTH);
+            return;
+        }
+
+        // updates and draws must not use the same region, as that would produce temporary "holes" in the world
+        self.world_buffer.advance();
+        self.world_buffer.bind_as_storage_buffer(buffer_indices::WORLD);
+
+        let region = self.world_buffer.current_region();
+        let mut ranges = mem::take(&mut self.stale_ranges[region]);
+        ranges.extend_from_slice(svo.changed_ranges());
+        for (i, stale) in self.stale_ranges.iter_mut().enumerate() {
+            if i != region {
+                stale.extend_from_slice(svo.changed_ranges());
+            }
+        }
+
         unsafe {
             let max_depth_exp = (-(svo.depth() as f32)).exp2();
-            self.world_buffer.write(max_depth_exp.to_bits());
-
-            // wait for last draw call to finish so that updates and draws do not race and produce temporary "holes" in the world
-            self.render_fence.borrow().wait();
+            let dst = self.world_buffer.as_mut_ptr();
+            dst.write(max_depth_exp.to_bits());
 
             let len = self.world_buffer.len() - 1;
-            svo.write_changes_to(self.world_buffer.offset(1), len, true);
+            if svo.write_ranges_to(dst.offset(1), len, &ranges).is_err() {
+                // the whole svo is written to the new buffer, which can be larger than the changes that did not fit.
+                // Grow with some headroom to avoid reallocating on every update
+                let svo_len = svo.size_in_bytes() / 4 + world::svo::Svo::<SerializedChunk, A>::PREAMBLE_LENGTH as usize;
+                let mut world_buffer = RingMappedBuffer::<u32>::new(1 + svo_len * 3 / 2, WORLD_BUFFER_REGIONS);
+                for _ in 0..WORLD_BUFFER_REGIONS {
+                    let dst = world_buffer.as_mut_ptr();
+                    dst.write(max_depth_exp.to_bits());
+                    svo.write_to(dst.offset(1));
+                    world_buffer.advance();
+                }
+                world_buffer.bind_as_storage_buffer(buffer_indices::WORLD);
+                self.stale_ranges.iter_mut().for_each(Vec::clear);
+                self.world_buffer = world_buffer;
+            }
+            svo.reset_change_tracking();
+
+            // accumulated shadows are no longer valid if the world changed
+            self.shadow_accumulation.get_mut().reset();
 
             self.stats = Stats {
                 used_bytes: svo.size_in_bytes(),
-                capacity_bytes: self.world_buffer.size_in_bytes(),
+                capacity_bytes: self.world_buffer.total_size_in_bytes(),
                 depth: svo.depth(),
             };
         }
@@ -159,21 +343,60 @@ impl Svo {
         self.stats
     }
 
+    /// Returns the GPU time per pass of a recent frame. Empty, if timer queries are not supported.
+    pub fn get_gpu_times(&self) -> Ref<'_, [(&'static str, Duration)]> {
+        Ref::map(self.gpu_timer.borrow(), GpuTimer::get_results)
+    }
+
+    /// Returns the frame number of the GPU times returned by [`Svo::get_gpu_times`]. See
+    /// [`GpuTimer::get_results_frame`].
+    pub fn get_gpu_times_frame(&self) -> Option<u64> {
+        self.gpu_timer.borrow().get_results_frame()
+    }
+
     /// Draws a full-screen quad on which the raytracing shader is executed.
     pub fn render(&self, params: &RenderParams, target: &Framebuffer) {
+        // every render call starts a new frame for the timer
+        self.gpu_timer.borrow_mut().next_frame();
+        self.render_view(params, target, "raytrace");
+    }
+
+    /// Renders another view of the current frame into `target`, e.g. a minimap. Unlike [`Svo::render`], no new frame
+    /// is started for the GPU timer and the view is measured as `pass`. Views other than the main view should not
+    /// enable `shadow_accumulate`, as there is only one shadow history.
+    pub fn render_view(&self, params: &RenderParams, target: &Framebuffer, pass: &'static str) {
         let view_mat = Matrix4::look_to_rh(params.cam_pos, params.cam_fwd, params.cam_up).invert().unwrap();
+        let mut gpu_timer = self.gpu_timer.borrow_mut();
 
         self.world_shader.bind();
 
         self.world_shader.set_f32("u_ambient", params.ambient_intensity);
         self.world_shader.set_f32vec3("u_light_dir", &params.light_dir);
+        self.world_shader.set_f32vec3("u_light_color", &params.light_color);
         self.world_shader.set_f32vec3("u_cam_pos", &params.cam_pos.to_vec());
         self.world_shader.set_f32mat4("u_view", &view_mat);
         self.world_shader.set_f32("u_fovy", params.fov_y_rad);
         self.world_shader.set_f32("u_aspect", params.aspect_ratio);
+        self.world_shader.set_f32vec2("u_ortho_size", &params.ortho_size.unwrap_or(Vector2::new(0.0, 0.0)));
+        self.world_shader.set_f32("u_max_ray_dst", params.max_ray_distance.unwrap_or(-1.0));
         self.world_shader.set_texture("u_texture", 0, &self.tex_array);
         self.world_shader.set_i32("u_render_shadows", params.render_shadows as i32);
         self.world_shader.set_f32("u_shadow_distance", params.shadow_distance);
+        self.world_shader.set_i32("u_shadow_accumulate", params.shadow_accumulate as i32);
+        self.world_shader.set_f32("u_shadow_softness", params.shadow_softness);
+        let kernel_size = if params.shadow_softness > 0.0 { SHADOW_KERNEL_SIZE } else { 1 };
+        self.world_shader.set_i32("u_shadow_kernel_size", kernel_size);
+
+        let mut shadow_accumulation = self.shadow_accumulation.borrow_mut();
+        if params.shadow_accumulate {
+            let sample_index = shadow_accumulation.next_sample(ShadowView {
+                view_mat,
+                light_dir: params.light_dir,
+                fov_y_rad: params.fov_y_rad,
+                aspect_ratio: params.aspect_ratio,
+            }, target);
+            self.world_shader.set_i32("u_shadow_sample_index", sample_index as i32);
+        }
 
         let mut selected_block = Vector3::new(f32::NAN, f32::NAN, f32::NAN);
         if let Some(pos) = params.selected_voxel {
@@ -181,29 +404,81 @@ impl Svo {
         }
         self.world_shader.set_f32vec3("u_highlight_pos", &selected_block);
 
+        let outline = params.edge_outline.unwrap_or(EdgeOutline { width: 0.0, color: Vector3::new(0.0, 0.0, 0.0) });
+        self.world_shader.set_f32("u_edge_outline_width", outline.width);
+        self.world_shader.set_f32vec3("u_edge_outline_color", &outline.color);
+        self.world_shader.set_i32("u_render_mode", params.render_mode as i32);
+        self.world_shader.set_f32vec3("u_fog.color", &params.fog.color);
+        self.world_shader.set_f32("u_fog.density", params.fog.density);
+        self.world_shader.set_f32("u_fog.start", params.fog.start);
+        self.world_shader.set_f32("u_fog.end", params.fog.end);
+
         unsafe {
             let (width, height) = (target.width(), target.height());
 
             gl::BindImageTexture(0, target.color_attachment(), 0, gl::FALSE, 0, gl::WRITE_ONLY, gl::RGBA32F);
+            if let Some(history) = shadow_accumulation.history.as_ref().filter(|_| params.shadow_accumulate) {
+                gl::BindImageTexture(1, history.color_attachment(), 0, gl::FALSE, 0, gl::READ_WRITE, gl::RGBA32F);
+            }
+            let _scope = gpu_timer.scope(pass);
             gl::DispatchCompute((width / 32 + 1) as u32, (height / 32 + 1) as u32, 1);
             gl::MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
         }
 
         self.world_shader.unbind();
-
-        // place a fence to allow for waiting on the current frame to be rendered
-        self.render_fence.borrow_mut().place();
     }
 
     /// Uploads the given `batch` to the GPU and runs a compute shader on it to calculate
-    /// SVO interceptions without rendering anything.
+    /// SVO interceptions without rendering anything. `result` has to be reset by the caller.
     pub fn raycast(&self, batch: &PickerBatch, result: &mut PickerBatchResult) {
+        self.run_picker(batch, |results| batch.deserialize_results(results, result));
+    }
+
+    /// Like [`Svo::raycast`], but writes the results into caller-owned slices. See
+    /// [`PickerBatch::deserialize_results_into_slices`] for the layout.
+    pub fn raycast_into_slices(
+        &self,
+        batch: &PickerBatch,
+        rays: &mut [RayResult],
+        multi_rays: &mut [RayResult],
+        aabbs: &mut [AabbResult],
+        capsules: &mut [CapsuleResult],
+        nearest_surfaces: &mut [NearestSurfaceResult],
+    ) {
+        self.run_picker(batch, |results| {
+            batch.deserialize_results_into_slices(results, rays, multi_rays, aabbs, capsules, nearest_surfaces);
+        });
+    }
+
+    /// Serializes the `batch`, runs the picker shader on it and passes the raw results to `f`.
+    fn run_picker<F: FnOnce(&[PickerResult])>(&self, batch: &PickerBatch, f: F) {
+        // grow the buffers, if the batch does not fit. Previous dispatches were already waited on, so the GPU no longer
+        // uses the old buffers.
+        let required = batch.task_count();
+        if required > self.picker_in_buffer.borrow().len() {
+            *self.picker_in_buffer.borrow_mut() = MappedBuffer::new(required);
+            *self.picker_out_buffer.borrow_mut() = MappedBuffer::new(required);
+        }
+        let in_buffer = self.picker_in_buffer.borrow();
+        let out_buffer = self.picker_out_buffer.borrow();
+
         self.picker_shader.bind();
+        // rebind buffers, as they might be replaced by asynchronous raycasts
+        in_buffer.bind_as_storage_buffer(buffer_indices::PICKER_IN);
+        out_buffer.bind_as_storage_buffer(buffer_indices::PICKER_OUT);
 
-        let in_data = self.picker_in_buffer.as_slice_mut();
-        let task_count = batch.serialize_tasks(in_data);
+        let task_count = batch.serialize_tasks(in_buffer.as_slice_mut()).unwrap();
+        self.dispatch_picker(task_count);
+        f(&out_buffer.as_slice()[..task_count]);
+
+        self.picker_shader.unbind();
+    }
 
+    /// Runs the bound picker shader for the first `task_count` tasks and waits for the results.
+    fn dispatch_picker(&self, task_count: usize) {
         unsafe {
+            let mut gpu_timer = self.gpu_timer.borrow_mut();
+            let _scope = gpu_timer.scope("picker");
             gl::DispatchCompute(task_count as u32, 1, 1);
 
             // memory barrier is not required because buffer is mapped with gl::MAP_COHERENT_BIT
@@ -214,11 +489,27 @@ impl Svo {
         // (https://www.khronos.org/opengl/wiki/Buffer_Object#Persistent_mapping)
         self.picker_fence.borrow_mut().place();
         self.picker_fence.borrow().wait();
+    }
 
-        self.picker_shader.unbind();
+    /// Dispatches the given `batch` without waiting for the results. Results can be retrieved
+    /// with `poll_raycasts` at the latest after the configured picker latency in frames.
+    pub fn raycast_async(&mut self, batch: PickerBatch) {
+        self.picker_system.dispatch(&self.picker_shader, batch);
+    }
 
-        let out_data = self.picker_out_buffer.as_slice();
-        batch.deserialize_results(&out_data[..task_count], result);
+    /// Returns the most recent asynchronous raycast that has completed, if any.
+    pub fn poll_raycasts(&mut self) -> Option<CompletedBatch> {
+        self.picker_system.poll()
+    }
+
+    pub fn get_picker_latency(&self) -> usize {
+        self.picker_system.get_latency()
+    }
+
+    /// Sets the maximum number of frames after which asynchronous raycasts are read back.
+    /// Raycasts that are still in flight are discarded.
+    pub fn set_picker_latency(&mut self, latency: usize) {
+        self.picker_system.set_latency(latency);
     }
 }
 
@@ -233,8 +524,8 @@ mod svo_tests {
     use crate::core::GlContext;
     use crate::graphics::framebuffer::{diff_images, Framebuffer};
     use crate::graphics::macros::assert_vec3_eq;
-    use crate::graphics::svo::{RenderParams, Svo};
-    use crate::graphics::svo_picker::{PickerBatch, PickerBatchResult, RayResult};
+    use crate::graphics::svo::{FogParams, RenderMode, RenderParams, Svo};
+    use crate::graphics::svo_picker::{MAX_SVO_PICKER_JOBS, NearestSurfaceResult, PickerBatch, PickerBatchResult, RayResult};
     use crate::graphics::svo_registry::{Material, VoxelRegistry};
     use crate::world::chunk::{Chunk, ChunkPos, ChunkStorageAllocator};
     use crate::world::memory::{Pool, StatsAllocator};
@@ -310,14 +601,22 @@ mod svo_tests {
         svo.render(&RenderParams {
             ambient_intensity: 0.3,
             light_dir: Vector3::new(-1.0, -1.0, -1.0).normalize(),
+            light_color: Vector3::new(1.0, 1.0, 1.0),
             cam_pos,
             cam_fwd: -Vector3::unit_z(),
             cam_up: Vector3::unit_y(),
             fov_y_rad: 72.0f32.to_radians(),
             aspect_ratio: width as f32 / height as f32,
+            ortho_size: None,
+            max_ray_distance: None,
             selected_voxel: Some(Point3::new(1.0, 1.0, 3.0)),
             render_shadows: true,
             shadow_distance: 500.0,
+            shadow_accumulate: false,
+            shadow_softness: 0.0,
+            render_mode: RenderMode::Shaded,
+            fog: FogParams::default(),
+            edge_outline: None,
         }, &fb);
         fb.unbind();
         gl_assert_no_error!();
@@ -357,23 +656,176 @@ mod svo_tests {
                 RayResult {
                     dst: assert_float_eq!(result.rays[0].dst, 0.5, 0.0001),
                     inside_voxel: false,
+                    value: 1,
                     pos: assert_vec3_eq!(result.rays[0].pos, Point3::new(0.5, 1.0, 0.5), 0.0001),
                     normal: Vector3::new(0.0, 1.0, 0.0),
                 },
                 RayResult {
                     dst: assert_float_eq!(result.rays[1].dst, 0.5, 0.0001),
                     inside_voxel: true,
+                    value: 1,
                     pos: assert_vec3_eq!(result.rays[1].pos, Point3::new(1.0, 0.5, 0.5), 0.0001),
                     normal: Vector3::new(-1.0, 0.0, 0.0),
                 },
                 RayResult {
                     dst: -1.0,
                     inside_voxel: false,
+                    value: 0,
                     pos: Point3::new(0.0, 0.0, 0.0),
                     normal: Vector3::new(0.0, 0.0, 0.0),
                 },
             ],
+            multi_rays: vec![],
             aabbs: vec![],
+            capsules: vec![],
+            nearest_surfaces: vec![],
+        });
+    }
+
+    /// Tests that the picker buffers grow to fit batches with more tasks than they initially hold.
+    #[test]
+    fn raycast_large_batch() {
+        let _context = GlContext::new_headless(1, 1); // do not drop context
+        let mut world_svo = create_world_svo(|chunk| {
+            chunk.set_block(0, 0, 0, 1);
         });
+
+        let mut svo = Svo::new(&create_voxel_registry());
+        svo.update(&mut world_svo);
+
+        let mut batch = PickerBatch::new();
+        for _ in 0..MAX_SVO_PICKER_JOBS + 50 {
+            batch.add_ray(Point3::new(0.5, 1.5, 0.5), Vector3::new(0.0, -1.0, 0.0), 1.0);
+        }
+
+        let mut result = PickerBatchResult::new();
+        svo.raycast(&batch, &mut result);
+
+        gl_assert_no_error!();
+        assert_eq!(svo.picker_in_buffer.borrow().len(), MAX_SVO_PICKER_JOBS + 50);
+        assert_eq!(result.rays.len(), MAX_SVO_PICKER_JOBS + 50);
+        assert!(result.rays.iter().all(|ray| ray.value == 1));
+    }
+
+    /// Tests if multi-hit rays report every voxel they pass through in order, merge adjacent voxels of the same value
+    /// and stop at `max_hits`.
+    #[test]
+    fn raycast_multi_hit() {
+        let _context = GlContext::new_headless(1, 1); // do not drop context
+        let mut world_svo = create_world_svo(|chunk| {
+            chunk.set_block(1, 0, 0, 1);
+            chunk.set_block(2, 0, 0, 1);
+            chunk.set_block(3, 0, 0, 2);
+            chunk.set_block(4, 0, 0, 1);
+        });
+
+        let mut svo = Svo::new(&create_voxel_registry());
+        svo.update(&mut world_svo);
+
+        let mut batch = PickerBatch::new();
+        batch.add_ray_multi(Point3::new(0.5, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0), 10.0, 4);
+        batch.add_ray_multi(Point3::new(0.5, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0), 10.0, 2);
+        batch.add_ray(Point3::new(0.5, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0), 10.0);
+
+        let mut result = PickerBatchResult::new();
+        svo.raycast(&batch, &mut result);
+        gl_assert_no_error!();
+
+        let values: Vec<Vec<u32>> = result.multi_rays.iter()
+            .map(|hits| hits.iter().map(|hit| hit.value).collect())
+            .collect();
+        assert_eq!(values, vec![vec![1, 2, 1], vec![1, 2]]);
+        for (hit, expected_dst) in result.multi_rays[0].iter().zip([0.5, 2.5, 3.5]) {
+            assert_float_eq!(hit.dst, expected_dst, 0.01);
+            assert_float_eq!(hit.pos.x, expected_dst + 0.5, 0.01);
+            assert_eq!(hit.normal, Vector3::new(-1.0, 0.0, 0.0));
+        }
+
+        // the first hit is identical to a regular ray
+        assert_eq!(result.rays[0].value, 1);
+        assert_float_eq!(result.multi_rays[0][0].dst, result.rays[0].dst, 0.0001);
+    }
+
+    /// Tests if a nearest surface query just outside a single voxel reports the face closest to it.
+    #[test]
+    fn raycast_nearest_surface() {
+        let _context = GlContext::new_headless(1, 1); // do not drop context
+        let mut world_svo = create_world_svo(|chunk| {
+            chunk.set_block(0, 0, 0, 1);
+        });
+
+        let mut svo = Svo::new(&create_voxel_registry());
+        svo.update(&mut world_svo);
+
+        let mut batch = PickerBatch::new();
+        batch.add_nearest_surface(Point3::new(0.5, 1.2, 0.5), 1.0);
+        batch.add_nearest_surface(Point3::new(1.1, 0.4, 0.5), 1.0);
+        batch.add_nearest_surface(Point3::new(0.5, 3.0, 0.5), 1.0);
+
+        let mut result = PickerBatchResult::new();
+        svo.raycast(&batch, &mut result);
+
+        gl_assert_no_error!();
+        assert_eq!(result.nearest_surfaces, vec![
+            NearestSurfaceResult {
+                dst: assert_float_eq!(result.nearest_surfaces[0].dst, 0.2, 0.0001),
+                dir: Vector3::new(0.0, -1.0, 0.0),
+                normal: Vector3::new(0.0, 1.0, 0.0),
+            },
+            NearestSurfaceResult {
+                dst: assert_float_eq!(result.nearest_surfaces[1].dst, 0.1, 0.0001),
+                dir: Vector3::new(-1.0, 0.0, 0.0),
+                normal: Vector3::new(1.0, 0.0, 0.0),
+            },
+            NearestSurfaceResult::default(),
+        ]);
+    }
+
+    /// Tests if asynchronous raycasts are read back after the configured latency and only the
+    /// most recent results are returned.
+    #[test]
+    fn raycast_async() {
+        let _context = GlContext::new_headless(1, 1); // do not drop context
+        let mut world_svo = create_world_svo(|chunk| {
+            chunk.set_block(0, 0, 0, 1);
+        });
+
+        let mut svo = Svo::new(&create_voxel_registry());
+        svo.update(&mut world_svo);
+        assert_eq!(svo.get_picker_latency(), 2);
+        assert!(svo.poll_raycasts().is_none());
+
+        let new_batch = |max_dst: f32| {
+            let mut batch = PickerBatch::new();
+            batch.add_ray(Point3::new(0.5, 1.5, 0.5), Vector3::new(0.0, -1.0, 0.0), max_dst);
+            batch
+        };
+
+        // results of the first frame are discarded in favour of the second one
+        svo.raycast_async(new_batch(0.25));
+        svo.raycast_async(new_batch(1.0));
+        unsafe { gl::Finish(); }
+
+        let completed = svo.poll_raycasts().unwrap();
+        gl_assert_no_error!();
+        assert_eq!(completed.latency, 1);
+        assert_eq!(completed.batch, new_batch(1.0));
+        assert_eq!(completed.result.rays, vec![
+            RayResult {
+                dst: assert_float_eq!(completed.result.rays[0].dst, 0.5, 0.0001),
+                inside_voxel: false,
+                value: 1,
+                pos: assert_vec3_eq!(completed.result.rays[0].pos, Point3::new(0.5, 1.0, 0.5), 0.0001),
+                normal: Vector3::new(0.0, 1.0, 0.0),
+            },
+        ]);
+        assert!(svo.poll_raycasts().is_none());
+
+        // results are forced to be available after `latency` frames
+        svo.raycast_async(new_batch(0.25));
+        svo.raycast_async(new_batch(0.25));
+        svo.raycast_async(new_batch(0.25));
+        let completed = svo.poll_raycasts().unwrap();
+        assert!(completed.latency <= 2);
     }
 }
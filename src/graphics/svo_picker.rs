@@ -1,19 +1,28 @@
-use cgmath::{Point3, Vector3};
+use cgmath::{InnerSpace, Point3, Vector3};
 
 use crate::graphics::macros::{AlignedPoint3, AlignedVec3};
 
-const MAX_SVO_PICKER_JOBS: usize = 100;
+/// Initial number of tasks the picker buffers can hold. Larger batches cause the buffers to grow.
+pub(super) const MAX_SVO_PICKER_JOBS: usize = 100;
 
+/// `PickerBatch` collects all casts for one raycast. Batches are meant to be reused across frames
+/// by calling [`PickerBatch::reset`] instead of allocating a new one every time.
 #[derive(Debug, PartialEq)]
 pub struct PickerBatch {
     pub rays: Vec<Ray>,
+    pub multi_rays: Vec<MultiRay>,
     pub aabbs: Vec<Aabb>,
+    pub capsules: Vec<Capsule>,
+    pub nearest_surfaces: Vec<NearestSurface>,
 }
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub(super) struct PickerTask {
     pub max_dst: f32,
+    /// `max_hits` is the number of results the task writes, starting at its own index. Tasks with
+    /// `max_hits` of 0 are placeholders for the results of a preceding multi-hit task.
+    pub max_hits: u32,
     pub pos: AlignedPoint3<f32>,
     pub dir: AlignedVec3<f32>,
 }
@@ -25,6 +34,8 @@ pub(super) struct PickerResult {
     pub dst: f32,
     /// `inside_voxel` is true, if the ray origin is within a voxel itself.
     pub inside_voxel: bool,
+    /// value is the leaf value of the hit voxel.
+    pub value: u32,
     /// pos of intersection.
     pub pos: AlignedPoint3<f32>,
     /// normal is the normal direction where the ray hit.
@@ -38,62 +49,147 @@ impl PickerBatch {
         Self::with_capacity(MAX_SVO_PICKER_JOBS)
     }
 
+    /// Reserves space for `capacity` rays and AABBs. This does not limit the batch, as the picker buffers grow to fit
+    /// any number of tasks.
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             rays: Vec::with_capacity(capacity),
+            multi_rays: Vec::new(),
             aabbs: Vec::with_capacity(capacity),
+            capsules: Vec::new(),
+            nearest_surfaces: Vec::new(),
         }
     }
 
     pub fn reset(&mut self) {
         self.rays.clear();
+        self.multi_rays.clear();
         self.aabbs.clear();
+        self.capsules.clear();
+        self.nearest_surfaces.clear();
     }
 
     pub fn add_ray(&mut self, pos: Point3<f32>, dir: Vector3<f32>, max_dst: f32) {
         self.rays.push(Ray { pos, dir, max_dst });
     }
 
+    /// Adds a ray that does not stop at the first hit, but returns up to `max_hits` voxels it
+    /// passes through, ordered by distance. Adjacent voxels of the same value are reported once.
+    /// `max_hits` is at least 1.
+    pub fn add_ray_multi(&mut self, pos: Point3<f32>, dir: Vector3<f32>, max_dst: f32, max_hits: u32) {
+        let max_hits = max_hits.max(1);
+        self.multi_rays.push(MultiRay { pos, dir, max_dst, max_hits });
+    }
+
     pub fn add_aabb(&mut self, aabb: Aabb) {
         self.aabbs.push(aabb);
     }
 
+    /// Adds an upright capsule, standing on `base`. `height` includes both hemispheres.
+    pub fn add_capsule(&mut self, base: Point3<f32>, height: f32, radius: f32) {
+        self.capsules.push(Capsule { base, height, radius });
+    }
+
+    /// Adds a query for the closest solid surface within `search_radius` around `point`, using
+    /// all 26 axis and diagonal directions.
+    pub fn add_nearest_surface(&mut self, point: Point3<f32>, search_radius: f32) {
+        self.add_nearest_surface_with_fan(point, search_radius, SurfaceFan::AxesAndDiagonals);
+    }
+
+    /// Like [`PickerBatch::add_nearest_surface`], but casts the given `fan` of rays.
+    pub fn add_nearest_surface_with_fan(&mut self, point: Point3<f32>, search_radius: f32, fan: SurfaceFan) {
+        self.nearest_surfaces.push(NearestSurface { point, search_radius, fan });
+    }
+
+    /// Returns the number of picker tasks this batch consists of. Every ray is one task, multi-hit
+    /// rays occupy one task per hit, nearest surface queries one task per direction of their fan,
+    /// while AABBs and capsules require multiple tasks depending on their size.
+    pub fn task_count(&self) -> usize {
+        self.rays.len()
+            + self.multi_rays.iter().map(|ray| ray.max_hits as usize).sum::<usize>()
+            + self.aabbs.iter().map(Aabb::picker_task_count).sum::<usize>()
+            + self.capsules.iter().map(Capsule::picker_task_count).sum::<usize>()
+            + self.nearest_surfaces.iter().map(|query| query.fan.ray_count()).sum::<usize>()
+    }
+
     /// `serialize_tasks` transforms all tasks on this batch into actual `PickerTasks` and writes them
-    /// to the given task buffer.
-    pub(super) fn serialize_tasks(&self, tasks: &mut [PickerTask]) -> usize {
+    /// to the given task buffer. Returns `None` without writing anything, if the buffer is too small
+    /// to hold [`PickerBatch::task_count`] tasks.
+    pub(super) fn serialize_tasks(&self, tasks: &mut [PickerTask]) -> Option<usize> {
+        if self.task_count() > tasks.len() {
+            return None;
+        }
+
         let mut offset = 0;
+        self.for_each_task(&mut |task| {
+            tasks[offset] = task;
+            offset += 1;
+        });
+        Some(offset)
+    }
 
+    /// Like [`PickerBatch::serialize_tasks`], but collects all tasks into a new vector.
+    pub(super) fn to_tasks(&self) -> Vec<PickerTask> {
+        let mut tasks = Vec::with_capacity(self.task_count());
+        self.for_each_task(&mut |task| tasks.push(task));
+        tasks
+    }
+
+    fn for_each_task<F: FnMut(PickerTask)>(&self, f: &mut F) {
         for task in &self.rays {
-            tasks[offset] = PickerTask {
+            f(PickerTask {
                 max_dst: task.max_dst,
+                max_hits: 1,
                 pos: AlignedPoint3(task.pos),
                 dir: AlignedVec3(task.dir),
-            };
-            offset += 1;
+            });
+        }
+
+        // The first task of a multi-hit ray does the actual work, the following ones only reserve
+        // space in the result buffer.
+        for ray in &self.multi_rays {
+            for i in 0..ray.max_hits {
+                f(PickerTask {
+                    max_dst: ray.max_dst,
+                    max_hits: if i == 0 { ray.max_hits } else { 0 },
+                    pos: AlignedPoint3(ray.pos),
+                    dir: AlignedVec3(ray.dir),
+                });
+            }
         }
 
         for aabb in &self.aabbs {
-            offset += aabb.generate_picker_tasks(&mut tasks[offset..]);
+            aabb.for_each_picker_task(f);
         }
 
-        offset
+        for capsule in &self.capsules {
+            capsule.for_each_picker_task(f);
+        }
+
+        for query in &self.nearest_surfaces {
+            query.for_each_picker_task(f);
+        }
     }
 
     /// `deserialize_results` reads all results from the given result buffer and parses the results
-    /// for all jobs on this batch.
+    /// for all jobs on this batch. Results are appended to `dst`, so it has to be reset by the
+    /// caller beforehand.
     pub(super) fn deserialize_results(&self, results: &[PickerResult], dst: &mut PickerBatchResult) {
         let mut offset = 0;
 
         for _ in &self.rays {
-            let result = results[offset];
+            dst.rays.push(RayResult::from(&results[offset]));
             offset += 1;
+        }
 
-            dst.rays.push(RayResult {
-                dst: result.dst,
-                inside_voxel: result.inside_voxel,
-                pos: result.pos.0,
-                normal: result.normal.0,
-            });
+        for ray in &self.multi_rays {
+            let count = ray.max_hits as usize;
+            let hits = results[offset..offset + count].iter()
+                .map(RayResult::from)
+                .take_while(RayResult::did_hit)
+                .collect();
+            dst.multi_rays.push(hits);
+            offset += count;
         }
 
         for aabb in &self.aabbs {
@@ -101,13 +197,81 @@ impl PickerBatch {
             dst.aabbs.push(result);
             offset += consumed;
         }
+
+        for capsule in &self.capsules {
+            let (result, consumed) = capsule.parse_picker_results(&results[offset..]);
+            dst.capsules.push(result);
+            offset += consumed;
+        }
+
+        for query in &self.nearest_surfaces {
+            let (result, consumed) = query.parse_picker_results(&results[offset..]);
+            dst.nearest_surfaces.push(result);
+            offset += consumed;
+        }
+    }
+
+    /// Like [`PickerBatch::deserialize_results`], but writes into caller-owned slices instead of
+    /// growing vectors. Results are stored in the order the jobs were added to the batch. Every
+    /// multi-hit ray occupies `max_hits` consecutive entries in `multi_rays`, where the hits are
+    /// followed by misses.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the slices is too small to hold the results of its job type.
+    pub(super) fn deserialize_results_into_slices(
+        &self,
+        results: &[PickerResult],
+        rays: &mut [RayResult],
+        multi_rays: &mut [RayResult],
+        aabbs: &mut [AabbResult],
+        capsules: &mut [CapsuleResult],
+        nearest_surfaces: &mut [NearestSurfaceResult],
+    ) {
+        let mut offset = 0;
+
+        for ray in &mut rays[..self.rays.len()] {
+            *ray = RayResult::from(&results[offset]);
+            offset += 1;
+        }
+
+        let multi_ray_count = self.multi_rays.iter().map(|ray| ray.max_hits as usize).sum::<usize>();
+        for ray in &mut multi_rays[..multi_ray_count] {
+            *ray = RayResult::from(&results[offset]);
+            offset += 1;
+        }
+
+        for (aabb, dst) in self.aabbs.iter().zip(&mut aabbs[..self.aabbs.len()]) {
+            let (result, consumed) = aabb.parse_picker_results(&results[offset..]);
+            *dst = result;
+            offset += consumed;
+        }
+
+        for (capsule, dst) in self.capsules.iter().zip(&mut capsules[..self.capsules.len()]) {
+            let (result, consumed) = capsule.parse_picker_results(&results[offset..]);
+            *dst = result;
+            offset += consumed;
+        }
+
+        for (query, dst) in self.nearest_surfaces.iter().zip(&mut nearest_surfaces[..self.nearest_surfaces.len()]) {
+            let (result, consumed) = query.parse_picker_results(&results[offset..]);
+            *dst = result;
+            offset += consumed;
+        }
     }
 }
 
+/// `PickerBatchResult` holds the results of a [`PickerBatch`]. Like the batch, a single instance
+/// should be reused across frames by calling [`PickerBatchResult::reset`] before every raycast, so
+/// that the vectors keep their capacity.
 #[derive(Debug, PartialEq)]
 pub struct PickerBatchResult {
     pub rays: Vec<RayResult>,
+    /// `multi_rays` contains all hits per multi-hit ray, ordered by distance.
+    pub multi_rays: Vec<Vec<RayResult>>,
     pub aabbs: Vec<AabbResult>,
+    pub capsules: Vec<CapsuleResult>,
+    pub nearest_surfaces: Vec<NearestSurfaceResult>,
 }
 
 impl PickerBatchResult {
@@ -118,13 +282,19 @@ impl PickerBatchResult {
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             rays: Vec::with_capacity(capacity),
+            multi_rays: Vec::new(),
             aabbs: Vec::with_capacity(capacity),
+            capsules: Vec::new(),
+            nearest_surfaces: Vec::new(),
         }
     }
 
     pub fn reset(&mut self) {
         self.rays.clear();
+        self.multi_rays.clear();
         self.aabbs.clear();
+        self.capsules.clear();
+        self.nearest_surfaces.clear();
     }
 }
 
@@ -135,6 +305,16 @@ pub struct Ray {
     pub max_dst: f32,
 }
 
+/// `MultiRay` is a ray that records up to `max_hits` intersections along its path instead of only
+/// the first one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MultiRay {
+    pub pos: Point3<f32>,
+    pub dir: Vector3<f32>,
+    pub max_dst: f32,
+    pub max_hits: u32,
+}
+
 /// `RayResult` represent a ray intersection with a voxel. Only if dst != -1.0, are any of the other
 /// fields valid. If a ray is cast from within a voxel, no intersection is returned for the voxel
 /// from within the cast originates.
@@ -142,6 +322,8 @@ pub struct Ray {
 pub struct RayResult {
     pub dst: f32,
     pub inside_voxel: bool,
+    /// value is the leaf value of the hit voxel, i.e. its block id.
+    pub value: u32,
     pub pos: Point3<f32>,
     pub normal: Vector3<f32>,
 }
@@ -153,6 +335,18 @@ impl RayResult {
     }
 }
 
+impl From<&PickerResult> for RayResult {
+    fn from(result: &PickerResult) -> Self {
+        Self {
+            dst: result.dst,
+            inside_voxel: result.inside_voxel,
+            value: result.value,
+            pos: result.pos.0,
+            normal: result.normal.0,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Aabb {
     pub pos: Point3<f32>,
@@ -180,7 +374,13 @@ impl Aabb {
         Self { pos, offset, extents }
     }
 
-    fn generate_picker_tasks(&self, dst: &mut [PickerTask]) -> usize {
+    fn picker_task_count(&self) -> usize {
+        let mut count = 0;
+        self.for_each_picker_task(&mut |_| count += 1);
+        count
+    }
+
+    fn for_each_picker_task<F: FnMut(PickerTask)>(&self, f: &mut F) {
         let blocks_per_axis = [
             self.extents.x.ceil() as i32,
             self.extents.y.ceil() as i32,
@@ -192,7 +392,6 @@ impl Aabb {
             self.extents.z / blocks_per_axis[2] as f32,
         ];
 
-        let mut offset = 0;
         let mut axes = [0; 3];
 
         // go through all block points across the AABB
@@ -228,18 +427,16 @@ impl Aabb {
                             y as f32 * step_size_per_axis[1],
                             z as f32 * step_size_per_axis[2],
                         );
-                        dst[offset] = PickerTask {
+                        f(PickerTask {
                             max_dst: 10.0,
+                            max_hits: 1,
                             pos: AlignedPoint3(self.pos + self.offset + point),
                             dir: AlignedVec3(Vector3::new(dir(0), dir(1), dir(2))),
-                        };
-                        offset += 1;
+                        });
                     }
                 }
             }
         }
-
-        offset
     }
 
     fn parse_picker_results(&self, data: &[PickerResult]) -> (AabbResult, usize) {
@@ -299,12 +496,238 @@ impl Aabb {
     }
 }
 
+/// `Capsule` is an upright capsule with its lowest point at `base`. The cylindrical part spans
+/// from `base.y + radius` to `base.y + height - radius`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Capsule {
+    pub base: Point3<f32>,
+    pub height: f32,
+    pub radius: f32,
+}
+
+/// `CapsuleResult` contains the shortest distance to any hit per axis in negative and positive
+/// direction, measured from the capsule surface. -1 means that nothing was hit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CapsuleResult {
+    pub neg: Vector3<f32>,
+    pub pos: Vector3<f32>,
+}
+
+impl Default for CapsuleResult {
+    fn default() -> Self {
+        Self {
+            neg: Vector3::new(-1.0, -1.0, -1.0),
+            pos: Vector3::new(-1.0, -1.0, -1.0),
+        }
+    }
+}
+
+impl Capsule {
+    pub fn new(base: Point3<f32>, height: f32, radius: f32) -> Self {
+        Self { base, height, radius }
+    }
+
+    fn picker_task_count(&self) -> usize {
+        let mut count = 0;
+        self.for_each_picker_task(&mut |_| count += 1);
+        count
+    }
+
+    /// Generates the tasks in the same fashion as [`Aabb`]: every sample point on the surface casts
+    /// one ray along each axis its surface normal points to. Both hemispheres are sampled at their
+    /// pole and at four points at 45 degrees, the cylinder is sampled with four points per ring and
+    /// one ring per block of height.
+    fn for_each_picker_task<F: FnMut(PickerTask)>(&self, f: &mut F) {
+        let mut cast = |pos: Point3<f32>, dir: Vector3<f32>| f(PickerTask {
+            max_dst: 10.0,
+            max_hits: 1,
+            pos: AlignedPoint3(pos),
+            dir: AlignedVec3(dir),
+        });
+
+        let horizontal = [
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        ];
+
+        let length = self.radius.mul_add(-2.0, self.height).max(0.0);
+        let bottom = self.base + Vector3::new(0.0, self.radius, 0.0);
+        let top = bottom + Vector3::new(0.0, length, 0.0);
+
+        // hemispheres
+        let diagonal = self.radius * std::f32::consts::FRAC_1_SQRT_2;
+        for (center, sign) in [(bottom, -1.0), (top, 1.0)] {
+            let vertical = Vector3::new(0.0, sign, 0.0);
+            cast(center + vertical * self.radius, vertical);
+
+            for dir in horizontal {
+                let point = center + Vector3::new(dir.x * diagonal, sign * diagonal, dir.z * diagonal);
+                cast(point, dir);
+                cast(point, vertical);
+            }
+        }
+
+        // cylinder
+        let steps = length.ceil() as i32;
+        let step_size = if steps > 0 { length / steps as f32 } else { 0.0 };
+        for i in 0..=steps {
+            let center = bottom + Vector3::new(0.0, i as f32 * step_size, 0.0);
+            for dir in horizontal {
+                cast(center + dir * self.radius, dir);
+            }
+        }
+    }
+
+    fn parse_picker_results(&self, data: &[PickerResult]) -> (CapsuleResult, usize) {
+        let mut result = CapsuleResult::default();
+        let mut res_index: usize = 0;
+
+        // Go through the tasks in the same order as they were generated and keep the shortest
+        // distance from every hit per axis in positive and negative direction.
+        self.for_each_picker_task(&mut |task| {
+            let dst = data[res_index].dst;
+            res_index += 1;
+
+            #[allow(clippy::float_cmp)]
+            if dst == -1.0 {
+                return;
+            }
+
+            let dir = task.dir.0;
+            for axis in 0..3 {
+                #[allow(clippy::float_cmp)]
+                if dir[axis] == 0.0 {
+                    continue;
+                }
+
+                let reference = if dir[axis] > 0.0 { &mut result.pos[axis] } else { &mut result.neg[axis] };
+
+                #[allow(clippy::float_cmp)]
+                if *reference == -1.0 {
+                    *reference = dst;
+                } else {
+                    *reference = reference.min(dst);
+                }
+            }
+        });
+        (result, res_index)
+    }
+}
+
+/// `SurfaceFan` defines the directions that are cast for a [`NearestSurface`] query.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurfaceFan {
+    /// 6 directions along the positive and negative axes.
+    Axes,
+    /// 18 directions: the axes plus the diagonals between two axes.
+    AxesAndEdges,
+    /// 26 directions: the axes plus all diagonals between two and three axes.
+    AxesAndDiagonals,
+}
+
+impl SurfaceFan {
+    pub fn ray_count(self) -> usize {
+        match self {
+            Self::Axes => 6,
+            Self::AxesAndEdges => 18,
+            Self::AxesAndDiagonals => 26,
+        }
+    }
+
+    fn max_components(self) -> i32 {
+        match self {
+            Self::Axes => 1,
+            Self::AxesAndEdges => 2,
+            Self::AxesAndDiagonals => 3,
+        }
+    }
+
+    /// Calls `f` with the normalized direction of every ray in this fan.
+    fn for_each_dir<F: FnMut(Vector3<f32>)>(self, f: &mut F) {
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let components = x * x + y * y + z * z;
+                    if components == 0 || components > self.max_components() {
+                        continue;
+                    }
+                    f(Vector3::new(x as f32, y as f32, z as f32).normalize());
+                }
+            }
+        }
+    }
+}
+
+/// `NearestSurface` queries the closest solid surface around `point` by casting a fan of rays.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NearestSurface {
+    pub point: Point3<f32>,
+    pub search_radius: f32,
+    pub fan: SurfaceFan,
+}
+
+/// `NearestSurfaceResult` contains the shortest distance of all rays in the fan, the direction of
+/// that ray and the normal of the surface it hit. Only if dst != -1.0, are the other fields valid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NearestSurfaceResult {
+    pub dst: f32,
+    pub dir: Vector3<f32>,
+    pub normal: Vector3<f32>,
+}
+
+impl Default for NearestSurfaceResult {
+    fn default() -> Self {
+        Self {
+            dst: -1.0,
+            dir: Vector3::new(0.0, 0.0, 0.0),
+            normal: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl NearestSurfaceResult {
+    #[allow(clippy::float_cmp)]
+    pub fn did_hit(&self) -> bool {
+        self.dst != -1.0
+    }
+}
+
+impl NearestSurface {
+    fn for_each_picker_task<F: FnMut(PickerTask)>(&self, f: &mut F) {
+        self.fan.for_each_dir(&mut |dir| f(PickerTask {
+            max_dst: self.search_radius,
+            max_hits: 1,
+            pos: AlignedPoint3(self.point),
+            dir: AlignedVec3(dir),
+        }));
+    }
+
+    fn parse_picker_results(&self, data: &[PickerResult]) -> (NearestSurfaceResult, usize) {
+        let mut result = NearestSurfaceResult::default();
+        let mut res_index: usize = 0;
+
+        self.fan.for_each_dir(&mut |dir| {
+            let hit = &data[res_index];
+            res_index += 1;
+
+            #[allow(clippy::float_cmp)]
+            if hit.dst == -1.0 || (result.did_hit() && result.dst <= hit.dst) {
+                return;
+            }
+            result = NearestSurfaceResult { dst: hit.dst, dir, normal: hit.normal.0 };
+        });
+        (result, res_index)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use cgmath::{Point3, Vector3};
+    use cgmath::{InnerSpace, Point3, Vector3};
 
     use crate::graphics::macros::{AlignedPoint3, AlignedVec3};
-    use crate::graphics::svo_picker::{Aabb, AabbResult, PickerBatch, PickerBatchResult, PickerResult, PickerTask, RayResult};
+    use crate::graphics::svo_picker::{Aabb, AabbResult, CapsuleResult, NearestSurfaceResult, PickerBatch, PickerBatchResult, PickerResult, PickerTask, RayResult, SurfaceFan};
 
     /// Tests if task serialization works as expected.
     #[test]
@@ -323,98 +746,171 @@ mod tests {
             extents: Vector3::new(1.5, 1.5, 1.5),
         });
 
-        let default_task = PickerTask { max_dst: 0.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 0.0)) };
+        let default_task = PickerTask { max_dst: 0.0, max_hits: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 0.0)) };
         let mut buffer = vec![default_task; 100];
-        let tasks = batch.serialize_tasks(&mut buffer);
+        let tasks = batch.serialize_tasks(&mut buffer).unwrap();
 
         // [2 rays] + [1 unit size aabb * ( 3 rays per corner * 8 corners )] + [1 irregular aabb * ( 3 rays per corner * 8 corners + 2 rays per half side * 4 halves per axis * 3 axis + 1 ray per face * 6 face )]
         // [ 2 ] + [ 24 ] + [ 54 ] = 80
         assert_eq!(tasks, 80);
         assert_eq!(buffer[..tasks], vec![
             // rays
-            PickerTask { max_dst: 20.0, pos: AlignedPoint3(Point3::new(1.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 40.0, pos: AlignedPoint3(Point3::new(2.0, 0.0, 2.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 20.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 40.0, max_hits: 1, pos: AlignedPoint3(Point3::new(2.0, 0.0, 2.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
             // aabb 1
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.0, 0.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.0, 1.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.0, 1.0, 0.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.0, 1.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.0, 1.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.0, 1.0, 1.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.0, 1.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.0, 1.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.0, 0.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.0, 1.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 1.0, 0.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 1.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 1.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 1.0, 1.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 1.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 1.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
             // aabb 2
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.75)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.75)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.75, 0.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.75, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.75, 0.75)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.75, 1.5)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 0.75, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.75)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.75)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.75, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.75, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.75, 0.0, 0.75)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.75, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.75, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.75, 0.75, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.75, 0.75, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.75, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.75, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.75, 1.5, 0.75)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.75, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(0.75, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.75)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.75)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.75, 0.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.75, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.75, 0.75)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.75, 1.5)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 0.75, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.75)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.75)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerTask { max_dst: 10.0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.75)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.75)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.75, 0.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.75, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.75, 0.75)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.75, 1.5)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 0.75, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.75)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.75)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.0, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.75, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.75, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.75, 0.0, 0.75)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.75, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.75, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.75, 0.75, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.75, 0.75, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.75, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.75, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.75, 1.5, 0.75)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.75, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(0.75, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.75)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.75)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.0, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.75, 0.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.75, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.75, 0.75)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.75, 1.5)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 0.75, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.75)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.75)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.5, 1.5, 1.5)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+        ]);
+    }
+
+    /// Tests that serializing a batch into a buffer that is too small is rejected without writing
+    /// anything.
+    #[test]
+    fn picker_batch_serialization_exceeding_buffer() {
+        let mut batch = PickerBatch::with_capacity(1);
+        for _ in 0..3 {
+            batch.add_aabb(Aabb {
+                pos: Point3::new(0.0, 0.0, 0.0),
+                offset: Vector3::new(0.0, 0.0, 0.0),
+                extents: Vector3::new(1.5, 1.5, 1.5),
+            });
+        }
+        assert_eq!(batch.task_count(), 3 * 54);
+
+        let default_task = PickerTask { max_dst: 0.0, max_hits: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 0.0)) };
+        let mut buffer = vec![default_task; 100];
+        assert_eq!(batch.serialize_tasks(&mut buffer), None);
+        assert!(buffer.iter().all(|task| *task == default_task));
+
+        let tasks = batch.to_tasks();
+        assert_eq!(tasks.len(), 3 * 54);
+
+        let mut buffer = vec![default_task; 3 * 54];
+        assert_eq!(batch.serialize_tasks(&mut buffer), Some(3 * 54));
+        assert_eq!(buffer, tasks);
+    }
+
+    /// Tests that multi-hit rays reserve one task per hit and that their results are grouped per
+    /// ray and truncated after the first miss.
+    #[test]
+    fn picker_batch_multi_ray() {
+        let mut batch = PickerBatch::new();
+        batch.add_ray(Point3::new(1.0, 0.0, 1.0), Vector3::new(0.0, 1.0, 0.0), 20.0);
+        batch.add_ray_multi(Point3::new(2.0, 0.0, 2.0), Vector3::new(1.0, 0.0, 0.0), 40.0, 3);
+        batch.add_ray_multi(Point3::new(3.0, 0.0, 3.0), Vector3::new(0.0, 0.0, 1.0), 10.0, 0);
+        assert_eq!(batch.task_count(), 5);
+
+        assert_eq!(batch.to_tasks(), vec![
+            PickerTask { max_dst: 20.0, max_hits: 1, pos: AlignedPoint3(Point3::new(1.0, 0.0, 1.0)), dir: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerTask { max_dst: 40.0, max_hits: 3, pos: AlignedPoint3(Point3::new(2.0, 0.0, 2.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 40.0, max_hits: 0, pos: AlignedPoint3(Point3::new(2.0, 0.0, 2.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 40.0, max_hits: 0, pos: AlignedPoint3(Point3::new(2.0, 0.0, 2.0)), dir: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerTask { max_dst: 10.0, max_hits: 1, pos: AlignedPoint3(Point3::new(3.0, 0.0, 3.0)), dir: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
         ]);
+
+        let buffer = vec![
+            PickerResult { dst: 4.0, inside_voxel: false, value: 1, pos: AlignedPoint3(Point3::new(1.0, 4.0, 1.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: 1.0, inside_voxel: false, value: 2, pos: AlignedPoint3(Point3::new(3.0, 0.0, 2.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: 3.0, inside_voxel: false, value: 5, pos: AlignedPoint3(Point3::new(5.0, 0.0, 2.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 0.0)) },
+        ];
+
+        let mut result = PickerBatchResult::new();
+        batch.deserialize_results(&buffer, &mut result);
+
+        assert_eq!(result, PickerBatchResult {
+            rays: vec![
+                RayResult { dst: 4.0, inside_voxel: false, value: 1, pos: Point3::new(1.0, 4.0, 1.0), normal: Vector3::new(0.0, -1.0, 0.0) },
+            ],
+            multi_rays: vec![
+                vec![
+                    RayResult { dst: 1.0, inside_voxel: false, value: 2, pos: Point3::new(3.0, 0.0, 2.0), normal: Vector3::new(-1.0, 0.0, 0.0) },
+                    RayResult { dst: 3.0, inside_voxel: false, value: 5, pos: Point3::new(5.0, 0.0, 2.0), normal: Vector3::new(-1.0, 0.0, 0.0) },
+                ],
+                vec![],
+            ],
+            aabbs: vec![],
+            capsules: vec![],
+            nearest_surfaces: vec![],
+        });
     }
 
     /// Tests if task deserialization works as expected.
@@ -436,88 +932,88 @@ mod tests {
 
         let buffer = vec![
             // rays
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 0.0)) },
-            PickerResult { dst: 10.0, inside_voxel: true, pos: AlignedPoint3(Point3::new(-1.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(10.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 0.0)) },
+            PickerResult { dst: 10.0, inside_voxel: true, value: 3, pos: AlignedPoint3(Point3::new(-1.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(10.0, 0.0, 0.0)) },
             // aabb 1
-            PickerResult { dst: 8.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: 8.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: 8.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: 4.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: 4.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerResult { dst: 4.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: 7.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: 2.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: 1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: 8.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: 8.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: 8.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: 4.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: 4.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: 4.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: 7.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: 2.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: 1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
             // aabb 2
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: 9.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.75)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: 8.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.75)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.75, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.75, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.75, 0.75)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.75, 1.5)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: 5.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 0.75, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.75)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.75)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.0, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.75, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.75, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.75, 0.0, 0.75)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.75, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.75, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerResult { dst: 7.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.75, 0.75, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.75, 0.75, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.75, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.75, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.75, 1.5, 0.75)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.75, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(0.75, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerResult { dst: 5.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.75)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.75)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: 5.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
-            PickerResult { dst: 3.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.75, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.75, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.75, 0.75)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.75, 1.5)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 0.75, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
-            PickerResult { dst: 1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.75)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: 4.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.75)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
-            PickerResult { dst: -1.0, inside_voxel: false, pos: AlignedPoint3(Point3::new(1.5, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: 9.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.75)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: 8.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.75)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.75, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.75, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.75, 0.75)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.75, 1.5)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: 5.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 0.75, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.75)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 0.75)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(-1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.0, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.75, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.75, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.75, 0.0, 0.75)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.75, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.75, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: 7.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.75, 0.75, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.75, 0.75, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.75, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.75, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.75, 1.5, 0.75)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.75, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(0.75, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: 5.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.75)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 0.75)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: 5.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(0.0, -1.0, 0.0)) },
+            PickerResult { dst: 3.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.0, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.75, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.75, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.75, 0.75)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.75, 1.5)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 0.75, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.0)), normal: AlignedVec3(Vector3::new(0.0, 0.0, -1.0)) },
+            PickerResult { dst: 1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.75)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: 4.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 0.75)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 1.0, 0.0)) },
+            PickerResult { dst: -1.0, inside_voxel: false, value: 0, pos: AlignedPoint3(Point3::new(1.5, 1.5, 1.5)), normal: AlignedVec3(Vector3::new(0.0, 0.0, 1.0)) },
         ];
 
         let mut result = PickerBatchResult::new();
@@ -525,13 +1021,207 @@ mod tests {
 
         assert_eq!(result, PickerBatchResult {
             rays: vec![
-                RayResult { dst: -1.0, inside_voxel: false, pos: Point3::new(0.0, 0.0, 0.0), normal: Vector3::new(0.0, 0.0, 0.0) },
-                RayResult { dst: 10.0, inside_voxel: true, pos: Point3::new(-1.0, 0.0, 0.0), normal: Vector3::new(10.0, 0.0, 0.0) },
+                RayResult { dst: -1.0, inside_voxel: false, value: 0, pos: Point3::new(0.0, 0.0, 0.0), normal: Vector3::new(0.0, 0.0, 0.0) },
+                RayResult { dst: 10.0, inside_voxel: true, value: 3, pos: Point3::new(-1.0, 0.0, 0.0), normal: Vector3::new(10.0, 0.0, 0.0) },
             ],
+            multi_rays: vec![],
             aabbs: vec![
                 AabbResult { neg: Vector3::new(8.0, 7.0, 8.0), pos: Vector3::new(2.0, 4.0, 1.0) },
                 AabbResult { neg: Vector3::new(9.0, 8.0, 7.0), pos: Vector3::new(1.0, 4.0, 3.0) },
             ],
+            capsules: vec![],
+            nearest_surfaces: vec![],
         });
     }
+
+    /// Tests if capsule task generation samples both hemispheres and every ring of the cylinder.
+    #[test]
+    fn picker_batch_capsule_serialization() {
+        let mut batch = PickerBatch::new();
+        batch.add_capsule(Point3::new(0.0, 0.0, 0.0), 2.0, 0.5);
+
+        let d = 0.5 * std::f32::consts::FRAC_1_SQRT_2;
+        let task = |x: f32, y: f32, z: f32, dx: f32, dy: f32, dz: f32| PickerTask {
+            max_dst: 10.0,
+            max_hits: 1,
+            pos: AlignedPoint3(Point3::new(x, y, z)),
+            dir: AlignedVec3(Vector3::new(dx, dy, dz)),
+        };
+
+        // [2 hemispheres * (1 pole + 4 diagonal points * 2 rays)] + [2 rings * 4 rays]
+        // [ 18 ] + [ 8 ] = 26
+        assert_eq!(batch.task_count(), 26);
+        assert_eq!(batch.to_tasks(), vec![
+            // bottom hemisphere
+            task(0.0, 0.0, 0.0, 0.0, -1.0, 0.0),
+            task(-d, 0.5 - d, 0.0, -1.0, 0.0, 0.0),
+            task(-d, 0.5 - d, 0.0, 0.0, -1.0, 0.0),
+            task(d, 0.5 - d, 0.0, 1.0, 0.0, 0.0),
+            task(d, 0.5 - d, 0.0, 0.0, -1.0, 0.0),
+            task(0.0, 0.5 - d, -d, 0.0, 0.0, -1.0),
+            task(0.0, 0.5 - d, -d, 0.0, -1.0, 0.0),
+            task(0.0, 0.5 - d, d, 0.0, 0.0, 1.0),
+            task(0.0, 0.5 - d, d, 0.0, -1.0, 0.0),
+            // top hemisphere
+            task(0.0, 2.0, 0.0, 0.0, 1.0, 0.0),
+            task(-d, 1.5 + d, 0.0, -1.0, 0.0, 0.0),
+            task(-d, 1.5 + d, 0.0, 0.0, 1.0, 0.0),
+            task(d, 1.5 + d, 0.0, 1.0, 0.0, 0.0),
+            task(d, 1.5 + d, 0.0, 0.0, 1.0, 0.0),
+            task(0.0, 1.5 + d, -d, 0.0, 0.0, -1.0),
+            task(0.0, 1.5 + d, -d, 0.0, 1.0, 0.0),
+            task(0.0, 1.5 + d, d, 0.0, 0.0, 1.0),
+            task(0.0, 1.5 + d, d, 0.0, 1.0, 0.0),
+            // cylinder
+            task(-0.5, 0.5, 0.0, -1.0, 0.0, 0.0),
+            task(0.5, 0.5, 0.0, 1.0, 0.0, 0.0),
+            task(0.0, 0.5, -0.5, 0.0, 0.0, -1.0),
+            task(0.0, 0.5, 0.5, 0.0, 0.0, 1.0),
+            task(-0.5, 1.5, 0.0, -1.0, 0.0, 0.0),
+            task(0.5, 1.5, 0.0, 1.0, 0.0, 0.0),
+            task(0.0, 1.5, -0.5, 0.0, 0.0, -1.0),
+            task(0.0, 1.5, 0.5, 0.0, 0.0, 1.0),
+        ]);
+
+        // a capsule without cylinder still samples its center ring once
+        let mut batch = PickerBatch::new();
+        batch.add_capsule(Point3::new(0.0, 0.0, 0.0), 0.5, 0.5);
+        assert_eq!(batch.task_count(), 18 + 4);
+    }
+
+    /// Tests if capsule results are reduced to the shortest distance per axis and direction.
+    #[test]
+    fn picker_batch_capsule_deserialization() {
+        let mut batch = PickerBatch::new();
+        batch.add_capsule(Point3::new(0.0, 0.0, 0.0), 2.0, 0.5);
+
+        let dsts = [
+            // bottom hemisphere
+            2.0, -1.0, 3.0, -1.0, 1.5, -1.0, -1.0, -1.0, -1.0,
+            // top hemisphere
+            -1.0, -1.0, -1.0, 4.0, 0.5, -1.0, -1.0, -1.0, -1.0,
+            // cylinder
+            -1.0, 5.0, 0.25, -1.0, 0.75, 3.0, 1.0, -1.0,
+        ];
+        let buffer = dsts.iter().map(|&dst| PickerResult {
+            dst,
+            inside_voxel: false,
+            value: 0,
+            pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)),
+            normal: AlignedVec3(Vector3::new(0.0, 0.0, 0.0)),
+        }).collect::<Vec<_>>();
+
+        let mut result = PickerBatchResult::new();
+        batch.deserialize_results(&buffer, &mut result);
+
+        assert_eq!(result.capsules, vec![
+            CapsuleResult { neg: Vector3::new(0.75, 1.5, 0.25), pos: Vector3::new(3.0, 0.5, -1.0) },
+        ]);
+    }
+
+    /// Tests that reusing a batch and result across multiple deserializations yields the same
+    /// results as fresh allocations, and that the slice variant writes the same results.
+    #[test]
+    fn picker_batch_result_reuse() {
+        let fill_batch = |batch: &mut PickerBatch, offset: f32| {
+            batch.add_ray(Point3::new(offset, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), 10.0);
+            batch.add_ray_multi(Point3::new(offset, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), 10.0, 2);
+            batch.add_aabb(Aabb::new(Point3::new(offset, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0)));
+            batch.add_capsule(Point3::new(offset, 0.0, 0.0), 2.0, 0.5);
+            batch.add_nearest_surface(Point3::new(offset, 0.0, 0.0), 2.0);
+        };
+        let results_for = |count: usize, offset: f32| (0..count).map(|i| PickerResult {
+            dst: if i % 3 == 0 { -1.0 } else { offset + i as f32 },
+            inside_voxel: false,
+            value: i as u32,
+            pos: AlignedPoint3(Point3::new(offset, i as f32, 0.0)),
+            normal: AlignedVec3(Vector3::new(1.0, 0.0, 0.0)),
+        }).collect::<Vec<_>>();
+
+        let mut batch = PickerBatch::new();
+        let mut result = PickerBatchResult::new();
+
+        for offset in [1.0, 2.0, 3.0] {
+            batch.reset();
+            result.reset();
+            fill_batch(&mut batch, offset);
+            let buffer = results_for(batch.task_count(), offset);
+            batch.deserialize_results(&buffer, &mut result);
+
+            let mut fresh_batch = PickerBatch::new();
+            fill_batch(&mut fresh_batch, offset);
+            let mut fresh_result = PickerBatchResult::new();
+            fresh_batch.deserialize_results(&buffer, &mut fresh_result);
+
+            assert_eq!(batch, fresh_batch);
+            assert_eq!(result, fresh_result);
+
+            let miss = RayResult { dst: -1.0, inside_voxel: false, value: 0, pos: Point3::new(0.0, 0.0, 0.0), normal: Vector3::new(0.0, 0.0, 0.0) };
+            let mut rays = [miss; 4];
+            let mut multi_rays = [miss; 4];
+            let mut aabbs = [AabbResult::default(); 4];
+            let mut capsules = [CapsuleResult::default(); 4];
+            let mut nearest_surfaces = [NearestSurfaceResult::default(); 4];
+            batch.deserialize_results_into_slices(&buffer, &mut rays, &mut multi_rays, &mut aabbs, &mut capsules, &mut nearest_surfaces);
+
+            assert_eq!(rays[..1], result.rays[..]);
+            assert_eq!(multi_rays[..result.multi_rays[0].len()], result.multi_rays[0][..]);
+            assert!(multi_rays[result.multi_rays[0].len()..2].iter().all(|ray| !ray.did_hit()));
+            assert_eq!(aabbs[..1], result.aabbs[..]);
+            assert_eq!(capsules[..1], result.capsules[..]);
+            assert_eq!(nearest_surfaces[..1], result.nearest_surfaces[..]);
+            assert_eq!(rays[1], miss);
+        }
+    }
+
+    /// Tests that nearest surface queries cast one ray per fan direction and keep the closest hit.
+    #[test]
+    fn picker_batch_nearest_surface() {
+        let mut batch = PickerBatch::new();
+        batch.add_nearest_surface_with_fan(Point3::new(1.0, 2.0, 3.0), 4.0, SurfaceFan::Axes);
+        batch.add_nearest_surface(Point3::new(0.0, 0.0, 0.0), 1.0);
+        assert_eq!(batch.task_count(), 6 + 26);
+
+        let tasks = batch.to_tasks();
+        let task = |dx: f32, dy: f32, dz: f32| PickerTask {
+            max_dst: 4.0,
+            max_hits: 1,
+            pos: AlignedPoint3(Point3::new(1.0, 2.0, 3.0)),
+            dir: AlignedVec3(Vector3::new(dx, dy, dz)),
+        };
+        assert_eq!(tasks[..6], vec![
+            task(-1.0, 0.0, 0.0),
+            task(0.0, -1.0, 0.0),
+            task(0.0, 0.0, -1.0),
+            task(0.0, 0.0, 1.0),
+            task(0.0, 1.0, 0.0),
+            task(1.0, 0.0, 0.0),
+        ]);
+        assert!(tasks[6..].iter().all(|task| (task.max_dst - 1.0).abs() < 0.0001 && (task.dir.0.magnitude() - 1.0).abs() < 0.0001));
+
+        let hit = |dst: f32, normal: Vector3<f32>| PickerResult {
+            dst,
+            inside_voxel: false,
+            value: 1,
+            pos: AlignedPoint3(Point3::new(0.0, 0.0, 0.0)),
+            normal: AlignedVec3(normal),
+        };
+        let mut buffer = vec![
+            hit(-1.0, Vector3::new(0.0, 0.0, 0.0)),
+            hit(3.0, Vector3::new(0.0, 1.0, 0.0)),
+            hit(-1.0, Vector3::new(0.0, 0.0, 0.0)),
+            hit(1.5, Vector3::new(0.0, 0.0, -1.0)),
+            hit(1.5, Vector3::new(0.0, -1.0, 0.0)),
+            hit(2.0, Vector3::new(-1.0, 0.0, 0.0)),
+        ];
+        buffer.extend((0..26).map(|_| hit(-1.0, Vector3::new(0.0, 0.0, 0.0))));
+
+        let mut result = PickerBatchResult::new();
+        batch.deserialize_results(&buffer, &mut result);
+
+        assert_eq!(result.nearest_surfaces, vec![
+            NearestSurfaceResult { dst: 1.5, dir: Vector3::new(0.0, 0.0, 1.0), normal: Vector3::new(0.0, 0.0, -1.0) },
+            NearestSurfaceResult::default(),
+        ]);
+    }
 }
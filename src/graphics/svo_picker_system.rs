@@ -0,0 +1,133 @@
+use crate::graphics::buffer::MappedBuffer;
+use crate::graphics::fence::Fence;
+use crate::graphics::shader::ShaderProgram;
+use crate::graphics::svo::buffer_indices;
+use crate::graphics::svo_picker::{MAX_SVO_PICKER_JOBS, PickerBatch, PickerBatchResult, PickerResult, PickerTask};
+
+/// `PickerSystem` runs SVO raycasts asynchronously. It keeps `latency + 1` sets of picker buffers and fences, and
+/// cycles through them with every dispatched batch. Results are read back once the GPU has finished, so that the CPU
+/// does not have to stall on every raycast like [`crate::graphics::Svo::raycast`] does.
+///
+/// The system expects to be used once per frame: first call [`PickerSystem::poll`] to retrieve finished results,
+/// then [`PickerSystem::dispatch`] to queue the next batch. Results are guaranteed to be available after `latency`
+/// frames at the latest, in which case the system blocks until the GPU is done.
+pub struct PickerSystem {
+    slots: Vec<PickerSlot>,
+    latency: usize,
+    frame: u64,
+}
+
+struct PickerSlot {
+    in_buffer: MappedBuffer<PickerTask>,
+    out_buffer: MappedBuffer<PickerResult>,
+    fence: Fence,
+    pending: Option<PendingBatch>,
+}
+
+struct PendingBatch {
+    batch: PickerBatch,
+    task_count: usize,
+    frame: u64,
+}
+
+/// `CompletedBatch` contains a dispatched batch together with its results.
+pub struct CompletedBatch {
+    pub batch: PickerBatch,
+    pub result: PickerBatchResult,
+    /// `latency` is the actual number of frames between dispatching the batch and reading its results.
+    pub latency: u64,
+}
+
+impl PickerSystem {
+    pub fn new(latency: usize) -> Self {
+        let mut system = Self { slots: Vec::new(), latency: 0, frame: 0 };
+        system.set_latency(latency);
+        system
+    }
+
+    pub fn get_latency(&self) -> usize {
+        self.latency
+    }
+
+    /// Changes the maximum number of frames until results are read back. All batches that are still in flight are
+    /// discarded. A latency of 0 is treated as 1.
+    pub fn set_latency(&mut self, latency: usize) {
+        let latency = latency.max(1);
+        for slot in &self.slots {
+            slot.fence.wait();
+        }
+
+        self.latency = latency;
+        self.slots = (0..=latency).map(|_| PickerSlot {
+            in_buffer: MappedBuffer::new(MAX_SVO_PICKER_JOBS),
+            out_buffer: MappedBuffer::new(MAX_SVO_PICKER_JOBS),
+            fence: Fence::new(),
+            pending: None,
+        }).collect();
+    }
+
+    /// Uploads the batch into the next free buffer set and dispatches the picker `shader` without waiting for the
+    /// results. Every call counts as one frame. If the results of the buffer set have not been polled yet, they are
+    /// discarded.
+    pub fn dispatch(&mut self, shader: &ShaderProgram, batch: PickerBatch) {
+        let index = (self.frame % self.slots.len() as u64) as usize;
+        let slot = &mut self.slots[index];
+
+        // make sure that the GPU is no longer using the buffers before overriding them
+        slot.fence.wait();
+
+        // grow the buffers, if the batch does not fit
+        let required = batch.task_count();
+        if required > slot.in_buffer.len() {
+            slot.in_buffer = MappedBuffer::new(required);
+            slot.out_buffer = MappedBuffer::new(required);
+        }
+
+        let task_count = batch.serialize_tasks(slot.in_buffer.as_slice_mut()).unwrap();
+        if task_count > 0 {
+            shader.bind();
+            slot.in_buffer.bind_as_storage_buffer(buffer_indices::PICKER_IN);
+            slot.out_buffer.bind_as_storage_buffer(buffer_indices::PICKER_OUT);
+            unsafe {
+                gl::DispatchCompute(task_count as u32, 1, 1);
+            }
+            shader.unbind();
+
+            slot.fence.place();
+        }
+
+        slot.pending = Some(PendingBatch { batch, task_count, frame: self.frame });
+        self.frame += 1;
+    }
+
+    /// Returns the most recently dispatched batch that has finished on the GPU. Batches that are `latency` frames
+    /// old are waited on. Older, finished batches are dropped in favour of more recent ones.
+    pub fn poll(&mut self) -> Option<CompletedBatch> {
+        let mut newest: Option<(u64, CompletedBatch)> = None;
+
+        for slot in &mut self.slots {
+            let Some(pending) = &slot.pending else {
+                continue;
+            };
+
+            let age = self.frame - pending.frame;
+            if age < self.latency as u64 && !slot.fence.is_signaled() {
+                continue;
+            }
+            slot.fence.wait();
+
+            let pending = slot.pending.take().unwrap();
+            if newest.as_ref().is_some_and(|(frame, _)| *frame > pending.frame) {
+                continue;
+            }
+
+            let mut result = PickerBatchResult::new();
+            let out_data = slot.out_buffer.as_slice();
+            pending.batch.deserialize_results(&out_data[..pending.task_count], &mut result);
+
+            newest = Some((pending.frame, CompletedBatch { batch: pending.batch, result, latency: age }));
+        }
+
+        newest.map(|(_, completed)| completed)
+    }
+}
@@ -1,3 +1,5 @@
+use cgmath::Vector3;
+
 use crate::graphics::buffer;
 use crate::graphics::buffer::Buffer;
 use crate::graphics::resource::Resource;
@@ -24,9 +26,17 @@ pub struct Material {
     tex_top_normal: Option<String>,
     tex_side_normal: Option<String>,
     tex_bottom_normal: Option<String>,
+    emissive_color: Vector3<f32>,
+    emissive_strength: f32,
 }
 
-#[repr(C)]
+/// `MaterialInstance` is the GPU representation of a [`Material`] and is uploaded as an array to
+/// [`crate::graphics::svo::buffer_indices::MATERIALS`]. Its layout must match the `Material` struct
+/// in `svo.glsl`: eight 4 byte scalars followed by the emissive color (vec3) and strength, which
+/// is packed into the vec3's padding. The struct is aligned to 16 bytes like the vec3, so that the
+/// array stride is 48 bytes as required by std430. Texture fields are indices into the texture
+/// array, or -1 if not set.
+#[repr(C, align(16))]
 #[derive(Clone, Default)]
 pub(super) struct MaterialInstance {
     pub specular_pow: f32,
@@ -37,6 +47,8 @@ pub(super) struct MaterialInstance {
     pub tex_top_normal: i32,
     pub tex_side_normal: i32,
     pub tex_bottom_normal: i32,
+    pub emissive_color: [f32; 3],
+    pub emissive_strength: f32,
 }
 
 impl Material {
@@ -50,6 +62,8 @@ impl Material {
             tex_top_normal: None,
             tex_side_normal: None,
             tex_bottom_normal: None,
+            emissive_color: Vector3::new(1.0, 1.0, 1.0),
+            emissive_strength: 0.0,
         }
     }
 
@@ -60,6 +74,14 @@ impl Material {
         self
     }
 
+    /// `emissive` makes the material glow in its texture color tinted by `color`. A `strength` of 1
+    /// ignores all lighting, values in between blend between the lit and the emissive color.
+    pub fn emissive(mut self, color: Vector3<f32>, strength: f32) -> Self {
+        self.emissive_color = color;
+        self.emissive_strength = strength;
+        self
+    }
+
     /// `all_sides` applies the same texture to all sides of the material.
     pub fn all_sides(self, name: &'static str) -> Self {
         self.top(name).side(name).bottom(name)
@@ -121,7 +143,7 @@ impl VoxelRegistry {
 
     pub(super) fn build_texture_array(&self) -> Result<Resource<TextureArray, TextureArrayError>, TextureArrayError> {
         let textures = self.textures.clone();
-        Resource::new(
+        Resource::try_new(
             move || {
                 let mut builder = TextureArrayBuilder::new(6, 4.0);
                 for tex in &textures {
@@ -140,6 +162,11 @@ impl VoxelRegistry {
             )
         }
 
+        // missing normal textures fall back to the face normal instead of the first texture
+        fn lookup_normal(array: &TextureArray, name: Option<&String>) -> i32 {
+            name.and_then(|name| array.lookup(name)).map_or(-1, |index| index as i32)
+        }
+
         let max_block_id = self.materials.iter()
             .max_by(|lhs, rhs| lhs.block.cmp(&rhs.block))
             .unwrap()
@@ -155,9 +182,11 @@ impl VoxelRegistry {
                 tex_top: lookup(tex_array, mat.tex_top.as_ref()),
                 tex_side: lookup(tex_array, mat.tex_side.as_ref()),
                 tex_bottom: lookup(tex_array, mat.tex_bottom.as_ref()),
-                tex_top_normal: lookup(tex_array, mat.tex_top_normal.as_ref()),
-                tex_side_normal: lookup(tex_array, mat.tex_side_normal.as_ref()),
-                tex_bottom_normal: lookup(tex_array, mat.tex_bottom_normal.as_ref()),
+                tex_top_normal: lookup_normal(tex_array, mat.tex_top_normal.as_ref()),
+                tex_side_normal: lookup_normal(tex_array, mat.tex_side_normal.as_ref()),
+                tex_bottom_normal: lookup_normal(tex_array, mat.tex_bottom_normal.as_ref()),
+                emissive_color: mat.emissive_color.into(),
+                emissive_strength: mat.emissive_strength,
             };
         }
 
@@ -17,7 +17,7 @@ mod tests {
     use crate::graphics::texture_array::{TextureArray, TextureArrayBuilder, TextureArrayError};
     use crate::world::chunk::{Chunk, ChunkPos, ChunkStorageAllocator};
     use crate::world::memory::{Pool, StatsAllocator};
-    use crate::world::octree::Position;
+    use crate::world::octree::{Octree, Position};
     use crate::world::svo::{ChunkBuffer, SerializedChunk, Svo};
     use crate::world::world::BorrowedChunk;
 
@@ -32,7 +32,10 @@ mod tests {
     #[repr(C)]
     struct BufferOut {
         result: OctreeResult,
+        // not aligned, as std430 packs `stack_ptr` into the padding of the vec3
+        normal: Vector3<f32>,
         stack_ptr: i32,
+        shaded_color: Vector3<f32>,
         stack: [StackFrame; 100],
     }
 
@@ -77,7 +80,7 @@ mod tests {
         unsafe {
             let max_depth_exp = (-(svo.depth() as f32)).exp2();
             world_buffer.write(max_depth_exp.to_bits());
-            svo.write_changes_to(world_buffer.offset(1), world_buffer.len() - 1, true);
+            svo.write_changes_to(world_buffer.offset(1), world_buffer.len() - 1, true).expect("world buffer too small");
         }
         world_buffer
     }
@@ -110,8 +113,15 @@ mod tests {
                     000, 000, 000, 000, /**/ 000, 000, 000, 000, /**/ 000, 255, 000, 255, /**/ 000, 255, 000, 255,
                     000, 000, 000, 000, /**/ 000, 000, 000, 000, /**/ 000, 255, 000, 255, /**/ 000, 255, 000, 255,
                 ])?
+                .add_rgba8("normal_tilted", 4, 4, vec![
+                    // tangent space normal (1, 0, 1) pointing halfway between tangent and face normal
+                    255, 128, 255, 255, /**/ 255, 128, 255, 255, /**/ 255, 128, 255, 255, /**/ 255, 128, 255, 255,
+                    255, 128, 255, 255, /**/ 255, 128, 255, 255, /**/ 255, 128, 255, 255, /**/ 255, 128, 255, 255,
+                    255, 128, 255, 255, /**/ 255, 128, 255, 255, /**/ 255, 128, 255, 255, /**/ 255, 128, 255, 255,
+                    255, 128, 255, 255, /**/ 255, 128, 255, 255, /**/ 255, 128, 255, 255, /**/ 255, 128, 255, 255,
+                ])?
                 .build()
-        ).unwrap();
+        );
 
         let material_buffer = Buffer::new(vec![
             MaterialInstance { // empty
@@ -123,6 +133,8 @@ mod tests {
                 tex_top_normal: -1,
                 tex_side_normal: -1,
                 tex_bottom_normal: -1,
+                emissive_color: [0.0; 3],
+                emissive_strength: 0.0,
             },
             MaterialInstance { // full
                 specular_pow: 0.0,
@@ -133,6 +145,8 @@ mod tests {
                 tex_top_normal: -1,
                 tex_side_normal: -1,
                 tex_bottom_normal: -1,
+                emissive_color: [0.0; 3],
+                emissive_strength: 0.0,
             },
             MaterialInstance { // coords
                 specular_pow: 0.0,
@@ -143,6 +157,8 @@ mod tests {
                 tex_top_normal: -1,
                 tex_side_normal: -1,
                 tex_bottom_normal: -1,
+                emissive_color: [0.0; 3],
+                emissive_strength: 0.0,
             },
             MaterialInstance { // transparent_1
                 specular_pow: 0.0,
@@ -153,6 +169,8 @@ mod tests {
                 tex_top_normal: -1,
                 tex_side_normal: -1,
                 tex_bottom_normal: -1,
+                emissive_color: [0.0; 3],
+                emissive_strength: 0.0,
             },
             MaterialInstance { // transparent_2
                 specular_pow: 0.0,
@@ -163,6 +181,32 @@ mod tests {
                 tex_top_normal: -1,
                 tex_side_normal: -1,
                 tex_bottom_normal: -1,
+                emissive_color: [0.0; 3],
+                emissive_strength: 0.0,
+            },
+            MaterialInstance { // full_normal_tilted
+                specular_pow: 0.0,
+                specular_strength: 0.0,
+                tex_top: tex_array.lookup("full").unwrap() as i32,
+                tex_side: tex_array.lookup("full").unwrap() as i32,
+                tex_bottom: tex_array.lookup("full").unwrap() as i32,
+                tex_top_normal: tex_array.lookup("normal_tilted").unwrap() as i32,
+                tex_side_normal: tex_array.lookup("normal_tilted").unwrap() as i32,
+                tex_bottom_normal: tex_array.lookup("normal_tilted").unwrap() as i32,
+                emissive_color: [0.0; 3],
+                emissive_strength: 0.0,
+            },
+            MaterialInstance { // full_emissive
+                specular_pow: 0.0,
+                specular_strength: 0.0,
+                tex_top: tex_array.lookup("full").unwrap() as i32,
+                tex_side: tex_array.lookup("full").unwrap() as i32,
+                tex_bottom: tex_array.lookup("full").unwrap() as i32,
+                tex_top_normal: -1,
+                tex_side_normal: -1,
+                tex_bottom_normal: -1,
+                emissive_color: [0.5, 1.0, 1.0],
+                emissive_strength: 1.0,
             },
         ], buffer::STATIC_READ);
 
@@ -187,7 +231,7 @@ mod tests {
 
         let shader = Resource::new(
             || ShaderProgramBuilder::new().load_shader_bundle("assets/shaders/svo.test.glsl")?.build()
-        ).unwrap();
+        );
 
         let (material_buffer, tex_array) = create_test_materials();
         material_buffer.bind_as_storage_buffer(buffer_indices::MATERIALS);
@@ -223,7 +267,9 @@ mod tests {
                 color: AlignedVec4::new(0.0, 0.0, 0.0, 0.0),
                 inside_voxel: AlignedBool::from(false),
             },
+            normal: Vector3::new(0.0, 0.0, 0.0),
             stack_ptr: 0,
+            shaded_color: Vector3::new(0.0, 0.0, 0.0),
             stack: [StackFrame {
                 t_min: 0.0,
                 ptr: 0,
@@ -626,6 +672,60 @@ mod tests {
         }, "cast translucent with adjacent different");
     }
 
+    /// Tests that the normal of a hit is taken from the material's normal texture, if it has one,
+    /// and that it falls back to the geometric face normal otherwise.
+    #[test]
+    fn normal_textures() {
+        let setup = setup_test(None, |chunk| {
+            chunk.set_block(0, 0, 0, 1);
+            chunk.set_block(2, 0, 0, 5);
+        });
+
+        // no normal texture
+        let buffer_out = cast_ray(&setup.shader, Point3::new(0.5, 0.5, -0.5), Vector3::new(0.0, 0.0, 1.0), 32.0, false);
+        assert_eq!(buffer_out.result.value, 1);
+        assert_eq!(buffer_out.result.face_id, 4);
+        assert_vec3_eq!(buffer_out.normal, Vector3::new(0.0, 0.0, -1.0), 0.001);
+
+        // tilted normal texture: the tangent of the -z face is -x
+        let buffer_out = cast_ray(&setup.shader, Point3::new(2.5, 0.5, -0.5), Vector3::new(0.0, 0.0, 1.0), 32.0, false);
+        assert_eq!(buffer_out.result.value, 5);
+        assert_eq!(buffer_out.result.face_id, 4);
+        let expected = Vector3::new(-1.0, 0.0, -1.0).normalize();
+        assert_vec3_eq!(buffer_out.normal, expected, 0.01);
+
+        // miss
+        let buffer_out = cast_ray(&setup.shader, Point3::new(0.5, 5.5, -0.5), Vector3::new(0.0, 0.0, 1.0), 32.0, false);
+        assert!(buffer_out.result.t < 0.0);
+        assert_eq!(buffer_out.normal, Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    /// Tests that lit materials depend on the light direction, while fully emissive materials
+    /// return their tinted texture color regardless of it.
+    #[test]
+    fn emissive_materials() {
+        let setup = setup_test(None, |chunk| {
+            chunk.set_block(0, 0, 0, 1);
+            chunk.set_block(2, 0, 0, 6);
+        });
+
+        let shade = |x: f32, light_dir: Vector3<f32>| {
+            setup.shader.bind();
+            setup.shader.set_f32vec3("u_light_dir", &light_dir);
+            setup.shader.unbind();
+            cast_ray(&setup.shader, Point3::new(x, 0.5, -0.5), Vector3::new(0.0, 0.0, 1.0), 32.0, false).shaded_color
+        };
+
+        // lit by a light facing the hit face, unlit otherwise
+        assert_vec3_eq!(shade(0.5, Vector3::new(0.0, 0.0, 1.0)), Vector3::new(1.0, 0.0, 0.0), 0.001);
+        assert_vec3_eq!(shade(0.5, Vector3::new(0.0, 0.0, -1.0)), Vector3::new(0.0, 0.0, 0.0), 0.001);
+
+        // emissive
+        for light_dir in [Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0), Vector3::new(1.0, 0.0, 0.0)] {
+            assert_vec3_eq!(shade(2.5, light_dir), Vector3::new(0.5, 0.0, 0.0), 0.001);
+        }
+    }
+
     /// Tests if the algorithm correctly detects starting from within a voxel and that it does not
     /// intersect with that voxel.
     #[test]
@@ -720,4 +820,48 @@ mod tests {
             inside_voxel: AlignedBool::from(false),
         });
     }
+
+    /// Tests that CPU raycasts through an [`Octree`] hit the same leaves as the shader for the same rays.
+    #[test]
+    fn cpu_raycast_matches_shader() {
+        let blocks = [(0, 0, 0), (1, 0, 0), (31, 0, 0), (5, 3, 7), (12, 20, 4), (30, 30, 30)];
+        let setup = setup_test(None, |chunk| {
+            for (x, y, z) in blocks {
+                chunk.set_block(x, y, z, 1);
+            }
+        });
+
+        let mut octree = Octree::new();
+        octree.expand_to(5);
+        for (x, y, z) in blocks {
+            octree.set_leaf(Position(x, y, z), 1);
+        }
+
+        let rays = [
+            (Point3::new(0.5, 1.5, 0.5), Vector3::new(0.0, -1.0, 0.0)),
+            (Point3::new(0.5, 0.5, -2.0), Vector3::new(0.0, 0.0, 1.0)),
+            (Point3::new(2.0, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0)),
+            (Point3::new(31.5, 0.5, 0.5), Vector3::new(-1.0, 0.0, 0.0)),
+            (Point3::new(2.5, 2.5, 2.5), Vector3::new(1.0, 1.0, 1.0)),
+            (Point3::new(0.5, 8.5, 10.5), Vector3::new(0.8, -0.9, -0.6)),
+            (Point3::new(12.25, 30.5, 4.75), Vector3::new(0.0, -1.0, 0.0)),
+            (Point3::new(-3.0, 25.0, -1.0), Vector3::new(0.6, -0.2, 0.2)),
+            (Point3::new(10.5, 10.5, 10.5), Vector3::new(0.3, 0.7, -0.2)),
+            (Point3::new(16.0, 16.0, 16.0), Vector3::new(-0.1, 0.3, 0.4)),
+        ];
+        for (i, (pos, dir)) in rays.into_iter().enumerate() {
+            let buffer_out = cast_ray(&setup.shader, pos, dir, 64.0, false);
+            let hit = octree.cast_ray(pos, dir, 64.0);
+            let case_name = format!("#{i} (pos={pos:?}, dir={dir:?})");
+
+            let Some((leaf_pos, dst, normal)) = hit else {
+                assert!(buffer_out.result.t < 0.0, "{case_name}");
+                continue;
+            };
+            let result_pos = buffer_out.result.pos.0;
+            assert_eq!(leaf_pos, Position(result_pos.x as u32, result_pos.y as u32, result_pos.z as u32), "{case_name}");
+            assert_float_eq!(dst, buffer_out.result.t, 0.001);
+            assert_vec3_eq!(normal, buffer_out.normal, 0.001);
+        }
+    }
 }
@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 
+use std::fs;
 use std::io;
+use std::path::Path;
 
-use gl::types::{GLenum, GLint, GLuint};
-use image::{ImageError, ImageFormat};
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use image::{ImageError, ImageFormat, RgbaImage};
 use rustc_hash::FxHashMap;
 
 use crate::core::assets;
@@ -29,17 +31,86 @@ impl From<ImageError> for TextureArrayError {
     }
 }
 
+/// `CompressedFormat` lists the supported block-compressed texture formats. All formats encode
+/// blocks of 4x4 pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CompressedFormat {
+    /// `Bc1` (DXT1) stores RGB with 1-bit alpha in 8 bytes per block.
+    Bc1,
+    /// `Bc3` (DXT5) stores RGBA with interpolated alpha in 16 bytes per block.
+    Bc3,
+    /// `Bc7` stores high quality RGBA in 16 bytes per block.
+    Bc7,
+}
+
+impl CompressedFormat {
+    fn gl_internal_format(self) -> GLenum {
+        match self {
+            // GL_COMPRESSED_RGBA_S3TC_DXT1_EXT (extension)
+            Self::Bc1 => 0x83F1 as GLenum,
+            // GL_COMPRESSED_RGBA_S3TC_DXT5_EXT (extension)
+            Self::Bc3 => 0x83F3 as GLenum,
+            Self::Bc7 => gl::COMPRESSED_RGBA_BPTC_UNORM,
+        }
+    }
+
+    fn block_size(self) -> usize {
+        match self {
+            Self::Bc1 => 8,
+            Self::Bc3 | Self::Bc7 => 16,
+        }
+    }
+
+    /// Returns the number of bytes of an image with the given dimensions.
+    pub fn data_size(self, width: u32, height: u32) -> usize {
+        (width.div_ceil(4) * height.div_ceil(4)) as usize * self.block_size()
+    }
+
+    /// Returns an error, if `is_supported` reports that the format cannot be used for texture
+    /// arrays.
+    fn check_support<F: Fn(GLenum) -> bool>(self, is_supported: F) -> Result<(), TextureArrayError> {
+        if is_supported(self.gl_internal_format()) {
+            return Ok(());
+        }
+        Err(TextureArrayError::Other(format!(
+            "compressed format {self:?} (0x{:X}) is not supported by the graphics driver", self.gl_internal_format(),
+        )))
+    }
+}
+
+fn is_internal_format_supported(internal_format: GLenum) -> bool {
+    let mut supported = gl::FALSE as GLint;
+    unsafe {
+        gl::GetInternalformativ(gl::TEXTURE_2D_ARRAY, internal_format, gl::INTERNALFORMAT_SUPPORTED, 1, &mut supported);
+    }
+    supported == gl::TRUE as GLint
+}
+
 enum ImageContent {
     /// File(path)
     File(String),
     /// RGB8(width, height, data)
     RGB8(u32, u32, Vec<u8>),
+    /// Compressed(format, width, height, data)
+    Compressed(CompressedFormat, u32, u32, Vec<u8>),
+}
+
+impl ImageContent {
+    fn compressed_format(&self) -> Option<CompressedFormat> {
+        match self {
+            Self::File(_) | Self::RGB8(..) => None,
+            Self::Compressed(format, ..) => Some(*format),
+        }
+    }
 }
 
 /// `TextureArrayBuilder` allows for combining multiple `ImageContent` values into one texture array.
 /// The first `ImageContent` object decides the resolution of the texture array. All other images
 /// must have the same dimensions. Adding a new image requires specifying a unique name which
 /// can later be used, to lookup the texture's index in the array.
+///
+/// An array either consists of uncompressed RGBA8 images (files or raw bytes), or of block
+/// compressed images of one `CompressedFormat`.
 pub struct TextureArrayBuilder {
     mip_levels: u8,
     max_anisotropy: f32,
@@ -57,21 +128,105 @@ impl TextureArrayBuilder {
         }
     }
 
-    pub fn add_file(&mut self, name: &str, path: &str) -> Result<&mut Self, TextureArrayError> {
+    /// Sets the number of mip levels to allocate. Levels are generated from the base level after
+    /// all images were uploaded, and are clamped to the full mip chain of the base resolution, e.g.
+    /// a 4x4 texture has at most 3 levels.
+    ///
+    /// Anisotropic filtering (`max_anisotropy`) is applied on top of mipmapping: it takes additional
+    /// samples along the direction of anisotropy, so that faces viewed at steep angles can use a
+    /// sharper level. Without mip levels, distant faces are minified from the base level and
+    /// shimmer regardless of the anisotropy.
+    pub fn with_mipmaps(&mut self, levels: u32) -> &mut Self {
+        self.mip_levels = levels.clamp(1, u8::MAX as u32) as u8;
+        self
+    }
+
+    /// Adds an image file, e.g. PNG or JPEG, that is decoded when building the array. Its
+    /// dimensions must match the dimensions of the first image in the array.
+    pub fn add_file<P: AsRef<Path>>(&mut self, name: &str, path: P) -> Result<&mut Self, TextureArrayError> {
+        let path = path.as_ref();
+        let Some(path) = path.to_str() else {
+            return Err(TextureArrayError::Other(format!("path '{}' is not valid unicode", path.display())));
+        };
+
+        self.check_format(None)?;
         self.register_texture(name.to_owned())?;
         self.content.push(ImageContent::File(path.to_owned()));
         Ok(self)
     }
 
+    /// Adds all PNG and JPEG files in the given directory, using their file names without extension
+    /// as lookup names. Files are added in alphabetical order. Other files and subdirectories are
+    /// ignored. The directory is read from disk, even if assets are bundled.
+    pub fn add_directory<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, TextureArrayError> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            let is_image = path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ["png", "jpg", "jpeg"].contains(&ext.to_lowercase().as_str()));
+            if path.is_file() && is_image {
+                files.push(path);
+            }
+        }
+        files.sort();
+
+        for path in files {
+            let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+                return Err(TextureArrayError::Other(format!("file name of '{}' is not valid unicode", path.display())));
+            };
+            self.add_file(name, &path)?;
+        }
+        Ok(self)
+    }
+
     pub fn add_rgba8(&mut self, name: &str, w: u32, h: u32, bytes: Vec<u8>) -> Result<&mut Self, TextureArrayError> {
         let mut bytes = bytes;
         Self::flip_image_v(&mut bytes, w, h, 4);
 
+        self.check_format(None)?;
         self.register_texture(String::from(name))?;
         self.content.push(ImageContent::RGB8(w, h, bytes));
         Ok(self)
     }
 
+    /// Adds a block compressed image. `data` contains the 4x4 pixel blocks of the base level in
+    /// row-major order, starting with the bottom row of blocks as expected by OpenGL. Images that
+    /// are stored top-down, e.g. DDS files, have to be flipped before compressing them. The size of
+    /// `data` must match [`CompressedFormat::data_size`].
+    ///
+    /// Mipmaps are not generated for compressed arrays, they only have a single level.
+    pub fn add_compressed(&mut self, name: &str, format: CompressedFormat, w: u32, h: u32, data: Vec<u8>) -> Result<&mut Self, TextureArrayError> {
+        self.check_format(Some(format))?;
+        if data.len() != format.data_size(w, h) {
+            return Err(TextureArrayError::Other(format!(
+                "compressed image '{name}' has {} bytes, but {format:?} requires {} bytes for {w}x{h} pixels",
+                data.len(), format.data_size(w, h),
+            )));
+        }
+
+        self.register_texture(String::from(name))?;
+        self.content.push(ImageContent::Compressed(format, w, h, data));
+        Ok(self)
+    }
+
+    /// Returns an error, if an image with the given `format` cannot be added to the images that
+    /// were already added. `None` stands for uncompressed RGBA8 images.
+    fn check_format(&self, format: Option<CompressedFormat>) -> Result<(), TextureArrayError> {
+        let Some(first) = self.content.first() else {
+            return Ok(());
+        };
+        let expected = first.compressed_format();
+        if expected == format {
+            return Ok(());
+        }
+
+        let describe = |format: Option<CompressedFormat>| format.map_or_else(|| String::from("RGBA8"), |format| format!("{format:?}"));
+        Err(TextureArrayError::Other(format!(
+            "texture array must only contain one format: expected {}, got {}", describe(expected), describe(format),
+        )))
+    }
+
     fn register_texture(&mut self, name: String) -> Result<(), TextureArrayError> {
         if self.textures.contains_key(&name) {
             return Err(TextureArrayError::Other(format!("name '{name}' is already registered")));
@@ -80,26 +235,41 @@ impl TextureArrayBuilder {
         Ok(())
     }
 
+    fn load_image(path: &str) -> Result<RgbaImage, TextureArrayError> {
+        let data = assets::read(path)?;
+        let format = ImageFormat::from_path(path)?;
+        Ok(image::load_from_memory_with_format(&data, format)?.flipv().into_rgba8())
+    }
+
     pub fn build(&self) -> Result<TextureArray, TextureArrayError> {
         let width;
         let height;
         let mut image = None;
 
+        if self.content.is_empty() {
+            return Err(TextureArrayError::Other(String::from("texture array must contain at least one image")));
+        }
+
         match &self.content[0] {
             ImageContent::File(path) => {
-                let data = assets::read(path)?;
-                let format = ImageFormat::from_path(path)?;
-                image = Some(image::load_from_memory_with_format(&data, format)?.flipv());
+                image = Some(Self::load_image(path)?);
                 width = image.as_ref().unwrap().width();
                 height = image.as_ref().unwrap().height();
             }
-            ImageContent::RGB8(w, h, _) => {
+            ImageContent::RGB8(w, h, _) | ImageContent::Compressed(_, w, h, _) => {
                 width = *w;
                 height = *h;
             }
         }
 
-        let mip_levels = self.mip_levels.min(width.min(height).ilog2() as u8);
+        let compressed = self.content[0].compressed_format();
+        let mut mip_levels = self.mip_levels.min(width.min(height).ilog2() as u8 + 1);
+        let mut internal_format = gl::RGBA8;
+        if let Some(format) = compressed {
+            format.check_support(is_internal_format_supported)?;
+            mip_levels = 1;
+            internal_format = format.gl_internal_format();
+        }
 
         let textures = self.textures.clone();
         let mut texture = TextureArray::new(
@@ -107,6 +277,7 @@ impl TextureArrayBuilder {
             height,
             self.content.len() as u32,
             mip_levels,
+            internal_format,
             self.max_anisotropy,
             textures,
         );
@@ -121,27 +292,34 @@ impl TextureArrayBuilder {
                 ImageContent::File(path) => {
                     if i > 0 {
                         // the first image was already loaded to fetch the dimensions of the array
-                        let data = assets::read(path)?;
-                        let format = ImageFormat::from_path(path)?;
-                        image = Some(image::load_from_memory_with_format(&data, format)?.flipv());
+                        image = Some(Self::load_image(path)?);
                     }
 
                     let image = image.as_ref().unwrap();
                     iw = image.width();
                     ih = image.height();
-                    data = image.as_rgba8().unwrap().as_raw();
+                    data = image.as_raw();
                 }
-                ImageContent::RGB8(w, h, bytes) => {
+                ImageContent::RGB8(w, h, bytes) | ImageContent::Compressed(_, w, h, bytes) => {
                     iw = *w;
                     ih = *h;
                     data = bytes;
                 }
             }
 
-            assert!(iw == width && ih == height, "image does not match base dimensions: got: {iw}x{ih}, base: {width}x{height}");
-            assert_eq!(data.len(), (iw * ih * 4) as usize);
-
-            texture.sub_image_3d(i as u32, iw, ih, data);
+            if iw != width || ih != height {
+                texture.unbind();
+                let name = self.textures.iter().find(|(_, index)| **index == i as u32).map_or("", |(name, _)| name.as_str());
+                return Err(TextureArrayError::Other(format!(
+                    "image '{name}' does not match base dimensions: got: {iw}x{ih}, base: {width}x{height}",
+                )));
+            }
+            if let Some(format) = compressed {
+                texture.compressed_sub_image_3d(i as u32, iw, ih, format, data);
+            } else {
+                assert_eq!(data.len(), (iw * ih * 4) as usize);
+                texture.sub_image_3d(i as u32, iw, ih, data);
+            }
             gl_assert_no_error!();
         }
         if mip_levels > 1 {
@@ -188,7 +366,7 @@ impl Drop for TextureArray {
 }
 
 impl TextureArray {
-    fn new(width: u32, height: u32, depth: u32, mip_levels: u8, max_anisotropy: f32, textures: FxHashMap<String, u32>) -> Self {
+    fn new(width: u32, height: u32, depth: u32, mip_levels: u8, internal_format: GLenum, max_anisotropy: f32, textures: FxHashMap<String, u32>) -> Self {
         assert!(mip_levels > 0, "mip_levels must at least be 1, but is {mip_levels}");
 
         let mut id = 0;
@@ -222,7 +400,7 @@ impl TextureArray {
             gl::TexStorage3D(
                 gl::TEXTURE_2D_ARRAY,
                 mip_levels as GLint,
-                gl::RGBA8,
+                internal_format,
                 width as GLint,
                 height as GLint,
                 depth as GLint,
@@ -254,6 +432,25 @@ impl TextureArray {
         }
     }
 
+    #[allow(clippy::unused_self)]
+    pub fn compressed_sub_image_3d(&mut self, depth: u32, width: u32, height: u32, format: CompressedFormat, data: &[u8]) {
+        unsafe {
+            gl::CompressedTexSubImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                0,
+                0,
+                depth as GLint,
+                width as GLint,
+                height as GLint,
+                1,
+                format.gl_internal_format(),
+                data.len() as GLsizei,
+                std::ptr::addr_of!(data[0]).cast(),
+            );
+        }
+    }
+
     #[allow(clippy::unused_self)]
     pub fn generate_mipmaps(&self) {
         unsafe { gl::GenerateMipmap(gl::TEXTURE_2D_ARRAY); }
@@ -276,3 +473,90 @@ impl Bind for TextureArray {
         unsafe { gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::core::GlContext;
+    use crate::graphics::resource::Bind;
+    use crate::graphics::texture_array::{CompressedFormat, TextureArrayBuilder, TextureArrayError};
+
+    /// Tests that the number of allocated mip levels is clamped to the full mip chain.
+    #[test]
+    fn with_mipmaps() {
+        let _context = GlContext::new_headless(1, 1); // do not drop context
+
+        let levels = |requested: u32| {
+            let texture = TextureArrayBuilder::new(1, 0.0)
+                .with_mipmaps(requested)
+                .add_rgba8("test", 4, 4, vec![255; 4 * 4 * 4]).unwrap()
+                .build().unwrap();
+
+            let mut levels = 0;
+            texture.bind();
+            unsafe { gl::GetTexParameteriv(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_IMMUTABLE_LEVELS, &mut levels); }
+            texture.unbind();
+            levels
+        };
+
+        assert_eq!(levels(0), 1);
+        assert_eq!(levels(2), 2);
+        assert_eq!(levels(10), 3);
+    }
+
+    /// Tests that all images of a directory are loaded with their file names as lookup names, and
+    /// that images with mismatching dimensions are reported as an error.
+    #[test]
+    fn add_directory() {
+        let _context = GlContext::new_headless(1, 1); // do not drop context
+
+        let dir = tempfile::tempdir().unwrap();
+        image::RgbaImage::new(4, 4).save(dir.path().join("stone.png")).unwrap();
+        image::RgbImage::new(4, 4).save(dir.path().join("dirt.jpg")).unwrap();
+        std::fs::write(dir.path().join("readme.txt"), "not an image").unwrap();
+
+        let texture = TextureArrayBuilder::new(1, 0.0)
+            .add_directory(dir.path()).unwrap()
+            .build().unwrap();
+        assert_eq!(texture.lookup("dirt"), Some(0));
+        assert_eq!(texture.lookup("stone"), Some(1));
+        assert_eq!(texture.lookup("readme"), None);
+
+        image::RgbaImage::new(2, 2).save(dir.path().join("small.png")).unwrap();
+        let result = TextureArrayBuilder::new(1, 0.0)
+            .add_directory(dir.path()).unwrap()
+            .build();
+        let Err(TextureArrayError::Other(msg)) = result else {
+            panic!("expected dimension mismatch error");
+        };
+        assert!(msg.starts_with("image 'small' does not match base dimensions"), "{msg}");
+    }
+
+    /// Tests that compressed images are validated and that unsupported formats are reported with a
+    /// descriptive error.
+    #[test]
+    fn add_compressed() {
+        let mut builder = TextureArrayBuilder::new(1, 0.0);
+        builder.add_compressed("a", CompressedFormat::Bc1, 8, 6, vec![0; 32]).unwrap();
+
+        let Err(TextureArrayError::Other(msg)) = builder.add_compressed("b", CompressedFormat::Bc1, 8, 8, vec![0; 16]) else {
+            panic!("expected data size error");
+        };
+        assert_eq!(msg, "compressed image 'b' has 16 bytes, but Bc1 requires 32 bytes for 8x8 pixels");
+
+        let Err(TextureArrayError::Other(msg)) = builder.add_compressed("c", CompressedFormat::Bc7, 8, 8, vec![0; 64]) else {
+            panic!("expected mixed format error");
+        };
+        assert_eq!(msg, "texture array must only contain one format: expected Bc1, got Bc7");
+
+        let Err(TextureArrayError::Other(msg)) = builder.add_rgba8("d", 1, 1, vec![0; 4]) else {
+            panic!("expected mixed format error");
+        };
+        assert_eq!(msg, "texture array must only contain one format: expected Bc1, got RGBA8");
+
+        let Err(TextureArrayError::Other(msg)) = CompressedFormat::Bc7.check_support(|_| false) else {
+            panic!("expected unsupported format error");
+        };
+        assert_eq!(msg, "compressed format Bc7 (0x8E8C) is not supported by the graphics driver");
+        assert!(CompressedFormat::Bc7.check_support(|_| true).is_ok());
+    }
+}
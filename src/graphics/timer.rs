@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use gl::types::GLuint;
+
+/// `GpuTimer` measures how long the GPU spends on named render passes using `GL_TIME_ELAPSED` queries. Query results
+/// only become available once the GPU has caught up, so they are read back asynchronously and are reported with a delay
+/// of up to `latency` frames. This avoids stalling the CPU on every measurement.
+///
+/// Passes must not be nested, as only one `GL_TIME_ELAPSED` query can be active at a time. A pass can be measured
+/// multiple times per frame, in which case its times are summed up.
+///
+/// If the driver does not support timer queries, all calls are no-ops and no results are reported.
+pub struct GpuTimer {
+    supported: bool,
+    latency: u64,
+    frame: u64,
+    active: Option<PendingQuery>,
+    pending: VecDeque<PendingQuery>,
+    free: Vec<GLuint>,
+    collecting: Option<(u64, Vec<(&'static str, Duration)>)>,
+    results: Vec<(&'static str, Duration)>,
+    results_frame: Option<u64>,
+}
+
+struct PendingQuery {
+    id: GLuint,
+    pass: &'static str,
+    frame: u64,
+}
+
+/// `GpuTimerScope` ends the measurement of a pass when it is dropped.
+pub struct GpuTimerScope<'a> {
+    timer: &'a mut GpuTimer,
+}
+
+impl Drop for GpuTimerScope<'_> {
+    fn drop(&mut self) {
+        self.timer.end();
+    }
+}
+
+impl GpuTimer {
+    /// Creates a new timer that reports results at the latest after `latency` frames. A latency of 0 is treated as 1.
+    pub fn new(latency: u64) -> Self {
+        Self {
+            supported: Self::query_support(),
+            latency: latency.max(1),
+            frame: 0,
+            active: None,
+            pending: VecDeque::new(),
+            free: Vec::new(),
+            collecting: None,
+            results: Vec::new(),
+            results_frame: None,
+        }
+    }
+
+    fn query_support() -> bool {
+        if !gl::GenQueries::is_loaded() || !gl::GetQueryObjectui64v::is_loaded() {
+            return false;
+        }
+        // some drivers expose the functions but report a counter without any bits
+        let mut bits = 0;
+        unsafe { gl::GetQueryiv(gl::TIME_ELAPSED, gl::QUERY_COUNTER_BITS, &mut bits); }
+        bits > 0
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.supported
+    }
+
+    /// Starts measuring the GPU commands issued until [`GpuTimer::end`] is called as part of `pass`.
+    pub fn begin(&mut self, pass: &'static str) {
+        if !self.supported {
+            return;
+        }
+        assert!(self.active.is_none(), "gpu timer passes must not be nested");
+
+        let id = self.free.pop().unwrap_or_else(|| {
+            let mut id = 0;
+            unsafe { gl::GenQueries(1, &mut id); }
+            id
+        });
+        unsafe { gl::BeginQuery(gl::TIME_ELAPSED, id); }
+        self.active = Some(PendingQuery { id, pass, frame: self.frame });
+    }
+
+    /// Stops measuring the active pass.
+    pub fn end(&mut self) {
+        let Some(query) = self.active.take() else {
+            return;
+        };
+        unsafe { gl::EndQuery(gl::TIME_ELAPSED); }
+        self.pending.push_back(query);
+    }
+
+    /// Measures `pass` until the returned scope is dropped.
+    pub fn scope(&mut self, pass: &'static str) -> GpuTimerScope<'_> {
+        self.begin(pass);
+        GpuTimerScope { timer: self }
+    }
+
+    /// Marks the end of a frame and reads back all available results. Queries that are `latency` frames old are waited
+    /// on.
+    pub fn next_frame(&mut self) {
+        if !self.supported {
+            return;
+        }
+        self.frame += 1;
+
+        while let Some(query) = self.pending.front() {
+            let age = self.frame - query.frame;
+            if age < self.latency && !Self::is_available(query.id) {
+                break;
+            }
+
+            let query = self.pending.pop_front().unwrap();
+            let elapsed = Self::read_elapsed(query.id);
+            self.free.push(query.id);
+
+            if self.collecting.as_ref().is_some_and(|(frame, _)| *frame != query.frame) {
+                self.publish();
+            }
+            let (_, times) = self.collecting.get_or_insert_with(|| (query.frame, Vec::new()));
+            let index = times.iter().position(|(pass, _)| *pass == query.pass).unwrap_or_else(|| {
+                times.push((query.pass, Duration::ZERO));
+                times.len() - 1
+            });
+            times[index].1 += elapsed;
+        }
+
+        // all queries of a frame are read, once no pending query belongs to it anymore
+        let is_complete = self.collecting.as_ref()
+            .is_some_and(|(frame, _)| self.pending.front().map_or(*frame < self.frame, |q| q.frame > *frame));
+        if is_complete {
+            self.publish();
+        }
+    }
+
+    fn publish(&mut self) {
+        if let Some((frame, times)) = self.collecting.take() {
+            self.results = times;
+            self.results_frame = Some(frame);
+        }
+    }
+
+    /// Returns the GPU time of every pass of the most recent frame whose results are available, in the order the passes
+    /// were first measured in that frame.
+    pub fn get_results(&self) -> &[(&'static str, Duration)] {
+        &self.results
+    }
+
+    /// Returns the number of the frame that the current results belong to, or `None` if no results are available yet.
+    /// It changes whenever the results of a newer frame are available.
+    pub fn get_results_frame(&self) -> Option<u64> {
+        self.results_frame
+    }
+
+    fn is_available(id: GLuint) -> bool {
+        let mut available = 0;
+        unsafe { gl::GetQueryObjectiv(id, gl::QUERY_RESULT_AVAILABLE, &mut available); }
+        available != 0
+    }
+
+    fn read_elapsed(id: GLuint) -> Duration {
+        let mut nanos = 0;
+        unsafe { gl::GetQueryObjectui64v(id, gl::QUERY_RESULT, &mut nanos); }
+        Duration::from_nanos(nanos)
+    }
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        if self.active.is_some() {
+            self.end();
+        }
+        let ids: Vec<GLuint> = self.free.iter()
+            .copied()
+            .chain(self.pending.iter().map(|q| q.id))
+            .collect();
+        if !ids.is_empty() {
+            unsafe { gl::DeleteQueries(ids.len() as i32, ids.as_ptr()); }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::GlContext;
+    use crate::graphics::timer::GpuTimer;
+
+    /// Tests that passes are measured and reported after at most `latency` frames, and that repeated passes within a
+    /// frame are summed up.
+    #[test]
+    fn gpu_timer() {
+        let _context = GlContext::new_headless(640, 490); // do not drop context
+
+        let mut timer = GpuTimer::new(2);
+        if !timer.is_supported() {
+            println!("skipping test: timer queries are not supported");
+            return;
+        }
+        assert!(timer.get_results().is_empty());
+        assert_eq!(timer.get_results_frame(), None);
+
+        for _ in 0..3 {
+            {
+                let _scope = timer.scope("clear");
+                unsafe { gl::Clear(gl::COLOR_BUFFER_BIT); }
+            }
+            timer.begin("finish");
+            unsafe { gl::Finish(); }
+            timer.end();
+            timer.begin("clear");
+            unsafe { gl::Clear(gl::COLOR_BUFFER_BIT); }
+            timer.end();
+            timer.next_frame();
+        }
+
+        let passes: Vec<&str> = timer.get_results().iter().map(|(pass, _)| *pass).collect();
+        assert_eq!(passes, vec!["clear", "finish"]);
+        assert!(timer.get_results_frame().is_some());
+    }
+}
@@ -16,7 +16,7 @@ extern crate gl;
 extern crate memoffset;
 extern crate test;
 
-use crate::gamelogic::game::Game;
+use crate::gamelogic::game::{Game, GameArgs};
 
 mod core;
 mod gamelogic;
@@ -34,23 +34,52 @@ pub fn global_allocated_bytes() -> usize {
     0
 }
 
+#[cfg(feature = "dhat-heap")]
+pub fn set_memory_limit(_bytes: Option<usize>) {}
+
+#[cfg(feature = "dhat-heap")]
+pub fn is_over_memory_budget() -> bool {
+    false
+}
+
+#[cfg(feature = "dhat-heap")]
+pub fn memory_pressure() -> f32 {
+    0.0
+}
+
 /// In normal operation, register a wrapper around the System allocator to collect how much memory was allocated
 /// during runtime.
 #[cfg(not(feature = "dhat-heap"))]
 #[global_allocator]
-static STATS_ALLOC: world::memory::GlobalStatsAllocator = world::memory::GlobalStatsAllocator {
-    allocated_bytes: std::sync::atomic::AtomicUsize::new(0),
-};
+static STATS_ALLOC: world::memory::GlobalStatsAllocator = world::memory::GlobalStatsAllocator::new();
 
 #[cfg(not(feature = "dhat-heap"))]
 pub fn global_allocated_bytes() -> usize {
     STATS_ALLOC.allocated_bytes.load(std::sync::atomic::Ordering::Acquire)
 }
 
+/// Calls [`world::memory::GlobalStatsAllocator::set_memory_limit`] on the global allocator.
+#[cfg(not(feature = "dhat-heap"))]
+pub fn set_memory_limit(bytes: Option<usize>) {
+    STATS_ALLOC.set_memory_limit(bytes);
+}
+
+/// Calls [`world::memory::GlobalStatsAllocator::is_over_budget`] on the global allocator.
+#[cfg(not(feature = "dhat-heap"))]
+pub fn is_over_memory_budget() -> bool {
+    STATS_ALLOC.is_over_budget()
+}
+
+/// Calls [`world::memory::GlobalStatsAllocator::memory_pressure`] on the global allocator.
+#[cfg(not(feature = "dhat-heap"))]
+pub fn memory_pressure() -> f32 {
+    STATS_ALLOC.memory_pressure()
+}
+
 fn main() {
     #[cfg(feature = "dhat-heap")]
         let _profiler = dhat::Profiler::builder().trim_backtraces(Some(20)).build();
 
-    let game = Game::new();
+    let game = Game::new(&GameArgs::parse(std::env::args().skip(1)));
     game.run();
 }
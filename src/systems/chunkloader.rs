@@ -1,16 +1,23 @@
 use std::cmp;
+use std::str::FromStr;
 
-use cgmath::Point3;
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Vector3};
 use rustc_hash::FxHashMap;
 
-use crate::world::chunk::ChunkPos;
+use crate::graphics::camera::Camera;
+use crate::world::chunk::{Chunk, ChunkPos};
 
 pub struct ChunkLoader {
     radius: u32,
+    unload_margin: u32,
     start_y: i32,
     end_y: i32,
+    lod_bands: LodBands,
 
     last_pos: Option<ChunkPos>,
+    /// `center` is the position of the last update, around which chunks are in the loading radius. See
+    /// [`ChunkLoader::is_in_radius`].
+    center: Option<ChunkPos>,
     loaded_chunks: FxHashMap<ChunkPos, u8>,
 }
 
@@ -29,15 +36,136 @@ impl ChunkEvent {
     }
 }
 
+/// `LodBands` selects a chunk's LOD depending on its horizontal distance to the loading center in chunks. Chunks
+/// within the first radius have full detail, and every further band reduces the LOD by one. Chunks beyond the last
+/// radius use one LOD less than the last band. The LOD never drops below 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LodBands {
+    radii: Vec<u32>,
+}
+
+impl LodBands {
+    /// Creates bands with the given radii. Returns `None`, if the radii are not strictly ascending.
+    pub fn new(radii: Vec<u32>) -> Option<Self> {
+        radii.windows(2).all(|w| w[0] < w[1]).then_some(Self { radii })
+    }
+
+    /// Returns the LOD of a chunk at `distance` chunks from the loading center.
+    pub fn lod_at(&self, distance: u32) -> u8 {
+        let band = self.radii.iter().position(|r| distance <= *r).unwrap_or(self.radii.len());
+        Chunk::DEPTH.saturating_sub(band as u8).max(1)
+    }
+}
+
+impl Default for LodBands {
+    fn default() -> Self {
+        Self { radii: vec![6, 12, 19] }
+    }
+}
+
+impl FromStr for LodBands {
+    type Err = ();
+
+    /// Parses comma separated radii, e.g. `6,12,19`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let radii = s.split(',')
+            .map(|r| r.trim().parse::<u32>().map_err(|_| ()))
+            .collect::<Result<_, _>>()?;
+        Self::new(radii).ok_or(())
+    }
+}
+
+/// `LoadQueue` holds chunks that are waiting to be loaded. Chunks are taken in order of their priority, which is
+/// calculated when taking them, so that turning the camera immediately changes which chunks are loaded next.
+#[derive(Default)]
+pub struct LoadQueue {
+    pending: FxHashMap<ChunkPos, u8>,
+}
+
+impl LoadQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the chunk with the given `lod` to the queue. If the chunk is already queued, only its LOD is updated.
+    pub fn push(&mut self, pos: ChunkPos, lod: u8) {
+        self.pending.insert(pos, lod);
+    }
+
+    /// Updates the LOD of the chunk, if it is queued.
+    pub fn set_lod(&mut self, pos: &ChunkPos, lod: u8) {
+        if let Some(old_lod) = self.pending.get_mut(pos) {
+            *old_lod = lod;
+        }
+    }
+
+    /// Removes the chunk from the queue. Returns true, if it was queued.
+    pub fn remove(&mut self, pos: &ChunkPos) -> bool {
+        self.pending.remove(pos).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Removes and returns up to `n` chunks with their LOD, highest priority first.
+    pub fn pop(&mut self, camera: &Camera, n: usize) -> Vec<(ChunkPos, u8)> {
+        let mut chunks: Vec<(f32, ChunkPos)> = self.pending.keys()
+            .map(|pos| (Self::score(pos, camera), *pos))
+            .collect();
+
+        // only the n chunks with the lowest scores are ordered, instead of all pending chunks
+        if n < chunks.len() {
+            if n == 0 {
+                return Vec::new();
+            }
+            chunks.select_nth_unstable_by(n - 1, |lhs, rhs| lhs.0.total_cmp(&rhs.0));
+            chunks.truncate(n);
+        }
+        chunks.sort_unstable_by(|lhs, rhs| lhs.0.total_cmp(&rhs.0));
+
+        chunks.into_iter()
+            .map(|(_, pos)| (pos, self.pending.remove(&pos).unwrap()))
+            .collect()
+    }
+
+    /// Returns the priority score of the chunk at `pos`, where lower scores are loaded first. The score is the
+    /// distance between the camera and the chunk's center, which is tripled for chunks directly behind the camera,
+    /// so that a chunk in view is preferred over any chunk behind the camera, that is less than three times closer.
+    fn score(pos: &ChunkPos, camera: &Camera) -> f32 {
+        let half = Chunk::SIZE as f32 / 2.0;
+        let center = pos.as_block_pos().cast::<f32>().unwrap() + Vector3::new(half, half, half);
+        let delta = center.to_vec() - camera.position.to_vec();
+
+        let distance = delta.magnitude();
+        if distance <= f32::EPSILON {
+            return 0.0;
+        }
+        let alignment = delta.dot(camera.forward.normalize()) / distance;
+        distance * (2.0 - alignment)
+    }
+}
+
 impl ChunkLoader {
     pub fn new(radius: u32, start_y: i32, end_y: i32) -> Self {
         assert!(start_y < end_y);
         Self {
             radius,
+            unload_margin: 0,
             start_y,
             end_y,
+            lod_bands: LodBands::default(),
 
             last_pos: None,
+            center: None,
             loaded_chunks: FxHashMap::default(),
         }
     }
@@ -52,6 +180,28 @@ impl ChunkLoader {
         self.last_pos = None;
     }
 
+    pub fn get_unload_margin(&self) -> u32 {
+        self.unload_margin
+    }
+
+    /// Keeps loaded chunks until they are more than `margin` chunks outside the loading radius. This avoids that
+    /// chunks are unloaded and loaded again repeatedly, while moving back and forth along the radius' edge.
+    pub fn set_unload_margin(&mut self, margin: u32) {
+        self.unload_margin = margin;
+        self.last_pos = None;
+    }
+
+    pub fn get_lod_bands(&self) -> &LodBands {
+        &self.lod_bands
+    }
+
+    /// Sets the bands used to select the LOD of chunks. Loaded chunks that change their LOD are reported by the next
+    /// update.
+    pub fn set_lod_bands(&mut self, lod_bands: LodBands) {
+        self.lod_bands = lod_bands;
+        self.last_pos = None;
+    }
+
     /// Returns a list of chunk events that occurred due to changes to the target position.
     /// Might be empty if the position did not change.
     pub fn update(&mut self, pos: Point3<f32>) -> Vec<ChunkEvent> {
@@ -73,7 +223,7 @@ impl ChunkLoader {
                 }
 
                 let mut pos = ChunkPos::new(current_pos.x + dx, 0, current_pos.z + dz);
-                let lod = Self::calculate_lod(&current_pos, &pos);
+                let lod = self.calculate_lod(&current_pos, &pos);
 
                 for y in self.start_y..self.end_y {
                     // ensure that y is still within loading radius
@@ -97,15 +247,16 @@ impl ChunkLoader {
             }
         }
 
-        // create delete events for chunks outside the loading radius
+        // create delete events for chunks outside the loading radius and its unload margin
         let mut delete_list = Vec::new();
-        let r_squared = r * r;
+        let ur = r + self.unload_margin as i32;
+        let ur_squared = ur * ur;
         for pos in self.loaded_chunks.keys() {
             let dx = (pos.x - current_pos.x).abs();
             let dy = (pos.y - current_pos.y).abs();
             let dz = (pos.z - current_pos.z).abs();
 
-            if (dy < -r || dy > r) || dx * dx + dz * dz > r_squared {
+            if dy > ur || dx * dx + dz * dz > ur_squared {
                 delete_list.push(*pos);
                 events.push(ChunkEvent::Unload { pos: *pos });
             }
@@ -113,6 +264,7 @@ impl ChunkLoader {
         for pos in delete_list {
             self.loaded_chunks.remove(&pos);
         }
+        self.center = Some(current_pos);
 
         // sort events by the targeted chunk's distance to the current position
         events.sort_by(|a, b| {
@@ -124,22 +276,33 @@ impl ChunkLoader {
         events
     }
 
-    fn calculate_lod(center: &ChunkPos, pos: &ChunkPos) -> u8 {
-        match pos.dst_2d_sq(center).sqrt() as i32 {
-            0..=6 => 5,
-            7..=12 => 4,
-            13..=19 => 3,
-            _ => 2,
-        }
+    fn calculate_lod(&self, center: &ChunkPos, pos: &ChunkPos) -> u8 {
+        self.lod_bands.lod_at(pos.dst_2d_sq(center).sqrt() as u32)
     }
 
     pub fn is_loaded(&self, pos: &ChunkPos) -> bool {
         self.loaded_chunks.contains_key(pos)
     }
 
+    /// Returns true, if the chunk is within the loading radius around the position of the last update. Before the
+    /// first update, no chunk is within the radius.
+    pub fn is_in_radius(&self, pos: &ChunkPos) -> bool {
+        let Some(center) = self.center else {
+            return false;
+        };
+        let r = self.radius as i32;
+        let (dx, dy, dz) = (pos.x - center.x, pos.y - center.y, pos.z - center.z);
+        (self.start_y..self.end_y).contains(&pos.y) && (-r..=r).contains(&dy) && dx * dx + dz * dz <= r * r
+    }
+
     pub fn add_loaded_chunk(&mut self, pos: ChunkPos, lod: u8) {
         self.loaded_chunks.insert(pos, lod);
     }
+
+    /// Returns the LOD of the loaded chunk, if it is loaded.
+    pub fn get_lod(&self, pos: &ChunkPos) -> Option<u8> {
+        self.loaded_chunks.get(pos).copied()
+    }
 }
 
 #[cfg(test)]
@@ -148,7 +311,10 @@ mod tests {
 
     use cgmath::Point3;
 
-    use crate::systems::chunkloader::{ChunkEvent, ChunkLoader};
+    use cgmath::Vector3;
+
+    use crate::graphics::camera::Camera;
+    use crate::systems::chunkloader::{ChunkEvent, ChunkLoader, LoadQueue, LodBands};
     use crate::world::chunk::ChunkPos;
 
     /// Asserts that chunks inside the specified radius are properly loaded with an accurate LOD
@@ -240,6 +406,120 @@ mod tests {
         assert_eq!(get_lod_scale_on_x_axis(&events, 1), change);
     }
 
+    /// Tests that only chunks within the loading radius around the last update's position are in the radius.
+    #[test]
+    fn is_in_radius() {
+        let mut cl = ChunkLoader::new(2, 0, 2);
+        assert!(!cl.is_in_radius(&ChunkPos::new(0, 0, 0)));
+
+        cl.update(Point3::new(32.0, 0.0, 0.0));
+        assert!(cl.is_in_radius(&ChunkPos::new(1, 0, 0)));
+        assert!(cl.is_in_radius(&ChunkPos::new(3, 1, 0)));
+        assert!(cl.is_in_radius(&ChunkPos::new(-1, 0, 0)));
+        assert!(!cl.is_in_radius(&ChunkPos::new(-2, 0, 0)));
+        assert!(!cl.is_in_radius(&ChunkPos::new(3, 0, 2)));
+        assert!(!cl.is_in_radius(&ChunkPos::new(1, 2, 0)));
+        assert!(!cl.is_in_radius(&ChunkPos::new(1, -1, 0)));
+    }
+
+    /// Tests that chunks are only unloaded once they are outside the unload margin, and that moving further away
+    /// unloads the now distant chunks and loads new ones.
+    #[test]
+    fn unload_margin() {
+        let mut cl = ChunkLoader::new(1, 0, 1);
+        cl.set_unload_margin(1);
+        cl.update(Point3::new(0.0, 0.0, 0.0));
+
+        // chunks that left the radius are kept within the margin
+        let mut events = cl.update(Point3::new(32.0, 0.0, 0.0));
+        events.sort();
+        assert_eq!(events, vec![
+            ChunkEvent::Load { pos: ChunkPos { x: 1, y: 0, z: -1 }, lod: 5 },
+            ChunkEvent::Load { pos: ChunkPos { x: 1, y: 0, z: 1 }, lod: 5 },
+            ChunkEvent::Load { pos: ChunkPos { x: 2, y: 0, z: 0 }, lod: 5 },
+        ]);
+
+        // moving back and forth at the edge does not cause any events
+        assert!(cl.update(Point3::new(0.0, 0.0, 0.0)).is_empty());
+        assert!(cl.update(Point3::new(32.0, 0.0, 0.0)).is_empty());
+
+        let mut events = cl.update(Point3::new(64.0, 0.0, 0.0));
+        events.sort();
+        assert_eq!(events, vec![
+            ChunkEvent::Load { pos: ChunkPos { x: 2, y: 0, z: -1 }, lod: 5 },
+            ChunkEvent::Load { pos: ChunkPos { x: 2, y: 0, z: 1 }, lod: 5 },
+            ChunkEvent::Load { pos: ChunkPos { x: 3, y: 0, z: 0 }, lod: 5 },
+            ChunkEvent::Unload { pos: ChunkPos { x: -1, y: 0, z: 0 } },
+            ChunkEvent::Unload { pos: ChunkPos { x: 0, y: 0, z: -1 } },
+            ChunkEvent::Unload { pos: ChunkPos { x: 0, y: 0, z: 1 } },
+        ]);
+    }
+
+    /// Tests that distances are mapped to the LOD of their band and that invalid bands are rejected.
+    #[test]
+    fn lod_bands() {
+        let bands = LodBands::default();
+        let lods: Vec<u8> = [0, 6, 7, 12, 13, 19, 20, 100].iter().map(|d| bands.lod_at(*d)).collect();
+        assert_eq!(lods, vec![5, 5, 4, 4, 3, 3, 2, 2]);
+
+        let bands: LodBands = "4, 8".parse().unwrap();
+        let lods: Vec<u8> = [0, 4, 5, 8, 9].iter().map(|d| bands.lod_at(*d)).collect();
+        assert_eq!(lods, vec![5, 5, 4, 4, 3]);
+
+        // the LOD never drops below 1
+        let bands = LodBands::new(vec![1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(bands.lod_at(4), 2);
+        assert_eq!(bands.lod_at(5), 1);
+        assert_eq!(bands.lod_at(7), 1);
+
+        assert_eq!("8,4".parse::<LodBands>(), Err(()));
+        assert_eq!("4,far".parse::<LodBands>(), Err(()));
+    }
+
+    /// Tests that changing the LOD bands reports LOD changes for loaded chunks.
+    #[test]
+    fn set_lod_bands() {
+        let mut cl = ChunkLoader::new(3, 0, 1);
+        cl.update(Point3::new(0.0, 0.0, 0.0));
+
+        cl.set_lod_bands(LodBands::new(vec![2]).unwrap());
+        let mut events = cl.update(Point3::new(0.0, 0.0, 0.0));
+        events.sort();
+        assert_eq!(events, vec![
+            ChunkEvent::LodChange { pos: ChunkPos { x: -3, y: 0, z: 0 }, lod: 4 },
+            ChunkEvent::LodChange { pos: ChunkPos { x: 0, y: 0, z: -3 }, lod: 4 },
+            ChunkEvent::LodChange { pos: ChunkPos { x: 0, y: 0, z: 3 }, lod: 4 },
+            ChunkEvent::LodChange { pos: ChunkPos { x: 3, y: 0, z: 0 }, lod: 4 },
+        ]);
+    }
+
+    /// Tests that queued chunks in front of the camera are taken before equidistant chunks behind it, and that turning
+    /// the camera changes the order.
+    #[test]
+    fn load_queue() {
+        let mut camera = Camera::new(72.0, 1.0, 0.01, 1024.0);
+        camera.position = Point3::new(16.0, 16.0, 16.0);
+        camera.forward = Vector3::new(1.0, 0.0, 0.0);
+
+        let mut queue = LoadQueue::new();
+        queue.push(ChunkPos::new(-2, 0, 0), 5);
+        queue.push(ChunkPos::new(2, 0, 0), 4);
+        queue.push(ChunkPos::new(0, 0, 4), 3);
+        queue.set_lod(&ChunkPos::new(2, 0, 0), 5);
+        assert_eq!(queue.len(), 3);
+
+        assert!(queue.pop(&camera, 0).is_empty());
+        assert_eq!(queue.pop(&camera, 1), vec![(ChunkPos::new(2, 0, 0), 5)]);
+
+        camera.forward = Vector3::new(-1.0, 0.0, 0.0);
+        queue.push(ChunkPos::new(2, 0, 0), 5);
+        assert_eq!(queue.pop(&camera, 2), vec![(ChunkPos::new(-2, 0, 0), 5), (ChunkPos::new(2, 0, 0), 5)]);
+
+        assert!(queue.remove(&ChunkPos::new(0, 0, 4)));
+        assert!(!queue.remove(&ChunkPos::new(0, 0, 4)));
+        assert!(queue.is_empty());
+    }
+
     fn get_lod_scale_on_x_axis(events: &Vec<ChunkEvent>, z: i32) -> Vec<u8> {
         let mut columns = HashMap::new();
 
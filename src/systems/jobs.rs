@@ -59,6 +59,20 @@ impl JobSystem {
         system
     }
 
+    /// Returns the number of workers for the requested count. 0 selects one worker per available core, except for one
+    /// core that is left for the main thread. At least one worker is always returned.
+    pub fn resolve_worker_count(requested: usize) -> usize {
+        if requested > 0 {
+            return requested;
+        }
+        num_cpus::get().saturating_sub(1).max(1)
+    }
+
+    /// Returns the number of worker threads.
+    pub fn worker_count(&self) -> usize {
+        self.worker_handles.len()
+    }
+
     /// Signals all worker threads to stop and joins them. Currently processed jobs are not
     /// cancelled and will cause this method to block.
     pub fn stop(self) {
@@ -173,9 +187,9 @@ impl JobSystem {
 #[cfg(test)]
 mod job_system_tests {
     use std::sync::{Arc, Mutex};
-    use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+    use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
     use std::thread;
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
     use crate::systems::jobs::JobSystem;
 
@@ -281,6 +295,38 @@ mod job_system_tests {
         let list = Arc::try_unwrap(list).unwrap().into_inner().unwrap();
         assert_eq!(list, vec!["normal"]);
     }
+
+    /// Tests that the configured number of workers is spawned and that they process jobs concurrently.
+    #[test]
+    fn worker_count() {
+        assert!(JobSystem::resolve_worker_count(0) >= 1);
+        assert_eq!(JobSystem::resolve_worker_count(5), 5);
+
+        let js = JobSystem::new(3);
+        assert_eq!(js.worker_count(), 3);
+
+        // every job waits until all jobs are started, which is only possible with three workers
+        let started = Arc::new(AtomicUsize::new(0));
+        let concurrent = Arc::new(AtomicUsize::new(0));
+        for _ in 0..3 {
+            let started = started.clone();
+            let concurrent = concurrent.clone();
+            js.push(false, move || {
+                started.fetch_add(1, Ordering::Relaxed);
+                let deadline = Instant::now() + Duration::from_secs(2);
+                while started.load(Ordering::Relaxed) < 3 && Instant::now() < deadline {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                if started.load(Ordering::Relaxed) == 3 {
+                    concurrent.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+
+        js.wait_until_empty_and_processed();
+        js.stop();
+        assert_eq!(concurrent.load(Ordering::Relaxed), 3);
+    }
 }
 
 /// `ChunkProcessor` is a decorator for [`JobSystem`]. It allows de-/queueing jobs per [`ChunkPos`].
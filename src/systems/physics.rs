@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::str::FromStr;
 
 use cgmath::{InnerSpace, Point3, Vector3};
 
@@ -25,6 +26,17 @@ impl Entity {
             self.euler_rotation.y.sin() * self.euler_rotation.x.cos(),
         ).normalize()
     }
+
+    /// Rotates the entity to look at `target`. Roll (`euler_rotation.z`) is kept.
+    pub fn look_at(&mut self, target: Point3<f32>) {
+        let dir = target - self.position;
+        if dir.magnitude2() == 0.0 {
+            return;
+        }
+        let dir = dir.normalize();
+        self.euler_rotation.x = dir.y.clamp(-1.0, 1.0).asin();
+        self.euler_rotation.y = dir.z.atan2(dir.x);
+    }
 }
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
@@ -56,6 +68,28 @@ impl Default for EntityCapabilities {
     }
 }
 
+/// `PhysicsMode` selects how an entity is moved by [`Physics`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum PhysicsMode {
+    /// The entity flies freely through the world without gravity or collisions.
+    #[default]
+    FreeFly,
+    /// The entity is pulled down by gravity and collides with the world.
+    Walk,
+}
+
+impl FromStr for PhysicsMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fly" => Ok(Self::FreeFly),
+            "walk" => Ok(Self::Walk),
+            _ => Err(()),
+        }
+    }
+}
+
 impl Entity {
     pub fn new(position: Point3<f32>, aabb_def: AABBDef) -> Self {
         Self {
@@ -71,6 +105,14 @@ impl Entity {
     pub fn get_state(&self) -> &EntityState {
         &self.state
     }
+
+    pub fn physics_mode(&self) -> PhysicsMode {
+        if self.caps.flying { PhysicsMode::FreeFly } else { PhysicsMode::Walk }
+    }
+
+    pub fn set_physics_mode(&mut self, mode: PhysicsMode) {
+        self.caps.flying = mode == PhysicsMode::FreeFly;
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -217,10 +259,10 @@ impl EntityBatch {
 mod tests {
     use std::vec;
 
-    use cgmath::{Point3, Vector3, Zero};
+    use cgmath::{InnerSpace, Point3, Vector3, Zero};
 
     use crate::graphics::svo_picker::{Aabb, AabbResult, PickerBatch, PickerBatchResult};
-    use crate::systems::physics::{AABBDef, Entity, EntityCapabilities, EntityState, Physics, Raycaster};
+    use crate::systems::physics::{AABBDef, Entity, EntityCapabilities, EntityState, Physics, PhysicsMode, Raycaster};
 
     struct MockRaycaster {
         call: Option<(PickerBatch, Box<dyn Fn(&mut PickerBatchResult) + 'static>)>,
@@ -270,7 +312,7 @@ mod tests {
         expected_batch.aabbs.push(Aabb::new(e.position, e.aabb_def.offset, e.aabb_def.extents));
 
         let mut mock = MockRaycaster::new();
-        mock.on(expected_batch, |dst| *dst = PickerBatchResult { rays: Vec::new(), aabbs: vec![AabbResult::default()] });
+        mock.on(expected_batch, |dst| *dst = PickerBatchResult { rays: Vec::new(), multi_rays: Vec::new(), aabbs: vec![AabbResult::default()], capsules: Vec::new(), nearest_surfaces: Vec::new() });
 
         let physics = Physics::new();
         physics.step(1.0, &mock, &mut e);
@@ -483,7 +525,7 @@ mod tests {
         }
 
         let mut mock = MockRaycaster::new();
-        mock.on(expected_batch, move |dst| *dst = PickerBatchResult { rays: Vec::new(), aabbs: aabb_results.clone() });
+        mock.on(expected_batch, move |dst| *dst = PickerBatchResult { rays: Vec::new(), multi_rays: Vec::new(), aabbs: aabb_results.clone(), capsules: Vec::new(), nearest_surfaces: Vec::new() });
 
         let physics = Physics::new();
         physics.step_many(1.0, &mock, &mut entities);
@@ -491,4 +533,51 @@ mod tests {
             assert_eq!(expected, &entities[i], "entity case '{}'", &test_cases[i].name);
         }
     }
+
+    /// Tests that walking entities fall onto a floor at y=0 and are stopped by walls, while flying entities pass
+    /// through both.
+    #[test]
+    fn physics_mode_floor_collision() {
+        // distances to a floor at y=0 and a wall at x=1
+        let query = |e: &Entity| AabbResult {
+            neg: Vector3::new(-1.0, e.position.y, -1.0),
+            pos: Vector3::new(1.0 - e.position.x, -1.0, -1.0),
+        };
+
+        let mut e = Entity::new(Point3::new(0.0, 2.0, 0.0), AABBDef::new(Vector3::zero(), Vector3::new(0.5, 0.5, 0.5)));
+        e.set_physics_mode(PhysicsMode::Walk);
+        assert_eq!(e.physics_mode(), PhysicsMode::Walk);
+        for _ in 0..60 {
+            e.velocity.x = 3.0;
+            let result = query(&e);
+            Physics::update_entity(&mut e, &result, 1.0 / 60.0);
+        }
+        assert!(e.position.y >= 0.0 && e.position.y < 0.01, "{:?}", e.position);
+        assert!(e.position.x <= 1.0 && e.position.x > 0.99, "{:?}", e.position);
+        assert_eq!(e.velocity, Vector3::new(3.0, 0.0, 0.0));
+        assert!(e.get_state().is_grounded);
+
+        e.set_physics_mode(PhysicsMode::FreeFly);
+        assert_eq!(e.physics_mode(), PhysicsMode::FreeFly);
+        e.velocity = Vector3::new(1.0, -1.0, 0.0);
+        let result = query(&e);
+        Physics::update_entity(&mut e, &result, 1.0);
+        assert!(e.position.y < 0.0 && e.position.x > 1.0, "{:?}", e.position);
+        assert!(!e.get_state().is_grounded);
+
+        assert_eq!("walk".parse(), Ok(PhysicsMode::Walk));
+        assert_eq!("fly".parse(), Ok(PhysicsMode::FreeFly));
+        assert_eq!("swim".parse::<PhysicsMode>(), Err(()));
+    }
+
+    /// Tests that the forward vector points at the target after calling `look_at`.
+    #[test]
+    fn look_at() {
+        let mut e = Entity::new(Point3::new(1.0, 1.0, 1.0), AABBDef::new(Vector3::zero(), Vector3::new(1.0, 1.0, 1.0)));
+        for target in [Point3::new(5.0, 1.0, 1.0), Point3::new(1.0, 1.0, -3.0), Point3::new(-2.0, 5.0, 4.0), Point3::new(1.0, -3.0, 1.5)] {
+            e.look_at(target);
+            let expected = (target - e.position).normalize();
+            assert!((e.get_forward() - expected).magnitude() < 1e-5, "{target:?}: {:?} != {expected:?}", e.get_forward());
+        }
+    }
 }
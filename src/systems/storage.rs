@@ -1,31 +1,349 @@
-use crate::world::chunk::{Chunk, ChunkPos};
+use std::collections::hash_map::Entry;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-pub struct Storage {}
+use rustc_hash::FxHashMap;
 
+use crate::world::chunk::{BlockId, Chunk, ChunkPos, ChunkStorageAllocator, NO_BLOCK};
+
+/// `Storage` caches generated chunks on disk, so that they do not have to be generated again on the next launch.
+///
+/// Chunks are grouped into region files of `REGION_SIZE`^3 chunks. Every region file starts with a magic number and a
+/// format version, followed by chunk entries. New versions of a chunk are appended to the file and supersede older
+/// entries. Region files of an unknown version are replaced on the next store.
+///
+/// Without a directory, nothing is stored and all loads fail with [`LoadError::NotFound`].
+///
+/// A storage can be shared between worker threads. Stores hold the region index lock while appending, so that
+/// concurrent stores do not interleave their entries.
+pub struct Storage {
+    dir: Option<PathBuf>,
+    /// `regions` maps region positions to the file offsets of their latest chunk entries. Regions are indexed on first
+    /// access.
+    regions: Mutex<FxHashMap<RegionPos, FxHashMap<ChunkPos, u64>>>,
+}
+
+type RegionPos = (i32, i32, i32);
+
+#[derive(Debug)]
 pub enum LoadError {
     NotFound,
+    Io(io::Error),
+    /// `InvalidData` is returned for region files of an unknown format or version, and for corrupted chunk entries.
+    InvalidData,
 }
 
-pub enum StoreError {}
+impl From<io::Error> for LoadError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
 
-// TODO should storage return normal chunk objects or just their storage? right now chunks also include
-//      lod data and potentially other values in the future which are outside the domain of the storage
-//      system.
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+}
 
-// TODO should storage system reference count chunks and automatically free & store them once they are
-//      unused? or should other components return their chunks back to the storage layer instead?
+impl From<io::Error> for StoreError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+const MAGIC: &[u8; 4] = b"VXRG";
+const VERSION: u32 = 1;
+const FILE_HEADER_LEN: u64 = 8;
+/// `ENTRY_HEADER_LEN` is the size of the chunk position and the payload length preceding every chunk entry.
+const ENTRY_HEADER_LEN: u64 = 16;
+const REGION_SIZE: i32 = 8;
 
-#[allow(clippy::pedantic)]
 impl Storage {
+    /// Creates a storage that does not store anything.
     pub fn new() -> Self {
-        Self {}
+        Self {
+            dir: None,
+            regions: Mutex::new(FxHashMap::default()),
+        }
+    }
+
+    /// Creates a storage that keeps its region files in `dir`. The directory is created on the first store.
+    pub fn with_dir<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: Some(dir.into()),
+            regions: Mutex::new(FxHashMap::default()),
+        }
     }
 
-    pub fn load(&mut self, _pos: &ChunkPos) -> Result<Chunk, LoadError> {
-        Err(LoadError::NotFound)
+    /// Loads the latest stored version of the chunk at `pos`, using `alloc` for the chunk's storage. The chunk has full
+    /// level of detail. Chunks that were stored with a different size than the allocator's are rejected as invalid.
+    pub fn load(&self, pos: &ChunkPos, alloc: &ChunkStorageAllocator) -> Result<Chunk, LoadError> {
+        let Some(dir) = &self.dir else {
+            return Err(LoadError::NotFound);
+        };
+        let region = Self::region_pos(pos);
+        let path = Self::region_path(dir, region);
+
+        let offset = {
+            let mut regions = self.regions.lock().unwrap();
+            if let Entry::Vacant(entry) = regions.entry(region) {
+                entry.insert(Self::read_index(&path)?);
+            }
+            regions[&region].get(pos).copied()
+        };
+        let Some(offset) = offset else {
+            return Err(LoadError::NotFound);
+        };
+
+        let mut file = BufReader::new(File::open(&path)?);
+        file.seek(SeekFrom::Start(offset + ENTRY_HEADER_LEN - 4))?;
+        let mut payload = vec![0; read_u32(&mut file)? as usize];
+        file.read_exact(&mut payload)?;
+        let size = 1 << alloc.depth();
+        let blocks = decode_blocks(&payload, size).ok_or(LoadError::InvalidData)?;
+
+        let mut chunk = Chunk::new(*pos, alloc.depth(), alloc.allocate());
+        chunk.fill_with(|x, y, z| {
+            let block = blocks[block_index(x, y, z, size)];
+            (block != NO_BLOCK).then_some(block)
+        });
+        Ok(chunk)
     }
 
-    pub fn store(&mut self, _chunk: &Chunk) -> Result<(), StoreError> {
+    /// Appends the chunk to its region file. Chunks without storage are skipped.
+    pub fn store(&self, chunk: &Chunk) -> Result<(), StoreError> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        if chunk.storage.is_none() {
+            return Ok(());
+        }
+        fs::create_dir_all(dir)?;
+
+        let region = Self::region_pos(&chunk.pos);
+        let path = Self::region_path(dir, region);
+        let mut regions = self.regions.lock().unwrap();
+        if let Entry::Vacant(entry) = regions.entry(region) {
+            let index = match Self::read_index(&path) {
+                Ok(index) => index,
+                Err(LoadError::InvalidData) => {
+                    // discard regions of an unknown version, they are replaced below
+                    fs::remove_file(&path)?;
+                    FxHashMap::default()
+                }
+                Err(LoadError::Io(err)) => return Err(err.into()),
+                Err(LoadError::NotFound) => unreachable!(),
+            };
+            entry.insert(index);
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let mut offset = file.metadata()?.len();
+        let mut file = BufWriter::new(file);
+        if offset == 0 {
+            file.write_all(MAGIC)?;
+            file.write_all(&VERSION.to_le_bytes())?;
+            offset = FILE_HEADER_LEN;
+        }
+
+        let payload = encode_blocks(chunk);
+        for v in [chunk.pos.x, chunk.pos.y, chunk.pos.z] {
+            file.write_all(&v.to_le_bytes())?;
+        }
+        file.write_all(&(payload.len() as u32).to_le_bytes())?;
+        file.write_all(&payload)?;
+        file.flush()?;
+
+        regions.get_mut(&region).unwrap().insert(chunk.pos, offset);
         Ok(())
     }
+
+    fn region_pos(pos: &ChunkPos) -> RegionPos {
+        (pos.x.div_euclid(REGION_SIZE), pos.y.div_euclid(REGION_SIZE), pos.z.div_euclid(REGION_SIZE))
+    }
+
+    fn region_path(dir: &Path, region: RegionPos) -> PathBuf {
+        dir.join(format!("r.{}.{}.{}.bin", region.0, region.1, region.2))
+    }
+
+    /// Returns the offsets of the latest entry of every chunk in the region file. Missing files are treated as empty.
+    /// A partially written entry at the end of the file is ignored.
+    fn read_index(path: &Path) -> Result<FxHashMap<ChunkPos, u64>, LoadError> {
+        let mut index = FxHashMap::default();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(index),
+            Err(err) => return Err(err.into()),
+        };
+        let file_len = file.metadata()?.len();
+        let mut file = BufReader::new(file);
+
+        let mut magic = [0; 4];
+        if file.read_exact(&mut magic).is_err() || &magic != MAGIC || read_u32(&mut file).ok() != Some(VERSION) {
+            return Err(LoadError::InvalidData);
+        }
+
+        let mut offset = FILE_HEADER_LEN;
+        while offset + ENTRY_HEADER_LEN <= file_len {
+            let pos = ChunkPos::new(read_i32(&mut file)?, read_i32(&mut file)?, read_i32(&mut file)?);
+            let len = u64::from(read_u32(&mut file)?);
+            if offset + ENTRY_HEADER_LEN + len > file_len {
+                break;
+            }
+            index.insert(pos, offset);
+            file.seek_relative(len as i64)?;
+            offset += ENTRY_HEADER_LEN + len;
+        }
+        Ok(index)
+    }
+}
+
+fn block_index(x: u32, y: u32, z: u32, size: u32) -> usize {
+    ((z * size + y) * size + x) as usize
+}
+
+/// Encodes all blocks of the chunk as runs of equal blocks, each consisting of the run length and the block id.
+fn encode_blocks(chunk: &Chunk) -> Vec<u8> {
+    let mut runs: Vec<(u32, BlockId)> = Vec::new();
+    let size = chunk.size();
+    for z in 0..size {
+        for y in 0..size {
+            for x in 0..size {
+                let block = chunk.get_block(x, y, z);
+                match runs.last_mut() {
+                    Some((count, last)) if *last == block => *count += 1,
+                    _ => runs.push((1, block)),
+                }
+            }
+        }
+    }
+    runs.iter().flat_map(|(count, block)| [count.to_le_bytes(), block.to_le_bytes()]).flatten().collect()
+}
+
+/// Decodes blocks encoded by [`encode_blocks`]. Returns `None`, if the runs do not cover a chunk with edge length `size`
+/// exactly.
+fn decode_blocks(payload: &[u8], size: u32) -> Option<Vec<BlockId>> {
+    if payload.len() % 8 != 0 {
+        return None;
+    }
+    let block_count = (size * size * size) as usize;
+    let mut blocks = Vec::with_capacity(block_count);
+    for run in payload.chunks_exact(8) {
+        let count = u32::from_le_bytes(run[0..4].try_into().unwrap()) as usize;
+        let block = BlockId::from_le_bytes(run[4..8].try_into().unwrap());
+        if blocks.len() + count > block_count {
+            return None;
+        }
+        blocks.resize(blocks.len() + count, block);
+    }
+    (blocks.len() == block_count).then_some(blocks)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_i32<R: Read>(reader: &mut R) -> io::Result<i32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(i32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, thread};
+
+    use crate::systems::storage::{LoadError, Storage};
+    use crate::world::chunk::{Chunk, ChunkPos, ChunkStorageAllocator};
+
+    fn new_chunk(alloc: &ChunkStorageAllocator, pos: ChunkPos, seed: u32) -> Chunk {
+        let mut chunk = Chunk::new(pos, 5, alloc.allocate());
+        chunk.fill_with(|x, y, z| (y < 10 || (x * 7 + z * 3 + seed) % 11 == 0).then_some(1 + (x + y + z + seed) % 4));
+        chunk
+    }
+
+    fn assert_chunk_eq(actual: &Chunk, expected: &Chunk) {
+        assert_eq!(actual.pos, expected.pos);
+        for z in 0..Chunk::SIZE {
+            for y in 0..Chunk::SIZE {
+                for x in 0..Chunk::SIZE {
+                    assert_eq!(actual.get_block(x, y, z), expected.get_block(x, y, z), "block at {x} {y} {z}");
+                }
+            }
+        }
+    }
+
+    /// Tests that stored chunks are loaded with identical blocks by a new storage instance, and that newer versions of
+    /// a chunk supersede older ones.
+    #[test]
+    fn store_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let alloc = ChunkStorageAllocator::new();
+
+        let storage = Storage::with_dir(dir.path());
+        let first = new_chunk(&alloc, ChunkPos::new(1, -2, 3), 0);
+        let second = new_chunk(&alloc, ChunkPos::new(2, -2, 3), 1);
+        let updated = new_chunk(&alloc, ChunkPos::new(1, -2, 3), 2);
+        storage.store(&first).unwrap();
+        storage.store(&second).unwrap();
+        assert_chunk_eq(&storage.load(&first.pos, &alloc).unwrap(), &first);
+        storage.store(&updated).unwrap();
+
+        let storage = Storage::with_dir(dir.path());
+        assert_chunk_eq(&storage.load(&updated.pos, &alloc).unwrap(), &updated);
+        assert_chunk_eq(&storage.load(&second.pos, &alloc).unwrap(), &second);
+        assert!(matches!(storage.load(&ChunkPos::new(3, -2, 3), &alloc), Err(LoadError::NotFound)));
+        assert!(matches!(storage.load(&ChunkPos::new(-100, 0, 0), &alloc), Err(LoadError::NotFound)));
+    }
+
+    /// Tests that region files of an unknown version are rejected on load and replaced on store, and that a storage
+    /// without directory does not store anything.
+    #[test]
+    fn invalid_region() {
+        let dir = tempfile::tempdir().unwrap();
+        let alloc = ChunkStorageAllocator::new();
+        let chunk = new_chunk(&alloc, ChunkPos::new(0, 0, 0), 0);
+        fs::write(dir.path().join("r.0.0.0.bin"), b"VXRG\x00\x00\x00\x00").unwrap();
+
+        let storage = Storage::with_dir(dir.path());
+        assert!(matches!(storage.load(&chunk.pos, &alloc), Err(LoadError::InvalidData)));
+        storage.store(&chunk).unwrap();
+        assert_chunk_eq(&Storage::with_dir(dir.path()).load(&chunk.pos, &alloc).unwrap(), &chunk);
+
+        let storage = Storage::new();
+        storage.store(&chunk).unwrap();
+        assert!(matches!(storage.load(&chunk.pos, &alloc), Err(LoadError::NotFound)));
+    }
+
+    /// Tests that chunks stored concurrently into the same region file from multiple threads can all be loaded again.
+    #[test]
+    fn store_concurrently() {
+        let dir = tempfile::tempdir().unwrap();
+        let alloc = ChunkStorageAllocator::new();
+        let storage = Storage::with_dir(dir.path());
+
+        let chunks = (0..4)
+            .map(|i| (0..4).map(|j| new_chunk(&alloc, ChunkPos::new(i, j, 0), (i * 4 + j) as u32)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        thread::scope(|scope| {
+            for chunks in &chunks {
+                let storage = &storage;
+                scope.spawn(move || {
+                    for chunk in chunks {
+                        storage.store(chunk).unwrap();
+                    }
+                });
+            }
+        });
+
+        let storage = Storage::with_dir(dir.path());
+        for chunk in chunks.iter().flatten() {
+            assert_chunk_eq(&storage.load(&chunk.pos, &alloc).unwrap(), chunk);
+        }
+    }
 }
@@ -2,6 +2,7 @@ use std::rc::Rc;
 use std::sync::Arc;
 
 use crate::systems::jobs::{ChunkProcessor, JobSystem};
+use crate::systems::storage::{LoadError, Storage};
 use crate::world::chunk::{Chunk, ChunkPos, ChunkStorageAllocator};
 
 pub trait ChunkGenerator {
@@ -16,6 +17,7 @@ pub trait ChunkGenerator {
 pub struct Generator {
     processor: ChunkProcessor<Option<Chunk>>,
     storage_allocator: Arc<ChunkStorageAllocator>,
+    storage: Arc<Storage>,
     gen: Arc<dyn ChunkGenerator + Send + Sync + 'static>,
 }
 
@@ -24,18 +26,35 @@ impl Generator {
         Self {
             processor: ChunkProcessor::new(job_system),
             storage_allocator,
+            storage: Arc::new(Storage::new()),
             gen: Arc::new(chunk_generator),
         }
     }
 
-    /// Enqueues a position for a chunk to be generated. The `lod` will be kept for the generated
-    /// chunk. If the underlying chunk generator does not indicate interest in the chunk, no chunk
-    /// will be allocated! Use `get_generated_chunks` to retrieve generated chunks.
+    /// Sets the storage from which chunks are loaded, if possible, instead of generating them. Generated chunks are
+    /// stored in it. Chunks that are already enqueued keep using the previous storage.
+    pub fn set_storage(&mut self, storage: Arc<Storage>) {
+        self.storage = storage;
+    }
+
+    /// Enqueues a position for a chunk to be loaded from storage or generated. The `lod` will be kept for the
+    /// chunk. If the chunk is not stored and the underlying chunk generator does not indicate interest in the chunk,
+    /// no chunk will be allocated! Use `get_generated_chunks` to retrieve generated chunks.
     pub fn enqueue_chunk(&mut self, pos: ChunkPos, lod: u8) {
         let alloc = self.storage_allocator.clone();
+        let storage = Arc::clone(&self.storage);
         let gen = Arc::clone(&self.gen);
 
         self.processor.enqueue(pos, false, move || {
+            match storage.load(&pos, &alloc) {
+                Ok(mut chunk) => {
+                    chunk.lod = lod;
+                    return Some(chunk);
+                }
+                Err(LoadError::NotFound) => {}
+                Err(err) => println!("error loading chunk {pos:?}, generating it instead: {err:?}"),
+            }
+
             if !gen.is_interested_in(&pos) {
                 return None;
             }
@@ -43,6 +62,9 @@ impl Generator {
             let mut chunk = Chunk::new(pos, lod, alloc.allocate());
             gen.generate_chunk(&mut chunk);
 
+            if let Err(err) = storage.store(&chunk) {
+                println!("error storing chunk {pos:?}: {err:?}");
+            }
             Some(chunk)
         });
     }
@@ -1,4 +1,6 @@
 use std::alloc::Allocator;
+use std::cell::Ref;
+use std::collections::VecDeque;
 use std::rc::Rc;
 use std::sync::Arc;
 
@@ -8,15 +10,115 @@ use rustc_hash::{FxHashMap, FxHashSet};
 use crate::graphics;
 use crate::graphics::framebuffer::Framebuffer;
 use crate::graphics::svo_picker::{PickerBatch, PickerBatchResult};
-use crate::systems::jobs::{ChunkProcessor, ChunkResult, JobSystem};
+use crate::graphics::svo_picker_system::CompletedBatch;
+use crate::systems::jobs::{ChunkProcessor, JobSystem};
 use crate::systems::physics::Raycaster;
 use crate::world;
-use crate::world::chunk::{BlockPos, ChunkPos};
+use crate::world::chunk::{BlockPos, Chunk, ChunkPos};
 use crate::world::memory::{AllocatorStats, Pool, StatsAllocator};
 use crate::world::octree::LeafId;
 use crate::world::svo::{ChunkBuffer, ChunkBufferPool, SerializedChunk, SvoSerializable};
 use crate::world::world::BorrowedChunk;
 
+/// `SerializationQueue` serializes [`BorrowedChunk`]s into [`SerializedChunk`]s on the worker threads of a
+/// [`JobSystem`]. The number of workers is determined by the job system. Finished chunks are sent back through a
+/// channel and have to be drained regularly, e.g. once per frame, using [`SerializationQueue::get_results`]. Their
+/// borrowed chunks have to be returned to the world afterwards.
+pub struct SerializationQueue {
+    processor: ChunkProcessor<SerializedChunk>,
+    chunk_buffer_pool: Arc<ChunkBufferPool>,
+    /// If true, serialized chunks are kept run-length encoded until they are added to the SVO. See
+    /// [`SerializedChunk::with_compression`].
+    compress: bool,
+}
+
+impl SerializationQueue {
+    pub fn new(job_system: Rc<JobSystem>, chunk_buffer_pool: Arc<ChunkBufferPool>) -> Self {
+        Self {
+            processor: ChunkProcessor::new(job_system),
+            chunk_buffer_pool,
+            compress: false,
+        }
+    }
+
+    /// Enables run-length encoding for all chunks enqueued afterwards.
+    pub fn set_compression(&mut self, compress: bool) {
+        self.compress = compress;
+    }
+
+    pub const fn get_compression(&self) -> bool {
+        self.compress
+    }
+
+    /// Enqueues the chunk for serialization. A chunk that was previously enqueued for the same position and is not
+    /// yet processed is discarded.
+    pub fn enqueue(&mut self, chunk: BorrowedChunk) {
+        let alloc = self.chunk_buffer_pool.clone();
+        let compress = self.compress;
+        self.processor.enqueue(chunk.pos, true, move || SerializedChunk::with_compression(chunk, &alloc, compress));
+    }
+
+    /// See [`ChunkProcessor::dequeue`].
+    pub fn dequeue(&mut self, pos: &ChunkPos) {
+        self.processor.dequeue(pos);
+    }
+
+    /// Returns up to `limit` serialized chunks without blocking.
+    pub fn get_results(&mut self, limit: u32) -> Vec<SerializedChunk> {
+        self.processor.get_results(limit).into_iter().map(|result| result.value).collect()
+    }
+
+    /// Returns true if there are chunks that are still being serialized or were not yet retrieved.
+    pub fn has_pending(&self) -> bool {
+        self.processor.has_pending()
+    }
+}
+
+#[cfg(test)]
+mod serialization_queue_tests {
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::systems::jobs::JobSystem;
+    use crate::systems::worldsvo::SerializationQueue;
+    use crate::world::chunk::{Chunk, ChunkPos, ChunkStorageAllocator};
+    use crate::world::memory::{Pool, StatsAllocator};
+    use crate::world::svo::ChunkBuffer;
+    use crate::world::world::BorrowedChunk;
+
+    /// Tests that all enqueued chunks are serialized by the worker threads and are returned with their data.
+    #[test]
+    fn enqueue_and_get_results() {
+        let js = Rc::new(JobSystem::new(4));
+        let alloc = Arc::new(Pool::new_in(Box::new(ChunkBuffer::new_in), None, StatsAllocator::new()));
+        let mut queue = SerializationQueue::new(js.clone(), alloc);
+
+        let storage_alloc = ChunkStorageAllocator::new();
+        for i in 0..16 {
+            let mut chunk = Chunk::new(ChunkPos::new(i, 0, 0), 5, storage_alloc.allocate());
+            chunk.set_block(0, 0, 0, 1);
+            queue.enqueue(BorrowedChunk::from(chunk));
+        }
+        js.wait_until_empty_and_processed();
+
+        let mut results = Vec::new();
+        while results.len() < 16 {
+            results.extend(queue.get_results(100));
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!queue.has_pending());
+
+        results.sort_by_key(|chunk| chunk.pos.x);
+        for (i, chunk) in results.iter().enumerate() {
+            assert_eq!(chunk.pos, ChunkPos::new(i as i32, 0, 0));
+            assert!(chunk.has_data());
+            assert!(chunk.borrowed_chunk.is_some());
+        }
+    }
+}
+
 /// Svo takes ownership of a [`graphics::Svo`] and populates it with world [`world::chunk::Chunk`]s.
 /// Adding chunks will serialize them in the background and attach them the GPU SVO. Removing
 /// chunks will also remove them from the GPU.
@@ -28,7 +130,7 @@ use crate::world::world::BorrowedChunk;
 /// inside the center chunk of the SVO and shifting all chunks in the opposite movement direction
 /// if the camera leaves the chunk.
 pub struct Svo {
-    processor: ChunkProcessor<SerializedChunk>,
+    queue: SerializationQueue,
 
     world_svo_alloc: StatsAllocator,
     world_svo: world::Svo<SerializedChunk, StatsAllocator>,
@@ -37,10 +139,19 @@ pub struct Svo {
     chunk_buffer_pool: Arc<ChunkBufferPool>,
 
     leaf_ids: FxHashMap<ChunkPos, LeafId>,
+    /// Chunks that were evicted from the world SVO to stay within its memory budget.
+    evicted_chunks: Vec<ChunkPos>,
     has_changed: bool,
     svo_coord_space: SvoCoordSpace,
+    /// Coordinate spaces of the last asynchronous raycasts, newest last. Their results are converted back into world
+    /// space with the coordinate space at the time they were dispatched, as chunks might have been shifted since.
+    async_coord_spaces: VecDeque<SvoCoordSpace>,
 }
 
+/// `UnsupportedChunkSize` is returned by [`Svo::set_chunk`] for chunks that do not have the default size. It hands the
+/// chunk back, so that it can be returned to the world.
+pub struct UnsupportedChunkSize(pub BorrowedChunk);
+
 pub struct AllocStats {
     pub chunk_buffers_used: usize,
     pub chunk_buffers_allocated: usize,
@@ -49,8 +160,14 @@ pub struct AllocStats {
 }
 
 impl Svo {
+    /// `MAX_IDLE_CHUNK_BUFFERS` limits how many returned chunk buffers are kept for reuse while chunks are loaded.
+    const MAX_IDLE_CHUNK_BUFFERS: usize = 64;
+    /// `RELOAD_BUDGET_PERCENT` is the share of the memory budget, below which evicted chunks can be loaded again.
+    /// Keeping a gap to the budget avoids evicting the reloaded chunks right away.
+    const RELOAD_BUDGET_PERCENT: usize = 75;
+
     pub fn new(job_system: Rc<JobSystem>, graphics_svo: graphics::Svo, render_distance: u32) -> Self {
-        let world_svo_alloc = StatsAllocator::new();
+        let world_svo_alloc = StatsAllocator::labeled("world svo");
 
         let chunk_buffer_pool = Pool::new_in(
             // It is difficult to pre-allocate memory here as chunk sizes are random/depend heavily on the world generation
@@ -59,32 +176,53 @@ impl Svo {
             // times until it fits. This is still more stable than and safes a lot of allocations.
             Box::new(|alloc| ChunkBuffer::with_capacity_in(100_000, alloc)),
             Some(Box::new(ChunkBuffer::reset)),
-            StatsAllocator::new(),
-        );
+            StatsAllocator::labeled("chunk buffers"),
+        ).with_max_idle(Self::MAX_IDLE_CHUNK_BUFFERS);
+        // avoid allocation hitches while the initial chunks around the player are serialized
+        chunk_buffer_pool.preallocate(Self::estimate_chunk_buffer_count(render_distance));
+        let chunk_buffer_pool = Arc::new(chunk_buffer_pool);
+        let svo_coord_space = SvoCoordSpace {
+            center: ChunkPos::new(0, 0, 0),
+            dst: render_distance,
+        };
+        let mut world_svo = world::Svo::new_in(world_svo_alloc.clone());
+        world_svo.set_eviction_center(svo_coord_space.center_svo_pos());
         Self {
-            processor: ChunkProcessor::new(job_system),
-            world_svo_alloc: world_svo_alloc.clone(),
-            world_svo: world::Svo::new_in(world_svo_alloc),
+            queue: SerializationQueue::new(job_system, chunk_buffer_pool.clone()),
+            world_svo_alloc,
+            world_svo,
             graphics_svo,
-            chunk_buffer_pool: Arc::new(chunk_buffer_pool),
+            chunk_buffer_pool,
             leaf_ids: FxHashMap::default(),
+            evicted_chunks: Vec::new(),
             has_changed: false,
-            svo_coord_space: SvoCoordSpace {
-                center: ChunkPos::new(0, 0, 0),
-                dst: render_distance,
-            },
+            svo_coord_space,
+            async_coord_spaces: VecDeque::new(),
         }
     }
 
+    /// Estimates the number of chunk buffers that are needed at once, when all chunks within `render_distance` are
+    /// loaded. The estimate is limited to the maximum number of idle buffers.
+    fn estimate_chunk_buffer_count(render_distance: u32) -> usize {
+        let side = 2 * render_distance as usize + 1;
+        (side * side * side).min(Self::MAX_IDLE_CHUNK_BUFFERS)
+    }
+
     /// Enqueues the borrowed chunk to be serialized into the GPU SVO structure. All moved chunk
     /// ownerships can be reclaimed by calling [`Svo::update`].
-    pub fn set_chunk(&mut self, chunk: BorrowedChunk) {
-        let alloc = self.chunk_buffer_pool.clone();
-        self.processor.enqueue(chunk.pos, true, move || SerializedChunk::new(chunk, &alloc));
+    ///
+    /// Chunks that do not have the default size are rejected, as the GPU traversal assumes that all
+    /// leaves of the SVO have the same depth.
+    pub fn set_chunk(&mut self, chunk: BorrowedChunk) -> Result<(), UnsupportedChunkSize> {
+        if chunk.depth() != Chunk::DEPTH {
+            return Err(UnsupportedChunkSize(chunk));
+        }
+        self.queue.enqueue(chunk);
+        Ok(())
     }
 
     pub fn remove_chunk(&mut self, pos: &ChunkPos) {
-        self.processor.dequeue(pos);
+        self.queue.dequeue(pos);
 
         if let Some(id) = self.leaf_ids.remove(pos) {
             self.world_svo.remove_leaf(id);
@@ -94,7 +232,7 @@ impl Svo {
 
     /// Returns if the SVO still has in-work chunks or if there are unconsumed chunks in the buffer.
     pub fn has_pending_jobs(&self) -> bool {
-        self.processor.has_pending()
+        self.queue.has_pending()
     }
 
     pub fn get_render_distance(&self) -> u32 {
@@ -110,6 +248,44 @@ impl Svo {
         }
     }
 
+    /// Drops idle chunk buffers until at most `n` are left. See [`crate::world::memory::Pool::shrink_to`].
+    pub fn shrink_chunk_buffer_pool(&self, n: usize) {
+        self.chunk_buffer_pool.shrink_to(n);
+    }
+
+    /// Limits the memory used by serialized chunks in the world SVO. The least recently serialized chunks are evicted
+    /// first, with the next [`Svo::update`]. See [`world::Svo::set_max_bytes`].
+    pub fn set_max_bytes(&mut self, max_bytes: Option<usize>) {
+        self.world_svo.set_max_bytes(max_bytes);
+        self.has_changed = true;
+    }
+
+    pub fn get_max_bytes(&self) -> Option<usize> {
+        self.world_svo.get_max_bytes()
+    }
+
+    /// Keeps serialized chunks run-length encoded until they are added to the world SVO. See
+    /// [`SerializationQueue::set_compression`].
+    pub fn set_chunk_compression(&mut self, compress: bool) {
+        self.queue.set_compression(compress);
+    }
+
+    pub const fn get_chunk_compression(&self) -> bool {
+        self.queue.get_compression()
+    }
+
+    /// Returns true, if evicted chunks can be set again without exceeding the memory budget right away.
+    pub fn has_budget_for_evicted_chunks(&self) -> bool {
+        self.world_svo.get_max_bytes()
+            .map_or(true, |max_bytes| self.world_svo.leaf_bytes() < max_bytes / 100 * Self::RELOAD_BUDGET_PERCENT)
+    }
+
+    /// Returns the positions of all chunks that were evicted due to the memory budget since the last call. These
+    /// chunks are no longer part of the SVO and have to be set again to become visible.
+    pub fn take_evicted_chunks(&mut self) -> Vec<ChunkPos> {
+        std::mem::take(&mut self.evicted_chunks)
+    }
+
     /// Updates the internal reference world center and performs "chunk shifting", if necessary.
     /// Additionally, it uploads all serialized chunks to the GPU, that have finished since the
     /// last update. Position is in world space.
@@ -121,7 +297,7 @@ impl Svo {
             self.on_coord_space_change();
         }
 
-        let results = self.processor.get_results(400);
+        let results = self.queue.get_results(400);
         let chunks = self.process_serialized_chunks(results);
 
         if !self.has_changed {
@@ -130,6 +306,14 @@ impl Svo {
 
         self.has_changed = false;
         self.world_svo.serialize();
+        for chunk in self.world_svo.take_evicted() {
+            self.leaf_ids.remove(&chunk.pos);
+            self.evicted_chunks.push(chunk.pos);
+        }
+        // reclaim space left behind by unloaded chunks once more than half of the buffer is unused
+        if self.world_svo.free_bytes() > self.world_svo.size_in_bytes() / 2 {
+            self.world_svo.defragment();
+        }
         self.graphics_svo.update(&mut self.world_svo);
 
         chunks
@@ -180,22 +364,23 @@ impl Svo {
         }
     }
 
-    fn process_serialized_chunks(&mut self, results: Vec<ChunkResult<SerializedChunk>>) -> Vec<BorrowedChunk> {
+    fn process_serialized_chunks(&mut self, results: Vec<SerializedChunk>) -> Vec<BorrowedChunk> {
         let mut chunks = Vec::new();
 
         for mut result in results {
-            let chunk = result.value.borrowed_chunk.take().unwrap();
+            let chunk = result.borrowed_chunk.take().unwrap();
             chunks.push(chunk);
 
-            let svo_pos = self.svo_coord_space.cnv_chunk_pos(result.pos);
+            let pos = result.pos;
+            let svo_pos = self.svo_coord_space.cnv_chunk_pos(pos);
             if svo_pos.is_none() {
                 continue;
             }
 
             // NOTE: this moves ownership of the serialized ChunkBuffer into the world svo octree.
             //       If not freed properly, the otherwise pooled objects cannot be reused.
-            let (id, _) = self.world_svo.set_leaf(svo_pos.unwrap(), result.value, true);
-            self.leaf_ids.insert(result.pos, id);
+            let (id, _) = self.world_svo.set_leaf(svo_pos.unwrap(), result, true);
+            self.leaf_ids.insert(pos, id);
             self.has_changed = true;
         }
 
@@ -204,6 +389,7 @@ impl Svo {
 
     pub fn set_radius(&mut self, radius: u32) {
         self.svo_coord_space.dst = radius;
+        self.world_svo.set_eviction_center(self.svo_coord_space.center_svo_pos());
         self.on_coord_space_change();
     }
 }
@@ -211,13 +397,20 @@ impl Svo {
 //noinspection DuplicatedCode
 #[cfg(test)]
 mod svo_tests {
+    use std::rc::Rc;
+
     use rustc_hash::FxHashMap;
 
+    use crate::core::GlContext;
+    use crate::graphics;
+    use crate::graphics::svo_registry::{Material, VoxelRegistry};
+    use crate::systems::jobs::JobSystem;
     use crate::systems::worldsvo::{Svo, SvoCoordSpace};
     use crate::world;
-    use crate::world::chunk::ChunkPos;
+    use crate::world::chunk::{Chunk, ChunkPos, ChunkStorageAllocator};
     use crate::world::octree::Position;
     use crate::world::svo::{SerializationResult, SvoSerializable};
+    use crate::world::world::BorrowedChunk;
 
     impl SvoSerializable for u32 {
         fn unique_id(&self) -> u64 {
@@ -369,6 +562,49 @@ mod svo_tests {
         assert_eq!(world_svo.get_leaf(Position(1, 1, 1)), None);
         assert_eq!(world_svo.get_leaf(Position(2, 1, 1)), None);
     }
+
+    /// Tests that exceeding the memory budget evicts the least recently serialized chunks and that they are reported
+    /// by `take_evicted_chunks`.
+    #[test]
+    fn take_evicted_chunks() {
+        let _context = GlContext::new_headless(1, 1); // do not drop context
+
+        let job_system = Rc::new(JobSystem::new(2));
+        let mut registry = VoxelRegistry::new();
+        registry
+            .add_texture("stone", "assets/textures/stone.png")
+            .add_material(0, Material::new())
+            .add_material(1, Material::new().all_sides("stone"));
+        let mut svo = Svo::new(Rc::clone(&job_system), graphics::Svo::new(&registry), 2);
+        let center = ChunkPos::new(0, 0, 0);
+
+        // every chunk is serialized with its own update, so that the serialization order is known
+        let storage_alloc = ChunkStorageAllocator::new();
+        let positions = [ChunkPos::new(0, 0, 0), ChunkPos::new(1, 0, 0), ChunkPos::new(-1, 0, 0), ChunkPos::new(0, 1, 0)];
+        for pos in positions {
+            let mut chunk = Chunk::new(pos, Chunk::DEPTH, storage_alloc.allocate());
+            chunk.set_block(0, 0, 0, 1);
+            assert!(svo.set_chunk(BorrowedChunk::from(chunk)).is_ok());
+
+            job_system.wait_until_empty_and_processed();
+            while svo.has_pending_jobs() {
+                svo.update(&center);
+            }
+        }
+        assert!(svo.take_evicted_chunks().is_empty());
+
+        // all chunks have the same size, so that the two oldest ones have to go
+        let chunk_bytes = svo.world_svo.leaf_bytes() / positions.len();
+        svo.set_max_bytes(Some(2 * chunk_bytes));
+        svo.update(&center);
+
+        assert_eq!(svo.take_evicted_chunks(), vec![positions[0], positions[1]]);
+        assert_eq!(svo.world_svo.leaf_bytes(), 2 * chunk_bytes);
+        assert!(!svo.leaf_ids.contains_key(&positions[0]));
+        assert!(!svo.leaf_ids.contains_key(&positions[1]));
+        assert!(svo.leaf_ids.contains_key(&positions[2]));
+        assert!(svo.leaf_ids.contains_key(&positions[3]));
+    }
 }
 
 /// Implement "overrides" for [`graphics::Svo`]. All positions are transformed from world space
@@ -379,8 +615,22 @@ impl Svo {
         self.graphics_svo.reload_resources();
     }
 
+    /// Calls [`graphics::Svo::reload_changed_shaders`].
+    pub fn reload_changed_shaders(&mut self) {
+        self.graphics_svo.reload_changed_shaders();
+    }
+
     /// Calls [`graphics::Svo::render`]. Positions are expected to be in world space.
     pub fn render(&self, params: graphics::svo::RenderParams, target: &Framebuffer) {
+        self.graphics_svo.render(&self.cnv_render_params(params), target);
+    }
+
+    /// Calls [`graphics::Svo::render_view`]. Positions are expected to be in world space.
+    pub fn render_view(&self, params: graphics::svo::RenderParams, target: &Framebuffer, pass: &'static str) {
+        self.graphics_svo.render_view(&self.cnv_render_params(params), target, pass);
+    }
+
+    fn cnv_render_params(&self, params: graphics::svo::RenderParams) -> graphics::svo::RenderParams {
         let mut params = params;
 
         // translate camera position into SVO
@@ -390,14 +640,58 @@ impl Svo {
         if let Some(pos) = params.selected_voxel {
             params.selected_voxel = Some(self.svo_coord_space.cnv_block_pos(pos));
         }
-
-        self.graphics_svo.render(&params, target);
+        params
     }
 
     /// Calls [`graphics::Svo::get_stats`].
     pub fn get_stats(&self) -> graphics::svo::Stats {
         self.graphics_svo.get_stats()
     }
+
+    /// Calls [`graphics::Svo::get_gpu_times`].
+    pub fn get_gpu_times(&self) -> Ref<'_, [(&'static str, std::time::Duration)]> {
+        self.graphics_svo.get_gpu_times()
+    }
+
+    /// Calls [`graphics::Svo::raycast_async`]. Positions are expected to be in world space. Results are retrieved with
+    /// [`Svo::poll_raycasts`].
+    pub fn raycast_async(&mut self, batch: PickerBatch) {
+        let mut batch = batch;
+        self.svo_coord_space.cnv_batch_to_svo(&mut batch);
+
+        self.async_coord_spaces.push_back(self.svo_coord_space);
+        while self.async_coord_spaces.len() > self.graphics_svo.get_picker_latency() + 1 {
+            self.async_coord_spaces.pop_front();
+        }
+        self.graphics_svo.raycast_async(batch);
+    }
+
+    /// Calls [`graphics::Svo::poll_raycasts`]. The batch and its results are converted back into world space.
+    pub fn poll_raycasts(&mut self) -> Option<CompletedBatch> {
+        let mut completed = self.graphics_svo.poll_raycasts()?;
+
+        // the latency is the number of raycasts that were dispatched since, including the completed one
+        let index = self.async_coord_spaces.len().checked_sub(completed.latency as usize)?;
+        let coord_space = self.async_coord_spaces[index];
+        coord_space.cnv_batch_to_world(&mut completed.batch);
+        coord_space.cnv_result_to_world(&mut completed.result);
+        Some(completed)
+    }
+
+    pub fn get_picker_latency(&self) -> usize {
+        self.graphics_svo.get_picker_latency()
+    }
+
+    /// Calls [`graphics::Svo::set_picker_latency`].
+    pub fn set_picker_latency(&mut self, latency: usize) {
+        self.graphics_svo.set_picker_latency(latency);
+        self.async_coord_spaces.clear();
+    }
+
+    /// Calls [`graphics::Svo::get_gpu_times_frame`].
+    pub fn get_gpu_times_frame(&self) -> Option<u64> {
+        self.graphics_svo.get_gpu_times_frame()
+    }
 }
 
 /// Implement [`Raycaster`] that calls [`graphics::Svo`] underneath. All positions are transformed
@@ -405,18 +699,9 @@ impl Svo {
 impl Raycaster for Svo {
     /// Calls [`graphics::Svo::raycast`]. Positions are expected to be in world space.
     fn raycast(&self, batch: &mut PickerBatch, result: &mut PickerBatchResult) {
-        for ray in &mut batch.rays {
-            ray.pos = self.svo_coord_space.cnv_block_pos(ray.pos);
-        }
-        for aabb in &mut batch.aabbs {
-            aabb.pos = self.svo_coord_space.cnv_block_pos(aabb.pos);
-        }
-
+        self.svo_coord_space.cnv_batch_to_svo(batch);
         self.graphics_svo.raycast(batch, result);
-
-        for ray in &mut result.rays {
-            ray.pos = self.svo_coord_space.cnv_svo_pos(ray.pos);
-        }
+        self.svo_coord_space.cnv_result_to_world(result);
     }
 }
 
@@ -461,6 +746,49 @@ impl SvoCoordSpace {
         block_pos.to_point()
     }
 
+    /// Converts all positions of the batch from world space to SVO space.
+    fn cnv_batch_to_svo(&self, batch: &mut PickerBatch) {
+        Self::cnv_batch(batch, |pos| self.cnv_block_pos(pos));
+    }
+
+    /// Converts all positions of the batch from SVO space to world space.
+    fn cnv_batch_to_world(&self, batch: &mut PickerBatch) {
+        Self::cnv_batch(batch, |pos| self.cnv_svo_pos(pos));
+    }
+
+    fn cnv_batch<F: Fn(Point3<f32>) -> Point3<f32>>(batch: &mut PickerBatch, cnv: F) {
+        for ray in &mut batch.rays {
+            ray.pos = cnv(ray.pos);
+        }
+        for ray in &mut batch.multi_rays {
+            ray.pos = cnv(ray.pos);
+        }
+        for aabb in &mut batch.aabbs {
+            aabb.pos = cnv(aabb.pos);
+        }
+        for capsule in &mut batch.capsules {
+            capsule.base = cnv(capsule.base);
+        }
+        for query in &mut batch.nearest_surfaces {
+            query.point = cnv(query.point);
+        }
+    }
+
+    /// Converts all hit positions of the result from SVO space to world space.
+    fn cnv_result_to_world(&self, result: &mut PickerBatchResult) {
+        for ray in &mut result.rays {
+            ray.pos = self.cnv_svo_pos(ray.pos);
+        }
+        for ray in result.multi_rays.iter_mut().flatten() {
+            ray.pos = self.cnv_svo_pos(ray.pos);
+        }
+    }
+
+    /// Returns the chunk position in SVO space, at which the center chunk is kept.
+    fn center_svo_pos(&self) -> world::octree::Position {
+        world::octree::Position(self.dst, self.dst, self.dst)
+    }
+
     /// Converts a chunk position from world space to the respective chunk position in SVO space,
     /// if possible. Conversion is not possible if the position is outside the coordinate space's
     /// `dst`.
@@ -469,7 +797,7 @@ impl SvoCoordSpace {
 
         let pos = pos.as_block_pos();
         let pos = self.cnv_block_pos(pos.cast().unwrap());
-        let pos = pos / 32.0;
+        let pos = pos / Chunk::SIZE as f32;
 
         // y is height based, so the full radius is used in both directions
         let dcy = pos.y - r;
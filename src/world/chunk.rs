@@ -4,6 +4,7 @@ use cgmath::{num_traits, Point3};
 
 use crate::world::memory::{Pool, Pooled, StatsAllocator};
 use crate::world::octree::{Octree, Position};
+use crate::world::svo;
 
 pub type BlockId = u32;
 pub type ChunkStorage = Octree<BlockId, StatsAllocator>;
@@ -12,29 +13,50 @@ pub const NO_BLOCK: BlockId = 0;
 
 // -------------------------------------------------------------------------------------------------
 
-/// `ChunkStorageAllocator` is an allocator for `ChunkStorage` objects.
+/// `ChunkStorageAllocator` is an allocator for `ChunkStorage` objects. All allocated storages have the same depth, which
+/// determines the size of the chunks they are used for.
 pub struct ChunkStorageAllocator {
     pool: Pool<ChunkStorage, StatsAllocator>,
+    depth: u8,
 }
 
 impl ChunkStorageAllocator {
+    /// Creates an allocator for chunks of the default size [`Chunk::SIZE`].
     pub fn new() -> Self {
+        Self::with_valid_depth(Chunk::DEPTH)
+    }
+
+    /// Creates an allocator for chunks with an edge length of `2^depth` blocks. The depth must be within
+    /// \[1; [`Chunk::MAX_DEPTH`]\].
+    pub fn with_depth(depth: u8) -> Result<Self, InvalidDepthError> {
+        if !(1..=Chunk::MAX_DEPTH).contains(&depth) {
+            return Err(InvalidDepthError(depth));
+        }
+        Ok(Self::with_valid_depth(depth))
+    }
+
+    fn with_valid_depth(depth: u8) -> Self {
         let pool = Pool::new_in(
-            Box::new(|alloc| {
+            Box::new(move |alloc| {
                 // It is difficult to choose the correct capacity for the octree storage, as octrees can differ a lot.
                 // Here, an average is taken to avoid repetitive storage expansion during game startup. This will not
                 // prevent from the program's memory usage to grow during runtime however.
                 let mut storage = ChunkStorage::with_capacity_in(5000, alloc);
-                storage.expand_to(5); // log2(32) = 5
+                storage.expand_to(depth);
                 storage
             }),
-            Some(Box::new(|storage| {
+            Some(Box::new(move |storage| {
                 storage.reset();
-                storage.expand_to(5);
+                storage.expand_to(depth);
             })),
-            StatsAllocator::new(),
+            StatsAllocator::labeled("chunk storage"),
         );
-        Self { pool }
+        Self { pool, depth }
+    }
+
+    /// Returns the depth of all allocated storages.
+    pub fn depth(&self) -> u8 {
+        self.depth
     }
 
     pub fn allocated_bytes(&self) -> usize {
@@ -52,7 +74,7 @@ impl Deref for ChunkStorageAllocator {
 
 #[cfg(test)]
 mod chunk_storage_allocator_tests {
-    use crate::world::chunk::ChunkStorageAllocator;
+    use crate::world::chunk::{Chunk, ChunkStorageAllocator, InvalidDepthError};
 
     /// Tests that newly allocated and reused storage objects always have a depth of 5 blocks to prevent visual voxel
     /// scale issues in the world.
@@ -85,23 +107,115 @@ mod chunk_storage_allocator_tests {
         alloc.pool.clear();
         assert_eq!(alloc.allocated_bytes(), 0);
     }
+
+    /// Tests that allocators for other chunk sizes keep the depth of new and reused storage objects, and that depths
+    /// outside the supported range are rejected.
+    #[test]
+    fn with_depth() {
+        assert_eq!(ChunkStorageAllocator::with_depth(0).err(), Some(InvalidDepthError(0)));
+        assert_eq!(ChunkStorageAllocator::with_depth(Chunk::MAX_DEPTH + 1).err(), Some(InvalidDepthError(Chunk::MAX_DEPTH + 1)));
+
+        for depth in [4, 6] {
+            let alloc = ChunkStorageAllocator::with_depth(depth).unwrap();
+            assert_eq!(alloc.depth(), depth);
+
+            let mut storage = alloc.allocate();
+            assert_eq!(storage.depth(), depth);
+            storage.construct_octants_with(2, |_| Some(1));
+            drop(storage);
+
+            assert_eq!(alloc.allocate().depth(), depth);
+        }
+    }
 }
 
 // -------------------------------------------------------------------------------------------------
 
-/// Chunk is a group of 32^3 voxels. It is the smallest voxel container. Many chunks make up the
+/// `CompactionPolicy` defines when a chunk's storage is compacted to free up empty octants, that are left behind after
+/// removing blocks. Empty octants would otherwise end up in the serialized SVO.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum CompactionPolicy {
+    /// Storage is only compacted by calling [`Chunk::compact`].
+    Manual,
+    /// Storage is compacted before serialization, if blocks were removed since the last compaction.
+    #[default]
+    BeforeSerialization,
+}
+
+/// `OutOfBoundsPolicy` defines how [`Chunk::set_block`] handles coordinates outside of the chunk.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum OutOfBoundsPolicy {
+    /// Panics with the offending coordinates.
+    #[default]
+    Panic,
+    /// Discards the change and leaves the chunk untouched.
+    Ignore,
+}
+
+/// `InvalidDepthError` is returned for chunk depths outside of \[1; [`Chunk::MAX_DEPTH`]\].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct InvalidDepthError(pub u8);
+
+/// `OutOfBoundsError` is returned if block coordinates are outside of the chunk.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct OutOfBoundsError {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+/// Chunk is a group of voxels, 32^3 by default. It is the smallest voxel container. Many chunks make up the
 /// world.
+///
+/// Chunks of other sizes can be created by using storage from a [`ChunkStorageAllocator::with_depth`]. The world only
+/// consists of chunks of the default size though, as chunk positions are in units of [`Chunk::SIZE`] blocks and all
+/// chunks in the SVO must have the same depth.
 pub struct Chunk {
     pub pos: ChunkPos,
     /// Indicates the level of detail. Defined as the maximum depth to iterate inside the chunk's
-    /// octree. 5 = maximum depth/full level of detail (2^5=32 - chunk block size along each axis).
+    /// octree. The chunk's depth is the full level of detail, e.g. 5 for the default size (2^5=32 -
+    /// chunk block size along each axis).
     pub lod: u8,
     pub storage: Option<Pooled<ChunkStorage>>,
+    pub compaction: CompactionPolicy,
+    pub out_of_bounds: OutOfBoundsPolicy,
+    /// Set if blocks were removed since the last compaction.
+    needs_compaction: bool,
+    depth: u8,
 }
 
 impl Chunk {
+    /// `SIZE` is the default edge length of a chunk in blocks.
+    pub const SIZE: u32 = 32;
+    /// `DEPTH` is the octree depth of chunks with the default size, log2([`Chunk::SIZE`]).
+    pub const DEPTH: u8 = Self::SIZE.trailing_zeros() as u8;
+    /// `BLOCK_MASK` extracts the position of a block inside its chunk of the default size from world space coordinates.
+    pub const BLOCK_MASK: i32 = Self::SIZE as i32 - 1;
+    /// `MAX_DEPTH` is the maximum octree depth of chunks. At least one level of the world SVO is needed above the
+    /// chunks, which must not be deeper than [`svo::MAX_DEPTH`] in total.
+    pub const MAX_DEPTH: u8 = svo::MAX_DEPTH - 1;
+
+    /// Creates a new chunk. Its size is determined by the depth of `storage`, see [`ChunkStorageAllocator::with_depth`].
     pub fn new(pos: ChunkPos, lod: u8, storage: Pooled<ChunkStorage>) -> Self {
-        Self { pos, lod, storage: Some(storage) }
+        Self {
+            pos,
+            lod,
+            depth: storage.depth(),
+            storage: Some(storage),
+            compaction: CompactionPolicy::default(),
+            out_of_bounds: OutOfBoundsPolicy::default(),
+            needs_compaction: false,
+        }
+    }
+
+    /// Returns the depth of the chunk's octree, which is the full level of detail.
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Returns the edge length of the chunk in blocks.
+    pub fn size(&self) -> u32 {
+        1 << self.depth
     }
 
     pub fn get_block(&self, x: u32, y: u32, z: u32) -> BlockId {
@@ -111,14 +225,34 @@ impl Chunk {
         *self.storage.as_ref().unwrap().get_leaf(Position(x, y, z)).unwrap_or(&NO_BLOCK)
     }
 
+    /// Sets the block at the given coordinates. Coordinates outside of the chunk are handled according to the chunk's
+    /// [`OutOfBoundsPolicy`].
     pub fn set_block(&mut self, x: u32, y: u32, z: u32, block: BlockId) {
+        if let Err(err) = self.try_set_block(x, y, z, block) {
+            match self.out_of_bounds {
+                OutOfBoundsPolicy::Panic => panic!("block position out of chunk bounds: {err:?}"),
+                OutOfBoundsPolicy::Ignore => {}
+            }
+        }
+    }
+
+    /// Sets the block at the given coordinates or returns an error, if they are outside of the chunk. Writing out of
+    /// bounds would otherwise wrap around inside the octree and corrupt it.
+    pub fn try_set_block(&mut self, x: u32, y: u32, z: u32, block: BlockId) -> Result<(), OutOfBoundsError> {
         assert!(self.storage.is_some());
 
+        let size = self.size();
+        if x >= size || y >= size || z >= size {
+            return Err(OutOfBoundsError { x, y, z });
+        }
+
         if block == NO_BLOCK {
-            self.storage.as_mut().unwrap().remove_leaf(Position(x, y, z));
+            let (removed, _) = self.storage.as_mut().unwrap().remove_leaf(Position(x, y, z));
+            self.needs_compaction |= removed.is_some();
         } else {
             self.storage.as_mut().unwrap().set_leaf(Position(x, y, z), block);
         }
+        Ok(())
     }
 
     /// Iterates through the whole chunk calling `f` for each block and sets it to the returned value. Any previous
@@ -126,7 +260,136 @@ impl Chunk {
     pub fn fill_with<F: Fn(u32, u32, u32) -> Option<BlockId>>(&mut self, f: F) {
         assert!(self.storage.is_some());
 
-        self.storage.as_mut().unwrap().construct_octants_with(5, |pos| f(pos.0, pos.1, pos.2));
+        self.storage.as_mut().unwrap().construct_octants_with(self.depth, |pos| f(pos.0, pos.1, pos.2));
+
+        // constructed octrees are already optimized
+        self.needs_compaction = false;
+    }
+
+    /// Removes all empty octants from the chunk's storage and returns true, if anything was removed. The storage keeps
+    /// the chunk's depth. Calling this manually is only necessary with [`CompactionPolicy::Manual`], e.g. for chunks that
+    /// are modified very frequently, or to free up memory of chunks that are not serialized.
+    pub fn compact(&mut self) -> bool {
+        self.needs_compaction = false;
+
+        let Some(storage) = self.storage.as_mut() else {
+            return false;
+        };
+        let changed = storage.compact();
+        storage.expand_to(self.depth); // compaction resets empty octrees
+        changed
+    }
+
+    /// Compacts the chunk's storage if the compaction policy allows it and blocks were removed since the last
+    /// compaction. Returns true, if anything was removed.
+    pub fn compact_if_needed(&mut self) -> bool {
+        if self.compaction != CompactionPolicy::BeforeSerialization || !self.needs_compaction {
+            return false;
+        }
+        self.compact()
+    }
+}
+
+#[cfg(test)]
+mod chunk_tests {
+    use crate::world::chunk::{Chunk, ChunkPos, ChunkStorageAllocator, CompactionPolicy, NO_BLOCK, OutOfBoundsError, OutOfBoundsPolicy};
+
+    /// Tests that blocks on the chunk's boundary can be set, while coordinates just past it are rejected.
+    #[test]
+    fn try_set_block() {
+        let alloc = ChunkStorageAllocator::new();
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0), 5, alloc.allocate());
+
+        assert_eq!(chunk.try_set_block(0, 0, 0, 1), Ok(()));
+        assert_eq!(chunk.try_set_block(31, 31, 31, 2), Ok(()));
+        assert_eq!(chunk.get_block(0, 0, 0), 1);
+        assert_eq!(chunk.get_block(31, 31, 31), 2);
+
+        assert_eq!(chunk.try_set_block(32, 0, 0, 3), Err(OutOfBoundsError { x: 32, y: 0, z: 0 }));
+        assert_eq!(chunk.try_set_block(0, 32, 0, 3), Err(OutOfBoundsError { x: 0, y: 32, z: 0 }));
+        assert_eq!(chunk.try_set_block(0, 0, 32, 3), Err(OutOfBoundsError { x: 0, y: 0, z: 32 }));
+        assert_eq!(chunk.try_set_block(u32::MAX, 0, 0, 3), Err(OutOfBoundsError { x: u32::MAX, y: 0, z: 0 }));
+
+        // rejected changes must not wrap around
+        assert_eq!(chunk.get_block(0, 0, 0), 1);
+        assert_eq!(chunk.storage.as_ref().unwrap().depth(), 5);
+    }
+
+    /// Tests that out of bounds coordinates panic by default.
+    #[test]
+    #[should_panic(expected = "block position out of chunk bounds")]
+    fn set_block_out_of_bounds_panics() {
+        let alloc = ChunkStorageAllocator::new();
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0), 5, alloc.allocate());
+        chunk.set_block(0, 32, 0, 1);
+    }
+
+    /// Tests that out of bounds coordinates are discarded with the ignore policy.
+    #[test]
+    fn set_block_out_of_bounds_ignored() {
+        let alloc = ChunkStorageAllocator::new();
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0), 5, alloc.allocate());
+        chunk.out_of_bounds = OutOfBoundsPolicy::Ignore;
+
+        chunk.set_block(31, 0, 0, 1);
+        chunk.set_block(32, 0, 0, 2);
+        assert_eq!(chunk.get_block(31, 0, 0), 1);
+        assert_eq!(chunk.get_block(0, 0, 0), NO_BLOCK);
+    }
+
+    /// Tests that chunks are only compacted if blocks were removed and the policy allows for it.
+    #[test]
+    fn compact_if_needed() {
+        let alloc = ChunkStorageAllocator::new();
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0), 5, alloc.allocate());
+
+        // adding blocks does not require compaction
+        chunk.set_block(0, 0, 0, 1);
+        chunk.set_block(31, 31, 31, 1);
+        assert!(!chunk.compact_if_needed());
+
+        // removing blocks leaves empty octants behind
+        chunk.set_block(31, 31, 31, NO_BLOCK);
+        assert!(chunk.compact_if_needed());
+        assert!(!chunk.compact_if_needed());
+
+        // removing everything keeps the storage's depth intact
+        chunk.set_block(0, 0, 0, NO_BLOCK);
+        assert!(chunk.compact_if_needed());
+        assert_eq!(chunk.storage.as_ref().unwrap().depth(), 5);
+        assert_eq!(chunk.get_block(0, 0, 0), NO_BLOCK);
+
+        // manual policy skips compaction
+        chunk.compaction = CompactionPolicy::Manual;
+        chunk.set_block(0, 0, 0, 1);
+        chunk.set_block(0, 0, 0, NO_BLOCK);
+        assert!(!chunk.compact_if_needed());
+        assert!(chunk.compact());
+    }
+
+    /// Tests that chunks of other sizes than the default use their storage's depth for bounds checks, filling and
+    /// compaction.
+    #[test]
+    fn chunk_sizes() {
+        for (depth, size) in [(4, 16), (6, 64)] {
+            let alloc = ChunkStorageAllocator::with_depth(depth).unwrap();
+            let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0), depth, alloc.allocate());
+            assert_eq!(chunk.depth(), depth);
+            assert_eq!(chunk.size(), size);
+
+            assert_eq!(chunk.try_set_block(size - 1, size - 1, size - 1, 1), Ok(()));
+            assert_eq!(chunk.try_set_block(size, 0, 0, 1), Err(OutOfBoundsError { x: size, y: 0, z: 0 }));
+            assert_eq!(chunk.get_block(size - 1, size - 1, size - 1), 1);
+
+            chunk.set_block(size - 1, size - 1, size - 1, NO_BLOCK);
+            assert!(chunk.compact());
+            assert_eq!(chunk.storage.as_ref().unwrap().depth(), depth);
+
+            chunk.fill_with(|x, y, z| (x == size - 1 && y == 0 && z == 1).then_some(2));
+            assert_eq!(chunk.storage.as_ref().unwrap().depth(), depth);
+            assert_eq!(chunk.get_block(size - 1, 0, 1), 2);
+            assert_eq!(chunk.get_block(0, 0, 0), NO_BLOCK);
+        }
     }
 }
 
@@ -148,7 +411,7 @@ impl ChunkPos {
     }
 
     pub fn from_block_pos(x: i32, y: i32, z: i32) -> Self {
-        Self { x: x >> 5, y: y >> 5, z: z >> 5 }
+        Self { x: x >> Chunk::DEPTH, y: y >> Chunk::DEPTH, z: z >> Chunk::DEPTH }
     }
 
     /// Returns the squared distance between this and the other chunk position.
@@ -168,7 +431,7 @@ impl ChunkPos {
     }
 
     pub fn as_block_pos(&self) -> Point3<i32> {
-        Point3::new(self.x << 5, self.y << 5, self.z << 5)
+        Point3::new(self.x << Chunk::DEPTH, self.y << Chunk::DEPTH, self.z << Chunk::DEPTH)
     }
 }
 
@@ -260,9 +523,9 @@ impl BlockPos {
     pub fn new(x: i32, y: i32, z: i32) -> Self {
         Self {
             chunk: ChunkPos::from_block_pos(x, y, z),
-            rel_x: (x & 31) as f32,
-            rel_y: (y & 31) as f32,
-            rel_z: (z & 31) as f32,
+            rel_x: (x & Chunk::BLOCK_MASK) as f32,
+            rel_y: (y & Chunk::BLOCK_MASK) as f32,
+            rel_z: (z & Chunk::BLOCK_MASK) as f32,
         }
     }
 
@@ -276,17 +539,17 @@ impl BlockPos {
 
         Self {
             chunk: ChunkPos::from_block_pos(x, y, z),
-            rel_x: (x & 31) as f32 + fx,
-            rel_y: (y & 31) as f32 + fy,
-            rel_z: (z & 31) as f32 + fz,
+            rel_x: (x & Chunk::BLOCK_MASK) as f32 + fx,
+            rel_y: (y & Chunk::BLOCK_MASK) as f32 + fy,
+            rel_z: (z & Chunk::BLOCK_MASK) as f32 + fz,
         }
     }
 
     pub fn to_point(&self) -> Point3<f32> {
         let mut pos = self.chunk.as_block_pos();
-        pos.x |= (self.rel_x as i32) & 31;
-        pos.y |= (self.rel_y as i32) & 31;
-        pos.z |= (self.rel_z as i32) & 31;
+        pos.x |= (self.rel_x as i32) & Chunk::BLOCK_MASK;
+        pos.y |= (self.rel_y as i32) & Chunk::BLOCK_MASK;
+        pos.z |= (self.rel_z as i32) & Chunk::BLOCK_MASK;
 
         Point3::new(
             pos.x as f32 + self.rel_x.fract(),
@@ -0,0 +1,91 @@
+use std::alloc::Allocator;
+
+use rustc_hash::FxHashSet;
+
+use crate::world::chunk::BlockId;
+use crate::world::octree::{Octree, Position};
+
+/// Advances the fluid `fluid_id` in `octree` by one step of a simple cellular flow: every fluid block spreads into the
+/// block below it, or into its four horizontal neighbours if it cannot flow down. Fluids spread into empty positions
+/// and replace every block that is not in `solid_ids`. Fluids do not leave the octree's bounds.
+///
+/// All fluid blocks spread based on the state before the step, so that fluids advance by at most one block per step.
+/// Returns all positions that were changed, so that only the affected chunks have to be serialized again.
+pub fn simulate_step<A: Allocator>(octree: &mut Octree<BlockId, A>, fluid_id: BlockId, solid_ids: &FxHashSet<BlockId>) -> FxHashSet<Position> {
+    let size = 1 << octree.depth();
+    let is_passable = |octree: &Octree<BlockId, A>, pos: Position| {
+        octree.get_leaf(pos).map_or(true, |block| *block != fluid_id && !solid_ids.contains(block))
+    };
+
+    let fluids: Vec<Position> = octree.iter_leaves()
+        .filter(|(_, block)| **block == fluid_id)
+        .map(|(pos, _)| pos)
+        .collect();
+
+    let mut changed = FxHashSet::default();
+    for Position(x, y, z) in fluids {
+        if y > 0 && is_passable(octree, Position(x, y - 1, z)) {
+            changed.insert(Position(x, y - 1, z));
+            continue;
+        }
+
+        let neighbors = [
+            x.checked_sub(1).map(|x| Position(x, y, z)),
+            (x + 1 < size).then_some(Position(x + 1, y, z)),
+            z.checked_sub(1).map(|z| Position(x, y, z)),
+            (z + 1 < size).then_some(Position(x, y, z + 1)),
+        ];
+        for pos in neighbors.into_iter().flatten() {
+            if is_passable(octree, pos) {
+                changed.insert(pos);
+            }
+        }
+    }
+
+    for pos in &changed {
+        octree.set_leaf(*pos, fluid_id);
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hash::FxHashSet;
+
+    use crate::world::chunk::BlockId;
+    use crate::world::fluids;
+    use crate::world::octree::{Octree, Position};
+
+    const STONE: BlockId = 1;
+    const WATER: BlockId = 2;
+    const GRASS: BlockId = 3;
+
+    /// Tests that water poured on a step spreads by one block per step, sideways on top of the step and down once it
+    /// reaches its edge.
+    #[test]
+    fn simulate_step() {
+        // a floor at y=0 with a step at y=1 for x < 2
+        let mut octree = Octree::new();
+        octree.fill(Position(0, 0, 0), Position(3, 0, 3), STONE);
+        octree.fill(Position(0, 1, 0), Position(1, 1, 3), STONE);
+        octree.set_leaf(Position(0, 2, 0), WATER);
+        octree.set_leaf(Position(0, 2, 2), GRASS);
+        let solid_ids = FxHashSet::from_iter([STONE]);
+
+        let changed = fluids::simulate_step(&mut octree, WATER, &solid_ids);
+        assert_eq!(changed, FxHashSet::from_iter([Position(1, 2, 0), Position(0, 2, 1)]));
+        assert_eq!(octree.get_leaf(Position(2, 2, 0)), None);
+
+        // non-solid blocks are replaced
+        let changed = fluids::simulate_step(&mut octree, WATER, &solid_ids);
+        assert_eq!(changed, FxHashSet::from_iter([Position(2, 2, 0), Position(1, 2, 1), Position(0, 2, 2)]));
+        assert_eq!(octree.get_leaf(Position(0, 2, 2)), Some(&WATER));
+
+        // water at the step's edge flows down instead of sideways
+        let changed = fluids::simulate_step(&mut octree, WATER, &solid_ids);
+        assert!(changed.contains(&Position(2, 1, 0)));
+        assert!(!changed.contains(&Position(3, 2, 0)));
+        assert_eq!(octree.get_leaf(Position(2, 1, 0)), Some(&WATER));
+        assert_eq!(octree.get_leaf(Position(0, 1, 0)), Some(&STONE));
+    }
+}
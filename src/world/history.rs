@@ -0,0 +1,141 @@
+use std::collections::VecDeque;
+
+use cgmath::Point3;
+
+use crate::world::chunk::BlockId;
+
+/// `BlockEdit` is a single block change at `pos` in world space.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BlockEdit {
+    pub pos: Point3<i32>,
+    pub old: BlockId,
+    pub new: BlockId,
+}
+
+/// `EditHistory` keeps the most recent block edits, so that they can be undone and redone. Recording a new edit
+/// discards all edits that were undone before.
+pub struct EditHistory {
+    undo: VecDeque<BlockEdit>,
+    redo: Vec<BlockEdit>,
+    capacity: usize,
+}
+
+impl EditHistory {
+    /// Creates a history that retains at most `capacity` edits. Older edits are dropped and cannot be undone anymore.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            undo: VecDeque::with_capacity(capacity),
+            redo: Vec::new(),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, edit: BlockEdit) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.undo.len() == self.capacity {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(edit);
+        self.redo.clear();
+    }
+
+    /// Reverts the most recent edit by calling `set_block` with its position and old block. If `set_block` returns
+    /// false, e.g. because the chunk is currently in use, the edit is kept and `None` is returned.
+    pub fn undo<F: FnOnce(Point3<i32>, BlockId) -> bool>(&mut self, set_block: F) -> Option<BlockEdit> {
+        let edit = *self.undo.back()?;
+        if !set_block(edit.pos, edit.old) {
+            return None;
+        }
+        self.undo.pop_back();
+        self.redo.push(edit);
+        Some(edit)
+    }
+
+    /// Applies the most recently undone edit again. See [`EditHistory::undo`].
+    pub fn redo<F: FnOnce(Point3<i32>, BlockId) -> bool>(&mut self, set_block: F) -> Option<BlockEdit> {
+        let edit = *self.redo.last()?;
+        if !set_block(edit.pos, edit.new) {
+            return None;
+        }
+        self.redo.pop();
+        self.undo.push_back(edit);
+        Some(edit)
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use cgmath::Point3;
+
+    use crate::world::chunk::{BlockId, Chunk, ChunkPos, ChunkStorageAllocator, NO_BLOCK};
+    use crate::world::history::{BlockEdit, EditHistory};
+    use crate::world::world::World;
+
+    const STONE: BlockId = 1;
+    const GRASS: BlockId = 2;
+
+    fn edit(world: &mut World, history: &mut EditHistory, pos: Point3<i32>, block: BlockId) {
+        let old = world.get_block(pos.x, pos.y, pos.z);
+        assert!(world.set_block(pos.x, pos.y, pos.z, block));
+        history.record(BlockEdit { pos, old, new: block });
+    }
+
+    /// Tests that undoing a place and a remove restores the original blocks, and that undone edits can be redone until
+    /// a new edit is recorded.
+    #[test]
+    fn undo_redo() {
+        let alloc = Arc::new(ChunkStorageAllocator::new());
+        let mut world = World::new();
+        world.set_chunk(Chunk::new(ChunkPos::new(0, 0, 0), Chunk::DEPTH, alloc.allocate()));
+        world.set_block(1, 1, 1, STONE);
+
+        let mut history = EditHistory::new(8);
+        edit(&mut world, &mut history, Point3::new(1, 2, 1), GRASS);
+        edit(&mut world, &mut history, Point3::new(1, 1, 1), NO_BLOCK);
+        assert_eq!(world.get_block(1, 1, 1), NO_BLOCK);
+
+        let mut set_block = |pos: Point3<i32>, block| world.set_block(pos.x, pos.y, pos.z, block);
+        assert_eq!(history.undo(&mut set_block), Some(BlockEdit { pos: Point3::new(1, 1, 1), old: STONE, new: NO_BLOCK }));
+        assert!(history.undo(&mut set_block).is_some());
+        assert_eq!(history.undo(&mut set_block), None);
+        assert_eq!(world.get_block(1, 1, 1), STONE);
+        assert_eq!(world.get_block(1, 2, 1), NO_BLOCK);
+
+        let mut set_block = |pos: Point3<i32>, block| world.set_block(pos.x, pos.y, pos.z, block);
+        assert!(history.redo(&mut set_block).is_some());
+        assert_eq!(world.get_block(1, 2, 1), GRASS);
+        assert_eq!((history.undo_len(), history.redo_len()), (1, 1));
+
+        // edits that cannot be applied are kept
+        assert_eq!(history.redo(|_, _| false), None);
+        assert_eq!(history.redo_len(), 1);
+
+        edit(&mut world, &mut history, Point3::new(2, 1, 1), GRASS);
+        assert_eq!((history.undo_len(), history.redo_len()), (2, 0));
+    }
+
+    /// Tests that the oldest edits are dropped once the capacity is reached.
+    #[test]
+    fn capacity() {
+        let mut history = EditHistory::new(2);
+        for x in 0..3 {
+            history.record(BlockEdit { pos: Point3::new(x, 0, 0), old: NO_BLOCK, new: STONE });
+        }
+        assert_eq!(history.undo_len(), 2);
+        assert_eq!(history.undo(|_, _| true).map(|edit| edit.pos.x), Some(2));
+        assert_eq!(history.undo(|_, _| true).map(|edit| edit.pos.x), Some(1));
+        assert_eq!(history.undo(|_, _| true), None);
+    }
+}
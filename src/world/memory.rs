@@ -1,8 +1,8 @@
 use std::alloc::{Allocator, AllocError, Global, GlobalAlloc, Layout, System};
 use std::ops::{Deref, DerefMut};
 use std::ptr::NonNull;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 pub type ConstructorFn<T, A> = Box<dyn Fn(A) -> T + Send + Sync + 'static>;
 pub type ResetFn<T> = Box<dyn Fn(&mut T) + Send>;
@@ -12,15 +12,36 @@ pub type ResetFn<T> = Box<dyn Fn(&mut T) + Send>;
 /// instance to the internal memory pool upon drop. If an old instance is reused, it will be
 /// `reset` before reuse.
 ///
+/// By default, all returned instances are kept for reuse. [`Pool::with_max_idle`] limits the number of idle instances,
+/// and [`Pool::shrink_to`] drops idle instances on demand.
+///
 /// Allocator is thread-safe and might be wrapped inside [`Arc`] or similar smart pointers.
 pub struct Pool<T, A: Allocator = Global> {
     alloc: A,
-    pool: Arc<crossbeam_queue::SegQueue<T>>,
-    total_allocated: AtomicUsize,
+    shared: Arc<PoolShared<T>>,
     constructor: ConstructorFn<T, A>,
     reset: Option<ResetFn<T>>,
 }
 
+/// `PoolShared` is the state of a [`Pool`] that is shared with all of its [`Pooled`] instances.
+struct PoolShared<T> {
+    idle: crossbeam_queue::SegQueue<T>,
+    total_allocated: AtomicUsize,
+    max_idle: AtomicUsize,
+}
+
+impl<T> PoolShared<T> {
+    /// Drops idle instances until at most `n` are left.
+    fn shrink_to(&self, n: usize) {
+        while self.idle.len() > n {
+            if self.idle.pop().is_none() {
+                break;
+            }
+            self.total_allocated.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
 impl<T> Pool<T> {
     pub fn new(constructor: ConstructorFn<T, Global>, reset: Option<ResetFn<T>>) -> Self {
         Self::new_in(constructor, reset, Global)
@@ -31,40 +52,69 @@ impl<T, A: Allocator + Clone> Pool<T, A> {
     pub fn new_in(constructor: ConstructorFn<T, A>, reset: Option<ResetFn<T>>, alloc: A) -> Self {
         Self {
             alloc,
-            pool: Arc::new(crossbeam_queue::SegQueue::new()),
-            total_allocated: AtomicUsize::new(0),
+            shared: Arc::new(PoolShared {
+                idle: crossbeam_queue::SegQueue::new(),
+                total_allocated: AtomicUsize::new(0),
+                max_idle: AtomicUsize::new(usize::MAX),
+            }),
             constructor,
             reset,
         }
     }
 
+    /// Limits the number of idle instances kept for reuse to `n`. Instances that are returned while the limit is
+    /// reached are dropped instead. The limit is not enforced strictly, if instances are returned concurrently.
+    pub fn with_max_idle(self, n: usize) -> Self {
+        self.shared.max_idle.store(n, Ordering::Relaxed);
+        self.shared.shrink_to(n);
+        self
+    }
+
+    /// Eagerly creates instances until `n` are idle, so that the next `n` calls to [`Pool::allocate`] do not have to
+    /// create new instances. `n` is limited to the maximum number of idle instances.
+    pub fn preallocate(&self, n: usize) {
+        let n = n.min(self.shared.max_idle.load(Ordering::Relaxed));
+        while self.shared.idle.len() < n {
+            self.shared.total_allocated.fetch_add(1, Ordering::Relaxed);
+            self.shared.idle.push((self.constructor)(self.alloc.clone()));
+        }
+    }
+
     /// Returns either a reused & reset instance from the pool, or creates a new instance.
     pub fn allocate(&self) -> Pooled<T> {
-        if let Some(mut elem) = self.pool.pop() {
+        if let Some(mut elem) = self.shared.idle.pop() {
             if self.reset.is_some() {
                 self.reset.as_ref().unwrap()(&mut elem);
             }
-            return Pooled::new(Arc::clone(&self.pool), elem);
+            return Pooled::new(Arc::clone(&self.shared), elem);
         }
-        self.total_allocated.fetch_add(1, Ordering::Relaxed);
-        Pooled::new(Arc::clone(&self.pool), (self.constructor)(self.alloc.clone()))
+        self.shared.total_allocated.fetch_add(1, Ordering::Relaxed);
+        Pooled::new(Arc::clone(&self.shared), (self.constructor)(self.alloc.clone()))
     }
 
-    /// Returns the total number of instances created by this pool, both in-use and reusable.
+    /// Returns the number of instances created by this pool that were not dropped yet, both in-use and idle.
     pub fn allocated_count(&self) -> usize {
-        self.total_allocated.load(Ordering::Relaxed)
+        self.shared.total_allocated.load(Ordering::Relaxed)
     }
 
     /// Returns the number of instances that are currently owned by some component.
     pub fn used_count(&self) -> usize {
-        self.allocated_count() - self.pool.len()
+        self.allocated_count().saturating_sub(self.idle_count())
+    }
+
+    /// Returns the number of instances that are kept for reuse.
+    pub fn idle_count(&self) -> usize {
+        self.shared.idle.len()
+    }
+
+    /// Drops idle instances until at most `n` are left, e.g. to free memory once the demand for instances dropped.
+    pub fn shrink_to(&self, n: usize) {
+        self.shared.shrink_to(n);
     }
 
     /// Drops all currently pooled instances.
     pub fn clear(&self) {
-        while !self.pool.is_empty() {
-            self.pool.pop();
-        }
+        self.shrink_to(0);
     }
 }
 
@@ -84,21 +134,27 @@ impl<T, A: Allocator + AllocatorStats> Pool<T, A> {
 
 // -------------------------------------------------------------------------------------------------
 
-/// Pooled ownership return their value back to the pool once dropped.
+/// Pooled ownership return their value back to the pool once dropped. If the pool already holds its maximum number of
+/// idle instances, the value is dropped instead.
 pub struct Pooled<T> {
-    pool: Arc<crossbeam_queue::SegQueue<T>>,
+    pool: Arc<PoolShared<T>>,
     value: Option<T>,
 }
 
 impl<T> Pooled<T> {
-    fn new(pool: Arc<crossbeam_queue::SegQueue<T>>, value: T) -> Self {
+    fn new(pool: Arc<PoolShared<T>>, value: T) -> Self {
         Self { pool, value: Some(value) }
     }
 }
 
 impl<T> Drop for Pooled<T> {
     fn drop(&mut self) {
-        self.pool.push(self.value.take().unwrap());
+        let value = self.value.take().unwrap();
+        if self.pool.idle.len() < self.pool.max_idle.load(Ordering::Relaxed) {
+            self.pool.idle.push(value);
+        } else {
+            self.pool.total_allocated.fetch_sub(1, Ordering::Relaxed);
+        }
     }
 }
 
@@ -119,8 +175,10 @@ impl<T> DerefMut for Pooled<T> {
 #[cfg(test)]
 mod pool_tests {
     use std::cell::RefCell;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
-    use crate::world::memory::Pool;
+    use crate::world::memory::{Pool, StatsAllocator};
 
     /// Tests that object allocation and reset/reuse works properly.
     #[test]
@@ -148,6 +206,70 @@ mod pool_tests {
         assert_eq!(alloc.allocated_count(), 1);
         assert_eq!(alloc.used_count(), 1);
     }
+
+    /// Tests that instances are dropped instead of returned to the pool, if the maximum number of idle instances is
+    /// reached, and that the pool can be shrunk on demand.
+    #[test]
+    fn max_idle() {
+        let alloc = Pool::new_in(Box::new(|alloc| Vec::<u8, _>::with_capacity_in(16, alloc)), None, StatsAllocator::new())
+            .with_max_idle(2);
+
+        let instances: Vec<_> = (0..4).map(|_| alloc.allocate()).collect();
+        assert_eq!(alloc.allocated_count(), 4);
+        assert_eq!(alloc.idle_count(), 0);
+        assert_eq!(alloc.allocated_bytes(), 4 * 16);
+
+        drop(instances);
+        assert_eq!(alloc.allocated_count(), 2);
+        assert_eq!(alloc.idle_count(), 2);
+        assert_eq!(alloc.used_count(), 0);
+        assert_eq!(alloc.allocated_bytes(), 2 * 16);
+
+        let instance = alloc.allocate();
+        assert_eq!(alloc.allocated_count(), 2);
+        assert_eq!(alloc.idle_count(), 1);
+
+        alloc.shrink_to(0);
+        assert_eq!(alloc.allocated_count(), 1);
+        assert_eq!(alloc.idle_count(), 0);
+        assert_eq!(alloc.used_count(), 1);
+        assert_eq!(alloc.allocated_bytes(), 16);
+
+        drop(instance);
+        assert_eq!(alloc.idle_count(), 1);
+    }
+
+    /// Tests that preallocated instances are reused without calling the constructor, and that preallocation respects the
+    /// maximum number of idle instances.
+    #[test]
+    fn preallocate() {
+        let constructed = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&constructed);
+        let alloc = Pool::new(
+            Box::new(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                0
+            }),
+            None,
+        ).with_max_idle(5);
+
+        alloc.preallocate(3);
+        assert_eq!(constructed.load(Ordering::Relaxed), 3);
+        assert_eq!(alloc.allocated_count(), 3);
+        assert_eq!(alloc.idle_count(), 3);
+
+        let instances: Vec<_> = (0..3).map(|_| alloc.allocate()).collect();
+        assert_eq!(constructed.load(Ordering::Relaxed), 3);
+        assert_eq!(alloc.used_count(), 3);
+
+        let instance = alloc.allocate();
+        assert_eq!(constructed.load(Ordering::Relaxed), 4);
+        drop((instances, instance));
+
+        alloc.preallocate(10);
+        assert_eq!(constructed.load(Ordering::Relaxed), 5);
+        assert_eq!(alloc.idle_count(), 5);
+    }
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -157,17 +279,53 @@ mod pool_tests {
 ///
 /// It is safe to Clone, all clones of the an instance contribute to the same metric.
 /// It is safe to use across multiple threads. It uses an `AtomicUsize` to avoid race conditions.
+///
+/// Allocators created with [`StatsAllocator::labeled`] are registered under a subsystem name, so that the memory usage
+/// can be broken down by subsystem using [`labeled_allocated_bytes`].
 #[derive(Clone, Default, Debug)]
 pub struct StatsAllocator {
     allocated_bytes: Arc<AtomicUsize>,
 }
 
+/// `LABELED_ALLOCATORS` references the metrics of all labeled allocators. Entries are removed lazily, once all clones of
+/// an allocator were dropped.
+static LABELED_ALLOCATORS: Mutex<Vec<(&'static str, Weak<AtomicUsize>)>> = Mutex::new(Vec::new());
+
 impl StatsAllocator {
     pub fn new() -> Self {
         Self {
             allocated_bytes: Arc::new(AtomicUsize::new(0)),
         }
     }
+
+    /// Creates a new allocator whose allocations are reported under `label`. Multiple allocators can share a label.
+    pub fn labeled(label: &'static str) -> Self {
+        let alloc = Self::new();
+        let mut registry = LABELED_ALLOCATORS.lock().unwrap();
+        registry.retain(|(_, bytes)| bytes.strong_count() > 0);
+        registry.push((label, Arc::downgrade(&alloc.allocated_bytes)));
+        alloc
+    }
+}
+
+/// Returns the currently allocated bytes of all labeled [`StatsAllocator`] instances, summed up by label, in the order
+/// the labels were first registered.
+pub fn labeled_allocated_bytes() -> Vec<(&'static str, usize)> {
+    let mut registry = LABELED_ALLOCATORS.lock().unwrap();
+    registry.retain(|(_, bytes)| bytes.strong_count() > 0);
+
+    let counters: Vec<_> = registry.iter().map(|(label, bytes)| (*label, bytes.upgrade())).collect();
+    drop(registry);
+
+    let mut result: Vec<(&'static str, usize)> = Vec::new();
+    for (label, bytes) in counters {
+        let bytes = bytes.map_or(0, |bytes| bytes.load(Ordering::Relaxed));
+        match result.iter_mut().find(|(l, _)| *l == label) {
+            Some((_, total)) => *total += bytes,
+            None => result.push((label, bytes)),
+        }
+    }
+    result
 }
 
 unsafe impl Allocator for StatsAllocator {
@@ -188,17 +346,104 @@ impl AllocatorStats for StatsAllocator {
     }
 }
 
+#[cfg(test)]
+mod stats_allocator_tests {
+    use std::alloc::{Allocator, Layout};
+
+    use crate::world::memory::{AllocatorStats, labeled_allocated_bytes, StatsAllocator};
+
+    fn labeled_bytes(label: &str) -> Option<usize> {
+        labeled_allocated_bytes().into_iter().find(|(l, _)| *l == label).map(|(_, bytes)| bytes)
+    }
+
+    /// Tests that labeled allocators report their allocations separately, that allocators sharing a label are summed
+    /// up, and that dropped allocators are removed from the registry.
+    #[test]
+    fn labeled() {
+        let a = StatsAllocator::labeled("stats_allocator_tests::a");
+        let b = StatsAllocator::labeled("stats_allocator_tests::b");
+        let b2 = StatsAllocator::labeled("stats_allocator_tests::b");
+
+        let _vec_a = Vec::<u8, _>::with_capacity_in(100, a.clone());
+        let vec_b = Vec::<u8, _>::with_capacity_in(30, b.clone());
+        let layout = Layout::from_size_align(20, 1).unwrap();
+        let ptr = b2.allocate(layout).unwrap();
+
+        assert_eq!(a.allocated_bytes(), 100);
+        assert_eq!(b.allocated_bytes(), 30);
+        assert_eq!(labeled_bytes("stats_allocator_tests::a"), Some(100));
+        assert_eq!(labeled_bytes("stats_allocator_tests::b"), Some(50));
+
+        unsafe { b2.deallocate(ptr.cast(), layout); }
+        drop(vec_b);
+        assert_eq!(labeled_bytes("stats_allocator_tests::b"), Some(0));
+
+        drop((b, b2));
+        assert_eq!(labeled_bytes("stats_allocator_tests::a"), Some(100));
+        assert_eq!(labeled_bytes("stats_allocator_tests::b"), None);
+    }
+}
+
 // -------------------------------------------------------------------------------------------------
 
 /// `GlobalStatsAllocator` is identical to `StatsAllocator` but implements the `GlobalAlloc` trait, allowing it to be used
 /// as a replacement allocator for the whole rust runtime.
+///
+/// Optionally, a soft memory limit can be set. Allocations that exceed it never fail, but mark the allocator as over
+/// budget, so that systems can react to the memory pressure, e.g. by pausing chunk loading.
 pub struct GlobalStatsAllocator {
     pub allocated_bytes: AtomicUsize,
+    // memory_limit is the soft limit in bytes, or 0 if there is none
+    memory_limit: AtomicUsize,
+    over_budget: AtomicBool,
+}
+
+impl GlobalStatsAllocator {
+    pub const fn new() -> Self {
+        Self {
+            allocated_bytes: AtomicUsize::new(0),
+            memory_limit: AtomicUsize::new(0),
+            over_budget: AtomicBool::new(false),
+        }
+    }
+
+    /// Sets the soft memory limit in bytes. `None` removes the limit.
+    pub fn set_memory_limit(&self, bytes: Option<usize>) {
+        self.memory_limit.store(bytes.unwrap_or(0), Ordering::Relaxed);
+        self.over_budget.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns true, if an allocation exceeded the memory limit and the allocated bytes are still above it.
+    pub fn is_over_budget(&self) -> bool {
+        if !self.over_budget.load(Ordering::Relaxed) {
+            return false;
+        }
+        // the flag is only set when allocating, so it is reset here once enough memory was freed
+        let limit = self.memory_limit.load(Ordering::Relaxed);
+        let is_over_budget = limit > 0 && self.allocated_bytes.load(Ordering::Relaxed) > limit;
+        if !is_over_budget {
+            self.over_budget.store(false, Ordering::Relaxed);
+        }
+        is_over_budget
+    }
+
+    /// Returns the ratio of allocated bytes to the memory limit, or 0 if no limit is set.
+    pub fn memory_pressure(&self) -> f32 {
+        let limit = self.memory_limit.load(Ordering::Relaxed);
+        if limit == 0 {
+            return 0.0;
+        }
+        self.allocated_bytes.load(Ordering::Relaxed) as f32 / limit as f32
+    }
 }
 
 unsafe impl GlobalAlloc for GlobalStatsAllocator {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        self.allocated_bytes.fetch_add(layout.size(), Ordering::Relaxed);
+        let allocated = self.allocated_bytes.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        let limit = self.memory_limit.load(Ordering::Relaxed);
+        if limit > 0 && allocated > limit {
+            self.over_budget.store(true, Ordering::Relaxed);
+        }
         System.alloc(layout)
     }
 
@@ -207,3 +452,44 @@ unsafe impl GlobalAlloc for GlobalStatsAllocator {
         System.dealloc(ptr, layout);
     }
 }
+
+#[cfg(test)]
+mod global_stats_allocator_tests {
+    use std::alloc::{GlobalAlloc, Layout};
+
+    use crate::world::memory::GlobalStatsAllocator;
+
+    /// Tests that exceeding the soft memory limit marks the allocator as over budget without failing the allocation,
+    /// and that the flag is reset once enough memory was freed.
+    #[test]
+    fn memory_limit() {
+        let alloc = GlobalStatsAllocator::new();
+        let layout = Layout::from_size_align(64, 8).unwrap();
+
+        unsafe {
+            let a = alloc.alloc(layout);
+            assert!(!alloc.is_over_budget());
+            assert!(alloc.memory_pressure().abs() < f32::EPSILON);
+
+            alloc.set_memory_limit(Some(100));
+            assert!((alloc.memory_pressure() - 0.64).abs() < 1e-6);
+
+            let b = alloc.alloc(layout);
+            assert!(!b.is_null());
+            assert!(alloc.is_over_budget());
+            assert!((alloc.memory_pressure() - 1.28).abs() < 1e-6);
+
+            alloc.dealloc(b, layout);
+            assert!(!alloc.is_over_budget());
+
+            let b = alloc.alloc(layout);
+            assert!(alloc.is_over_budget());
+            alloc.set_memory_limit(None);
+            assert!(!alloc.is_over_budget());
+            assert!(alloc.memory_pressure().abs() < f32::EPSILON);
+
+            alloc.dealloc(a, layout);
+            alloc.dealloc(b, layout);
+        }
+    }
+}
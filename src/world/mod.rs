@@ -2,7 +2,10 @@ pub use svo::Svo;
 
 pub mod memory;
 pub mod chunk;
+pub mod fluids;
+pub mod history;
 pub mod octree;
 pub mod svo;
+pub mod vox;
 #[allow(clippy::module_inception)]
 pub mod world;
@@ -1,11 +1,20 @@
 use std::alloc::{Allocator, Global};
 use std::cmp::max;
+use std::hash::Hash;
 use std::mem;
 
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Vector3};
 use cgmath::num_traits::Pow;
+use rustc_hash::FxHashMap;
 
 pub type OctantId = u32;
 
+/// Number of fractional bits of single precision floats. Limits the depth of octrees that rays can be cast through.
+/// See `MAX_SCALE` in `svo.glsl`.
+const CAST_RAY_MAX_SCALE: i32 = 23;
+/// Maximum number of steps a ray takes through the octree. See `MAX_STEPS` in `svo.glsl`.
+const CAST_RAY_MAX_STEPS: usize = 1000;
+
 /// `LeafId` describes a leaf's position inside the octree by storing the child's `idx` inside its
 /// `parent` octant.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
@@ -18,7 +27,7 @@ pub struct LeafId {
 pub struct Position(pub u32, pub u32, pub u32);
 
 impl Position {
-    fn idx(&self) -> u8 {
+    pub(super) fn idx(&self) -> u8 {
         (self.0 + self.1 * 2 + self.2 * 4) as u8
     }
 
@@ -26,6 +35,80 @@ impl Position {
         let depth = max(1, max(self.0, max(self.1, self.2)));
         (depth as f32).log2().floor() as u8 + 1
     }
+
+    /// Returns the Morton code (Z-order) of this position inside an octree of the given depth. On every level, three
+    /// bits are appended in the same order as the octree's child indices, so that siblings have contiguous codes.
+    pub fn to_morton(self, depth: u8) -> u64 {
+        assert!(depth <= 21, "morton codes support a depth of at most 21");
+
+        let mut code = 0u64;
+        for level in (0..depth).rev() {
+            let idx = (self.0 >> level & 1) + (self.1 >> level & 1) * 2 + (self.2 >> level & 1) * 4;
+            code = code << 3 | idx as u64;
+        }
+        code
+    }
+
+    /// Returns the position for the given Morton code inside an octree of the given depth. This is the inverse of
+    /// [`Position::to_morton`].
+    pub fn from_morton(code: u64, depth: u8) -> Self {
+        assert!(depth <= 21, "morton codes support a depth of at most 21");
+
+        let mut pos = Self(0, 0, 0);
+        for level in 0..depth {
+            let idx = (code >> (level * 3)) as u32;
+            pos.0 |= (idx & 1) << level;
+            pos.1 |= (idx >> 1 & 1) << level;
+            pos.2 |= (idx >> 2 & 1) << level;
+        }
+        pos
+    }
+
+    /// Returns true, if the cube at this position with edge length `size` overlaps the inclusive box from `min` to
+    /// `max`.
+    fn cube_overlaps(&self, size: u32, min: Self, max: Self) -> bool {
+        self.0 <= max.0 && self.0 + size > min.0
+            && self.1 <= max.1 && self.1 + size > min.1
+            && self.2 <= max.2 && self.2 + size > min.2
+    }
+
+    /// Returns the absolute position of the child at `idx` inside an octant at this position. `size` is the edge
+    /// length of the child.
+    fn child(&self, idx: u8, size: u32) -> Self {
+        Self(
+            self.0 + size * (idx as u32 & 1),
+            self.1 + size * ((idx as u32 >> 1) & 1),
+            self.2 + size * ((idx as u32 >> 2) & 1),
+        )
+    }
+}
+
+/// Face describes one of the six axis directions of a voxel in order [x-, x+, y-, y+, z-, z+].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Face {
+    NegX,
+    PosX,
+    NegY,
+    PosY,
+    NegZ,
+    PosZ,
+}
+
+impl Face {
+    pub const ALL: [Self; 6] = [Self::NegX, Self::PosX, Self::NegY, Self::PosY, Self::NegZ, Self::PosZ];
+
+    /// Returns the position of the neighbour in this direction, or None if it would be negative.
+    fn offset(self, pos: Position) -> Option<Position> {
+        let Position(x, y, z) = pos;
+        match self {
+            Self::NegX => Some(Position(x.checked_sub(1)?, y, z)),
+            Self::PosX => Some(Position(x.checked_add(1)?, y, z)),
+            Self::NegY => Some(Position(x, y.checked_sub(1)?, z)),
+            Self::PosY => Some(Position(x, y.checked_add(1)?, z)),
+            Self::NegZ => Some(Position(x, y, z.checked_sub(1)?)),
+            Self::PosZ => Some(Position(x, y, z.checked_add(1)?)),
+        }
+    }
 }
 
 impl std::ops::Div<u32> for Position {
@@ -48,6 +131,30 @@ impl std::ops::RemAssign<u32> for Position {
     }
 }
 
+/// `OctreeStats` describes how much of an octree's memory is actually in use.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct OctreeStats {
+    /// `octant_count` is the number of allocated octants, excluding the ones on the free list.
+    pub octant_count: usize,
+    pub leaf_count: usize,
+    /// `free_count` is the number of allocated octants that are available for reuse.
+    pub free_count: usize,
+    pub depth: u8,
+}
+
+/// `OctreeVisitor` is called by [`Octree::visit`] for every octant and leaf in depth first order.
+pub trait OctreeVisitor<T> {
+    /// Called before any children of the octant are visited. `depth` is 0 for the root octant and `child_mask` has
+    /// the bit `1 << idx` set for every non-empty child.
+    fn enter_octant(&mut self, id: OctantId, depth: u8, child_mask: u8);
+
+    /// Called for every leaf with its absolute position.
+    fn leaf(&mut self, pos: Position, value: &T);
+
+    /// Called after all children of the octant were visited.
+    fn exit_octant(&mut self, id: OctantId);
+}
+
 /// Octree is a data structure that subdivides three-dimensional space into octants. One octant
 /// can contain up to 8 leaf nodes, or 8 child octants which further subdivide their parent octant
 /// to contain 8 children/leaves.
@@ -79,6 +186,19 @@ impl<T: PartialEq, A: Allocator> PartialEq for Octree<T, A> {
     }
 }
 
+/// Clones the octree including its free list, so that all `OctantId`s and `LeafId`s of the original remain valid for
+/// the clone.
+impl<T: Clone, A: Allocator + Clone> Clone for Octree<T, A> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root,
+            octants: self.octants.clone(),
+            free_list: self.free_list.clone(),
+            depth: self.depth,
+        }
+    }
+}
+
 impl<T, A: Allocator> Octree<T, A> {
     pub fn new_in(alloc: A) -> Self {
         Self::with_capacity_in(0, alloc)
@@ -100,7 +220,57 @@ impl<T, A: Allocator> Octree<T, A> {
     /// new `LeafId`, that holds the leaf value, as well as any previous value that was overridden.
     pub fn set_leaf(&mut self, pos: Position, leaf: T) -> (LeafId, Option<T>) {
         self.expand_to(pos.required_depth());
+        self.set_leaf_unchecked(pos, leaf)
+    }
+
+    /// Adds all given leaf values at their positions. Unlike calling [`Octree::set_leaf`] for every leaf, the tree is
+    /// expanded only once to fit all positions. If the tree is already deep enough, the result is identical to
+    /// setting all leaves one by one.
+    pub fn set_leaves<I: IntoIterator<Item=(Position, T)>>(&mut self, items: I) {
+        let items: Vec<_> = items.into_iter().collect();
+
+        let depth = items.iter().map(|(pos, _)| pos.required_depth()).max();
+        let Some(depth) = depth else {
+            return;
+        };
+        self.expand_to(depth);
+
+        for (pos, leaf) in items {
+            self.set_leaf_unchecked(pos, leaf);
+        }
+    }
+
+    /// Returns a mutable reference to the leaf at the given position. If there is no leaf yet, the value returned by
+    /// `f` is inserted first. Missing octants are created along the way like in [`Octree::set_leaf`].
+    pub fn get_or_insert_with<F: FnOnce() -> T>(&mut self, pos: Position, f: F) -> &mut T {
+        self.expand_to(pos.required_depth());
+
+        let mut it = self.root.unwrap();
+        let mut pos = pos;
+        let mut size = 2f32.pow(self.depth as i32) as u32;
+
+        while size >= 1 {
+            size /= 2;
+            let idx = (pos / size).idx();
+            pos %= size;
 
+            if size == 1 {
+                let octant = &mut self.octants[it as usize];
+                if !octant.children[idx as usize].is_leaf() {
+                    octant.set_child(idx, Child::Leaf(f()));
+                }
+                return octant.children[idx as usize].get_leaf_value_mut().unwrap();
+            }
+
+            it = self.step_into_or_create_octant_at(it, idx);
+        }
+
+        unreachable!("could not reach end of tree");
+    }
+
+    /// Sets the leaf value at the given position without expanding the tree first. The tree has to be deep enough to
+    /// contain the position.
+    fn set_leaf_unchecked(&mut self, pos: Position, leaf: T) -> (LeafId, Option<T>) {
         let mut it = self.root.unwrap();
         let mut pos = pos;
         let mut size = 2f32.pow(self.depth as i32) as u32;
@@ -121,6 +291,74 @@ impl<T, A: Allocator> Octree<T, A> {
         unreachable!("could not reach end of tree");
     }
 
+    /// Sets every position inside the inclusive box from `min` to `max` to a clone of `value`. The tree is expanded
+    /// to contain `max`, if necessary, and only descended once for the whole box.
+    pub fn fill(&mut self, min: Position, max: Position, value: T) where T: Clone {
+        if min.0 > max.0 || min.1 > max.1 || min.2 > max.2 {
+            return;
+        }
+
+        self.expand_to(max.required_depth());
+
+        let size = 2f32.pow(self.depth as i32) as u32;
+        self.fill_octant(self.root.unwrap(), Position(0, 0, 0), size, min, max, &value);
+    }
+
+    fn fill_octant(&mut self, id: OctantId, pos: Position, size: u32, min: Position, max: Position, value: &T) where T: Clone {
+        let size = size / 2;
+
+        for i in 0u8..8 {
+            let child_pos = pos.child(i, size);
+            if !child_pos.cube_overlaps(size, min, max) {
+                continue;
+            }
+
+            if size == 1 {
+                self.octants[id as usize].set_child(i, Child::Leaf(value.clone()));
+                continue;
+            }
+
+            let child_id = self.step_into_or_create_octant_at(id, i);
+            self.fill_octant(child_id, child_pos, size, min, max, value);
+        }
+    }
+
+    /// Removes all leaves inside the inclusive box from `min` to `max`. Like [`Octree::remove_leaf`], empty parents
+    /// are *not* removed from the tree.
+    pub fn clear_region(&mut self, min: Position, max: Position) {
+        let Some(root) = self.root else {
+            return;
+        };
+        if min.0 > max.0 || min.1 > max.1 || min.2 > max.2 {
+            return;
+        }
+
+        let size = 2f32.pow(self.depth as i32) as u32;
+        self.clear_octant(root, Position(0, 0, 0), size, min, max);
+    }
+
+    fn clear_octant(&mut self, id: OctantId, pos: Position, size: u32, min: Position, max: Position) {
+        let size = size / 2;
+
+        for i in 0u8..8 {
+            let child_pos = pos.child(i, size);
+            if !child_pos.cube_overlaps(size, min, max) {
+                continue;
+            }
+
+            match &self.octants[id as usize].children[i as usize] {
+                Child::None => {}
+                Child::Octant(child_id) => {
+                    let child_id = *child_id;
+                    self.clear_octant(child_id, child_pos, size, min, max);
+                }
+                Child::Leaf(_) => {
+                    self.octants[id as usize].set_child(i, Child::None);
+                }
+            }
+        }
+    }
+
     /// Constructs the octree anew by building the deepest octants at `depth` first and filling them with leaf values
     /// returned by `f`. It then merges those octants until the root octant is built. If no leaf values are returned
     /// for an octant, that branch is skipped. The resulting octree is hence already optimized.
@@ -141,11 +379,7 @@ impl<T, A: Allocator> Octree<T, A> {
         let mut new_parent = None;
 
         for i in 0u8..8 {
-            let child_pos = Position(
-                pos.0 + size * ((i as u32) & 1),
-                pos.1 + size * ((i as u32 >> 1) & 1),
-                pos.2 + size * ((i as u32 >> 2) & 1),
-            );
+            let child_pos = pos.child(i, size);
 
             if size > 1 {
                 let child_id = self.construct_octants_with_impl(size, child_pos, f);
@@ -306,6 +540,284 @@ impl<T, A: Allocator> Octree<T, A> {
         None
     }
 
+    /// Returns a reference to the value of the neighbouring leaf in direction of `face`, if it exists. Neighbours
+    /// outside of the octree's bounds are treated as missing.
+    pub fn get_neighbor(&self, pos: Position, face: Face) -> Option<&T> {
+        self.root?;
+
+        let neighbor = face.offset(pos)?;
+        let size = 2f32.pow(self.depth as i32) as u32;
+        if neighbor.0 >= size || neighbor.1 >= size || neighbor.2 >= size {
+            return None;
+        }
+
+        self.get_leaf(neighbor)
+    }
+
+    /// Returns all six neighbouring leaves of the given position in the order of [`Face::ALL`].
+    pub fn neighbors(&self, pos: Position) -> [Option<&T>; 6] {
+        Face::ALL.map(|face| self.get_neighbor(pos, face))
+    }
+
+    /// Returns a mutable reference to the value of the leaf at the given position, if it exists. The tree's structure
+    /// is not changed.
+    pub fn get_leaf_mut(&mut self, pos: Position) -> Option<&mut T> {
+        let leaf_id = self.find_leaf(pos)?;
+        self.octants[leaf_id.parent as usize].children[leaf_id.idx as usize].get_leaf_value_mut()
+    }
+
+    /// Returns the `LeafId` of the leaf at the given position, if it exists.
+    fn find_leaf(&self, pos: Position) -> Option<LeafId> {
+        if self.root.is_none() || pos.required_depth() > self.depth {
+            return None;
+        }
+
+        let mut it = self.root.unwrap();
+        let mut pos = pos;
+        let mut size = 2f32.pow(self.depth as i32) as u32;
+
+        while size > 1 {
+            size /= 2;
+            let idx = (pos / size).idx();
+            pos %= size;
+
+            match &self.octants[it as usize].children[idx as usize] {
+                Child::None => break,
+                Child::Octant(id) => it = *id,
+                Child::Leaf(_) => return Some(LeafId { parent: it, idx }),
+            }
+        }
+
+        None
+    }
+
+    /// Walks the whole tree depth first, visiting children in the order of their index, and reports all octants and
+    /// leaves to the given `visitor`.
+    pub fn visit<V: OctreeVisitor<T>>(&self, visitor: &mut V) {
+        if let Some(root) = self.root {
+            let size = 2f32.pow(self.depth as i32) as u32;
+            self.visit_octant(root, Position(0, 0, 0), size, 0, visitor);
+        }
+    }
+
+    fn visit_octant<V: OctreeVisitor<T>>(&self, id: OctantId, pos: Position, size: u32, depth: u8, visitor: &mut V) {
+        let octant = &self.octants[id as usize];
+
+        let mut child_mask = 0u8;
+        for (idx, child) in octant.children.iter().enumerate() {
+            if !child.is_none() {
+                child_mask |= 1 << idx;
+            }
+        }
+        visitor.enter_octant(id, depth, child_mask);
+
+        let size = size / 2;
+        for (idx, child) in octant.children.iter().enumerate() {
+            let child_pos = pos.child(idx as u8, size);
+            match child {
+                Child::None => {}
+                Child::Octant(child_id) => self.visit_octant(*child_id, child_pos, size, depth + 1, visitor),
+                Child::Leaf(value) => visitor.leaf(child_pos, value),
+            }
+        }
+
+        visitor.exit_octant(id);
+    }
+
+    /// Returns an iterator over all leaves and their absolute positions. The tree is walked depth first, skipping
+    /// empty children.
+    pub fn iter_leaves(&self) -> impl Iterator<Item=(Position, &T)> {
+        let mut stack = Vec::new();
+        if let Some(root) = self.root {
+            let size = 2f32.pow(self.depth as i32) as u32;
+            stack.push((root, Position(0, 0, 0), size, 0));
+        }
+        LeafIter { octree: self, stack }
+    }
+
+    /// Returns an iterator over mutable references to all leaves and their absolute positions. Unlike
+    /// [`Octree::iter_leaves`], leaves are returned in the order of their octants in memory.
+    pub fn iter_leaves_mut(&mut self) -> impl Iterator<Item=(Position, &mut T)> {
+        // resolve the absolute position and child edge length of every octant that is reachable from the root
+        let mut positions = vec![None; self.octants.len()];
+        if let Some(root) = self.root {
+            let mut stack = vec![(root, Position(0, 0, 0), 2f32.pow(self.depth as i32) as u32)];
+            while let Some((id, pos, size)) = stack.pop() {
+                let size = size / 2;
+                positions[id as usize] = Some((pos, size));
+
+                for (i, child) in self.octants[id as usize].children.iter().enumerate() {
+                    if let Child::Octant(child_id) = child {
+                        stack.push((*child_id, pos.child(i as u8, size), size));
+                    }
+                }
+            }
+        }
+
+        self.octants.iter_mut()
+            .zip(positions)
+            .filter_map(|(octant, pos)| Some((octant, pos?)))
+            .flat_map(|(octant, (pos, size))| {
+                octant.children.iter_mut().enumerate().filter_map(move |(i, child)| {
+                    child.get_leaf_value_mut().map(|value| (pos.child(i as u8, size), value))
+                })
+            })
+    }
+
+    /// Casts a ray through the octree on the CPU and returns the position of the first leaf that is hit, the distance
+    /// to it and the normal of the face that was hit. One leaf has an edge length of 1. The traversal is a port of
+    /// `intersect_octree` in `svo.glsl`: octants are stepped through front to back with a fixed size stack, and a leaf
+    /// that contains the ray's `origin` is ignored. See the shader for a detailed explanation of the algorithm.
+    pub fn cast_ray(&self, origin: Point3<f32>, dir: Vector3<f32>, max_dst: f32) -> Option<(Position, f32, Vector3<f32>)> {
+        let mut octant = self.root?;
+        if self.depth as i32 >= CAST_RAY_MAX_SCALE {
+            return None;
+        }
+
+        // rescale inputs to be [0;1] and shift them to [1;2), so that the float bits can be used directly
+        let octree_scale = 2f32.powi(-(self.depth as i32));
+        let ro = Vector3::new(1.0, 1.0, 1.0) + origin.to_vec() * octree_scale;
+        let max_dst = max_dst * octree_scale;
+
+        // prevent division by zero
+        let rd = dir.normalize().map(|v| if v.abs() < f32::EPSILON { f32::EPSILON.copysign(v) } else { v });
+
+        // all ray directions are mirrored to be negative
+        let t_coef = rd.map(|v| -1.0 / v.abs());
+        let mut t_bias = Vector3::new(t_coef.x * ro.x, t_coef.y * ro.y, t_coef.z * ro.z);
+        let mut octant_mask = 0;
+        for axis in 0..3 {
+            if rd[axis] > 0.0 {
+                octant_mask ^= 1 << axis;
+                t_bias[axis] = 3f32.mul_add(t_coef[axis], -t_bias[axis]);
+            }
+        }
+        let t_at = |pos: Vector3<f32>| Vector3::new(
+            pos.x.mul_add(t_coef.x, -t_bias.x),
+            pos.y.mul_add(t_coef.y, -t_bias.y),
+            pos.z.mul_add(t_coef.z, -t_bias.z),
+        );
+
+        let t_enter = t_at(Vector3::new(2.0, 2.0, 2.0));
+        let mut t_min = t_enter.x.max(t_enter.y).max(t_enter.z).max(0.0);
+        let t_exit = t_at(Vector3::new(1.0, 1.0, 1.0));
+        let mut t_max = t_exit.x.min(t_exit.y).min(t_exit.z);
+
+        let mut idx = 0;
+        let mut pos = Vector3::new(1.0, 1.0, 1.0);
+        let t_center = t_at(Vector3::new(1.5, 1.5, 1.5));
+        for axis in 0..3 {
+            if t_min < t_center[axis] {
+                idx ^= 1 << axis;
+                pos[axis] = 1.5;
+            }
+        }
+
+        let mut scale = CAST_RAY_MAX_SCALE - 1;
+        let mut scale_exp2 = 0.5f32;
+        let mut stack = [(octant, t_max); CAST_RAY_MAX_SCALE as usize + 1];
+
+        for _ in 0..CAST_RAY_MAX_STEPS {
+            if max_dst >= 0.0 && t_min > max_dst {
+                return None;
+            }
+
+            let t_corner = t_at(pos);
+            let tc_max = t_corner.x.min(t_corner.y).min(t_corner.z);
+
+            let child = &self.octants[octant as usize].children[idx ^ octant_mask];
+            if !child.is_none() && t_min <= t_max {
+                if let Child::Octant(child_id) = child {
+                    let half_scale = scale_exp2 * 0.5;
+                    let t_center = t_coef.map(|v| v * half_scale) + t_corner;
+                    let tv_max = t_max.min(tc_max);
+
+                    if t_min <= tv_max {
+                        // phase: PUSH
+                        stack[scale as usize] = (octant, t_max);
+                        octant = *child_id;
+
+                        scale -= 1;
+                        scale_exp2 = half_scale;
+
+                        idx = 0;
+                        for axis in 0..3 {
+                            if t_min < t_center[axis] {
+                                idx ^= 1 << axis;
+                                pos[axis] += scale_exp2;
+                            }
+                        }
+
+                        t_max = tv_max;
+                        continue;
+                    }
+                } else if t_min > 0.0 {
+                    // phase: HIT
+                    // the entered face is on the axis with the largest entry distance
+                    let t_corner = t_at(pos + Vector3::new(scale_exp2, scale_exp2, scale_exp2));
+                    let mut face_axis = 0;
+                    for axis in 1..3 {
+                        if t_corner[axis] > t_corner[face_axis] {
+                            face_axis = axis;
+                        }
+                    }
+                    let mut normal = Vector3::new(0.0, 0.0, 0.0);
+                    normal[face_axis] = -rd[face_axis].signum();
+
+                    // undo mirroring and the initial coordinate system shift & rescale
+                    let mut leaf_pos = [0; 3];
+                    for axis in 0..3 {
+                        let mut pos = pos[axis];
+                        if octant_mask & (1 << axis) != 0 {
+                            pos = 3.0 - scale_exp2 - pos;
+                        }
+                        leaf_pos[axis] = ((pos - 1.0) / octree_scale) as u32;
+                    }
+
+                    return Some((Position(leaf_pos[0], leaf_pos[1], leaf_pos[2]), t_min / octree_scale, normal));
+                }
+            }
+
+            // phase: ADVANCE
+            let mut step_mask = 0;
+            for axis in 0..3 {
+                if tc_max >= t_corner[axis] {
+                    step_mask ^= 1 << axis;
+                    pos[axis] -= scale_exp2;
+                }
+            }
+            t_min = tc_max;
+            idx ^= step_mask;
+
+            if idx & step_mask != 0 {
+                // phase: POP
+                // the highest differing bit of the position before and after the step is the scale to ascend to
+                let mut differing_bits = 0;
+                for axis in 0..3 {
+                    if step_mask & (1 << axis) != 0 {
+                        differing_bits |= pos[axis].to_bits() ^ (pos[axis] + scale_exp2).to_bits();
+                    }
+                }
+                scale = differing_bits.checked_ilog2()? as i32;
+                if scale >= CAST_RAY_MAX_SCALE {
+                    return None;
+                }
+                scale_exp2 = 2f32.powi(scale - CAST_RAY_MAX_SCALE);
+                (octant, t_max) = stack[scale as usize];
+
+                // round the position down to the new scale and recalculate the idx from the rounded to bits
+                idx = 0;
+                for axis in 0..3 {
+                    let shifted = pos[axis].to_bits() >> scale;
+                    pos[axis] = f32::from_bits(shifted << scale);
+                    idx |= (shifted as usize & 1) << axis;
+                }
+            }
+        }
+
+        None
+    }
+
     /// Expands the octant's depth by the given value. If necessary, the existing root octant
     /// is wrapped in new parent octants.
     pub fn expand(&mut self, by: u8) {
@@ -335,25 +847,47 @@ impl<T, A: Allocator> Octree<T, A> {
         }
     }
 
+    /// Reduces the octree's depth as long as the root octant's only child is an octant at index 0. The child becomes
+    /// the new root and the previous root is added to the free list. Positions of all leaves stay the same.
+    pub fn contract(&mut self) {
+        while let Some(root_id) = self.root {
+            let root = &self.octants[root_id as usize];
+            if root.children_count != 1 {
+                return;
+            }
+            let Some(child_id) = root.children[0].get_octant_value() else {
+                return;
+            };
+
+            self.octants[child_id as usize].parent = None;
+            self.delete_octant(root_id);
+
+            self.root = Some(child_id);
+            self.depth -= 1;
+        }
+    }
+
     /// Removes all octants from the tree that have no children and no content. The algorithm
     /// is depth first, so removal cascades through the whole tree removing all empty children
-    /// as well.
-    pub fn compact(&mut self) {
+    /// as well. Returns true, if any octant was removed.
+    pub fn compact(&mut self) -> bool {
         if self.root.is_none() {
-            return;
+            return false;
         }
 
-        self.compact_octant(self.root.unwrap());
+        let changed = self.compact_octant(self.root.unwrap());
 
         if self.octants[self.root.unwrap() as usize].children_count != 0 {
-            return;
+            return changed;
         }
 
         self.reset();
+        true
     }
 
-    fn compact_octant(&mut self, octant_id: OctantId) {
+    fn compact_octant(&mut self, octant_id: OctantId) -> bool {
         let children = self.octants[octant_id as usize].children.len();
+        let mut changed = false;
 
         for i in 0..children {
             let id = {
@@ -365,14 +899,86 @@ impl<T, A: Allocator> Octree<T, A> {
             }
             let id = id.unwrap();
 
-            self.compact_octant(id);
+            changed |= self.compact_octant(id);
 
             let octant = &self.octants[id as usize];
             if octant.children_count == 0 {
                 self.delete_octant(id);
                 self.octants[octant_id as usize].set_child(i as u8, Child::None);
+                changed = true;
             }
         }
+
+        changed
+    }
+
+    /// Merges all structurally identical subtrees, so that they share a single octant, and returns the number of
+    /// octants that were added to the free list. Leaf values are not changed.
+    ///
+    /// Afterwards, octants can have more than one parent. Modifying a shared octant would modify all of its copies,
+    /// so the tree must be treated as read-only after calling this method, e.g. for serialization. Use
+    /// [`Octree::reset`] or [`Octree::construct_octants_with`] to make it writable again.
+    pub fn dedup(&mut self) -> usize where T: Hash + Eq {
+        let Some(root) = self.root else {
+            return 0;
+        };
+
+        // find the canonical octant for every reachable octant bottom up
+        let mut canonical = vec![None; self.octants.len()];
+        let mut seen = FxHashMap::default();
+        self.dedup_octant(root, &mut canonical, &mut seen);
+        drop(seen);
+
+        // point all children to their canonical octants
+        for (id, octant) in self.octants.iter_mut().enumerate() {
+            if canonical[id] != Some(id as OctantId) {
+                continue;
+            }
+            for child in &mut octant.children {
+                if let Child::Octant(child_id) = child {
+                    *child_id = canonical[*child_id as usize].unwrap();
+                }
+            }
+        }
+
+        // duplicates are no longer reachable and can be freed
+        let mut reclaimed = 0;
+        for (id, canonical_id) in canonical.iter().enumerate() {
+            if canonical_id.is_none() || *canonical_id == Some(id as OctantId) {
+                continue;
+            }
+
+            let octant = &mut self.octants[id];
+            octant.parent = None;
+            octant.children_count = 0;
+            for ch in &mut octant.children {
+                *ch = Child::None;
+            }
+
+            self.free_list.push(id as OctantId);
+            reclaimed += 1;
+        }
+        reclaimed
+    }
+
+    fn dedup_octant<'a>(&'a self, id: OctantId, canonical: &mut Vec<Option<OctantId>>, seen: &mut FxHashMap<[DedupKey<'a, T>; 8], OctantId>) -> OctantId
+        where T: Hash + Eq {
+        if let Some(canonical_id) = canonical[id as usize] {
+            return canonical_id;
+        }
+
+        let mut key = [DedupKey::None; 8];
+        for (idx, child) in self.octants[id as usize].children.iter().enumerate() {
+            key[idx] = match child {
+                Child::None => DedupKey::None,
+                Child::Octant(child_id) => DedupKey::Octant(self.dedup_octant(*child_id, canonical, seen)),
+                Child::Leaf(value) => DedupKey::Leaf(value),
+            };
+        }
+
+        let canonical_id = *seen.entry(key).or_insert(id);
+        canonical[id as usize] = Some(canonical_id);
+        canonical_id
     }
 
     /// Returns either an available octant from the octree's free list, or allocates a new one.
@@ -411,14 +1017,95 @@ impl<T, A: Allocator> Octree<T, A> {
         self.free_list.push(id);
     }
 
+    /// Returns the inclusive minimum and maximum position of all leaves, or None if the tree has no leaves.
+    pub fn occupied_bounds(&self) -> Option<(Position, Position)> {
+        self.iter_leaves().fold(None, |bounds, (pos, _)| {
+            let Some((min, max)) = bounds else {
+                return Some((pos, pos));
+            };
+            Some((
+                Position(min.0.min(pos.0), min.1.min(pos.1), min.2.min(pos.2)),
+                Position(max.0.max(pos.0), max.1.max(pos.1), max.2.max(pos.2)),
+            ))
+        })
+    }
+
+    /// Returns statistics about the octree's octants and leaves.
+    pub fn stats(&self) -> OctreeStats {
+        // octants on the free list are reset and do not contain any leaves
+        let leaf_count = self.octants.iter()
+            .flat_map(|octant| octant.children.iter())
+            .filter(|child| child.is_leaf())
+            .count();
+
+        OctreeStats {
+            octant_count: self.octants.len() - self.free_list.len(),
+            leaf_count,
+            free_count: self.free_list.len(),
+            depth: self.depth,
+        }
+    }
+
     /// Returns the octree's depth.
     pub fn depth(&self) -> u8 {
         self.depth
     }
 }
 
+/// `DedupKey` identifies a child by its content when deduplicating subtrees. Child octants are represented by their
+/// canonical id.
+#[derive(Hash, Eq, PartialEq)]
+enum DedupKey<'a, T> {
+    None,
+    Octant(OctantId),
+    Leaf(&'a T),
+}
+
+impl<T> Clone for DedupKey<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for DedupKey<'_, T> {}
+
+/// `LeafIter` walks all leaves of an octree depth first. See [`Octree::iter_leaves`].
+struct LeafIter<'a, T, A: Allocator> {
+    octree: &'a Octree<T, A>,
+    // stack contains the octant's id, its absolute position, its edge length and the index of the next child to visit
+    stack: Vec<(OctantId, Position, u32, u8)>,
+}
+
+impl<'a, T, A: Allocator> Iterator for LeafIter<'a, T, A> {
+    type Item = (Position, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let octree = self.octree;
+
+        while let Some(top) = self.stack.last_mut() {
+            let (id, pos, size, idx) = *top;
+            if idx == 8 {
+                self.stack.pop();
+                continue;
+            }
+            top.3 += 1;
+
+            let size = size / 2;
+            let child_pos = pos.child(idx, size);
+
+            match &octree.octants[id as usize].children[idx as usize] {
+                Child::None => {}
+                Child::Octant(child_id) => self.stack.push((*child_id, child_pos, size, 0)),
+                Child::Leaf(value) => return Some((child_pos, value)),
+            }
+        }
+
+        None
+    }
+}
+
 /// Child represents possible states for an octant in the octree.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub(super) enum Child<T> {
     #[default]
     None,
@@ -479,7 +1166,7 @@ impl<T: PartialEq> PartialEq for Child<T> {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub(super) struct Octant<T> {
     parent: Option<OctantId>,
     children_count: u8,
@@ -508,7 +1195,40 @@ impl<T> Octant<T> {
 mod tests {
     use Child::*;
 
-    use crate::world::octree::{Child, LeafId, Octant, Octree, Position};
+    use cgmath::{Point3, Vector3};
+
+    use crate::world::octree::{Child, Face, LeafId, Octant, OctantId, Octree, OctreeStats, OctreeVisitor, Position};
+
+    /// Tests that Morton codes round trip across different depths and follow the octree's child order.
+    #[test]
+    fn position_morton() {
+        for depth in [1u8, 2, 5, 10, 21] {
+            let max = (1u64 << depth) - 1;
+            for pos in [
+                Position(0, 0, 0),
+                Position(1, 0, 1),
+                Position(max as u32, 0, 1),
+                Position(max as u32, max as u32, max as u32),
+                Position((max / 3) as u32, (max / 2) as u32, (max / 5) as u32),
+            ] {
+                let code = pos.to_morton(depth);
+                assert!(code < 1 << (depth * 3), "depth {depth}: {pos:?}");
+                assert_eq!(Position::from_morton(code, depth), pos, "depth {depth}");
+            }
+        }
+
+        // children of one octant have contiguous codes in the order of their child index
+        let parent = Position(6, 2, 4);
+        for i in 0u8..8 {
+            let child = parent.child(i, 1);
+            assert_eq!(child.to_morton(4), parent.to_morton(4) + i as u64);
+        }
+
+        assert_eq!(Position(1, 0, 0).to_morton(2), 1);
+        assert_eq!(Position(0, 1, 0).to_morton(2), 2);
+        assert_eq!(Position(0, 0, 1).to_morton(2), 4);
+        assert_eq!(Position(2, 0, 0).to_morton(2), 8);
+    }
 
     /// Tests that adding a leaf at a depth > 1 results in the correct octree state.
     #[test]
@@ -855,7 +1575,7 @@ mod tests {
             depth: 2,
         });
 
-        octree.compact();
+        assert!(octree.compact());
 
         assert_eq!(octree, Octree {
             octants: vec![
@@ -880,9 +1600,12 @@ mod tests {
             depth: 2,
         });
 
+        // compacting an already compact octree does not change anything
+        assert!(!octree.compact());
+
         octree.remove_leaf(Position(0, 1, 3));
         octree.remove_leaf(Position(1, 1, 3));
-        octree.compact();
+        assert!(octree.compact());
 
         assert_eq!(octree, Octree {
             octants: vec![],
@@ -890,5 +1613,444 @@ mod tests {
             root: Option::None,
             depth: 0,
         });
+
+        // compacting an empty octree does not change anything
+        assert!(!octree.compact());
+    }
+
+    /// Tests that iterating over all leaves returns every leaf with its absolute position, and that leaves can be
+    /// modified in place.
+    #[test]
+    fn octree_iter_leaves() {
+        let mut octree = Octree::new();
+        assert_eq!(octree.iter_leaves().count(), 0);
+        assert_eq!(octree.iter_leaves_mut().count(), 0);
+
+        octree.set_leaf(Position(6, 7, 5), 10);
+        octree.set_leaf(Position(0, 0, 0), 20);
+        octree.set_leaf(Position(1, 0, 6), 30);
+        octree.set_leaf(Position(1, 0, 7), 40);
+        octree.remove_leaf(Position(1, 0, 7));
+
+        let mut leaves: Vec<_> = octree.iter_leaves().map(|(pos, value)| (pos, *value)).collect();
+        leaves.sort_unstable();
+        assert_eq!(leaves, vec![
+            (Position(0, 0, 0), 20),
+            (Position(1, 0, 6), 30),
+            (Position(6, 7, 5), 10),
+        ]);
+
+        for (pos, value) in octree.iter_leaves_mut() {
+            *value += pos.0;
+        }
+        assert_eq!(octree.get_leaf(Position(6, 7, 5)), Some(&16));
+        assert_eq!(octree.get_leaf(Position(0, 0, 0)), Some(&20));
+        assert_eq!(octree.get_leaf(Position(1, 0, 6)), Some(&31));
+
+        // positions must not depend on the depth of the tree
+        octree.expand(2);
+        let mut leaves: Vec<_> = octree.iter_leaves_mut().map(|(pos, value)| (pos, *value)).collect();
+        leaves.sort_unstable();
+        assert_eq!(leaves, vec![
+            (Position(0, 0, 0), 20),
+            (Position(1, 0, 6), 31),
+            (Position(6, 7, 5), 16),
+        ]);
+    }
+
+    /// Tests that leaves can be modified in place without changing the tree's structure.
+    #[test]
+    fn octree_get_leaf_mut() {
+        let mut octree = Octree::new();
+        assert_eq!(octree.get_leaf_mut(Position(0, 0, 0)), Option::None);
+
+        octree.set_leaf(Position(1, 1, 3), 20);
+        octree.set_leaf(Position(0, 0, 0), 10);
+
+        let expected = {
+            let mut expected = Octree::new();
+            expected.set_leaf(Position(1, 1, 3), 25);
+            expected.set_leaf(Position(0, 0, 0), 10);
+            expected
+        };
+
+        *octree.get_leaf_mut(Position(1, 1, 3)).unwrap() += 5;
+        assert_eq!(octree, expected);
+        assert_eq!(octree.get_leaf(Position(1, 1, 3)), Some(&25));
+
+        // missing leaves, empty octants and positions outside of the tree
+        assert_eq!(octree.get_leaf_mut(Position(1, 1, 1)), Option::None);
+        assert_eq!(octree.get_leaf_mut(Position(3, 3, 3)), Option::None);
+        assert_eq!(octree.get_leaf_mut(Position(4, 0, 0)), Option::None);
+        assert_eq!(octree, expected);
+    }
+
+    /// Tests that setting leaves in bulk results in the same leaves as setting them one by one. The serialized result
+    /// is compared in `svo_tests::serialize_set_leaves`, as the octant ids differ between both octrees.
+    #[test]
+    fn octree_set_leaves() {
+        let items = vec![
+            (Position(1, 1, 3), 10),
+            (Position(6, 7, 5), 20),
+            (Position(0, 0, 0), 30),
+            (Position(1, 1, 3), 40),
+            (Position(15, 2, 9), 50),
+        ];
+
+        let mut expected = Octree::new();
+        for (pos, leaf) in items.clone() {
+            expected.set_leaf(pos, leaf);
+        }
+
+        let mut octree = Octree::new();
+        octree.set_leaves(items.clone());
+        assert_eq!(octree.depth(), expected.depth());
+        assert_eq!(octree.depth(), 4);
+        for (pos, _) in &items {
+            assert_eq!(octree.get_leaf(*pos), expected.get_leaf(*pos));
+        }
+        assert_eq!(octree.get_leaf(Position(1, 1, 3)), Some(&40));
+
+        // empty iterators do not change anything
+        let mut before = Octree::new();
+        before.set_leaves(items);
+        octree.set_leaves(Vec::new());
+        assert_eq!(octree, before);
+    }
+
+    /// Tests that contracting an octree removes all unnecessary root octants while keeping leaf positions intact.
+    #[test]
+    fn octree_contract() {
+        let mut octree = Octree::new();
+        octree.expand_to(5);
+        octree.set_leaf(Position(1, 0, 0), 10);
+        assert_eq!(octree.depth(), 5);
+
+        octree.contract();
+
+        assert_eq!(octree, Octree {
+            octants: vec![
+                Octant {
+                    parent: Option::None,
+                    children: [None, Leaf(10), None, None, None, None, None, None],
+                    children_count: 1,
+                },
+                Octant {
+                    parent: Option::None,
+                    children: [None, None, None, None, None, None, None, None],
+                    children_count: 0,
+                },
+                Octant {
+                    parent: Option::None,
+                    children: [None, None, None, None, None, None, None, None],
+                    children_count: 0,
+                },
+                Octant {
+                    parent: Option::None,
+                    children: [None, None, None, None, None, None, None, None],
+                    children_count: 0,
+                },
+                Octant {
+                    parent: Option::None,
+                    children: [None, None, None, None, None, None, None, None],
+                    children_count: 0,
+                },
+            ],
+            free_list: vec![4, 3, 2, 1],
+            root: Some(0),
+            depth: 1,
+        });
+        assert_eq!(octree.get_leaf(Position(1, 0, 0)), Some(&10));
+
+        // octrees with children outside of index 0 cannot be contracted
+        octree.set_leaf(Position(2, 0, 0), 20);
+        octree.contract();
+        assert_eq!(octree.depth(), 2);
+        assert_eq!(octree.get_leaf(Position(1, 0, 0)), Some(&10));
+        assert_eq!(octree.get_leaf(Position(2, 0, 0)), Some(&20));
+
+        // empty octrees are left untouched
+        let mut octree = Octree::<u32>::new();
+        octree.contract();
+        assert_eq!(octree.depth(), 0);
+    }
+
+    /// Tests that CPU raycasts yield the same results as the shader implementation.
+    #[test]
+    fn octree_cast_ray() {
+        let mut octree = Octree::new();
+        assert_eq!(octree.cast_ray(Point3::new(0.5, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0), 32.0), Option::None);
+
+        octree.expand_to(5);
+        octree.set_leaf(Position(0, 0, 0), 1);
+        octree.set_leaf(Position(1, 0, 0), 1);
+        octree.set_leaf(Position(31, 0, 0), 1);
+        octree.set_leaf(Position(30, 30, 30), 1);
+
+        // hit from above
+        assert_eq!(
+            octree.cast_ray(Point3::new(0.5, 1.5, 0.5), Vector3::new(0.0, -1.0, 0.0), 1.0),
+            Some((Position(0, 0, 0), 0.5, Vector3::new(0.0, 1.0, 0.0))),
+        );
+
+        // ray starting inside a leaf ignores it
+        assert_eq!(
+            octree.cast_ray(Point3::new(0.5, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0), 1.0),
+            Some((Position(1, 0, 0), 0.5, Vector3::new(-1.0, 0.0, 0.0))),
+        );
+
+        // ray starting outside the octree
+        assert_eq!(
+            octree.cast_ray(Point3::new(0.5, 0.5, -2.0), Vector3::new(0.0, 0.0, 1.0), 1.0),
+            Option::None,
+        );
+        assert_eq!(
+            octree.cast_ray(Point3::new(0.5, 0.5, -2.0), Vector3::new(0.0, 0.0, 1.0), 3.0),
+            Some((Position(0, 0, 0), 2.0, Vector3::new(0.0, 0.0, -1.0))),
+        );
+
+        // traversal through many empty octants
+        assert_eq!(
+            octree.cast_ray(Point3::new(2.0, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0), 32.0),
+            Some((Position(31, 0, 0), 29.0, Vector3::new(-1.0, 0.0, 0.0))),
+        );
+        assert_eq!(
+            octree.cast_ray(Point3::new(31.5, 0.5, 0.5), Vector3::new(-1.0, 0.0, 0.0), 32.0),
+            Some((Position(1, 0, 0), 29.5, Vector3::new(1.0, 0.0, 0.0))),
+        );
+
+        // diagonal
+        let (pos, dst, normal) = octree.cast_ray(Point3::new(2.5, 2.5, 2.5), Vector3::new(1.0, 1.0, 1.0), 64.0).unwrap();
+        assert_eq!(pos, Position(30, 30, 30));
+        assert!((-27.5f32).mul_add(3f32.sqrt(), dst).abs() < 0.0001);
+        // the ray enters through the corner, which resolves to the x-axis face like in the shader
+        assert_eq!(normal, Vector3::new(-1.0, 0.0, 0.0));
+    }
+
+    /// Tests that octree statistics exclude octants on the free list.
+    #[test]
+    fn octree_stats() {
+        let mut octree = Octree::new();
+        assert_eq!(octree.stats(), OctreeStats { octant_count: 0, leaf_count: 0, free_count: 0, depth: 0 });
+
+        octree.set_leaf(Position(0, 1, 3), 10);
+        octree.set_leaf(Position(1, 1, 3), 20);
+        octree.set_leaf(Position(6, 7, 5), 30);
+        assert_eq!(octree.stats(), OctreeStats { octant_count: 6, leaf_count: 3, free_count: 0, depth: 3 });
+
+        octree.remove_leaf(Position(6, 7, 5));
+        octree.compact();
+        assert_eq!(octree.stats(), OctreeStats { octant_count: 3, leaf_count: 2, free_count: 3, depth: 3 });
+    }
+
+    /// Tests that neighbours are looked up in all directions and that positions outside of the tree are ignored.
+    #[test]
+    fn octree_get_neighbor() {
+        let mut octree = Octree::new();
+        assert_eq!(octree.get_neighbor(Position(0, 0, 0), Face::PosX), Option::None);
+
+        octree.expand_to(2);
+        octree.set_leaf(Position(1, 1, 1), 1);
+        octree.set_leaf(Position(0, 1, 1), 2);
+        octree.set_leaf(Position(2, 1, 1), 3);
+        octree.set_leaf(Position(1, 0, 1), 4);
+        octree.set_leaf(Position(1, 2, 1), 5);
+        octree.set_leaf(Position(1, 1, 0), 6);
+        octree.set_leaf(Position(1, 1, 2), 7);
+
+        assert_eq!(octree.get_neighbor(Position(1, 1, 1), Face::NegX), Some(&2));
+        assert_eq!(octree.get_neighbor(Position(1, 1, 1), Face::PosZ), Some(&7));
+        assert_eq!(octree.neighbors(Position(1, 1, 1)), [Some(&2), Some(&3), Some(&4), Some(&5), Some(&6), Some(&7)]);
+
+        // neighbours on the tree's boundary
+        assert_eq!(octree.neighbors(Position(0, 1, 1)), [
+            Option::None, Some(&1), Option::None, Option::None, Option::None, Option::None,
+        ]);
+        assert_eq!(octree.get_neighbor(Position(3, 3, 3), Face::PosX), Option::None);
+        assert_eq!(octree.get_neighbor(Position(u32::MAX, 0, 0), Face::PosX), Option::None);
+    }
+
+    /// Tests that cloned octrees are structurally identical, so that leaf ids stay valid, and independent of the
+    /// original.
+    #[test]
+    fn octree_clone() {
+        let mut octree = Octree::new();
+        octree.set_leaf(Position(0, 1, 3), 10);
+        let (leaf_id, _) = octree.set_leaf(Position(6, 7, 5), 20);
+        octree.remove_leaf(Position(0, 1, 3));
+        octree.compact();
+
+        let mut clone = octree.clone();
+        assert_eq!(clone, octree);
+        assert_eq!(clone.remove_leaf_by_id(leaf_id), Some(20));
+
+        // modifying the clone does not change the original
+        assert_ne!(clone, octree);
+        assert_eq!(octree.get_leaf(Position(6, 7, 5)), Some(&20));
+    }
+
+    /// Tests that filling and clearing a box region sets exactly the positions inside the box.
+    #[test]
+    fn octree_fill_and_clear_region() {
+        let mut octree = Octree::new();
+        octree.set_leaf(Position(0, 0, 0), 1);
+        octree.fill(Position(1, 2, 3), Position(5, 4, 9), 2);
+        assert_eq!(octree.depth(), 4);
+
+        let mut leaves: Vec<_> = octree.iter_leaves().map(|(pos, value)| (pos, *value)).collect();
+        leaves.sort_unstable();
+
+        let mut expected = vec![(Position(0, 0, 0), 1)];
+        for x in 1..=5 {
+            for y in 2..=4 {
+                for z in 3..=9 {
+                    expected.push((Position(x, y, z), 2));
+                }
+            }
+        }
+        expected.sort_unstable();
+        assert_eq!(leaves, expected);
+
+        // empty boxes are ignored
+        octree.fill(Position(2, 0, 0), Position(1, 0, 0), 3);
+        assert_eq!(octree.stats().leaf_count, expected.len());
+
+        octree.clear_region(Position(0, 0, 0), Position(5, 4, 8));
+        let mut leaves: Vec<_> = octree.iter_leaves().map(|(pos, value)| (pos, *value)).collect();
+        leaves.sort_unstable();
+
+        let mut expected = vec![];
+        for x in 1..=5 {
+            for y in 2..=4 {
+                expected.push((Position(x, y, 9), 2));
+            }
+        }
+        expected.sort_unstable();
+        assert_eq!(leaves, expected);
+
+        // clearing outside of the tree does not panic
+        octree.clear_region(Position(0, 0, 0), Position(100, 100, 100));
+        assert_eq!(octree.stats().leaf_count, 0);
+    }
+
+    /// Tests that leaves are only inserted if they do not exist yet.
+    #[test]
+    fn octree_get_or_insert_with() {
+        let mut octree = Octree::new();
+
+        assert_eq!(*octree.get_or_insert_with(Position(1, 1, 3), || 20), 20);
+
+        let mut expected = Octree::new();
+        expected.set_leaf(Position(1, 1, 3), 20);
+        assert_eq!(octree, expected);
+
+        // existing leaves are returned without calling the closure
+        let value = octree.get_or_insert_with(Position(1, 1, 3), || panic!("closure must not be called"));
+        assert_eq!(*value, 20);
+        *value = 30;
+        assert_eq!(octree.get_leaf(Position(1, 1, 3)), Some(&30));
+        assert_eq!(octree.stats().leaf_count, 1);
+
+        // siblings update the parent's children count
+        octree.get_or_insert_with(Position(0, 1, 3), || 10);
+        expected.set_leaf(Position(1, 1, 3), 30);
+        expected.set_leaf(Position(0, 1, 3), 10);
+        assert_eq!(octree, expected);
+    }
+
+    /// Tests that the visitor is called for all octants and leaves in depth first order.
+    #[test]
+    fn octree_visit() {
+        struct Dump(Vec<String>);
+
+        impl OctreeVisitor<i32> for Dump {
+            fn enter_octant(&mut self, id: OctantId, depth: u8, child_mask: u8) {
+                self.0.push(format!("enter {id} depth={depth} mask={child_mask:08b}"));
+            }
+
+            fn leaf(&mut self, pos: Position, value: &i32) {
+                self.0.push(format!("leaf {pos:?}={value}"));
+            }
+
+            fn exit_octant(&mut self, id: OctantId) {
+                self.0.push(format!("exit {id}"));
+            }
+        }
+
+        let mut dump = Dump(vec![]);
+        Octree::new().visit(&mut dump);
+        assert!(dump.0.is_empty());
+
+        let mut octree = Octree::new();
+        octree.set_leaf(Position(1, 1, 3), 20);
+        octree.set_leaf(Position(0, 1, 3), 10);
+        octree.set_leaf(Position(1, 0, 0), 30);
+
+        octree.visit(&mut dump);
+        assert_eq!(dump.0, vec![
+            "enter 1 depth=0 mask=00010001",
+            "enter 0 depth=1 mask=00000010",
+            "leaf Position(1, 0, 0)=30",
+            "exit 0",
+            "enter 2 depth=1 mask=11000000",
+            "leaf Position(0, 1, 3)=10",
+            "leaf Position(1, 1, 3)=20",
+            "exit 2",
+            "exit 1",
+        ]);
+    }
+
+    /// Tests that the occupied bounds enclose all leaves tightly.
+    #[test]
+    fn octree_occupied_bounds() {
+        let mut octree = Octree::new();
+        assert_eq!(octree.occupied_bounds(), Option::None);
+
+        octree.set_leaf(Position(6, 7, 5), 10);
+        assert_eq!(octree.occupied_bounds(), Some((Position(6, 7, 5), Position(6, 7, 5))));
+
+        octree.set_leaf(Position(1, 0, 6), 20);
+        octree.set_leaf(Position(3, 2, 1), 30);
+        assert_eq!(octree.occupied_bounds(), Some((Position(1, 0, 1), Position(6, 7, 6))));
+
+        // empty octants are ignored
+        octree.remove_leaf(Position(6, 7, 5));
+        assert_eq!(octree.occupied_bounds(), Some((Position(1, 0, 1), Position(3, 2, 6))));
+
+        octree.remove_leaf(Position(1, 0, 6));
+        octree.remove_leaf(Position(3, 2, 1));
+        assert_eq!(octree.occupied_bounds(), Option::None);
+    }
+
+    /// Tests that identical subtrees are merged while all leaf values stay the same.
+    #[test]
+    fn octree_dedup() {
+        let mut octree = Octree::new();
+        assert_eq!(octree.dedup(), 0);
+
+        // two identical sibling subtrees and one that differs in its leaf value
+        octree.fill(Position(0, 0, 0), Position(1, 1, 1), 1);
+        octree.fill(Position(2, 0, 0), Position(3, 1, 1), 1);
+        octree.fill(Position(0, 2, 0), Position(1, 3, 1), 2);
+        let original = octree.clone();
+        assert_eq!(octree.stats().octant_count, 4);
+
+        assert_eq!(octree.dedup(), 1);
+        assert_eq!(octree.stats().octant_count, 3);
+        assert_eq!(octree.stats().free_count, 1);
+
+        let root = &octree.octants[octree.root.unwrap() as usize];
+        assert_eq!(root.children[0], root.children[1]);
+        assert_ne!(root.children[0], root.children[2]);
+
+        for (pos, value) in original.iter_leaves() {
+            assert_eq!(octree.get_leaf(pos), Some(value));
+        }
+        assert_eq!(octree.get_leaf(Position(0, 0, 2)), Option::None);
+
+        // deduplicating again does not change anything
+        let deduped = octree.clone();
+        assert_eq!(octree.dedup(), 0);
+        assert_eq!(octree, deduped);
     }
 }
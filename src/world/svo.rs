@@ -6,13 +6,17 @@ use std::sync::Arc;
 
 use rustc_hash::{FxHashMap, FxHashSet};
 
-use crate::world::chunk::{BlockId, ChunkPos};
+use crate::world::chunk::{BlockId, ChunkPos, NO_BLOCK};
 use crate::world::memory::{Pool, Pooled, StatsAllocator};
-use crate::world::octree::{LeafId, Octant, OctantId, Octree, Position};
+use crate::world::octree::{Child, LeafId, Octant, OctantId, Octree, Position};
 use crate::world::world::BorrowedChunk;
 
 pub type ChunkBufferPool<A = StatsAllocator> = Pool<ChunkBuffer<A>, A>;
 
+/// `MAX_DEPTH` is the maximum depth of an SVO that can be traversed on the GPU. The traversal steps through the SVO with
+/// single precision floats, which only have 23 bits in their fractional part (see `MAX_SCALE` in `svo.glsl`).
+pub const MAX_DEPTH: u8 = 23;
+
 /// `ChunkBuffer` abstracts the temporary storage used for serializing octants into the SVO format.
 pub struct ChunkBuffer<A: Allocator = Global> {
     data: Vec<u32, A>,
@@ -138,6 +142,17 @@ pub struct Svo<T: SvoSerializable, A: Allocator = Global> {
 
     /// Reusable buffer for serializing octants data to be copied into actual `SvoBuffer`.
     tmp_octant_buffer: Option<ChunkBuffer>,
+
+    /// Optional upper limit for the serialized leaf data in bytes. See [`Svo::set_max_bytes`].
+    max_bytes: Option<usize>,
+    /// Position from which the distance of leaves is measured, when evicting them. See [`Svo::set_eviction_center`].
+    eviction_center: Position,
+    /// Number of the current [`Svo::serialize`] call. Leaves set in between two calls are serialized at the same time.
+    serialization_time: u64,
+    /// Serialization time per leaf `unique_id`, from which the least recently serialized leaves are evicted first.
+    serialization_times: FxHashMap<u64, u64>,
+    /// Leaves that were evicted to stay within `max_bytes`, but not yet retrieved by [`Svo::take_evicted`].
+    evicted: Vec<T>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -160,7 +175,7 @@ impl<T: SvoSerializable> Svo<T> {
 
 impl<T: SvoSerializable, A: Allocator> Svo<T, A> {
     /// Static size of the serialized data required for wrapping the root octant into a traversable format.
-    const PREAMBLE_LENGTH: u32 = 5;
+    pub const PREAMBLE_LENGTH: u32 = 5;
 
     pub fn new_in(alloc: A) -> Self {
         Self::with_capacity_in(0, alloc)
@@ -174,6 +189,11 @@ impl<T: SvoSerializable, A: Allocator> Svo<T, A> {
             leaf_info: FxHashMap::default(),
             root_info: None,
             tmp_octant_buffer: Some(ChunkBuffer::new()),
+            max_bytes: None,
+            eviction_center: Position(0, 0, 0),
+            serialization_time: 0,
+            serialization_times: FxHashMap::default(),
+            evicted: Vec::new(),
         }
     }
 
@@ -184,6 +204,39 @@ impl<T: SvoSerializable, A: Allocator> Svo<T, A> {
         self.buffer.clear();
         self.leaf_info.clear();
         self.root_info = None;
+        self.serialization_times.clear();
+        self.evicted.clear();
+    }
+
+    /// Limits the number of bytes that serialized leaves may occupy. If the limit is exceeded during
+    /// [`Svo::serialize`], leaves are removed until the data fits again. The least recently serialized leaves are
+    /// evicted first. Among leaves that were serialized at the same time, the ones farthest away from the eviction
+    /// center go first. Evicted leaves can be retrieved with [`Svo::take_evicted`].
+    pub fn set_max_bytes(&mut self, max_bytes: Option<usize>) {
+        self.max_bytes = max_bytes;
+    }
+
+    pub fn get_max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    /// Sets the position in leaf coordinates, around which leaves are kept when evicting equally old leaves to stay
+    /// within the byte limit. See [`Svo::set_max_bytes`].
+    pub fn set_eviction_center(&mut self, pos: Position) {
+        self.eviction_center = pos;
+    }
+
+    /// Returns the number of bytes occupied by serialized leaves, excluding the root octree.
+    pub fn leaf_bytes(&self) -> usize {
+        self.buffer.octant_to_range.iter()
+            .filter(|(id, _)| **id != u64::MAX)
+            .map(|(_, range)| range.length)
+            .sum::<usize>() * 4
+    }
+
+    /// Returns all leaves that were evicted since the last call, to give the owner a chance to re-add them later.
+    pub fn take_evicted(&mut self) -> Vec<T> {
+        std::mem::take(&mut self.evicted)
     }
 
     /// See [`Octree::set_leaf`]. Setting `serialize` to false attempts to bypass re-serializing the leaf in case it
@@ -194,6 +247,7 @@ impl<T: SvoSerializable, A: Allocator> Svo<T, A> {
 
         if serialize || !self.leaf_info.contains_key(&uid) {
             self.change_set.insert(OctantChange::Add(uid, leaf_id));
+            self.serialization_times.insert(uid, self.serialization_time);
         }
 
         (leaf_id, prev_leaf)
@@ -211,6 +265,7 @@ impl<T: SvoSerializable, A: Allocator> Svo<T, A> {
         if let Some(value) = &value {
             let uid = value.unique_id();
             self.change_set.insert(OctantChange::Remove(uid));
+            self.serialization_times.remove(&uid);
         }
         value
     }
@@ -253,14 +308,106 @@ impl<T: SvoSerializable, A: Allocator> Svo<T, A> {
             }
         }
 
-        // rebuild root octree
-        let result = self.serialize_root(&mut tmp_buffer);
-        let offset = self.buffer.insert(u64::MAX, &tmp_buffer);
+        self.evict_over_budget();
+        self.rebuild_root(&mut tmp_buffer);
+        self.serialization_time += 1;
+
+        // return tmp buffer for reuse
+        self.tmp_octant_buffer = Some(tmp_buffer);
+    }
+
+    /// Removes the least recently serialized leaves until the serialized leaf data fits into `max_bytes` again. See
+    /// [`Svo::set_max_bytes`].
+    fn evict_over_budget(&mut self) {
+        let Some(max_bytes) = self.max_bytes else {
+            return;
+        };
+
+        let mut used_bytes = self.leaf_bytes();
+        if used_bytes <= max_bytes {
+            return;
+        }
+
+        // oldest first, then farthest first, ids only break ties to keep eviction deterministic. Leaves without
+        // serialized data are skipped, as evicting them does not free any memory.
+        let center = self.eviction_center;
+        let mut candidates = self.octree.iter_leaves()
+            .map(|(pos, value)| (value.unique_id(), pos))
+            .filter(|(id, _)| self.buffer.octant_to_range.contains_key(id))
+            .map(|(id, pos)| (self.serialization_times.get(&id).copied().unwrap_or(0), Self::distance_squared(pos, center), id, pos))
+            .collect::<Vec<(u64, u64, u64, Position)>>();
+        candidates.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)));
+
+        let mut evict_positions = Vec::new();
+        for (_, _, id, pos) in candidates {
+            if used_bytes <= max_bytes {
+                break;
+            }
+            if let Some(range) = self.buffer.octant_to_range.get(&id) {
+                used_bytes -= range.length * 4;
+            }
+            evict_positions.push(pos);
+        }
+
+        for pos in evict_positions {
+            if let (Some(value), _) = self.octree.remove_leaf(pos) {
+                let uid = value.unique_id();
+                self.buffer.remove(uid);
+                self.leaf_info.remove(&uid);
+                self.serialization_times.remove(&uid);
+                self.evicted.push(value);
+            }
+        }
+    }
+
+    fn distance_squared(a: Position, b: Position) -> u64 {
+        let dx = u64::from(a.0.abs_diff(b.0));
+        let dy = u64::from(a.1.abs_diff(b.1));
+        let dz = u64::from(a.2.abs_diff(b.2));
+        dx * dx + dy * dy + dz * dz
+    }
+
+    /// Serializes the root octree again and copies it into the buffer.
+    fn rebuild_root(&mut self, tmp_buffer: &mut ChunkBuffer) {
+        let result = self.serialize_root(tmp_buffer);
+        let offset = self.buffer.insert(u64::MAX, tmp_buffer);
         tmp_buffer.reset();
         self.root_info = Some(LeafInfo { buf_offset: offset, serialization: result });
+    }
 
-        // return tmp buffer for reuse
+    /// Moves all serialized data to the front of the buffer to get rid of free ranges left behind by removed leaves,
+    /// and shrinks the buffer accordingly. Since the root octree uses absolute pointers to its leaves, it is serialized
+    /// again afterwards. Returns true, if anything changed. Changes have to be written with
+    /// [`Svo::write_changes_to`] like after [`Svo::serialize`].
+    pub fn defragment(&mut self) -> bool {
+        if self.root_info.is_none() {
+            return false;
+        }
+
+        let len_before = self.buffer.bytes.len();
+        let moves = self.buffer.defragment();
+        if moves.is_empty() {
+            return len_before != self.buffer.bytes.len();
+        }
+
+        for (id, _, new_range) in moves {
+            if let Some(info) = self.leaf_info.get_mut(&id) {
+                info.buf_offset = new_range.start;
+            }
+        }
+
+        let mut tmp_buffer = self.tmp_octant_buffer.take().unwrap();
+        self.rebuild_root(&mut tmp_buffer);
         self.tmp_octant_buffer = Some(tmp_buffer);
+        true
+    }
+
+    /// Returns the number of bytes inside the serialized buffer that are currently unused.
+    pub fn free_bytes(&self) -> usize {
+        let len = self.buffer.bytes.len();
+        self.buffer.free_ranges.iter()
+            .map(|range| range.length.min(len.saturating_sub(range.start)))
+            .sum::<usize>() * 4
     }
 
     fn serialize_root(&self, dst: &mut ChunkBuffer) -> SerializationResult {
@@ -300,6 +447,45 @@ impl<T: SvoSerializable, A: Allocator> Svo<T, A> {
         self.root_info.unwrap().serialization.depth
     }
 
+    /// Returns the serialized SVO buffer as raw bytes in native endianness. This allows for uploading the data with
+    /// custom buffer implementations. The returned bytes are only traversable if they are prefixed with
+    /// [`Svo::preamble_bytes`], which is what [`Svo::write_to`] does. Must be called after [`Svo::serialize`].
+    pub fn serialized_bytes(&self) -> &[u8] {
+        let len = self.buffer.bytes.len() * 4;
+        unsafe { std::slice::from_raw_parts(self.buffer.bytes.as_ptr().cast::<u8>(), len) }
+    }
+
+    /// Returns the preamble bytes in native endianness that have to be written in front of
+    /// [`Svo::serialized_bytes`]. Returns `None` if nothing was serialized yet. Must be called after
+    /// [`Svo::serialize`].
+    pub fn preamble_bytes(&self) -> Option<Vec<u8>> {
+        let preamble = self.preamble_words()?;
+        Some(preamble.iter().flat_map(|x| x.to_ne_bytes()).collect())
+    }
+
+    /// Returns the full serialized SVO in the layout of [`Svo::write_to`] as little-endian bytes, independent of the
+    /// host's endianness. Unlike [`Svo::serialized_bytes`], the result is portable and can be persisted or sent to
+    /// other machines. Returns an empty vector if nothing was serialized yet.
+    pub fn to_le_bytes(&self) -> Vec<u8> {
+        let Some(preamble) = self.preamble_words() else {
+            return Vec::new();
+        };
+        preamble.iter()
+            .chain(self.buffer.bytes.iter())
+            .flat_map(|x| x.to_le_bytes())
+            .collect()
+    }
+
+    /// Returns the preamble as `u32` elements. Returns `None` if nothing was serialized yet.
+    fn preamble_words(&self) -> Option<[u32; 5]> {
+        let info = self.root_info?;
+
+        // PREAMBLE_LENGTH depends on the generic parameters of Svo and cannot be used as an array length
+        let mut preamble = [0u32; 5];
+        unsafe { Self::write_preamble(info, preamble.as_mut_ptr()); }
+        Some(preamble)
+    }
+
     /// Writes the full serialized SVO buffer to the `dst` pointer. Returns the number of elements written. Must be
     /// called after [`Svo::serialize`].
     pub unsafe fn write_to(&self, dst: *mut u32) -> usize {
@@ -321,35 +507,218 @@ impl<T: SvoSerializable, A: Allocator> Svo<T, A> {
     /// Writes all changes after the last reset to the given buffer. The implementation assumes that the same buffer,
     /// that was used in the initial call to [`Svo::write_to`] and previous calls to this method, is reused. If `reset`
     /// is true, the change tracker is reset. Must be called after [`Svo::serialize`].
-    pub unsafe fn write_changes_to(&mut self, dst: *mut u32, dst_len: usize, reset: bool) {
+    ///
+    /// `dst_len` is the number of `u32` elements that can be written to `dst`. The required length is the preamble
+    /// plus the end of the last changed range inside the serialized buffer, which is at most
+    /// `size_in_bytes() / 4 + PREAMBLE_LENGTH`. If `dst` is too small, nothing is written, changes are kept and
+    /// [`BufferTooSmall`] is returned, so that the caller can allocate a larger buffer and fill it with
+    /// [`Svo::write_to`].
+    pub unsafe fn write_changes_to(&mut self, dst: *mut u32, dst_len: usize, reset: bool) -> Result<(), BufferTooSmall> {
+        self.write_ranges_to(dst, dst_len, &self.buffer.updated_ranges)?;
+        if reset {
+            self.reset_change_tracking();
+        }
+        Ok(())
+    }
+
+    /// Same as [`Svo::write_changes_to`], but copies the given `ranges` instead of the tracked changes and does not
+    /// touch the change tracker. This allows for updating multiple copies of the buffer, that each missed different
+    /// changes, by collecting [`Svo::changed_ranges`] for every copy. Ranges are clamped to the serialized buffer, as
+    /// it might have been shrunk by [`Svo::defragment`] after they were collected.
+    pub unsafe fn write_ranges_to(&self, dst: *mut u32, dst_len: usize, ranges: &[Range]) -> Result<(), BufferTooSmall> {
         if self.root_info.is_none() {
-            return;
+            return Ok(());
         }
-        if self.buffer.updated_ranges.is_empty() {
-            return;
+        if ranges.is_empty() {
+            return Ok(());
+        }
+
+        let buffer_len = self.buffer.bytes.len();
+        let clamped = move || ranges.iter()
+            .filter(move |range| range.start < buffer_len)
+            .map(move |range| Range { start: range.start, length: range.length.min(buffer_len - range.start) });
+
+        let required = clamped()
+            .map(|range| range.start + range.length)
+            .max()
+            .unwrap_or(0) + Self::PREAMBLE_LENGTH as usize;
+        if required > dst_len {
+            return Err(BufferTooSmall { required });
         }
 
         let info = self.root_info.unwrap();
         let dst = Self::write_preamble(info, dst);
 
-        for changed_range in &self.buffer.updated_ranges {
+        for changed_range in clamped() {
             let offset = changed_range.start as isize;
             let src = self.buffer.bytes.as_ptr().offset(offset);
+            ptr::copy(src, dst.offset(offset), changed_range.length);
+        }
+        Ok(())
+    }
 
-            // For now a simple implementation suffices instead of having a mechanism that grows the target buffer,
-            // as that involves doing so on the GPU. Panic instead to make it easy to spot and prefer a cheaper,
-            // over-sized buffer.
-            assert!(changed_range.start + changed_range.length < dst_len,
-                    "dst is not large enough: len={} range_start={} range_length={}",
-                    dst_len, changed_range.start, changed_range.length,
-            );
+    /// Writes the full SVO including the preamble into `dst`, which is cleared beforehand. Unlike
+    /// [`Svo::write_to`], the serialized buffer and its change tracking are not touched. Leaves are copied
+    /// back-to-back from their already serialized data and the root octree is serialized again for their new offsets,
+    /// so that the output contains no free ranges. Leaves have to be serialized by [`Svo::serialize`] beforehand, all
+    /// others are skipped.
+    pub fn serialize_into(&self, dst: &mut Vec<u32>) -> SerializationResult {
+        dst.clear();
 
-            ptr::copy(src, dst.offset(offset), changed_range.length);
+        let Some(root_id) = self.octree.root else {
+            return SerializationResult { child_mask: 0, leaf_mask: 0, depth: 0 };
+        };
+        dst.extend(std::iter::repeat(0).take(Self::PREAMBLE_LENGTH as usize));
+
+        // copy leaf data and remember its absolute offset inside dst
+        let mut offsets = FxHashMap::default();
+        for octant in &self.octree.octants {
+            for child in &octant.children {
+                let Child::Leaf(value) = child else {
+                    continue;
+                };
+                let uid = value.unique_id();
+                if offsets.contains_key(&uid) {
+                    continue;
+                }
+                let Some(range) = self.buffer.octant_to_range.get(&uid) else {
+                    continue;
+                };
+                offsets.insert(uid, dst.len() as u32);
+                dst.extend_from_slice(&self.buffer.bytes[range.start..range.start + range.length]);
+            }
         }
 
-        if reset {
-            self.buffer.updated_ranges.clear();
+        let root_offset = dst.len();
+        let result = serialize_octant(&self.octree, root_id, dst, 0, &|params| {
+            let uid = params.content.unique_id();
+            let (Some(info), Some(offset)) = (self.leaf_info.get(&uid), offsets.get(&uid)) else {
+                return;
+            };
+
+            let mut mask = ((info.serialization.child_mask as u32) << 8) | info.serialization.leaf_mask as u32;
+            if (params.idx % 2) != 0 {
+                mask <<= 16;
+            }
+            params.dst[(params.idx / 2) as usize] |= mask;
+            params.dst[(4 + params.idx) as usize] = *offset;
+            params.result.depth = params.result.depth.max(info.serialization.depth + 1);
+        });
+
+        let info = LeafInfo { buf_offset: root_offset - Self::PREAMBLE_LENGTH as usize, serialization: result };
+        unsafe { Self::write_preamble(info, dst.as_mut_ptr()); }
+
+        result
+    }
+
+    /// Returns a decoder for traversing the serialized data on the CPU. Returns `None` if nothing was serialized yet.
+    /// Must be called after [`Svo::serialize`].
+    pub fn decoder(&self) -> Option<SvoDecoder<'_>> {
+        let preamble = self.preamble_words()?;
+        Some(SvoDecoder::with_preamble(preamble, &self.buffer.bytes))
+    }
+
+    /// Returns the serialized leaf value at the given position by traversing the serialized data like the shaders do.
+    /// For SVOs of [`SerializedChunk`]s, this is the block id at the block position in SVO space. Returns `None` if
+    /// there is no value. Must be called after [`Svo::serialize`].
+    pub fn read_leaf(&self, pos: Position) -> Option<u32> {
+        self.decoder()?.lookup(pos, self.depth())
+    }
+
+    /// Returns a FNV-1a checksum of the serialized buffer, e.g. to detect corrupted data after loading it from disk.
+    /// It is computed over the little-endian bytes and is hence the same on every platform.
+    pub fn checksum(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        self.buffer.bytes.iter()
+            .flat_map(|x| x.to_le_bytes())
+            .fold(OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+    }
+
+    /// Traverses the serialized data like the shaders do and checks that all pointers stay inside the buffer, that
+    /// every absolute pointer points to a serialized leaf and that the traversed depth matches the root's depth.
+    /// Serialized leaves must be octants themselves, like [`SerializedChunk`]s are. Must be called after
+    /// [`Svo::serialize`].
+    pub fn validate(&self) -> Result<(), SvoValidationError> {
+        let Some(decoder) = self.decoder() else {
+            return Ok(());
+        };
+
+        let targets = self.buffer.octant_to_range.values()
+            .map(|range| range.start as u32 + Self::PREAMBLE_LENGTH)
+            .collect::<FxHashSet<u32>>();
+
+        // the preamble contains the root octant as child 0
+        let expected = self.depth();
+        let actual = Self::validate_octant(&decoder, &targets, 0, 0, 0, expected)?;
+        if actual != expected {
+            return Err(SvoValidationError::DepthMismatch { expected, actual });
         }
+        Ok(())
+    }
+
+    /// Validates the child octant at `idx` of the octant at `ptr` and all of its children. Returns its depth.
+    fn validate_octant(decoder: &SvoDecoder, targets: &FxHashSet<u32>, ptr: u32, idx: u8, level: u8, max_depth: u8) -> Result<u8, SvoValidationError> {
+        let (child_mask, leaf_mask) = decoder.read_descriptor(ptr, idx);
+        if child_mask == 0 {
+            return Ok(0);
+        }
+
+        // non-empty octants are at least one level deep, this also prevents endless recursion in case of pointer cycles
+        if level >= max_depth {
+            return Err(SvoValidationError::DepthMismatch { expected: max_depth, actual: level + 1 });
+        }
+        if leaf_mask & !child_mask != 0 {
+            return Err(SvoValidationError::InvalidMask { ptr, idx });
+        }
+
+        let is_relative = decoder.read_leaf(ptr, idx) & (1 << 31) != 0;
+        let next_ptr = decoder.read_next_ptr(ptr, idx);
+        if !is_relative && !targets.contains(&next_ptr) {
+            return Err(SvoValidationError::UnknownAbsolutePointer { ptr: next_ptr });
+        }
+        if next_ptr as usize + 12 > decoder.len() {
+            return Err(SvoValidationError::PointerOutOfBounds { ptr: next_ptr });
+        }
+
+        let mut depth = 0;
+        for child_idx in 0..8 {
+            let bit = 1 << child_idx;
+            if child_mask & bit == 0 {
+                continue;
+            }
+            if leaf_mask & bit != 0 {
+                depth = depth.max(1);
+                continue;
+            }
+            let child_depth = Self::validate_octant(decoder, targets, next_ptr, child_idx, level + 1, max_depth)?;
+            depth = depth.max(child_depth + 1);
+        }
+        Ok(depth)
+    }
+
+    /// Resets the change tracker used by [`Svo::write_changes_to`], e.g. after the full buffer was written using
+    /// [`Svo::write_to`].
+    pub fn reset_change_tracking(&mut self) {
+        self.buffer.updated_ranges.clear();
+    }
+
+    /// Returns the ranges of the serialized buffer, excluding the preamble, that changed since the last reset of the
+    /// change tracker.
+    pub fn changed_ranges(&self) -> &[Range] {
+        &self.buffer.updated_ranges
+    }
+
+    /// Returns the number of bytes of serialized data that the next call to [`Svo::write_changes_to`] copies,
+    /// excluding the preamble.
+    pub fn pending_update_bytes(&self) -> usize {
+        self.buffer.updated_ranges.iter().map(|range| range.length).sum::<usize>() * 4
+    }
+
+    /// Returns the number of separate ranges that the next call to [`Svo::write_changes_to`] copies.
+    pub fn pending_update_count(&self) -> usize {
+        self.buffer.updated_ranges.len()
     }
 
     /// Writes a "fake" octant with the SVO root octant as its first child octant to build the entry point into
@@ -366,6 +735,27 @@ impl<T: SvoSerializable, A: Allocator> Svo<T, A> {
     }
 }
 
+/// `BufferTooSmall` is returned by [`Svo::write_changes_to`] if the target buffer cannot hold all changes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BufferTooSmall {
+    /// `required` is the minimum number of `u32` elements the target buffer must have.
+    pub required: usize,
+}
+
+/// `SvoValidationError` is returned by [`Svo::validate`] if the serialized data is inconsistent.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SvoValidationError {
+    /// A pointer resolves to a position at which no complete octant fits into the buffer.
+    PointerOutOfBounds { ptr: u32 },
+    /// An absolute pointer does not point to the start of a serialized leaf.
+    UnknownAbsolutePointer { ptr: u32 },
+    /// The leaf mask of the child octant at `idx` of the octant at `ptr` contains children that are not part of its
+    /// child mask.
+    InvalidMask { ptr: u32, idx: u8 },
+    /// The traversed depth differs from the serialized root's depth.
+    DepthMismatch { expected: u8, actual: u8 },
+}
+
 /// `SerializedChunk` is a wrapper that serializes the given chunk on creation and stores the results.
 pub struct SerializedChunk {
     pub pos: ChunkPos,
@@ -373,24 +763,142 @@ pub struct SerializedChunk {
     pub lod: u8,
     pub borrowed_chunk: Option<BorrowedChunk>,
     buffer: Option<Pooled<ChunkBuffer<StatsAllocator>>>,
+    /// Run-length encoded data that is kept instead of `buffer`, if the chunk was created with compression. See
+    /// [`rle_encode`].
+    compressed: Option<Vec<u32>>,
     result: SerializationResult,
+    /// All levels of detail stored in `buffer`, if created by [`SerializedChunk::new_with_lods`].
+    lod_levels: Vec<LodLevel>,
+}
+
+/// `LodLevel` describes one level of detail stored inside a [`SerializedChunk`] created by
+/// [`SerializedChunk::new_with_lods`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LodLevel {
+    pub lod: u8,
+    /// Offset in `u32` elements relative to the start of the chunk's serialized data.
+    pub offset: usize,
+    /// Metadata of the serialized octree, like it would be returned for a chunk serialized with only this level.
+    pub serialization: SerializationResult,
 }
 
 impl SerializedChunk {
+    /// Serializes the given chunk. Its storage is compacted beforehand, if the chunk's
+    /// [`crate::world::chunk::CompactionPolicy`] requires it.
     pub fn new(chunk: BorrowedChunk, alloc: &Arc<ChunkBufferPool>) -> Self {
+        Self::with_compression(chunk, alloc, false)
+    }
+
+    /// Like [`SerializedChunk::new`], but if `compress` is true, the serialized data is kept run-length encoded until
+    /// it is copied into the SVO. This reduces the memory of chunks that are mostly empty or uniform, as their
+    /// serialized octants mostly consist of zeros. The encoded data is stored outside of the pool, so that the pooled
+    /// buffer is returned right away and keeps its capacity for the next chunk.
+    pub fn with_compression(chunk: BorrowedChunk, alloc: &Arc<ChunkBufferPool>, compress: bool) -> Self {
+        let mut chunk = chunk;
+        chunk.compact_if_needed();
+
         let pos = chunk.pos;
         let lod = chunk.lod;
-
-        // use hash of position as the unique id
-        let mut hasher = DefaultHasher::new();
-        pos.hash(&mut hasher);
-        let pos_hash = hasher.finish();
+        let pos_hash = Self::hash_pos(&pos);
 
         let storage = chunk.storage.as_ref().unwrap();
         let mut buffer = alloc.allocate();
         let result = Self::serialize(storage, &mut buffer.data, lod);
+        let (buffer, compressed) = if result.depth == 0 {
+            (None, None)
+        } else if compress {
+            let mut encoded = Vec::new();
+            rle_encode(&buffer.data, &mut encoded);
+            encoded.shrink_to_fit();
+            (None, Some(encoded))
+        } else {
+            (Some(buffer), None)
+        };
+        Self { pos, pos_hash, lod, borrowed_chunk: Some(chunk), buffer, compressed, result, lod_levels: Vec::new() }
+    }
+
+    /// Serializes the given chunk once for every level of detail in `lods`, so that renderers can blend between
+    /// adjacent levels instead of switching abruptly. The chunk's own `lod` is ignored.
+    ///
+    /// Every level is a self-contained octree. They are stored back-to-back in ascending order:
+    /// ```text
+    /// [octree for lods.start][octree for lods.start + 1]...[octree for lods.end - 1]
+    /// ```
+    /// The SVO only points at the first level, so without further changes the chunk is rendered like one serialized
+    /// with `lods.start`, using its child and leaf masks. Since octrees only contain relative pointers, every other
+    /// level can be traversed by adding its [`LodLevel::offset`] to the chunk's pointer and using the masks of its
+    /// [`LodLevel::serialization`].
+    ///
+    /// # Panics
+    ///
+    /// If `lods` is empty.
+    pub fn new_with_lods(chunk: BorrowedChunk, alloc: &Arc<ChunkBufferPool>, lods: std::ops::Range<u8>) -> Self {
+        assert!(!lods.is_empty(), "at least one level of detail is required");
+
+        let mut chunk = chunk;
+        chunk.compact_if_needed();
+
+        let pos = chunk.pos;
+        let pos_hash = Self::hash_pos(&pos);
+
+        let storage = chunk.storage.as_ref().unwrap();
+        let mut buffer = alloc.allocate();
+        let mut lod_levels = Vec::with_capacity(lods.len());
+        for lod in lods.clone() {
+            let offset = buffer.data.len();
+            let serialization = Self::serialize(storage, &mut buffer.data, lod);
+            lod_levels.push(LodLevel { lod, offset, serialization });
+        }
+
+        let result = lod_levels[0].serialization;
         let buffer = if result.depth > 0 { Some(buffer) } else { None };
-        Self { pos, pos_hash, lod, borrowed_chunk: Some(chunk), buffer, result }
+        Self { pos, pos_hash, lod: lods.start, borrowed_chunk: Some(chunk), buffer, compressed: None, result, lod_levels }
+    }
+
+    /// Serializes a dense array of `size * size * size` blocks, indexed by `x + y * size + z * size * size`, without
+    /// creating a chunk first. This is meant for importers and test fixtures. Blocks equal to [`NO_BLOCK`] are treated
+    /// as empty. The resulting chunk has no borrowed chunk attached.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is not a power of two greater than one or if `blocks` does not contain exactly `size^3` elements.
+    pub fn from_dense(pos: ChunkPos, blocks: &[BlockId], size: u32, lod: u8, alloc: &Arc<ChunkBufferPool>) -> Self {
+        assert!(size > 1 && size.is_power_of_two(), "size must be a power of two greater than one");
+        assert_eq!(blocks.len(), (size * size * size) as usize, "blocks must contain size^3 elements");
+
+        let mut octree = Octree::new();
+        octree.construct_octants_with(size.trailing_zeros() as u8, |pos| {
+            let block = blocks[(pos.0 + pos.1 * size + pos.2 * size * size) as usize];
+            if block == NO_BLOCK { None } else { Some(block) }
+        });
+
+        let mut buffer = alloc.allocate();
+        let result = Self::serialize(&octree, &mut buffer.data, lod);
+        let buffer = if result.depth > 0 { Some(buffer) } else { None };
+        Self { pos, pos_hash: Self::hash_pos(&pos), lod, borrowed_chunk: None, buffer, compressed: None, result, lod_levels: Vec::new() }
+    }
+
+    /// Returns all levels of detail, if the chunk was created by [`SerializedChunk::new_with_lods`].
+    pub fn lod_levels(&self) -> &[LodLevel] {
+        &self.lod_levels
+    }
+
+    /// Uses the hash of the chunk position as the unique id.
+    fn hash_pos(pos: &ChunkPos) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        pos.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns true if the chunk still holds serialized data that was not yet copied into an SVO.
+    pub fn has_data(&self) -> bool {
+        self.buffer.is_some() || self.compressed.is_some()
+    }
+
+    /// Returns the size in bytes of the run-length encoded data, if the chunk is compressed and was not copied into the
+    /// SVO yet.
+    pub fn compressed_size(&self) -> Option<usize> {
+        self.compressed.as_ref().map(|encoded| encoded.len() * 4)
     }
 
     fn serialize<A1: Allocator, A2: Allocator>(octree: &Octree<BlockId, A1>, dst: &mut Vec<u32, A2>, lod: u8) -> SerializationResult {
@@ -418,19 +926,49 @@ impl SvoSerializable for SerializedChunk {
     /// Serializes the already serialized chunk by copying its results into the given buffer and returning the cached
     /// result.
     fn serialize(&mut self, dst: &mut Vec<u32>, _lod: u8) -> SerializationResult {
-        if self.buffer.is_some() {
-            let buffer = self.buffer.as_ref().unwrap();
+        // Drop the buffer so that he allocator can reuse it. A SerializedChunk only needs it's buffer for the
+        // serialization to the SVO. After that, it is indexed by an absolute pointer. If the content changes
+        // however, a new SerializedChunk is built and the old one discarded.
+        if let Some(encoded) = self.compressed.take() {
+            rle_decode(&encoded, dst);
+        } else if let Some(buffer) = self.buffer.take() {
             dst.extend(buffer.data.iter());
-
-            // Drop the buffer so that he allocator can reuse it. A SerializedChunk only needs it's buffer for the
-            // serialization to the SVO. After that, it is indexed by an absolute pointer. If the content changes
-            // however, a new SerializedChunk is built and the old one discarded.
-            self.buffer = None;
         }
         self.result
     }
 }
 
+/// Run-length encodes runs of zeros in `src` and appends the result to `dst`. The encoded data consists of segments,
+/// each starting with the number of literal values, followed by the literals themselves and the number of zeros after
+/// them.
+fn rle_encode(src: &[u32], dst: &mut Vec<u32>) {
+    let mut i = 0;
+    while i < src.len() {
+        let literals = src[i..].iter().take_while(|x| **x != 0).count();
+        dst.push(literals as u32);
+        dst.extend_from_slice(&src[i..i + literals]);
+        i += literals;
+
+        let zeros = src[i..].iter().take_while(|x| **x == 0).count();
+        dst.push(zeros as u32);
+        i += zeros;
+    }
+}
+
+/// Expands data encoded by [`rle_encode`] and appends it to `dst`.
+fn rle_decode<A: Allocator>(src: &[u32], dst: &mut Vec<u32, A>) {
+    let mut i = 0;
+    while i < src.len() {
+        let literals = src[i] as usize;
+        dst.extend_from_slice(&src[i + 1..i + 1 + literals]);
+        i += 1 + literals;
+
+        let zeros = src[i] as usize;
+        dst.extend(std::iter::repeat(0).take(zeros));
+        i += 1;
+    }
+}
+
 struct ChildEncodeParams<'a, T> {
     /// Id of the octant containing the child to be serialized.
     parent_id: OctantId,
@@ -557,14 +1095,118 @@ fn pick_leaf_for_lod<'a, T, A: Allocator>(octree: &'a Octree<T, A>, parent: &'a
     None
 }
 
+/// `SvoDecoder` reads a serialized SVO on the CPU in the same way the shaders do. It expects the buffer layout written
+/// by [`Svo::write_to`], i.e. starting with the preamble. This allows for validating serialized data and for tools that
+/// traverse the SVO without a GPU.
+pub struct SvoDecoder<'a> {
+    /// Preamble to be used in front of `data`, if `data` does not start with it. See [`SvoDecoder::with_preamble`].
+    preamble: Option<[u32; 5]>,
+    data: &'a [u32],
+}
+
+impl<'a> SvoDecoder<'a> {
+    pub fn new(data: &'a [u32]) -> Self {
+        Self { preamble: None, data }
+    }
+
+    /// Decodes `data` as if it were prefixed with `preamble`. This allows for traversing the serialized buffer of an
+    /// [`Svo`] without copying it.
+    pub fn with_preamble(preamble: [u32; 5], data: &'a [u32]) -> Self {
+        Self { preamble: Some(preamble), data }
+    }
+
+    /// Returns the number of `u32` elements including the preamble.
+    pub fn len(&self) -> usize {
+        self.preamble.map_or(0, |preamble| preamble.len()) + self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the child and leaf mask of the octant at child index `idx` of the octant at `ptr`.
+    pub fn read_descriptor(&self, ptr: u32, idx: u8) -> (u8, u8) {
+        let mut descriptor = self.get(ptr + idx as u32 / 2);
+        if idx % 2 != 0 {
+            descriptor >>= 16;
+        }
+        ((descriptor >> 8) as u8, descriptor as u8)
+    }
+
+    /// Resolves the pointer to the child octant at `idx` of the octant at `ptr`. Mirrors `get_octant_ptr` in
+    /// `svo.glsl`.
+    pub fn read_next_ptr(&self, ptr: u32, idx: u8) -> u32 {
+        let offset = ptr + 4 + idx as u32;
+        let next_ptr = self.get(offset);
+        if next_ptr & (1 << 31) != 0 {
+            // use as relative offset if relative bit is set
+            return offset + (next_ptr & 0x7fff_ffff);
+        }
+        next_ptr
+    }
+
+    /// Returns the raw leaf value at child index `idx` of the octant at `ptr`.
+    pub fn read_leaf(&self, ptr: u32, idx: u8) -> u32 {
+        self.get(ptr + 4 + idx as u32)
+    }
+
+    fn get(&self, index: u32) -> u32 {
+        let index = index as usize;
+        match &self.preamble {
+            Some(preamble) if index < preamble.len() => preamble[index],
+            Some(preamble) => self.data[index - preamble.len()],
+            None => self.data[index],
+        }
+    }
+
+    /// Returns the leaf value at the given position inside an SVO with the given total `depth`, or None if there is no
+    /// leaf. Mirrors `lookup_octree` in `svo.glsl`.
+    pub fn lookup(&self, pos: Position, depth: u8) -> Option<u32> {
+        let mut size = 1u32 << depth;
+        if pos.0 >= size || pos.1 >= size || pos.2 >= size {
+            return None;
+        }
+
+        // the preamble contains the root octant as child 0
+        let mut ptr = 0;
+        let mut parent_idx = 0;
+        let mut pos = pos;
+
+        while size > 1 {
+            size /= 2;
+            let idx = (pos / size).idx();
+            pos %= size;
+
+            let (child_mask, leaf_mask) = self.read_descriptor(ptr, parent_idx);
+            let bit = 1 << idx;
+            if child_mask & bit == 0 {
+                return None;
+            }
+
+            let next_ptr = self.read_next_ptr(ptr, parent_idx);
+            if leaf_mask & bit != 0 {
+                return Some(self.read_leaf(next_ptr, idx));
+            }
+
+            ptr = next_ptr;
+            parent_idx = idx;
+        }
+
+        None
+    }
+}
+
 #[cfg(test)]
 mod svo_tests {
+    use std::sync::Arc;
+
     use rustc_hash::FxHashMap;
 
-    use crate::world::chunk::{BlockId, ChunkPos};
+    use crate::world::chunk::{BlockId, Chunk, ChunkPos, ChunkStorageAllocator};
     use crate::world::memory::{Pool, StatsAllocator};
     use crate::world::octree::{LeafId, Octree, Position};
-    use crate::world::svo::{ChunkBuffer, LeafInfo, Range, SerializationResult, SerializedChunk, Svo, SvoBuffer};
+    use crate::world::svo::{BufferTooSmall, ChunkBuffer, LeafInfo, LodLevel, Range, rle_decode, rle_encode, SerializationResult, SerializedChunk, Svo, SvoBuffer, SvoSerializable, SvoValidationError};
+    use crate::world::world::BorrowedChunk;
 
     /// Tests that serializing an SVO with `SerializedChunk` values produces the expected result buffer.
     #[test]
@@ -584,8 +1226,10 @@ mod svo_tests {
             lod: 0,
             borrowed_chunk: None,
             buffer: Some(buffer),
+            compressed: None,
             result,
             pos_hash: 100,
+            lod_levels: Vec::new(),
         };
 
         let mut svo = Svo::new();
@@ -851,7 +1495,7 @@ mod svo_tests {
             ]),
         });
 
-        unsafe { svo.write_changes_to(buffer.as_mut_ptr(), buffer.capacity(), true); };
+        unsafe { svo.write_changes_to(buffer.as_mut_ptr(), buffer.capacity(), true).unwrap(); };
         assert_eq!(buffer[..size], [
             vec![
                 // preamble
@@ -865,6 +1509,435 @@ mod svo_tests {
         ].concat());
     }
 
+    /// Tests that defragmenting an SVO shrinks its buffer and keeps the root's absolute pointers valid.
+    #[test]
+    fn defragment() {
+        let mut svo = Svo::new();
+        assert!(!svo.defragment());
+
+        let mut leaf_ids = Vec::new();
+        for i in 0..64u32 {
+            let (leaf_id, _) = svo.set_leaf(Position(i % 4, i / 4 % 4, i / 16), 100 + i, true);
+            leaf_ids.push(leaf_id);
+        }
+        svo.serialize();
+        for leaf_id in leaf_ids.into_iter().step_by(2) {
+            svo.remove_leaf(leaf_id);
+        }
+        svo.serialize();
+
+        let size_before = svo.size_in_bytes();
+        assert!(svo.free_bytes() > 0);
+        assert!(svo.defragment());
+        assert!(svo.size_in_bytes() < size_before);
+        assert_eq!(svo.free_bytes(), 0);
+
+        let root_info = svo.root_info.unwrap();
+        assert_eq!(root_info.buf_offset, svo.buffer.octant_to_range[&u64::MAX].start);
+        for i in (1..64u32).step_by(2) {
+            let info = svo.leaf_info[&(100 + i as u64)];
+            assert_eq!(svo.buffer.bytes[info.buf_offset], 100 + i);
+        }
+
+        // the root octree references the new leaf offsets
+        let root_range = svo.buffer.octant_to_range[&u64::MAX];
+        let root = &svo.buffer.bytes[root_range.start..root_range.start + root_range.length];
+        for i in (1..64u64).step_by(2) {
+            let pointer = svo.leaf_info[&(100 + i)].buf_offset as u32 + 5;
+            assert!(root.contains(&pointer), "missing pointer for leaf {i}");
+        }
+
+        assert!(!svo.defragment());
+    }
+
+    /// Tests that exceeding the byte budget evicts the leaves farthest away from the eviction center first, if they were
+    /// serialized at the same time, and that older leaves are evicted before closer ones.
+    #[test]
+    fn evict_over_budget() {
+        let mut svo = Svo::new();
+        svo.set_max_bytes(Some(3 * 4));
+        svo.set_eviction_center(Position(5, 0, 0));
+
+        for i in 1..=3u32 {
+            svo.set_leaf(Position(i, 0, 0), i, true);
+        }
+        svo.serialize();
+        assert!(svo.take_evicted().is_empty());
+
+        // the new leaves are newer, so two of the older leaves have to go, starting with the farthest
+        svo.set_leaf(Position(4, 0, 0), 4, true);
+        svo.set_leaf(Position(5, 0, 0), 5, true);
+        svo.serialize();
+
+        let mut evicted = svo.take_evicted();
+        evicted.sort_unstable();
+        assert_eq!(evicted, vec![1, 2]);
+        assert_eq!(svo.get_leaf(Position(1, 0, 0)), None);
+        assert_eq!(svo.get_leaf(Position(2, 0, 0)), None);
+        assert!(!svo.leaf_info.contains_key(&1));
+        assert!(!svo.leaf_info.contains_key(&2));
+        assert_eq!(svo.leaf_bytes(), 3 * 4);
+
+        // after moving the center, the oldest leaf is evicted, even though it is closer than the newer ones
+        svo.set_eviction_center(Position(0, 0, 0));
+        svo.set_leaf(Position(0, 0, 0), 6, true);
+        svo.serialize();
+        assert_eq!(svo.take_evicted(), vec![3]);
+        assert_eq!(svo.get_leaf(Position(0, 0, 0)), Some(&6));
+        assert_eq!(svo.get_leaf(Position(4, 0, 0)), Some(&4));
+        assert_eq!(svo.get_leaf(Position(5, 0, 0)), Some(&5));
+    }
+
+    /// Tests that inserting leaves past the byte budget evicts the least recently serialized leaves in the order they
+    /// were serialized.
+    #[test]
+    fn evict_least_recently_serialized() {
+        let mut svo = Svo::new();
+        svo.set_max_bytes(Some(3 * 4));
+
+        // leaves closer to the eviction center are serialized later, so that distance alone would evict the newest
+        for i in (1..=6u32).rev() {
+            svo.set_leaf(Position(i, 0, 0), i, true);
+            svo.serialize();
+        }
+        assert_eq!(svo.take_evicted(), vec![6, 5, 4]);
+        assert_eq!(svo.leaf_bytes(), 3 * 4);
+
+        // serializing an old leaf again makes it the most recent one
+        svo.set_leaf(Position(3, 0, 0), 3, true);
+        svo.set_leaf(Position(7, 0, 0), 7, true);
+        svo.serialize();
+        assert_eq!(svo.take_evicted(), vec![2]);
+        assert_eq!(svo.get_leaf(Position(1, 0, 0)), Some(&1));
+        assert_eq!(svo.get_leaf(Position(3, 0, 0)), Some(&3));
+        assert_eq!(svo.get_leaf(Position(7, 0, 0)), Some(&7));
+    }
+
+    /// Tests that an octree built with `Octree::set_leaves` serializes to the same buffer as one built by setting every
+    /// leaf one by one.
+    #[test]
+    fn serialize_set_leaves() {
+        let items: Vec<(Position, BlockId)> = vec![
+            (Position(1, 1, 3), 10),
+            (Position(6, 7, 5), 20),
+            (Position(0, 0, 0), 30),
+            (Position(1, 1, 3), 40),
+            (Position(15, 2, 9), 50),
+        ];
+
+        let mut expected_octree = Octree::new();
+        for (pos, leaf) in items.clone() {
+            expected_octree.set_leaf(pos, leaf);
+        }
+        let mut expected = Vec::new();
+        let expected_result = SerializedChunk::serialize(&expected_octree, &mut expected, 0);
+
+        let mut octree = Octree::new();
+        octree.set_leaves(items);
+        let mut actual = Vec::new();
+        let actual_result = SerializedChunk::serialize(&octree, &mut actual, 0);
+
+        assert_eq!(actual, expected);
+        assert_eq!(actual_result, expected_result);
+    }
+
+    /// Tests that run-length encoding round trips for different inputs.
+    #[test]
+    fn rle_round_trip() {
+        for input in [
+            vec![],
+            vec![0, 0, 0, 0],
+            vec![1, 2, 3],
+            vec![0, 1, 0, 0, 2, 3, 0],
+            vec![1 << 31, 0, 0, 0, 0, 0, 0, 0, 0, u32::MAX],
+        ] {
+            let mut encoded = Vec::new();
+            rle_encode(&input, &mut encoded);
+
+            let mut decoded = Vec::new();
+            rle_decode(&encoded, &mut decoded);
+            assert_eq!(decoded, input);
+        }
+
+        let mut encoded = Vec::new();
+        rle_encode(&[0, 1, 0, 0, 2, 3, 0], &mut encoded);
+        assert_eq!(encoded, vec![0, 1, 1, 1, 2, 2, 2, 3, 1]);
+    }
+
+    /// Tests that compressed chunks serialize to the same data as uncompressed ones, while using less memory.
+    #[test]
+    fn serialize_compressed_chunk() {
+        let storage_alloc = ChunkStorageAllocator::new();
+        let alloc = Arc::new(Pool::new_in(Box::new(ChunkBuffer::new_in), None, StatsAllocator::new()));
+
+        let new_chunk = || {
+            let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0), 5, storage_alloc.allocate());
+            chunk.set_block(0, 0, 0, 1);
+            chunk.set_block(31, 31, 31, 2);
+            chunk.set_block(5, 6, 7, 3);
+            BorrowedChunk::from(chunk)
+        };
+
+        let mut uncompressed = SerializedChunk::new(new_chunk(), &alloc);
+        let mut compressed = SerializedChunk::with_compression(new_chunk(), &alloc, true);
+        assert_eq!(uncompressed.compressed_size(), None);
+        // the pooled buffer of the compressed chunk is returned right away
+        assert_eq!(alloc.used_count(), 1);
+
+        let uncompressed_size = uncompressed.buffer.as_ref().unwrap().data.len() * 4;
+        assert!(compressed.compressed_size().unwrap() < uncompressed_size);
+
+        let mut expected = Vec::new();
+        let expected_result = uncompressed.serialize(&mut expected, 0);
+        let mut actual = Vec::new();
+        let actual_result = compressed.serialize(&mut actual, 0);
+        assert_eq!(actual, expected);
+        assert_eq!(actual_result, expected_result);
+        assert_eq!(compressed.compressed_size(), None);
+    }
+
+    /// Tests that chunks of other sizes than the default serialize with their own depth.
+    #[test]
+    fn serialize_chunk_sizes() {
+        let alloc = Arc::new(Pool::new_in(Box::new(ChunkBuffer::new_in), None, StatsAllocator::new()));
+
+        for depth in [4, 6] {
+            let storage_alloc = ChunkStorageAllocator::with_depth(depth).unwrap();
+            let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0), depth, storage_alloc.allocate());
+            let last = chunk.size() - 1;
+            chunk.set_block(0, 0, 0, 1);
+            chunk.set_block(last, last, last, 2);
+
+            let serialized = SerializedChunk::new(BorrowedChunk::from(chunk), &alloc);
+            assert_eq!(serialized.result.depth, depth);
+            assert_eq!(serialized.result.child_mask, 1 | 128);
+        }
+    }
+
+    /// Tests that all levels of detail are serialized back-to-back and that the first one is used by default.
+    #[test]
+    fn serialize_chunk_with_lods() {
+        let storage_alloc = ChunkStorageAllocator::new();
+        let alloc = Arc::new(Pool::new_in(Box::new(ChunkBuffer::new_in), None, StatsAllocator::new()));
+
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0), 5, storage_alloc.allocate());
+        chunk.set_block(0, 0, 0, 1);
+        chunk.set_block(31, 31, 31, 2);
+        chunk.set_block(5, 6, 7, 3);
+
+        let mut expected = Vec::new();
+        let mut expected_levels = Vec::new();
+        for lod in 3..6 {
+            let offset = expected.len();
+            let serialization = SerializedChunk::serialize(chunk.storage.as_ref().unwrap(), &mut expected, lod);
+            expected_levels.push(LodLevel { lod, offset, serialization });
+        }
+
+        let mut sc = SerializedChunk::new_with_lods(BorrowedChunk::from(chunk), &alloc, 3..6);
+        assert_eq!(sc.lod, 3);
+        assert_eq!(sc.lod_levels(), expected_levels.as_slice());
+        assert!(expected_levels.windows(2).all(|w| w[0].offset < w[1].offset));
+
+        let mut actual = Vec::new();
+        let result = sc.serialize(&mut actual, 0);
+        assert_eq!(actual, expected);
+        assert_eq!(result, expected_levels[0].serialization);
+    }
+
+    /// Tests that serializing a dense block array produces the same data as setting every block of a chunk.
+    #[test]
+    fn serialize_dense_chunk() {
+        let storage_alloc = ChunkStorageAllocator::new();
+        let alloc = Arc::new(Pool::new_in(Box::new(ChunkBuffer::new_in), None, StatsAllocator::new()));
+
+        let mut blocks = vec![0 as BlockId; 32 * 32 * 32];
+        let mut chunk = Chunk::new(ChunkPos::new(1, 2, 3), 5, storage_alloc.allocate());
+        for z in 0..32 {
+            for y in 0..32 {
+                for x in 0..32 {
+                    if (x + y + z) % 7 != 0 {
+                        continue;
+                    }
+                    let block = x % 3 + 1;
+                    blocks[(x + y * 32 + z * 32 * 32) as usize] = block;
+                    chunk.set_block(x, y, z, block);
+                }
+            }
+        }
+
+        let mut expected_chunk = SerializedChunk::new(BorrowedChunk::from(chunk), &alloc);
+        let mut dense_chunk = SerializedChunk::from_dense(ChunkPos::new(1, 2, 3), &blocks, 32, 5, &alloc);
+        assert_eq!(dense_chunk.unique_id(), expected_chunk.unique_id());
+        assert!(dense_chunk.borrowed_chunk.is_none());
+
+        let mut expected = Vec::new();
+        let expected_result = expected_chunk.serialize(&mut expected, 0);
+        let mut actual = Vec::new();
+        let actual_result = dense_chunk.serialize(&mut actual, 0);
+        assert_eq!(actual, expected);
+        assert_eq!(actual_result, expected_result);
+    }
+
+    /// Tests that validation accepts correctly serialized data and detects corrupted pointers and depths.
+    #[test]
+    fn validate_and_checksum() {
+        let alloc = Arc::new(Pool::new_in(Box::new(ChunkBuffer::new_in), None, StatsAllocator::new()));
+        let new_svo = || {
+            let mut svo = Svo::new();
+            for i in 0..2u32 {
+                let mut blocks = vec![0 as BlockId; 32 * 32 * 32];
+                blocks[0] = 1;
+                blocks[31 + 5 * 32 + 7 * 32 * 32] = 2 + i;
+                let chunk = SerializedChunk::from_dense(ChunkPos::new(i as i32, 0, 0), &blocks, 32, 5, &alloc);
+                svo.set_leaf(Position(i, 0, 0), chunk, true);
+            }
+            svo.serialize();
+            svo
+        };
+
+        let mut svo = new_svo();
+        assert_eq!(svo.validate(), Ok(()));
+        assert_eq!(svo.checksum(), svo.checksum());
+
+        // the root references the first chunk with an absolute pointer
+        let root_start = svo.buffer.octant_to_range[&u64::MAX].start;
+        let root_ptr = root_start + 4;
+        let valid_ptr = svo.buffer.bytes[root_ptr];
+
+        svo.buffer.bytes[root_ptr] = 7;
+        assert_eq!(svo.validate(), Err(SvoValidationError::UnknownAbsolutePointer { ptr: 7 }));
+        assert_ne!(svo.checksum(), new_svo().checksum());
+
+        svo.buffer.bytes[root_ptr] = (1 << 31) | 100_000;
+        assert!(matches!(svo.validate(), Err(SvoValidationError::PointerOutOfBounds { .. })));
+
+        svo.buffer.bytes[root_ptr] = valid_ptr;
+        assert_eq!(svo.validate(), Ok(()));
+
+        svo.root_info.as_mut().unwrap().serialization.depth = 7;
+        assert_eq!(svo.validate(), Err(SvoValidationError::DepthMismatch { expected: 7, actual: 6 }));
+    }
+
+    /// Tests that writing changes to a buffer that is too small fails without writing anything or losing changes.
+    #[test]
+    fn write_changes_to_small_buffer() {
+        let mut svo = Svo::new();
+        svo.set_leaf(Position(0, 0, 0), 10, true);
+        svo.set_leaf(Position(1, 0, 0), 20, true);
+        svo.serialize();
+
+        let required = svo.size_in_bytes() / 4 + 5;
+        let mut buffer = vec![0u32; required];
+        let result = unsafe { svo.write_changes_to(buffer.as_mut_ptr(), required - 1, true) };
+        assert_eq!(result, Err(BufferTooSmall { required }));
+        assert!(buffer.iter().all(|x| *x == 0));
+
+        let result = unsafe { svo.write_changes_to(buffer.as_mut_ptr(), required, true) };
+        assert_eq!(result, Ok(()));
+
+        let mut expected = vec![0u32; required];
+        let size = unsafe { svo.write_to(expected.as_mut_ptr()) };
+        assert_eq!(size, required);
+        assert_eq!(buffer, expected);
+
+        // all changes were written
+        let result = unsafe { svo.write_changes_to(buffer.as_mut_ptr(), 0, true) };
+        assert_eq!(result, Ok(()));
+    }
+
+    /// Tests that a buffer, which missed previous changes, is brought up to date by writing the collected ranges of
+    /// all changes since, and that ranges outside the serialized buffer are skipped.
+    #[test]
+    fn write_ranges_to() {
+        let mut first = vec![0u32; 4096];
+        let mut second = vec![0u32; 4096];
+
+        let mut svo = Svo::new();
+        svo.set_leaf(Position(0, 0, 0), 10, true);
+        svo.serialize();
+        unsafe { svo.write_changes_to(first.as_mut_ptr(), first.len(), false).unwrap(); }
+        let mut missed = svo.changed_ranges().to_vec();
+        svo.reset_change_tracking();
+
+        svo.set_leaf(Position(1, 0, 0), 20, true);
+        svo.serialize();
+        missed.extend_from_slice(svo.changed_ranges());
+        unsafe {
+            svo.write_ranges_to(second.as_mut_ptr(), second.len(), &missed).unwrap();
+            svo.write_changes_to(first.as_mut_ptr(), first.len(), true).unwrap();
+        }
+
+        let mut expected = vec![0u32; 4096];
+        unsafe { svo.write_to(expected.as_mut_ptr()); }
+        assert_eq!(first, expected);
+        assert_eq!(second, expected);
+
+        let end = svo.size_in_bytes() / 4;
+        let result = unsafe { svo.write_ranges_to(second.as_mut_ptr(), end, &[Range { start: end, length: 100 }]) };
+        assert_eq!(result, Ok(()));
+        assert_eq!(second, expected);
+    }
+
+    /// Tests that pending update metrics reflect the changes that `write_changes_to` would copy.
+    #[test]
+    fn pending_updates() {
+        let mut svo = Svo::new();
+        assert_eq!(svo.pending_update_bytes(), 0);
+        assert_eq!(svo.pending_update_count(), 0);
+
+        svo.set_leaf(Position(0, 0, 0), 10, true);
+        svo.set_leaf(Position(1, 0, 0), 20, true);
+        svo.serialize();
+        assert_eq!(svo.pending_update_bytes(), svo.size_in_bytes());
+        assert_eq!(svo.pending_update_count(), 1);
+
+        svo.reset_change_tracking();
+        assert_eq!(svo.pending_update_bytes(), 0);
+        assert_eq!(svo.pending_update_count(), 0);
+    }
+
+    /// Tests that the raw serialized bytes combined with the preamble are identical to the output of `write_to`.
+    #[test]
+    fn serialized_bytes() {
+        let mut svo = Svo::new();
+        assert_eq!(svo.preamble_bytes(), None);
+        assert!(svo.serialized_bytes().is_empty());
+
+        svo.set_leaf(Position(0, 0, 0), 10, true);
+        svo.set_leaf(Position(1, 0, 0), 20, true);
+        svo.serialize();
+
+        let mut buffer = vec![0u32; 200];
+        let size = unsafe { svo.write_to(buffer.as_mut_ptr()) };
+        let expected = buffer[..size].iter().flat_map(|x| x.to_ne_bytes()).collect::<Vec<u8>>();
+
+        let preamble = svo.preamble_bytes().unwrap();
+        assert_eq!([preamble.as_slice(), svo.serialized_bytes()].concat(), expected);
+    }
+
+    /// Tests that little-endian bytes contain the same data as `write_to` and have a fixed byte order.
+    #[test]
+    fn to_le_bytes() {
+        let mut svo = Svo::new();
+        assert!(svo.to_le_bytes().is_empty());
+
+        svo.set_leaf(Position(1, 0, 0), 0x0102_0304, true);
+        svo.serialize();
+
+        let mut buffer = vec![0u32; 200];
+        let size = unsafe { svo.write_to(buffer.as_mut_ptr()) };
+
+        let bytes = svo.to_le_bytes();
+        assert_eq!(bytes.len(), size * 4);
+        let words = bytes.chunks_exact(4)
+            .map(|x| u32::from_le_bytes(x.try_into().unwrap()))
+            .collect::<Vec<u32>>();
+        assert_eq!(words, buffer[..size]);
+
+        // the only leaf value is serialized right after the preamble
+        assert_eq!(bytes[20..24], [4, 3, 2, 1]);
+    }
+
     /// Tests that all different LOD levels work correctly when serializing an SVO.
     #[test]
     fn serialize_with_lod() {
@@ -1236,6 +2309,144 @@ mod svo_tests {
     }
 }
 
+#[cfg(test)]
+mod svo_decoder_tests {
+    use crate::world::chunk::{BlockId, ChunkPos};
+    use crate::world::memory::{Pool, StatsAllocator};
+    use crate::world::octree::{Octree, Position};
+    use crate::world::svo::{ChunkBuffer, SerializedChunk, Svo, SvoDecoder};
+
+    /// Tests that decoding a serialized SVO on the CPU returns the original leaf values.
+    #[test]
+    fn lookup() {
+        let mut octree = Octree::new();
+        octree.set_leaf(Position(31, 0, 0), 1 as BlockId);
+        octree.set_leaf(Position(0, 31, 0), 2 as BlockId);
+        octree.set_leaf(Position(5, 6, 7), 3 as BlockId);
+        octree.expand_to(5);
+
+        let alloc = Pool::new_in(Box::new(ChunkBuffer::new_in), None, StatsAllocator::new());
+        let mut buffer = alloc.allocate();
+        let result = SerializedChunk::serialize(&octree, &mut buffer.data, 0);
+        let sc = SerializedChunk {
+            pos: ChunkPos::new(1, 0, 0),
+            lod: 0,
+            borrowed_chunk: None,
+            buffer: Some(buffer),
+            compressed: None,
+            result,
+            pos_hash: 100,
+            lod_levels: Vec::new(),
+        };
+
+        let mut svo = Svo::new();
+        svo.set_leaf(Position(1, 0, 0), sc, true);
+        svo.serialize();
+        assert_eq!(svo.depth(), 6);
+
+        let mut data = vec![0u32; svo.size_in_bytes() / 4 + 5];
+        let size = unsafe { svo.write_to(data.as_mut_ptr()) };
+        let decoder = SvoDecoder::new(&data[..size]);
+
+        assert_eq!(decoder.read_descriptor(0, 0), (2, 0));
+        assert_eq!(decoder.lookup(Position(32 + 31, 0, 0), 6), Some(1));
+        assert_eq!(decoder.lookup(Position(32, 31, 0), 6), Some(2));
+        assert_eq!(decoder.lookup(Position(32 + 5, 6, 7), 6), Some(3));
+
+        // empty space and positions outside of the SVO
+        assert_eq!(decoder.lookup(Position(32, 0, 0), 6), None);
+        assert_eq!(decoder.lookup(Position(31, 0, 0), 6), None);
+        assert_eq!(decoder.lookup(Position(64, 0, 0), 6), None);
+    }
+
+    /// Tests that an SVO serialized into a separate buffer is traversable and leaves the SVO's buffer untouched.
+    #[test]
+    fn serialize_into() {
+        let alloc = Pool::new_in(Box::new(ChunkBuffer::new_in), None, StatsAllocator::new());
+        let new_chunk = |pos_hash: u64, value: BlockId| {
+            let mut octree = Octree::new();
+            octree.set_leaf(Position(3, 2, 1), value);
+            octree.expand_to(5);
+
+            let mut buffer = alloc.allocate();
+            let result = SerializedChunk::serialize(&octree, &mut buffer.data, 0);
+            SerializedChunk {
+                pos: ChunkPos::new(0, 0, 0),
+                lod: 0,
+                borrowed_chunk: None,
+                buffer: Some(buffer),
+                compressed: None,
+                result,
+                pos_hash,
+                lod_levels: Vec::new(),
+            }
+        };
+
+        let mut svo = Svo::new();
+        svo.set_leaf(Position(0, 0, 0), new_chunk(100, 1), true);
+        let (removed, _) = svo.set_leaf(Position(1, 0, 0), new_chunk(101, 2), true);
+        svo.set_leaf(Position(0, 1, 0), new_chunk(102, 3), true);
+        svo.serialize();
+        svo.remove_leaf(removed);
+        svo.serialize();
+
+        let pending_bytes = svo.pending_update_bytes();
+        let size_in_bytes = svo.size_in_bytes();
+
+        let mut data = vec![42];
+        let result = svo.serialize_into(&mut data);
+        assert_eq!(result.depth, 6);
+        assert_eq!(svo.pending_update_bytes(), pending_bytes);
+        assert_eq!(svo.size_in_bytes(), size_in_bytes);
+
+        let decoder = SvoDecoder::new(&data);
+        assert_eq!(decoder.lookup(Position(3, 2, 1), 6), Some(1));
+        assert_eq!(decoder.lookup(Position(32 + 3, 2, 1), 6), None);
+        assert_eq!(decoder.lookup(Position(3, 32 + 2, 1), 6), Some(3));
+        assert_eq!(decoder.lookup(Position(0, 0, 0), 6), None);
+    }
+
+    /// Tests that reading leaves from a serialized SVO returns the same values as the source octree.
+    #[test]
+    fn read_leaf() {
+        let mut octree = Octree::new();
+        for i in 0..500u32 {
+            let pos = Position(i * 7 % 32, i * 13 % 32, i * 29 % 32);
+            octree.set_leaf(pos, i % 10 + 1 as BlockId);
+        }
+        octree.expand_to(5);
+
+        let alloc = Pool::new_in(Box::new(ChunkBuffer::new_in), None, StatsAllocator::new());
+        let mut buffer = alloc.allocate();
+        let result = SerializedChunk::serialize(&octree, &mut buffer.data, 0);
+        let sc = SerializedChunk {
+            pos: ChunkPos::new(1, 0, 0),
+            lod: 0,
+            borrowed_chunk: None,
+            buffer: Some(buffer),
+            compressed: None,
+            result,
+            pos_hash: 100,
+            lod_levels: Vec::new(),
+        };
+
+        let mut svo = Svo::new();
+        assert_eq!(svo.read_leaf(Position(0, 0, 0)), None);
+        svo.set_leaf(Position(1, 0, 0), sc, true);
+        svo.serialize();
+
+        for x in 0..32 {
+            for y in 0..32 {
+                for z in 0..32 {
+                    let expected = octree.get_leaf(Position(x, y, z)).copied();
+                    assert_eq!(svo.read_leaf(Position(32 + x, y, z)), expected, "mismatch at ({x}, {y}, {z})");
+                    assert_eq!(svo.read_leaf(Position(x, y, z)), None);
+                }
+            }
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct Range {
     pub start: usize,
@@ -1337,6 +2548,40 @@ impl<A: Allocator> SvoBuffer<A> {
         Self::merge_ranges(&mut self.free_ranges);
     }
 
+    /// Moves all used ranges to the front of the buffer while keeping their order and truncates the buffer to the
+    /// used length. Returns the id, old and new range for every range that was moved.
+    fn defragment(&mut self) -> Vec<(u64, Range, Range)> {
+        let mut used: Vec<_> = self.octant_to_range.iter().map(|(id, range)| (*id, *range)).collect();
+        used.sort_unstable_by_key(|(_, range)| range.start);
+
+        let mut moves = Vec::new();
+        let mut ptr = 0;
+        for (id, range) in used {
+            if range.start != ptr {
+                self.bytes.copy_within(range.start..range.start + range.length, ptr);
+
+                let new_range = Range { start: ptr, length: range.length };
+                self.octant_to_range.insert(id, new_range);
+                moves.push((id, range, new_range));
+            }
+            ptr += range.length;
+        }
+
+        self.bytes.truncate(ptr);
+        self.free_ranges.clear();
+
+        if let Some((_, _, first)) = moves.first() {
+            self.updated_ranges.push(Range { start: first.start, length: ptr - first.start });
+            Self::merge_ranges(&mut self.updated_ranges);
+        }
+        self.updated_ranges.retain(|range| range.start < ptr);
+        for range in &mut self.updated_ranges {
+            range.length = range.length.min(ptr - range.start);
+        }
+
+        moves
+    }
+
     /// Orders all free ranges by start index and merges adjacent ranges into one.
     fn merge_ranges(ranges: &mut Vec<Range>) {
         // Unstable is fine here as no equivalent objects can exist. It should be slightly faster
@@ -1469,6 +2714,52 @@ mod svo_buffer_tests {
         });
     }
 
+    /// Tests that defragmenting moves all used ranges to the front and shrinks the buffer.
+    #[test]
+    fn buffer_defragment() {
+        let mut buffer = SvoBuffer::with_capacity_in(0, Global);
+        buffer.insert(1, &ChunkBuffer { data: vec![0, 1] });
+        buffer.insert(2, &ChunkBuffer { data: vec![2, 3, 4] });
+        buffer.insert(3, &ChunkBuffer { data: vec![5] });
+        buffer.insert(4, &ChunkBuffer { data: vec![6, 7] });
+        buffer.remove(1);
+        buffer.remove(3);
+        buffer.updated_ranges.clear();
+
+        let moves = buffer.defragment();
+        assert_eq!(moves, vec![
+            (2, Range { start: 2, length: 3 }, Range { start: 0, length: 3 }),
+            (4, Range { start: 6, length: 2 }, Range { start: 3, length: 2 }),
+        ]);
+        assert_eq!(buffer, SvoBuffer {
+            bytes: vec![2, 3, 4, 6, 7],
+            free_ranges: vec![],
+            updated_ranges: vec![Range { start: 0, length: 5 }],
+            octant_to_range: FxHashMap::from_iter([
+                (2, Range { start: 0, length: 3 }),
+                (4, Range { start: 3, length: 2 }),
+            ]),
+        });
+
+        // already defragmented buffers are not changed
+        assert_eq!(buffer.defragment(), vec![]);
+
+        // churn the buffer to produce many small gaps
+        for i in 0..100 {
+            buffer.insert(10 + i, &ChunkBuffer { data: vec![i as u32; 1 + i as usize % 3] });
+        }
+        for i in (0..100).step_by(2) {
+            buffer.remove(10 + i);
+        }
+        let len_before = buffer.bytes.len();
+        buffer.defragment();
+        assert!(buffer.bytes.len() < len_before);
+        for i in (1..100).step_by(2) {
+            let range = buffer.octant_to_range[&(10 + i)];
+            assert_eq!(&buffer.bytes[range.start..range.start + range.length], vec![i as u32; range.length].as_slice());
+        }
+    }
+
     /// Tests that range merging edge cases work properly.
     #[test]
     fn merge_ranges() {
@@ -0,0 +1,180 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::world::chunk::{BlockId, Chunk, ChunkStorage};
+use crate::world::octree::Position;
+
+#[derive(Debug)]
+pub enum VoxError {
+    Io(io::Error),
+    /// `InvalidHeader` is returned if the file does not start with the `VOX ` magic number and a `MAIN` chunk.
+    InvalidHeader,
+    /// `UnexpectedEof` is returned if a chunk is larger than the remaining file.
+    UnexpectedEof,
+    /// `MissingModel` is returned if the file does not contain any voxel data.
+    MissingModel,
+}
+
+impl From<io::Error> for VoxError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Imports the first model of the `MagicaVoxel` `.vox` file at `path`. See [`parse`].
+pub fn import<P: AsRef<Path>, F: Fn(u8) -> Option<BlockId>>(path: P, mapping: F) -> Result<Vec<(Position, BlockId)>, VoxError> {
+    parse(&fs::read(path)?, mapping)
+}
+
+/// Parses the first model of a `MagicaVoxel` `.vox` file and returns the position and block of every voxel. `mapping`
+/// maps the file's palette indices (1-255) to block ids. Voxels without mapping are skipped.
+///
+/// `MagicaVoxel` uses z as the up axis, which is swapped with y. Files with multiple models are supported, but only the
+/// first model is imported, as the scene graph that places the models is ignored.
+pub fn parse<F: Fn(u8) -> Option<BlockId>>(data: &[u8], mapping: F) -> Result<Vec<(Position, BlockId)>, VoxError> {
+    if !data.starts_with(b"VOX ") || data.get(8..12) != Some(b"MAIN".as_slice()) {
+        return Err(VoxError::InvalidHeader);
+    }
+
+    // children of the MAIN chunk follow its (usually empty) content
+    let mut offset = 20 + read_u32(data, 12)? as usize;
+    let end = offset + read_u32(data, 16)? as usize;
+    while offset < end {
+        let id = data.get(offset..offset + 4).ok_or(VoxError::UnexpectedEof)?;
+        let content_size = read_u32(data, offset + 4)? as usize;
+        let children_size = read_u32(data, offset + 8)? as usize;
+        let content = data.get(offset + 12..offset + 12 + content_size).ok_or(VoxError::UnexpectedEof)?;
+
+        if id == b"XYZI" {
+            return parse_voxels(content, mapping);
+        }
+        offset += 12 + content_size + children_size;
+    }
+    Err(VoxError::MissingModel)
+}
+
+fn parse_voxels<F: Fn(u8) -> Option<BlockId>>(content: &[u8], mapping: F) -> Result<Vec<(Position, BlockId)>, VoxError> {
+    let count = read_u32(content, 0)? as usize;
+    let voxels = content.get(4..4 + count * 4).ok_or(VoxError::UnexpectedEof)?;
+    Ok(voxels.chunks_exact(4)
+        .filter_map(|v| {
+            let block = mapping(v[3])?;
+            Some((Position(v[0] as u32, v[2] as u32, v[1] as u32), block))
+        })
+        .collect())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, VoxError> {
+    let bytes = data.get(offset..offset + 4).ok_or(VoxError::UnexpectedEof)?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Sets all `voxels` in `storage`, moved by `offset`. The storage is expanded to fit all voxels.
+pub fn stamp_into_octree(storage: &mut ChunkStorage, voxels: &[(Position, BlockId)], offset: Position) {
+    storage.set_leaves(voxels.iter().map(|(pos, block)| (add(*pos, offset), *block)));
+}
+
+/// Sets all `voxels` in `chunk`, moved by `offset`. Voxels outside of the chunk are skipped. Returns the number of
+/// voxels that were set.
+pub fn stamp_into_chunk(chunk: &mut Chunk, voxels: &[(Position, BlockId)], offset: Position) -> usize {
+    let mut count = 0;
+    for (pos, block) in voxels {
+        let pos = add(*pos, offset);
+        if chunk.try_set_block(pos.0, pos.1, pos.2, *block).is_ok() {
+            count += 1;
+        }
+    }
+    count
+}
+
+fn add(pos: Position, offset: Position) -> Position {
+    Position(pos.0 + offset.0, pos.1 + offset.1, pos.2 + offset.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::world::chunk::{Chunk, ChunkPos, ChunkStorageAllocator, NO_BLOCK};
+    use crate::world::octree::Position;
+    use crate::world::vox;
+    use crate::world::vox::VoxError;
+
+    /// Builds a `.vox` file with a SIZE and a XYZI chunk for the given voxels.
+    fn build_vox(voxels: &[[u8; 4]]) -> Vec<u8> {
+        let mut size = Vec::new();
+        size.extend_from_slice(b"SIZE");
+        size.extend_from_slice(&12u32.to_le_bytes());
+        size.extend_from_slice(&0u32.to_le_bytes());
+        for _ in 0..3 {
+            size.extend_from_slice(&4u32.to_le_bytes());
+        }
+
+        let mut xyzi = Vec::new();
+        xyzi.extend_from_slice(b"XYZI");
+        xyzi.extend_from_slice(&(4 + voxels.len() as u32 * 4).to_le_bytes());
+        xyzi.extend_from_slice(&0u32.to_le_bytes());
+        xyzi.extend_from_slice(&(voxels.len() as u32).to_le_bytes());
+        xyzi.extend(voxels.iter().flatten());
+
+        let mut data = Vec::new();
+        data.extend_from_slice(b"VOX ");
+        data.extend_from_slice(&150u32.to_le_bytes());
+        data.extend_from_slice(b"MAIN");
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&((size.len() + xyzi.len()) as u32).to_le_bytes());
+        data.extend(size);
+        data.extend(xyzi);
+        data
+    }
+
+    /// Tests that voxels are imported with swapped y and z axes, mapped palette indices, and that unmapped voxels are
+    /// skipped.
+    #[test]
+    fn import() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.vox");
+        fs::write(&path, build_vox(&[[0, 0, 0, 1], [1, 2, 3, 2], [3, 0, 1, 1], [2, 2, 2, 9]])).unwrap();
+
+        let voxels = vox::import(&path, |index| (index < 3).then_some(index as u32 * 10)).unwrap();
+        assert_eq!(voxels.len(), 3);
+        assert_eq!(voxels, vec![
+            (Position(0, 0, 0), 10),
+            (Position(1, 3, 2), 20),
+            (Position(3, 1, 0), 10),
+        ]);
+    }
+
+    /// Tests that invalid files are rejected.
+    #[test]
+    fn parse_invalid() {
+        let mapping = |index: u8| Some(index as u32);
+        assert!(matches!(vox::parse(b"PNG ", mapping), Err(VoxError::InvalidHeader)));
+
+        let data = build_vox(&[[0, 0, 0, 1]]);
+        assert!(matches!(vox::parse(&data[..data.len() - 1], mapping), Err(VoxError::UnexpectedEof)));
+
+        let data = build_vox(&[]);
+        let mut without_model = data[..data.len() - 16].to_vec();
+        without_model[16..20].copy_from_slice(&24u32.to_le_bytes());
+        assert!(matches!(vox::parse(&without_model, mapping), Err(VoxError::MissingModel)));
+    }
+
+    /// Tests that imported voxels are stamped into a chunk at an offset and voxels outside of the chunk are skipped.
+    #[test]
+    fn stamp_into_chunk() {
+        let voxels = vec![(Position(0, 0, 0), 1), (Position(2, 1, 0), 2), (Position(31, 0, 0), 3)];
+        let alloc = ChunkStorageAllocator::new();
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0), 5, alloc.allocate());
+
+        assert_eq!(vox::stamp_into_chunk(&mut chunk, &voxels, Position(1, 2, 3)), 2);
+        assert_eq!(chunk.get_block(1, 2, 3), 1);
+        assert_eq!(chunk.get_block(3, 3, 3), 2);
+        assert_eq!(chunk.get_block(0, 0, 0), NO_BLOCK);
+
+        let mut storage = alloc.allocate();
+        vox::stamp_into_octree(&mut storage, &voxels, Position(1, 2, 3));
+        assert_eq!(storage.get_leaf(Position(32, 2, 3)), Some(&3));
+    }
+}
@@ -6,7 +6,7 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::world::chunk;
-use crate::world::chunk::{Chunk, ChunkPos};
+use crate::world::chunk::{BlockPos, Chunk, ChunkPos, ChunkStorageAllocator};
 
 /// `BorrowedChunk` wraps around an actual chunks which temporarily got its ownership transferred
 /// to the borrowed chunk. It is intended to be returned to the [`World`] by calling
@@ -151,7 +151,8 @@ impl World {
     pub fn get_block(&self, x: i32, y: i32, z: i32) -> chunk::BlockId {
         let pos = ChunkPos::from_block_pos(x, y, z);
         if let Some(chunk) = self.chunks.get(&pos) {
-            return chunk.get_block((x & 31) as u32, (y & 31) as u32, (z & 31) as u32);
+            let mask = Chunk::BLOCK_MASK;
+            return chunk.get_block((x & mask) as u32, (y & mask) as u32, (z & mask) as u32);
         }
         chunk::NO_BLOCK
     }
@@ -161,13 +162,55 @@ impl World {
     pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: chunk::BlockId) -> bool {
         let pos = ChunkPos::from_block_pos(x, y, z);
         if let Some(chunk) = self.chunks.get_mut(&pos) {
-            chunk.set_block((x & 31) as u32, (y & 31) as u32, (z & 31) as u32, block);
+            let mask = Chunk::BLOCK_MASK;
+            chunk.set_block((x & mask) as u32, (y & mask) as u32, (z & mask) as u32, block);
             self.mark_chunk_as_changed(&pos);
             return true;
         }
         false
     }
 
+    /// Sets the block id at the given position in world space like [`World::set_block`], but creates an empty chunk
+    /// from `alloc` if no chunk exists at that position. Does nothing if the chunk was borrowed, as the borrowed chunk
+    /// would be overridden otherwise. Returns true, if the block was set.
+    pub fn set_block_or_create_chunk(&mut self, x: i32, y: i32, z: i32, block: chunk::BlockId, alloc: &ChunkStorageAllocator) -> bool {
+        if self.set_block(x, y, z, block) {
+            return true;
+        }
+
+        let pos = BlockPos::new(x, y, z);
+        if self.borrowed_chunks.contains_key(&pos.chunk) {
+            return false;
+        }
+
+        let mut chunk = Chunk::new(pos.chunk, Chunk::DEPTH, alloc.allocate());
+        chunk.set_block(pos.rel_x as u32, pos.rel_y as u32, pos.rel_z as u32, block);
+        self.set_chunk(chunk);
+        true
+    }
+
+    /// Returns the y coordinate of the highest non-empty block in the column at the given x and z
+    /// world space coordinates. All loaded chunks of that column are traversed from top to bottom
+    /// until the first block is found. Borrowed chunks are skipped. Returns `None` if the column
+    /// does not contain any blocks.
+    pub fn height_at(&self, x: i32, z: i32) -> Option<i32> {
+        let (chunk_x, chunk_z) = (x >> Chunk::DEPTH, z >> Chunk::DEPTH);
+        let mut column = self.chunks.values()
+            .filter(|chunk| chunk.pos.x == chunk_x && chunk.pos.z == chunk_z)
+            .collect::<Vec<_>>();
+        column.sort_unstable_by_key(|chunk| -chunk.pos.y);
+
+        let (rel_x, rel_z) = ((x & Chunk::BLOCK_MASK) as u32, (z & Chunk::BLOCK_MASK) as u32);
+        for chunk in column {
+            for y in (0..Chunk::SIZE).rev() {
+                if chunk.get_block(rel_x, y, rel_z) != chunk::NO_BLOCK {
+                    return Some((chunk.pos.y << Chunk::DEPTH) + y as i32);
+                }
+            }
+        }
+        None
+    }
+
     /// Returns up to limit chunk positions of chunks that have been changed.
     pub fn get_changed_chunks(&mut self, limit: u32) -> Vec<ChunkPos> {
         // clean up dropped borrowed chunk references
@@ -219,6 +262,68 @@ mod tests {
         assert_eq!(block, 99);
     }
 
+    /// Tests that placing blocks creates missing chunks, can be read back, and that removing blocks marks the chunk as
+    /// changed, so that it is serialized again.
+    #[test]
+    fn set_block_or_create_chunk() {
+        let alloc = ChunkStorageAllocator::new();
+        let mut world = super::World::new();
+
+        assert!(world.set_block_or_create_chunk(-1, 40, 3, 7, &alloc));
+        assert_eq!(world.get_block(-1, 40, 3), 7);
+        assert_eq!(world.get_chunk(&ChunkPos::new(-1, 1, 0)).unwrap().lod, Chunk::DEPTH);
+        assert_eq!(world.get_changed_chunks(10), vec![ChunkPos::new(-1, 1, 0)]);
+
+        assert!(world.set_block_or_create_chunk(-1, 40, 3, chunk::NO_BLOCK, &alloc));
+        assert_eq!(world.get_block(-1, 40, 3), chunk::NO_BLOCK);
+        assert_eq!(world.get_changed_chunks(10), vec![ChunkPos::new(-1, 1, 0)]);
+
+        // borrowed chunks are not overridden
+        world.set_block(-2, 40, 3, 7);
+        let borrow = world.borrow_chunk(&ChunkPos::new(-1, 1, 0)).unwrap();
+        assert!(!world.set_block_or_create_chunk(-1, 40, 3, 7, &alloc));
+        world.return_chunk(borrow);
+        assert_eq!(world.get_block(-2, 40, 3), 7);
+        assert_eq!(world.get_block(-1, 40, 3), chunk::NO_BLOCK);
+    }
+
+    /// Tests that the highest block in a column is found across multiple chunks along the y-axis.
+    #[test]
+    fn height_at() {
+        let alloc = Arc::new(ChunkStorageAllocator::new());
+        let mut world = super::World::new();
+
+        // generate a simple terrain column spanning two chunks: height = 20 + x + z
+        for chunk_y in 0..3 {
+            let mut chunk = Chunk::new(ChunkPos::new(0, chunk_y, 0), 5, alloc.allocate());
+            chunk.fill_with(|x, y, z| {
+                let y = (chunk_y << 5) + y as i32;
+                (y <= 20 + (x + z) as i32).then_some(1)
+            });
+            world.set_chunk(chunk);
+        }
+
+        assert_eq!(world.height_at(0, 0), Some(20));
+        assert_eq!(world.height_at(5, 6), Some(31));
+        assert_eq!(world.height_at(6, 6), Some(32));
+        assert_eq!(world.height_at(31, 31), Some(82));
+
+        // no chunks loaded in column
+        assert_eq!(world.height_at(-1, 0), None);
+
+        // empty column after removing the blocks
+        for y in 0..=20 {
+            world.set_block(0, y, 0, chunk::NO_BLOCK);
+        }
+        assert_eq!(world.height_at(0, 0), None);
+
+        // borrowed chunks are skipped
+        let borrow = world.borrow_chunk(&ChunkPos::new(0, 2, 0));
+        assert_eq!(world.height_at(31, 31), Some(63));
+        world.return_chunk(borrow.unwrap());
+        assert_eq!(world.height_at(31, 31), Some(82));
+    }
+
     /// Tests that different chunk actions are properly registered as chunk changes.
     #[test]
     fn world_changed_chunks() {